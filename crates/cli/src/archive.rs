@@ -0,0 +1,221 @@
+//! `bookrab export` and `bookrab import`: the whole library in a
+//! `.tar.gz` (see [bookrab_core::books::archive]). Archives are
+//! written and read while they are made (or sent to a server),
+//! never kept in memory.
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter, Read, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use bookrab_core::{
+    books::{archive::ImportSummary, RootBookDir},
+    errors::BookrabError,
+};
+use clap::Args;
+
+use crate::{remote::Remote, CliError};
+
+#[derive(Args)]
+pub struct ExportArgs {
+    /// File where the archive is written (`-` writes it to stdout)
+    #[arg(long, short, value_name = "PATH")]
+    out: PathBuf,
+    /// Also exports the search history
+    #[arg(long)]
+    history: bool,
+}
+
+#[derive(Args)]
+pub struct ImportArgs {
+    /// Archive made by `bookrab export` (`-` reads it from stdin).
+    /// Books with the same title as existing ones replace them
+    #[arg(value_name = "PATH")]
+    archive: PathBuf,
+}
+
+fn is_stdio(path: &Path) -> bool {
+    path.as_os_str() == "-"
+}
+
+/// Exports the library to the file of `args` or, with `-`, to `out`.
+/// A file that couldn't be finished is removed.
+pub fn export(
+    root: &mut RootBookDir,
+    args: &ExportArgs,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    export_with(args, |writer| root.export(writer, args.history), out, err)
+}
+
+/// Like [export], with the library of `remote`.
+pub fn export_remote(
+    remote: &Remote,
+    args: &ExportArgs,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    export_with(
+        args,
+        |mut writer| remote.export(&mut writer, args.history),
+        out,
+        err,
+    )
+}
+
+/// Writes the archive of `write` where `args` asks.
+fn export_with(
+    args: &ExportArgs,
+    write: impl FnOnce(&mut dyn Write) -> Result<(), BookrabError>,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    if is_stdio(&args.out) {
+        let mut writer = BufWriter::new(out);
+        write(&mut writer)?;
+        writer.flush()?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let file = File::create(&args.out).map_err(|e| BookrabError::CouldntWriteFile {
+        error: (),
+        path: args.out.clone(),
+        err: e,
+    })?;
+    let mut writer = BufWriter::new(file);
+    let result = write(&mut writer).and_then(|_| {
+        writer.flush().map_err(|e| BookrabError::CouldntWriteFile {
+            error: (),
+            path: args.out.clone(),
+            err: e,
+        })
+    });
+    if let Err(e) = result {
+        drop(writer);
+        let _ = fs::remove_file(&args.out);
+        return Err(e.into());
+    }
+    writeln!(err, "exported the library to {}", args.out.display())?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Imports the archive of `args` (or, with `-`, `input`) and writes
+/// what was restored to `out`.
+pub fn import(
+    root: &mut RootBookDir,
+    args: &ImportArgs,
+    input: impl Read,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let summary = if is_stdio(&args.archive) {
+        root.import(BufReader::new(input))?
+    } else {
+        root.import(BufReader::new(open(&args.archive)?))?
+    };
+    write_summary(&summary, out)
+}
+
+/// Like [import], to the library of `remote`.
+pub fn import_remote(
+    remote: &Remote,
+    args: &ImportArgs,
+    input: impl Read + Send + 'static,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let summary = if is_stdio(&args.archive) {
+        remote.import(input)?
+    } else {
+        remote.import(open(&args.archive)?)?
+    };
+    write_summary(&summary, out)
+}
+
+fn open(path: &Path) -> Result<File, BookrabError> {
+    File::open(path).map_err(|e| BookrabError::CouldntReadFile {
+        error: (),
+        path: path.to_path_buf(),
+        err: e,
+    })
+}
+
+fn write_summary(summary: &ImportSummary, out: &mut impl Write) -> Result<ExitCode, CliError> {
+    writeln!(
+        out,
+        "imported {} books and {} history entries",
+        summary.books, summary.history_entries
+    )?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, io};
+
+    use bookrab_core::books::test_utils::{create_book_dir, random_string, s, DBCONNECTION};
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli<T: clap::Args> {
+        #[command(flatten)]
+        args: T,
+    }
+
+    fn books(root: &mut RootBookDir) -> Vec<(String, Vec<String>)> {
+        let mut books: Vec<(String, Vec<String>)> = root
+            .list()
+            .unwrap()
+            .into_iter()
+            .map(|book| {
+                let mut tags: Vec<String> = book.tags.into_iter().collect();
+                tags.sort();
+                (book.title, tags)
+            })
+            .collect();
+        books.sort();
+        books
+    }
+
+    #[test]
+    fn export_and_import() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = create_book_dir(connection);
+        root.upload("1", "As armas", s(vec!["a", "b"])).unwrap();
+        root.upload("2", "e os barões", s(vec![])).unwrap();
+        let path = temp_dir().join(format!("bookrab-cli-{}.tar.gz", random_string()));
+        let cli = Cli::<ExportArgs>::parse_from(["export", "--out", path.to_str().unwrap()]);
+        let mut err = vec![];
+        export(&mut root, &cli.args, &mut io::sink(), &mut err).unwrap();
+        assert!(String::from_utf8(err).unwrap().starts_with("exported"));
+        let exported = books(&mut root);
+
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut other = create_book_dir(connection);
+        let cli = Cli::<ImportArgs>::parse_from(["import", path.to_str().unwrap()]);
+        let mut out = vec![];
+        import(&mut other, &cli.args, io::empty(), &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "imported 2 books and 0 history entries\n"
+        );
+        assert_eq!(books(&mut other), exported);
+        assert_eq!(other.text("2").unwrap(), "e os barões");
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn through_stdio() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = create_book_dir(connection);
+        root.upload("1", "As armas", s(vec!["a"])).unwrap();
+        let cli = Cli::<ExportArgs>::parse_from(["export", "--out", "-"]);
+        let mut archive = vec![];
+        export(&mut root, &cli.args, &mut archive, &mut io::sink()).unwrap();
+
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut other = create_book_dir(connection);
+        let cli = Cli::<ImportArgs>::parse_from(["import", "-"]);
+        import(&mut other, &cli.args, archive.as_slice(), &mut io::sink()).unwrap();
+        assert_eq!(books(&mut other), books(&mut root));
+    }
+}
@@ -0,0 +1,161 @@
+//! `bookrab search --patterns-file`: searches many patterns in one
+//! process (in parallel with `--jobs`) and writes a JSON record per
+//! pattern, one per line, in the order of the file.
+use std::collections::BTreeMap;
+use std::fs;
+use std::io::{Read, Write};
+use std::path::Path;
+use std::process::ExitCode;
+use std::sync::{atomic::AtomicUsize, atomic::Ordering, mpsc};
+use std::thread;
+
+use bookrab_core::{books::SearchResults, errors::BookrabError};
+
+use crate::{search::JsonBook, CliError};
+
+/// Line written for a pattern.
+#[derive(serde::Serialize)]
+struct Record<'a> {
+    pattern: &'a str,
+    #[serde(flatten)]
+    outcome: Outcome<'a>,
+}
+
+#[derive(serde::Serialize)]
+#[serde(untagged)]
+enum Outcome<'a> {
+    Searched {
+        match_count: usize,
+        books: Vec<JsonBook<'a>>,
+    },
+    /// e.g. the pattern isn't a valid regex
+    Failed { error: &'a BookrabError },
+}
+
+/// Patterns of the file of `path` (or of `input` with `-`), one per line.
+/// Empty lines are skipped.
+pub fn read_patterns(path: &Path, mut input: impl Read) -> Result<Vec<String>, BookrabError> {
+    let text = if path.as_os_str() == "-" {
+        let mut text = String::new();
+        input
+            .read_to_string(&mut text)
+            .map_err(|e| BookrabError::CouldntReadFile {
+                error: (),
+                path: path.to_path_buf(),
+                err: e,
+            })?;
+        text
+    } else {
+        fs::read_to_string(path).map_err(|e| BookrabError::CouldntReadFile {
+            error: (),
+            path: path.to_path_buf(),
+            err: e,
+        })?
+    };
+    Ok(text
+        .lines()
+        .filter(|line| !line.is_empty())
+        .map(str::to_string)
+        .collect())
+}
+
+/// Searches `patterns` with one thread for each of the `workers`
+/// (see [crate::search::run]) and writes their records to `out` as
+/// soon as the ones before them are written. A pattern that fails
+/// doesn't stop the others, but the command exits with 2; otherwise
+/// it exits with 1 when no pattern matched, like grep.
+pub fn run<W: Send>(
+    workers: Vec<W>,
+    search: impl Fn(&mut W, String) -> Result<Vec<SearchResults>, BookrabError> + Sync,
+    patterns: &[String],
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let next = AtomicUsize::new(0);
+    let mut failed = false;
+    let mut matched = false;
+    thread::scope(|scope| {
+        let (sender, receiver) = mpsc::channel();
+        for mut worker in workers {
+            let sender = sender.clone();
+            let (next, search) = (&next, &search);
+            scope.spawn(move || loop {
+                let index = next.fetch_add(1, Ordering::Relaxed);
+                let Some(pattern) = patterns.get(index) else {
+                    break;
+                };
+                let results = search(&mut worker, pattern.clone());
+                // the output stopped (e.g. a broken pipe)
+                if sender.send((index, results)).is_err() {
+                    break;
+                }
+            });
+        }
+        drop(sender);
+        // records that arrived before the ones of previous patterns
+        let mut pending = BTreeMap::new();
+        let mut written = 0;
+        for (index, results) in receiver.iter() {
+            pending.insert(index, results);
+            while let Some(results) = pending.remove(&written) {
+                let outcome = match &results {
+                    Ok(results) => Outcome::Searched {
+                        match_count: results.iter().map(SearchResults::match_count).sum(),
+                        books: results.iter().map(JsonBook::from).collect(),
+                    },
+                    Err(error) => Outcome::Failed { error },
+                };
+                failed |= results.is_err();
+                matched |= results.as_ref().is_ok_and(|results| !results.is_empty());
+                let record = Record {
+                    pattern: &patterns[written],
+                    outcome,
+                };
+                serde_json::to_writer(&mut *out, &record).map_err(std::io::Error::from)?;
+                writeln!(out)?;
+                written += 1;
+            }
+        }
+        Ok::<(), CliError>(())
+    })?;
+    Ok(if failed {
+        ExitCode::from(2)
+    } else if matched {
+        ExitCode::SUCCESS
+    } else {
+        ExitCode::from(1)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn patterns_from_stdin() {
+        let patterns = read_patterns(Path::new("-"), "armas\n\nbarões\r\n".as_bytes()).unwrap();
+        assert_eq!(patterns, ["armas", "barões"]);
+    }
+
+    #[test]
+    fn records_in_order() {
+        let patterns: Vec<String> = (0..20).map(|n| n.to_string()).collect();
+        // later patterns finish first
+        let search = |_: &mut (), pattern: String| {
+            let n: u64 = pattern.parse().unwrap();
+            thread::sleep(std::time::Duration::from_millis(20 - n));
+            Ok(vec![])
+        };
+        let mut out = vec![];
+        let code = run(vec![(); 4], search, &patterns, &mut out).unwrap();
+        assert_eq!(code, ExitCode::from(1));
+        let written: Vec<String> = String::from_utf8(out)
+            .unwrap()
+            .lines()
+            .map(|line| {
+                serde_json::from_str::<serde_json::Value>(line).unwrap()["pattern"].to_string()
+            })
+            .collect();
+        let expected: Vec<String> = patterns.iter().map(|p| format!("\"{p}\"")).collect();
+        assert_eq!(written, expected);
+    }
+}
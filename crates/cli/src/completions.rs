@@ -0,0 +1,151 @@
+//! `bookrab completions`: completions for bash, zsh and fish. The script
+//! calls `bookrab` back while completing (see [clap_complete::env]), so
+//! titles and tags are completed with the ones of the library.
+use std::env;
+use std::io::Write;
+use std::path::PathBuf;
+use std::process::ExitCode;
+
+use bookrab_core::books::{RootBookDir, TagCount};
+use clap::{Args, ValueEnum};
+use clap_complete::{
+    env::{Bash, EnvCompleter, Fish, Zsh},
+    CompletionCandidate,
+};
+
+use crate::{open_library, remote::Remote, CliError};
+
+/// Variable that asks `bookrab` to complete instead of running.
+pub const COMPLETE_VAR: &str = "COMPLETE";
+
+#[derive(Args)]
+pub struct CompletionsArgs {
+    /// e.g. `source <(bookrab completions bash)` in ~/.bashrc
+    shell: Shell,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum Shell {
+    Bash,
+    Zsh,
+    Fish,
+}
+
+/// Writes the script that registers the completions of `shell`.
+/// `completer` is the `bookrab` called while completing.
+pub fn run(
+    args: &CompletionsArgs,
+    completer: &str,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let shell: &dyn EnvCompleter = match args.shell {
+        Shell::Bash => &Bash,
+        Shell::Zsh => &Zsh,
+        Shell::Fish => &Fish,
+    };
+    shell.write_registration(COMPLETE_VAR, "bookrab", "bookrab", completer, out)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Titles of the books of `root` (sorted).
+fn book_titles(root: &mut RootBookDir) -> Vec<CompletionCandidate> {
+    let books = root.list().unwrap_or_default();
+    title_candidates(books.into_iter().map(|book| book.title).collect())
+}
+
+fn title_candidates(mut titles: Vec<String>) -> Vec<CompletionCandidate> {
+    titles.sort();
+    titles.into_iter().map(CompletionCandidate::new).collect()
+}
+
+/// Tags of `root`, with the number of books that have them (most used first).
+fn book_tags(root: &mut RootBookDir) -> Vec<CompletionCandidate> {
+    tag_candidates(root.tag_counts().unwrap_or_default())
+}
+
+fn tag_candidates(counts: Vec<TagCount>) -> Vec<CompletionCandidate> {
+    counts
+        .into_iter()
+        .map(|count| {
+            CompletionCandidate::new(count.tag).help(Some(format!("{} books", count.count).into()))
+        })
+        .collect()
+}
+
+/// Server of `BOOKRAB_SERVER`. The shell doesn't pass the options being
+/// completed to the candidates, so `--server` only works through it.
+fn remote() -> Option<Remote> {
+    let url = env::var("BOOKRAB_SERVER").ok()?;
+    let api_key = env::var("BOOKRAB_API_KEY").ok();
+    Some(Remote::new(&url, api_key.as_deref()))
+}
+
+/// Configuration file of `BOOKRAB_CONFIG` (like `--server`, `--config`
+/// only works through it).
+fn config_file() -> Option<PathBuf> {
+    env::var_os("BOOKRAB_CONFIG").map(PathBuf::from)
+}
+
+/// Titles of the library of the configuration or of the server
+/// (nothing if it can't be read).
+pub fn titles() -> Vec<CompletionCandidate> {
+    match remote() {
+        Some(remote) => {
+            let books = remote.books().unwrap_or_default();
+            title_candidates(books.into_iter().map(|book| book.title).collect())
+        }
+        None => open_library(config_file().as_deref(), |_| {}, book_titles).unwrap_or_default(),
+    }
+}
+
+/// Tags of the library of the configuration or of the server
+/// (nothing if it can't be read).
+pub fn tags() -> Vec<CompletionCandidate> {
+    match remote() {
+        Some(remote) => tag_candidates(remote.tag_counts().unwrap_or_default()),
+        None => open_library(config_file().as_deref(), |_| {}, book_tags).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bookrab_core::books::test_utils::{root_for_tag_tests, DBCONNECTION};
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: CompletionsArgs,
+    }
+
+    fn values(candidates: &[CompletionCandidate]) -> Vec<String> {
+        candidates
+            .iter()
+            .map(|candidate| candidate.get_value().to_string_lossy().to_string())
+            .collect()
+    }
+
+    #[test]
+    fn titles_and_tags() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = root_for_tag_tests(connection);
+        assert_eq!(values(&book_titles(&mut root)), ["1", "2", "3", "4"]);
+        let tags = book_tags(&mut root);
+        assert_eq!(values(&tags), ["a", "b", "c", "d"]);
+        assert_eq!(tags[0].get_help().unwrap().to_string(), "4 books");
+    }
+
+    #[test]
+    fn registration() {
+        for shell in ["bash", "zsh", "fish"] {
+            let cli = Cli::parse_from(["completions", shell]);
+            let mut out = vec![];
+            run(&cli.args, "/usr/bin/bookrab", &mut out).unwrap();
+            let script = String::from_utf8(out).unwrap();
+            assert!(script.contains("/usr/bin/bookrab"), "{shell}: {script}");
+            assert!(script.contains(COMPLETE_VAR), "{shell}: {script}");
+        }
+    }
+}
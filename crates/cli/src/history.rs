@@ -0,0 +1,242 @@
+//! `bookrab history`: lists past searches (see [bookrab_core::books::history::SearchHistory::get_history])
+//! and runs them again. Like the history routes of the REST API,
+//! it needs the history kept in a database (see [query_history])
+//! or a server (see [crate::remote]).
+use std::io::Write;
+use std::process::ExitCode;
+
+use bookrab_client::HistoryQuery;
+use bookrab_core::{
+    books::{history::HistoryFilter, history_backend::query_history, RootBookDir},
+    database::history::SearchHistoryEntry,
+    errors::BookrabError,
+};
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeDelta};
+use clap::{Args, Subcommand};
+use clap_complete::ArgValueCandidates;
+
+use crate::{completions, list, remote::Remote, search, CliError};
+
+#[derive(Args)]
+#[command(args_conflicts_with_subcommands = true)]
+pub struct HistoryArgs {
+    #[command(subcommand)]
+    command: Option<HistoryCommand>,
+    #[command(flatten)]
+    list: ListArgs,
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    /// Runs the search of an entry again (on every book, with its tags
+    /// and options) and prints the results like `bookrab search`
+    Rerun {
+        /// Id of the entry (see `bookrab history`)
+        id: i32,
+        #[arg(long, value_enum, default_value_t = search::Format::Grep)]
+        format: search::Format,
+    },
+}
+
+#[derive(Args)]
+struct ListArgs {
+    /// Only searches made since then: a duration like 30m, 12h, 7d or 2w,
+    /// or a date like 2024-01-31
+    #[arg(long, value_parser = parse_since)]
+    since: Option<NaiveDateTime>,
+    /// Only searches whose pattern contains this
+    #[arg(long)]
+    pattern: Option<String>,
+    /// Only entries of this book
+    #[arg(long, add = ArgValueCandidates::new(completions::titles))]
+    title: Option<String>,
+    /// Maximum number of entries (newest first)
+    #[arg(long)]
+    limit: Option<i64>,
+    #[arg(long, value_enum, default_value_t = list::Format::Table)]
+    format: list::Format,
+}
+
+/// Date of `value`, which is a date or a duration before now.
+fn parse_since(value: &str) -> Result<NaiveDateTime, String> {
+    if let Ok(date) = NaiveDate::parse_from_str(value, "%Y-%m-%d") {
+        return Ok(date.and_time(Default::default()));
+    }
+    let error = || format!("expected a duration like 7d or a date like 2024-01-31, not {value:?}");
+    let unit = value.chars().last().ok_or_else(error)?;
+    let amount: i64 = value[..value.len() - unit.len_utf8()]
+        .parse()
+        .map_err(|_| error())?;
+    let duration = match unit {
+        'm' => TimeDelta::try_minutes(amount),
+        'h' => TimeDelta::try_hours(amount),
+        'd' => TimeDelta::try_days(amount),
+        'w' => TimeDelta::try_weeks(amount),
+        _ => None,
+    }
+    .ok_or_else(error)?;
+    // dates of the history are local, like the ones of the other backends
+    Ok(Local::now().naive_local() - duration)
+}
+
+/// Lists the entries of the history or runs one of them again.
+pub fn run(
+    root: &mut RootBookDir,
+    args: &HistoryArgs,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let config = root.config().clone();
+    if let Some(HistoryCommand::Rerun { id, format }) = &args.command {
+        let entry = query_history(&config, root.connection.as_deref_mut(), |history| {
+            history.get_entry(*id)
+        })?;
+        let Some(entry) = entry else {
+            return Err(BookrabError::InexistentHistoryEntry { error: (), id: *id }.into());
+        };
+        let context = entry.context();
+        let results = search::search(root, entry.pattern, &context)?;
+        return search::write_results(*format, &results, &context, out);
+    }
+    let filter = HistoryFilter {
+        from: args.list.since,
+        pattern: args.list.pattern.clone(),
+        title: args.list.title.clone(),
+        limit: args.list.limit,
+        ..Default::default()
+    };
+    let entries = query_history(&config, root.connection.as_deref_mut(), |history| {
+        history.get_history(&filter)
+    })?;
+    write_entries(&entries, args.list.format, out)
+}
+
+/// Like [run], with the history of `remote`.
+pub fn run_remote(
+    remote: &Remote,
+    args: &HistoryArgs,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    if let Some(HistoryCommand::Rerun { id, format }) = &args.command {
+        let entries = remote.history(HistoryQuery::default(), None)?;
+        let Some(entry) = entries.into_iter().find(|entry| entry.id == *id) else {
+            return Err(BookrabError::InexistentHistoryEntry { error: (), id: *id }.into());
+        };
+        let context = entry.context();
+        let results = remote.search(entry.pattern, &context)?;
+        return search::write_results(*format, &results, &context, out);
+    }
+    let query = HistoryQuery {
+        from: args.list.since,
+        pattern: args.list.pattern.clone(),
+        title: args.list.title.clone(),
+        ..Default::default()
+    };
+    let entries = remote.history(query, args.list.limit)?;
+    write_entries(&entries, args.list.format, out)
+}
+
+fn write_entries(
+    entries: &[SearchHistoryEntry],
+    format: list::Format,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let rows = entries
+        .iter()
+        .map(|entry| {
+            vec![
+                entry.id.to_string(),
+                entry.date.format("%Y-%m-%d %H:%M:%S").to_string(),
+                entry.match_count.to_string(),
+                entry.title.clone(),
+                entry.pattern.clone(),
+            ]
+        })
+        .collect();
+    list::write_rows(
+        format,
+        &["id", "date", "matches", "title", "pattern"],
+        rows,
+        &entries,
+        out,
+    )?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use bookrab_core::books::{
+        history::HistoryContext,
+        test_utils::{random_string, root_for_tag_tests, DBCONNECTION},
+    };
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: HistoryArgs,
+    }
+
+    fn history(root: &mut RootBookDir, args: &[&str]) -> (ExitCode, String) {
+        let cli = Cli::parse_from([&["history"], args].concat());
+        let mut out = vec![];
+        let code = run(root, &cli.args, &mut out).unwrap();
+        (code, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn since() {
+        let date = parse_since("2024-01-31").unwrap();
+        assert_eq!(date.to_string(), "2024-01-31 00:00:00");
+        let week_ago = Local::now().naive_local() - TimeDelta::days(7);
+        let since = parse_since("7d").unwrap();
+        assert!((since - week_ago).num_seconds().abs() < 5);
+        assert!(parse_since("7y").is_err());
+        assert!(parse_since("d").is_err());
+        assert!(parse_since("").is_err());
+    }
+
+    #[test]
+    fn list_and_rerun() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = root_for_tag_tests(connection);
+        // the history is shared by the tests, so the pattern has
+        // something that no other test searches
+        let marker = random_string();
+        let pattern = format!("barões|{marker}");
+        let context = HistoryContext {
+            include_tags: ["c".to_string()].into(),
+            ..Default::default()
+        };
+        search::search(&mut root, pattern.clone(), &context).unwrap();
+
+        let (code, out) = history(&mut root, &["--pattern", &marker, "--format", "json"]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        let entries: serde_json::Value = serde_json::from_str(&out).unwrap();
+        // books "1" and "2" have the tag "c"
+        let entries = entries.as_array().unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0]["pattern"], pattern.as_str());
+        let id = entries[0]["id"].to_string();
+
+        let (_, out) = history(&mut root, &["--pattern", &marker, "--since", "1h"]);
+        assert_eq!(out.lines().count(), 3);
+        assert!(out.starts_with("ID"));
+        let (_, out) = history(&mut root, &["--pattern", &marker, "--since", "2999-01-01"]);
+        assert_eq!(out.lines().count(), 1);
+
+        let (code, out) = history(&mut root, &["rerun", &id]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert_eq!(out, "2:1:As armas e os barões assinalados,\n");
+
+        let cli = Cli::parse_from(["history", "rerun", "0"]);
+        assert!(matches!(
+            run(&mut root, &cli.args, &mut vec![]),
+            Err(CliError::Bookrab(BookrabError::InexistentHistoryEntry {
+                id: 0,
+                ..
+            }))
+        ));
+    }
+}
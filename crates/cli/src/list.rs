@@ -0,0 +1,287 @@
+//! `bookrab list`: lists the books (or their tags) for shells and scripts.
+use std::cmp::Reverse;
+use std::io::Write;
+use std::process::ExitCode;
+
+use bookrab_core::{
+    books::{
+        catalog::BookInfo, language::Language, BookListElement, FilterMode, Include, RootBookDir,
+        TagCount,
+    },
+    errors::BookrabError,
+};
+use clap::{Args, ValueEnum};
+use clap_complete::ArgValueCandidates;
+
+use crate::{completions, remote::Remote, CliError};
+
+#[derive(Args)]
+pub struct ListArgs {
+    /// Only lists books with this tag (repeat it for books with all of them)
+    #[arg(long, value_name = "TAG", add = ArgValueCandidates::new(completions::tags))]
+    tag: Vec<String>,
+    #[arg(long, value_enum, default_value_t = Format::Table)]
+    format: Format,
+    #[arg(long, value_enum, default_value_t = Sort::Title)]
+    sort: Sort,
+    /// Lists the tags of the books with the number of books that have
+    /// them (most used first) instead of the books
+    #[arg(long)]
+    tags: bool,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// Aligned columns with a header
+    Table,
+    /// An array of objects
+    Json,
+    /// Comma separated values with a header
+    Csv,
+}
+
+#[derive(Clone, Copy, PartialEq, ValueEnum)]
+enum Sort {
+    Title,
+    /// Largest books first
+    Size,
+}
+
+impl ListArgs {
+    fn include(&self) -> Include {
+        Include {
+            mode: FilterMode::All,
+            tags: self.tag.iter().cloned().collect(),
+        }
+    }
+}
+
+/// Lists the books of `root` (or their tags) in `out`.
+pub fn run(
+    root: &mut RootBookDir,
+    args: &ListArgs,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let include = args.include();
+    let books = root.list_filtered(|book| include.matches(book))?;
+    write_books(books, |book| root.book_info(&book.title), args, out)
+}
+
+/// Like [run], on the library of `remote`.
+pub fn run_remote(
+    remote: &Remote,
+    args: &ListArgs,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let include = args.include();
+    let mut books = remote.books()?;
+    books.retain(|book| include.matches(book));
+    write_books(books, |book| remote.book_info(book), args, out)
+}
+
+/// Writes `books` (or their tags) to `out`, with the
+/// metadata of `info_of` unless only tags are listed.
+fn write_books(
+    books: Vec<BookListElement>,
+    mut info_of: impl FnMut(&BookListElement) -> Result<Option<BookInfo>, BookrabError>,
+    args: &ListArgs,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    if args.tags {
+        let mut counts: Vec<TagCount> = vec![];
+        for tag in books.iter().flat_map(|book| &book.tags) {
+            match counts.iter_mut().find(|count| &count.tag == tag) {
+                Some(count) => count.count += 1,
+                None => counts.push(TagCount {
+                    tag: tag.clone(),
+                    count: 1,
+                }),
+            }
+        }
+        // like [RootBookDir::tag_counts]
+        counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        let rows = counts
+            .iter()
+            .map(|count| vec![count.tag.clone(), count.count.to_string()])
+            .collect();
+        write_rows(args.format, &["tag", "books"], rows, &counts, out)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let mut infos = vec![];
+    for book in books {
+        // the book may have been removed after it was listed
+        if let Some(info) = info_of(&book)? {
+            infos.push(info);
+        }
+    }
+    infos.sort_by(|a, b| a.title.cmp(&b.title));
+    if args.sort == Sort::Size {
+        infos.sort_by_key(|info| Reverse(info.size));
+    }
+    let rows = infos
+        .iter()
+        .map(|info| {
+            vec![
+                info.title.clone(),
+                info.size.to_string(),
+                info.language.map(language_code).unwrap_or_default(),
+                sorted_tags(info).join(","),
+            ]
+        })
+        .collect();
+    write_rows(
+        args.format,
+        &["title", "size", "language", "tags"],
+        rows,
+        &infos,
+        out,
+    )?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Code of `language` (e.g. `pt`), like it is stored.
+pub fn language_code(language: Language) -> String {
+    serde_json::to_value(language)
+        .ok()
+        .and_then(|code| code.as_str().map(str::to_string))
+        .unwrap_or_default()
+}
+
+fn sorted_tags(info: &BookInfo) -> Vec<&str> {
+    let mut tags: Vec<&str> = info.tags.iter().map(String::as_str).collect();
+    tags.sort();
+    tags
+}
+
+/// Writes `rows` as a table or as CSV, or `items` as JSON.
+pub fn write_rows(
+    format: Format,
+    header: &[&str],
+    rows: Vec<Vec<String>>,
+    items: &impl serde::Serialize,
+    out: &mut impl Write,
+) -> std::io::Result<()> {
+    match format {
+        Format::Json => {
+            serde_json::to_writer_pretty(&mut *out, items).map_err(std::io::Error::from)?;
+            writeln!(out)
+        }
+        Format::Csv => {
+            let header = header.iter().map(|name| name.to_string()).collect();
+            for row in std::iter::once(header).chain(rows) {
+                let fields: Vec<String> = row.iter().map(|field| csv_field(field)).collect();
+                writeln!(out, "{}", fields.join(","))?;
+            }
+            Ok(())
+        }
+        Format::Table => {
+            let header: Vec<String> = header.iter().map(|name| name.to_uppercase()).collect();
+            let mut widths: Vec<usize> = header.iter().map(|name| name.chars().count()).collect();
+            for row in &rows {
+                for (width, field) in widths.iter_mut().zip(row) {
+                    *width = (*width).max(field.chars().count());
+                }
+            }
+            for row in std::iter::once(header).chain(rows) {
+                let mut line = String::new();
+                for (index, (field, width)) in row.iter().zip(&widths).enumerate() {
+                    if index + 1 == row.len() {
+                        line.push_str(field);
+                    } else {
+                        let padding = width - field.chars().count();
+                        line.push_str(&format!("{field}{}  ", " ".repeat(padding)));
+                    }
+                }
+                writeln!(out, "{}", line.trim_end())?;
+            }
+            Ok(())
+        }
+    }
+}
+
+/// `field` quoted when it has commas, quotes or line breaks.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bookrab_core::books::test_utils::{root_for_tag_tests, DBCONNECTION};
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: ListArgs,
+    }
+
+    fn list(args: &[&str]) -> String {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = root_for_tag_tests(connection);
+        let cli = Cli::parse_from([&["list"], args].concat());
+        let mut out = vec![];
+        run(&mut root, &cli.args, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn table_and_csv() {
+        let table = list(&["--tag", "c"]);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("TITLE  SIZE"));
+        assert!(lines[1].starts_with("1  "));
+        assert!(lines[1].ends_with("a,b,c,d"));
+        assert!(lines[2].ends_with("a,b,c"));
+
+        let csv = list(&["--tag", "c", "--tag", "d", "--format", "csv"]);
+        let lines: Vec<&str> = csv.lines().collect();
+        assert_eq!(lines[0], "title,size,language,tags");
+        assert!(lines[1].starts_with("1,"));
+        assert!(lines[1].ends_with(",\"a,b,c,d\""));
+        assert_eq!(lines.len(), 2);
+    }
+
+    #[test]
+    fn sorted_by_size() {
+        let json = list(&["--sort", "size", "--format", "json"]);
+        let books: serde_json::Value = serde_json::from_str(&json).unwrap();
+        let sizes: Vec<u64> = books
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|book| book["size"].as_u64().unwrap())
+            .collect();
+        assert_eq!(sizes.len(), 4);
+        assert!(sizes.windows(2).all(|pair| pair[0] >= pair[1]));
+    }
+
+    #[test]
+    fn tag_counts() {
+        assert_eq!(
+            list(&["--tags", "--format", "csv"]),
+            "tag,books\na,4\nb,3\nc,2\nd,1\n"
+        );
+        assert_eq!(
+            list(&["--tags", "--tag", "c"]),
+            "TAG  BOOKS\na    2\nb    2\nc    2\nd    1\n"
+        );
+        let json = list(&["--tags", "--format", "json"]);
+        let tags: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(tags[0]["tag"], "a");
+        assert_eq!(tags[0]["count"], 4);
+    }
+
+    #[test]
+    fn quoted_csv_fields() {
+        assert_eq!(csv_field("a"), "a");
+        assert_eq!(csv_field("a,b"), "\"a,b\"");
+        assert_eq!(csv_field("o \"rei\""), "\"o \"\"rei\"\"\"");
+    }
+}
@@ -0,0 +1,292 @@
+//! Command line interface of bookrab. It uses the library of the
+//! configuration file (the same one as the TUI) directly, which is handy
+//! for scripts and cron jobs where neither the TUI nor HTTP is convenient.
+//! With `--server`, it uses the library of a bookrab server instead
+//! (see [remote]).
+mod archive;
+mod batch;
+mod completions;
+mod history;
+mod list;
+mod manage;
+mod remote;
+mod search;
+mod stats;
+mod upload;
+
+use std::io::{self, BufRead, IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use bookrab_core::{
+    books::RootBookDir,
+    config::{ensure_config_works, BookrabConfig},
+    database::{DbConnectionManager, DbPool},
+    errors::BookrabError,
+};
+use clap::{CommandFactory, Parser, Subcommand};
+use clap_complete::CompleteEnv;
+use remote::Remote;
+
+#[derive(Parser)]
+#[command(
+    name = "bookrab",
+    version,
+    about = "Searches and manages a bookrab library"
+)]
+struct Cli {
+    /// Uses the library of this bookrab server (e.g. http://localhost:8000)
+    /// instead of the one of the configuration file
+    #[arg(long, global = true, value_name = "URL", env = "BOOKRAB_SERVER")]
+    server: Option<String>,
+    /// Token sent to the server (see the login route of the REST API)
+    #[arg(
+        long,
+        global = true,
+        value_name = "KEY",
+        env = "BOOKRAB_API_KEY",
+        hide_env_values = true
+    )]
+    api_key: Option<String>,
+    /// Configuration file to use instead of the default one (e.g. to
+    /// keep several libraries on one machine)
+    #[arg(long, global = true, value_name = "PATH", env = "BOOKRAB_CONFIG")]
+    config: Option<PathBuf>,
+    #[command(subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Searches the books and prints the matching lines (like grep)
+    Search(search::SearchArgs),
+    /// Lists the books (or their tags) as a table, JSON or CSV
+    List(list::ListArgs),
+    /// Prints the number of books, their size and their words (by tag with --per-tag)
+    Stats(stats::StatsArgs),
+    /// Lists past searches (or runs one of them again with `rerun ID`)
+    History(history::HistoryArgs),
+    /// Uploads files as books (Markdown, HTML and EPUB are converted)
+    Upload(upload::UploadArgs),
+    /// Deletes a book (after asking, unless --yes is given)
+    Delete(manage::DeleteArgs),
+    /// Changes the title of a book
+    Rename(manage::RenameArgs),
+    /// Writes the whole library to a .tar.gz (for backups and migrations)
+    Export(archive::ExportArgs),
+    /// Restores a library written by `bookrab export`
+    Import(archive::ImportArgs),
+    /// Prints the completions of a shell (titles and tags included)
+    Completions(completions::CompletionsArgs),
+}
+
+/// Why a command failed.
+#[derive(Debug)]
+enum CliError {
+    Bookrab(BookrabError),
+    /// Writing the output failed
+    Io(io::Error),
+    /// The command doesn't work with `--server` (the REST API can't do it)
+    NotRemote(&'static str),
+}
+
+impl From<BookrabError> for CliError {
+    fn from(value: BookrabError) -> Self {
+        CliError::Bookrab(value)
+    }
+}
+
+impl From<io::Error> for CliError {
+    fn from(value: io::Error) -> Self {
+        CliError::Io(value)
+    }
+}
+
+/// Short message of `error` followed by its other fields
+/// (e.g. the path of a file or the problem of a regex), one per line.
+fn error_details(error: &BookrabError) -> String {
+    let json = serde_json::to_value(error).unwrap_or_default();
+    let Some(fields) = json
+        .as_object()
+        .and_then(|variant| variant.values().next())
+        .and_then(|fields| fields.as_object())
+    else {
+        return format!("{error:?}");
+    };
+    let mut text = match fields.get("error").and_then(|message| message.as_str()) {
+        Some(message) => message.to_string(),
+        None => format!("{error:?}"),
+    };
+    for (name, value) in fields.iter().filter(|(name, _)| *name != "error") {
+        let value = match value.as_str() {
+            Some(value) => value.to_string(),
+            None => value.to_string(),
+        };
+        text.push_str(&format!("\n  {name}: {value}"));
+    }
+    text
+}
+
+/// Opens the library of the configuration file (`config_file` or the
+/// default one) and passes it to `f`. Without a database (or when it
+/// can't be reached), the library works like there is none and `warn`
+/// is told why.
+fn open_library<T>(
+    config_file: Option<&Path>,
+    warn: impl Fn(&str),
+    f: impl FnOnce(&mut RootBookDir) -> T,
+) -> Result<T, String> {
+    let config: BookrabConfig = match config_file {
+        Some(path) => confy::load_path(path),
+        None => confy::load("bookrab", None),
+    }
+    .map_err(|e| format!("couldn't read the configuration: {e}"))?;
+    let config = ensure_config_works(config).map_err(|e| format!("bad configuration: {e}"))?;
+    let pool = config.database_url.as_ref().map(|url| {
+        DbPool::builder()
+            .max_size(1)
+            .build_unchecked(DbConnectionManager::new(url))
+    });
+    let mut connection = match pool.as_ref().map(|pool| pool.get()) {
+        Some(Ok(connection)) => Some(connection),
+        Some(Err(e)) => {
+            warn(&format!(
+                "couldn't connect to the database ({e}), going on without it"
+            ));
+            None
+        }
+        None => None,
+    };
+    let mut root = match connection.as_mut() {
+        Some(connection) => RootBookDir::new(config.clone(), connection),
+        None => RootBookDir::without_database(config.clone()),
+    };
+    Ok(f(&mut root))
+}
+
+/// Runs `command` on the library of `root`.
+fn run(
+    command: &Command,
+    root: &mut RootBookDir,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    match command {
+        Command::Search(args) => search::run(root, args, out),
+        Command::List(args) => list::run(root, args, out),
+        Command::Stats(args) => stats::run(root, args, out),
+        Command::History(args) => history::run(root, args, out),
+        Command::Upload(args) => upload::run(root, args, out, &mut io::stderr()),
+        Command::Delete(args) => {
+            let stdin = io::stdin();
+            let mut input = stdin.is_terminal().then(|| stdin.lock());
+            let input = input.as_mut().map(|input| input as &mut dyn BufRead);
+            manage::delete(root, args, input, out)
+        }
+        Command::Rename(args) => manage::rename(root, args, out),
+        Command::Export(args) => archive::export(root, args, out, &mut io::stderr()),
+        Command::Import(args) => archive::import(root, args, io::stdin().lock(), out),
+        Command::Completions(_) => unreachable!("completions don't need the library"),
+    }
+}
+
+/// Runs `command` on the library of `remote`.
+fn run_remote(
+    command: &Command,
+    remote: &Remote,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    match command {
+        Command::Search(args) => search::run_remote(remote, args, out),
+        Command::List(args) => list::run_remote(remote, args, out),
+        Command::Stats(args) => stats::run_remote(remote, args, out),
+        Command::History(args) => history::run_remote(remote, args, out),
+        Command::Upload(args) => upload::run_remote(remote, args, out, &mut io::stderr()),
+        Command::Delete(args) => {
+            let stdin = io::stdin();
+            let mut input = stdin.is_terminal().then(|| stdin.lock());
+            let input = input.as_mut().map(|input| input as &mut dyn BufRead);
+            manage::delete_remote(remote, args, input, out)
+        }
+        Command::Rename(args) => manage::rename_remote(remote, args, out),
+        Command::Export(args) => archive::export_remote(remote, args, out, &mut io::stderr()),
+        Command::Import(args) => archive::import_remote(remote, args, io::stdin(), out),
+        Command::Completions(_) => unreachable!("completions don't need the library"),
+    }
+}
+
+fn main() -> ExitCode {
+    // completes and exits when the shell asks (see [completions])
+    CompleteEnv::with_factory(Cli::command)
+        .var(completions::COMPLETE_VAR)
+        .complete();
+    let cli = Cli::parse();
+    let mut out = io::stdout().lock();
+    let result = match &cli.command {
+        Command::Completions(args) => {
+            let completer = std::env::args().next().unwrap_or("bookrab".to_string());
+            completions::run(args, &completer, &mut out)
+        }
+        command if cli.server.is_some() => {
+            let remote = Remote::new(cli.server.as_deref().unwrap(), cli.api_key.as_deref());
+            run_remote(command, &remote, &mut out)
+        }
+        command => {
+            let warn = |warning: &str| eprintln!("bookrab: {warning}");
+            match open_library(cli.config.as_deref(), warn, |root| {
+                run(command, root, &mut out)
+            }) {
+                Ok(result) => result,
+                Err(e) => {
+                    eprintln!("bookrab: {e}");
+                    return ExitCode::from(2);
+                }
+            }
+        }
+    };
+    let _ = out.flush();
+    match result {
+        Ok(code) => code,
+        // e.g. `bookrab search x | head`
+        Err(CliError::Io(e)) if e.kind() == io::ErrorKind::BrokenPipe => ExitCode::SUCCESS,
+        Err(CliError::Io(e)) => {
+            eprintln!("bookrab: couldn't write the output: {e}");
+            ExitCode::from(2)
+        }
+        Err(CliError::NotRemote(command)) => {
+            eprintln!("bookrab: {command} isn't available with --server");
+            ExitCode::from(2)
+        }
+        Err(CliError::Bookrab(e)) => {
+            eprintln!("bookrab: {}", error_details(&e));
+            ExitCode::from(2)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bookrab_core::books::test_utils::random_string;
+
+    use super::*;
+
+    #[test]
+    fn config_file() {
+        let folder = std::env::temp_dir().join(format!("bookrab-test-{}", random_string()));
+        let config = BookrabConfig {
+            book_path: folder.join("books"),
+            database_url: None,
+            ..Default::default()
+        };
+        let config_file = folder.join("bookrab.toml");
+        confy::store_path(&config_file, config).unwrap();
+        let book_path = open_library(
+            Some(&config_file),
+            |_| {},
+            |root| root.config().book_path.clone(),
+        )
+        .unwrap();
+        assert_eq!(book_path, folder.join("books"));
+        assert!(book_path.is_dir());
+        std::fs::remove_dir_all(folder).unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+//! `bookrab delete` and `bookrab rename`: maintenance of the library
+//! (see [RootBookDir::delete] and [RootBookDir::rename]).
+use std::io::{BufRead, Write};
+use std::process::ExitCode;
+
+use bookrab_core::{books::RootBookDir, errors::BookrabError};
+use clap::Args;
+use clap_complete::ArgValueCandidates;
+
+use crate::{completions, remote::Remote, CliError};
+
+#[derive(Args)]
+pub struct DeleteArgs {
+    /// Title of the book
+    #[arg(add = ArgValueCandidates::new(completions::titles))]
+    title: String,
+    /// Deletes the book without asking
+    #[arg(short, long)]
+    yes: bool,
+}
+
+#[derive(Args)]
+pub struct RenameArgs {
+    /// Current title of the book
+    #[arg(add = ArgValueCandidates::new(completions::titles))]
+    old: String,
+    /// New title of the book (there can't be a book with it yet)
+    new: String,
+}
+
+/// Deletes the book of `args`. Unless `--yes` is given, the deletion
+/// has to be confirmed with `input`, which is `None` when nobody
+/// can answer (e.g. stdin isn't a terminal). Exits with 1 if it
+/// isn't confirmed.
+pub fn delete(
+    root: &mut RootBookDir,
+    args: &DeleteArgs,
+    input: Option<&mut dyn BufRead>,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    if root.get_by_title(args.title.clone())?.is_none() {
+        return Err(BookrabError::InexistentBook {
+            error: (),
+            path: root.txt_path(&args.title)?,
+        }
+        .into());
+    }
+    if !confirmed(args, input, out)? {
+        return Ok(ExitCode::from(1));
+    }
+    root.delete(&args.title)?;
+    writeln!(out, "deleted \"{}\"", args.title)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Like [delete], on the library of `remote`.
+pub fn delete_remote(
+    remote: &Remote,
+    args: &DeleteArgs,
+    input: Option<&mut dyn BufRead>,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    if !remote.exists(&args.title)? {
+        return Err(BookrabError::InexistentBook {
+            error: (),
+            path: args.title.clone().into(),
+        }
+        .into());
+    }
+    if !confirmed(args, input, out)? {
+        return Ok(ExitCode::from(1));
+    }
+    remote.delete(&args.title)?;
+    writeln!(out, "deleted \"{}\"", args.title)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Whether the deletion of `args` was confirmed (see [delete]).
+fn confirmed(
+    args: &DeleteArgs,
+    input: Option<&mut dyn BufRead>,
+    out: &mut impl Write,
+) -> Result<bool, CliError> {
+    if args.yes {
+        return Ok(true);
+    }
+    let Some(input) = input else {
+        writeln!(out, "use --yes to delete \"{}\"", args.title)?;
+        return Ok(false);
+    };
+    write!(out, "delete \"{}\"? [y/N] ", args.title)?;
+    out.flush()?;
+    let mut answer = String::new();
+    input.read_line(&mut answer)?;
+    if !matches!(answer.trim().to_lowercase().as_str(), "y" | "yes") {
+        writeln!(out, "\"{}\" wasn't deleted", args.title)?;
+        return Ok(false);
+    }
+    Ok(true)
+}
+
+/// Renames the book of `args`.
+pub fn rename(
+    root: &mut RootBookDir,
+    args: &RenameArgs,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let book = root.rename(&args.old, &args.new)?;
+    writeln!(out, "renamed \"{}\" to \"{}\"", args.old, book.title)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Like [rename], on the library of `remote`.
+pub fn rename_remote(
+    remote: &Remote,
+    args: &RenameArgs,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let title = remote.rename(&args.old, &args.new)?;
+    writeln!(out, "renamed \"{}\" to \"{}\"", args.old, title)?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use bookrab_core::books::test_utils::{create_book_dir, s, DBCONNECTION};
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli<T: clap::Args> {
+        #[command(flatten)]
+        args: T,
+    }
+
+    fn delete_with(
+        root: &mut RootBookDir,
+        args: &[&str],
+        answer: Option<&str>,
+    ) -> (ExitCode, String) {
+        let cli = Cli::<DeleteArgs>::parse_from([&["delete"], args].concat());
+        let mut input = answer.map(|answer| answer.as_bytes());
+        let mut out = vec![];
+        let code = delete(
+            root,
+            &cli.args,
+            input.as_mut().map(|input| input as &mut dyn BufRead),
+            &mut out,
+        )
+        .unwrap();
+        (code, String::from_utf8(out).unwrap())
+    }
+
+    fn titles(root: &mut RootBookDir) -> Vec<String> {
+        let mut titles: Vec<String> = root.list().unwrap().into_iter().map(|b| b.title).collect();
+        titles.sort();
+        titles
+    }
+
+    #[test]
+    fn delete_asks_for_confirmation() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = create_book_dir(connection);
+        for title in ["1", "2", "3"] {
+            root.upload(title, "As armas", s(vec![])).unwrap();
+        }
+
+        let (code, out) = delete_with(&mut root, &["1"], Some("n\n"));
+        assert_eq!(code, ExitCode::from(1));
+        assert_eq!(out, "delete \"1\"? [y/N] \"1\" wasn't deleted\n");
+        let (code, _) = delete_with(&mut root, &["1"], None);
+        assert_eq!(code, ExitCode::from(1));
+        assert_eq!(titles(&mut root), ["1", "2", "3"]);
+
+        let (code, _) = delete_with(&mut root, &["1"], Some("y\n"));
+        assert_eq!(code, ExitCode::SUCCESS);
+        let (code, out) = delete_with(&mut root, &["2", "--yes"], None);
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert_eq!(out, "deleted \"2\"\n");
+        assert_eq!(titles(&mut root), ["3"]);
+
+        let cli = Cli::<DeleteArgs>::parse_from(["delete", "2", "--yes"]);
+        assert!(matches!(
+            delete(&mut root, &cli.args, None, &mut vec![]),
+            Err(CliError::Bookrab(BookrabError::InexistentBook { .. }))
+        ));
+    }
+
+    #[test]
+    fn rename_books() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = create_book_dir(connection);
+        root.upload("1", "As armas", s(vec!["a"])).unwrap();
+        root.upload("2", "e os barões", s(vec![])).unwrap();
+
+        let cli = Cli::<RenameArgs>::parse_from(["rename", "1", "lusiadas"]);
+        let mut out = vec![];
+        rename(&mut root, &cli.args, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "renamed \"1\" to \"lusiadas\"\n"
+        );
+        assert_eq!(titles(&mut root), ["2", "lusiadas"]);
+
+        let cli = Cli::<RenameArgs>::parse_from(["rename", "2", "lusiadas"]);
+        assert!(matches!(
+            rename(&mut root, &cli.args, &mut vec![]),
+            Err(CliError::Bookrab(BookrabError::BookAlreadyExists { .. }))
+        ));
+    }
+}
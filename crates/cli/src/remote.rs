@@ -0,0 +1,446 @@
+//! Remote mode (`--server URL`): commands work on the library of a
+//! bookrab REST API (see [bookrab_client]) instead of the local one,
+//! which is handy to manage a server without a shell on it.
+use std::collections::HashSet;
+use std::io::{Read, Write};
+
+use bookrab_client::{
+    AdminStats, Client, ClientError, HistoryEntry, HistoryQuery, ListQuery, SearchRequest,
+};
+use bookrab_core::{
+    books::{
+        archive::ImportSummary, catalog::BookInfo, history::HistoryContext, language::Language,
+        BookListElement, FilterMode, LineKind, ResultLine, SearchResults, Span, TagCount,
+    },
+    database::{history::SearchHistoryEntry, tags::TagList},
+    errors::BookrabError,
+};
+
+/// Server whose library is used.
+pub struct Remote {
+    /// e.g. `http://localhost:8000`
+    pub url: String,
+    client: Client,
+}
+
+impl Remote {
+    /// Server of `url`. `api_key` is a token of the server
+    /// (see the login route of the REST API).
+    pub fn new(url: &str, api_key: Option<&str>) -> Remote {
+        let mut client = Client::new(url);
+        if let Some(api_key) = api_key {
+            client = client.with_token(api_key);
+        }
+        Remote {
+            url: url.to_string(),
+            client,
+        }
+    }
+
+    fn error(&self, err: ClientError) -> BookrabError {
+        BookrabError::RemoteRequestFailed {
+            error: (),
+            url: self.url.clone(),
+            reason: err.reason(),
+        }
+    }
+
+    /// Every book of the server.
+    pub fn books(&self) -> Result<Vec<BookListElement>, BookrabError> {
+        let books = self
+            .client
+            .list_all(&ListQuery::default())
+            .map_err(|e| self.error(e))?;
+        Ok(books
+            .into_iter()
+            .map(|book| BookListElement {
+                title: book.title,
+                tags: book.tags,
+                language: book.language.and_then(language),
+            })
+            .collect())
+    }
+
+    /// Tags with the number of books that have them (most used first).
+    pub fn tag_counts(&self) -> Result<Vec<TagCount>, BookrabError> {
+        let counts = self.client.tags().map_err(|e| self.error(e))?;
+        Ok(counts
+            .into_iter()
+            .map(|count| TagCount {
+                tag: count.tag,
+                count: count.count,
+            })
+            .collect())
+    }
+
+    /// Metadata of `book` (`None` if it was removed).
+    pub fn book_info(&self, book: &BookListElement) -> Result<Option<BookInfo>, BookrabError> {
+        let info = self.client.info(&book.title).map_err(|e| self.error(e))?;
+        Ok(info.map(|info| BookInfo {
+            title: book.title.clone(),
+            tags: book.tags.clone(),
+            language: book.language,
+            checksum: info.checksum,
+            size: info.size,
+        }))
+    }
+
+    /// Searches `pattern` in every book in a single request, like
+    /// [crate::search::search]. The server doesn't send line
+    /// numbers, so the lines don't have them.
+    pub fn search(
+        &self,
+        pattern: String,
+        context: &HistoryContext,
+    ) -> Result<Vec<SearchResults>, BookrabError> {
+        let mode = |mode: &FilterMode| match mode {
+            FilterMode::All => bookrab_client::FilterMode::All,
+            FilterMode::Any => bookrab_client::FilterMode::Any,
+        };
+        let tags = |tags: &HashSet<String>| Some(tags.iter().cloned().collect());
+        let request = SearchRequest {
+            pattern,
+            before_context: Some(context.before_context),
+            after_context: Some(context.after_context),
+            case_insensitive: Some(context.case_insensitive),
+            case_smart: Some(context.case_smart),
+            ignore_accents: Some(context.ignore_accents),
+            include_tags: tags(&context.include_tags),
+            include_mode: Some(mode(&context.include_mode)),
+            exclude_tags: tags(&context.exclude_tags),
+            exclude_mode: Some(mode(&context.exclude_mode)),
+            ..Default::default()
+        };
+        let books = self
+            .client
+            .search_all(&request)
+            .map_err(|e| self.error(e))?;
+        Ok(books
+            .into_iter()
+            .filter(|book| !book.results.is_empty())
+            .map(|book| SearchResults {
+                title: book.title,
+                lines: book.results.iter().flat_map(|r| result_lines(r)).collect(),
+                results: book.results,
+                score: book.score,
+                duration_ms: book.duration_ms,
+            })
+            .collect())
+    }
+
+    /// Entries of the history of the server that match `query`
+    /// (newest first). Only `limit` entries are asked if it is set.
+    pub fn history(
+        &self,
+        query: HistoryQuery,
+        limit: Option<i64>,
+    ) -> Result<Vec<SearchHistoryEntry>, BookrabError> {
+        let entries = self
+            .client
+            .history_all(&query, limit)
+            .map_err(|e| self.error(e))?;
+        Ok(entries.into_iter().map(history_entry).collect())
+    }
+
+    /// Uploads `txt` as the book `title`.
+    pub fn upload(
+        &self,
+        title: &str,
+        txt: &str,
+        tags: &HashSet<String>,
+    ) -> Result<(), BookrabError> {
+        let tags: Vec<&str> = tags.iter().map(String::as_str).collect();
+        self.client
+            .upload(title, txt, &tags)
+            .map_err(|e| self.error(e))
+    }
+
+    /// Whether the server has a book called `title`.
+    pub fn exists(&self, title: &str) -> Result<bool, BookrabError> {
+        let info = self.client.info(title).map_err(|e| self.error(e))?;
+        Ok(info.is_some())
+    }
+
+    /// Deletes a book of the server.
+    pub fn delete(&self, title: &str) -> Result<(), BookrabError> {
+        self.client.delete(title).map_err(|e| self.error(e))
+    }
+
+    /// Statistics of the library of the server (see [Client::admin_stats]).
+    pub fn stats(&self) -> Result<AdminStats, BookrabError> {
+        self.client.admin_stats().map_err(|e| self.error(e))
+    }
+
+    /// Changes the title of a book and returns the new one.
+    pub fn rename(&self, title: &str, new_title: &str) -> Result<String, BookrabError> {
+        let book = self
+            .client
+            .rename(title, new_title)
+            .map_err(|e| self.error(e))?;
+        Ok(book.title)
+    }
+
+    /// Writes the library of the server to `writer` (see [Client::export]).
+    pub fn export(&self, writer: &mut impl Write, history: bool) -> Result<(), BookrabError> {
+        self.client
+            .export(history, writer)
+            .map_err(|e| self.error(e))?;
+        Ok(())
+    }
+
+    /// Sends an archive made by `bookrab export` to the server.
+    pub fn import(
+        &self,
+        archive: impl Read + Send + 'static,
+    ) -> Result<ImportSummary, BookrabError> {
+        let summary = self.client.import(archive).map_err(|e| self.error(e))?;
+        Ok(ImportSummary {
+            books: summary.books,
+            history_entries: summary.history_entries,
+            ..Default::default()
+        })
+    }
+}
+
+/// [Language] of the server (both are stored by their codes).
+pub fn language(language: bookrab_client::Language) -> Option<Language> {
+    serde_json::to_value(language)
+        .and_then(serde_json::from_value)
+        .ok()
+}
+
+/// Lines of a snippet of the server. Lines with `[matched]` markers
+/// are matches and the others are context before or after them.
+fn result_lines(snippet: &str) -> Vec<ResultLine> {
+    let mut matched = false;
+    let mut lines = vec![];
+    for line in snippet.lines() {
+        let mut text = String::new();
+        let mut spans = vec![];
+        let mut rest = line;
+        while let Some((before, after)) = rest.split_once("[matched]") {
+            text.push_str(before);
+            let (found, after) = after.split_once("[/matched]").unwrap_or((after, ""));
+            let start = text.len();
+            text.push_str(found);
+            spans.push(Span {
+                start,
+                end: text.len(),
+            });
+            rest = after;
+        }
+        text.push_str(rest);
+        let kind = if !spans.is_empty() {
+            matched = true;
+            LineKind::Match
+        } else if matched {
+            LineKind::After
+        } else {
+            LineKind::Before
+        };
+        lines.push(ResultLine {
+            line_number: None,
+            byte_offset: 0,
+            text,
+            spans,
+            kind,
+        });
+    }
+    lines
+}
+
+fn history_entry(entry: HistoryEntry) -> SearchHistoryEntry {
+    let mode = |mode: bookrab_client::FilterMode| match mode {
+        bookrab_client::FilterMode::All => "All".to_string(),
+        bookrab_client::FilterMode::Any => "Any".to_string(),
+    };
+    let tags = |tags: Vec<String>| TagList(tags.into_iter().map(Some).collect());
+    SearchHistoryEntry {
+        id: entry.id,
+        title: entry.title,
+        pattern: entry.pattern,
+        date: entry.date,
+        include_mode: mode(entry.include_mode),
+        include_tags: tags(entry.include_tags),
+        exclude_mode: mode(entry.exclude_mode),
+        exclude_tags: tags(entry.exclude_tags),
+        case_insensitive: entry.case_insensitive,
+        case_smart: entry.case_smart,
+        ignore_accents: entry.ignore_accents,
+        before_context: entry.before_context,
+        after_context: entry.after_context,
+        match_count: entry.match_count,
+        duration_ms: entry.duration_ms,
+        hits: entry.hits,
+        // entries of the server are of the owner of the token
+        user_id: 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use super::*;
+
+    /// Server that answers `requests` requests with `respond(method and path)`
+    /// (a status, extra headers and a JSON body).
+    fn serve(requests: usize, respond: fn(&str) -> (u16, &'static str, String)) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let (method, rest) = request_line.split_once(' ').unwrap();
+                let path = rest.split(' ').next().unwrap();
+                let (status, headers, body) = respond(&format!("{method} {path}"));
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} X\r\nContent-Type: application/json\r\n{headers}\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    #[test]
+    fn lines_of_snippets() {
+        let lines = result_lines(
+            "antes\nAs [matched]armas[/matched] e os [matched]barões[/matched]\ndepois\n",
+        );
+        let kinds: Vec<LineKind> = lines.iter().map(|line| line.kind).collect();
+        assert_eq!(kinds, [LineKind::Before, LineKind::Match, LineKind::After]);
+        assert_eq!(lines[1].text, "As armas e os barões");
+        assert_eq!(
+            lines[1].spans,
+            [Span { start: 3, end: 8 }, Span { start: 14, end: 21 }]
+        );
+        assert!(lines.iter().all(|line| line.line_number.is_none()));
+    }
+
+    #[test]
+    fn books_and_their_info() {
+        let url = serve(3, |request| match request {
+            "GET /v1/books/list?limit=100&offset=0" => (
+                200,
+                "",
+                r#"{"items": [{"title": "os lusíadas", "tags": ["a"], "language": "pt"}],
+                    "total": 1, "next": null}"#
+                    .to_string(),
+            ),
+            "HEAD /v1/books/os%20lus%C3%ADadas" => (
+                200,
+                "x-book-size: 42\r\nx-book-tag-count: 1\r\nx-book-checksum: abc\r\n",
+                String::new(),
+            ),
+            _ => (
+                404,
+                "",
+                r#"{"InexistentBook": {"error": "E0004"}}"#.to_string(),
+            ),
+        });
+        let remote = Remote::new(&url, Some("key"));
+        let books = remote.books().unwrap();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].language, Some(Language::Portuguese));
+        let info = remote.book_info(&books[0]).unwrap().unwrap();
+        assert_eq!(info.size, 42);
+        assert_eq!(info.checksum, "abc");
+        let missing = BookListElement {
+            title: "x".to_string(),
+            tags: HashSet::new(),
+            language: None,
+        };
+        assert_eq!(remote.book_info(&missing).unwrap(), None);
+    }
+
+    #[test]
+    fn search_of_the_server() {
+        // every book comes in a single response
+        let url = serve(1, |request| {
+            assert_eq!(request, "POST /v1/books/search");
+            let book = r#"{"title": "2", "results": ["As armas e os [matched]barões[/matched] assinalados,\n"], "score": 1.0, "duration_ms": 3, "truncated": false}"#;
+            let empty = r#"{"title": "3", "results": [], "score": 0.0, "duration_ms": 1, "truncated": false}"#;
+            (200, "", format!("{empty}\n{book}\n"))
+        });
+        let remote = Remote::new(&url, None);
+        let results = remote
+            .search("barões".to_string(), &Default::default())
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].match_count(), 1);
+        assert_eq!(
+            results[0].lines[0].text,
+            "As armas e os barões assinalados,"
+        );
+    }
+
+    #[test]
+    fn delete_and_stats() {
+        let url = serve(2, |request| match request {
+            "DELETE /v1/books/os%20lus%C3%ADadas" => (204, "", String::new()),
+            "GET /v1/admin/stats" => (
+                200,
+                "",
+                r#"{"library": {"books": 2, "languages": [{"language": "pt", "count": 2}],
+                    "tags": [{"tag": "epic", "count": 1}]},
+                    "history": null, "index": null,
+                    "storage": {"text_bytes": null, "disk_bytes": 4096}}"#
+                    .to_string(),
+            ),
+            _ => (404, "", "{}".to_string()),
+        });
+        let remote = Remote::new(&url, None);
+        remote.delete("os lusíadas").unwrap();
+        let stats = remote.stats().unwrap();
+        assert_eq!(stats.library.books, 2);
+        assert_eq!(
+            stats.library.languages[0].language.and_then(language),
+            Some(Language::Portuguese)
+        );
+        assert_eq!(stats.storage.disk_bytes, 4096);
+    }
+
+    #[test]
+    fn errors_of_the_server() {
+        let url = serve(1, |_| {
+            let body = r#"{"Unauthorized": {"error": "E0024: authentication required.",
+                "reason": "the token expired"}}"#;
+            (401, "", body.to_string())
+        });
+        let Err(BookrabError::RemoteRequestFailed { reason, .. }) =
+            Remote::new(&url, Some("old")).history(HistoryQuery::default(), Some(10))
+        else {
+            panic!("the request should fail");
+        };
+        assert_eq!(
+            reason,
+            "the server answered 401 Unauthorized: E0024: authentication required."
+        );
+    }
+}
@@ -0,0 +1,441 @@
+//! `bookrab search`: searches the books like [RootBookDir::search_by_tags]
+//! (or many patterns at once, see [batch]).
+use std::collections::HashSet;
+use std::io::{self, Write};
+use std::path::PathBuf;
+use std::process::ExitCode;
+use std::time::Duration;
+
+use bookrab_core::{
+    books::{
+        history::HistoryContext, Exclude, FilterMode, Include, LineKind, ResultLine, RootBookDir,
+        SearchOptions, SearchResults,
+    },
+    database::{DbConnectionManager, DbPool},
+    errors::BookrabError,
+};
+use clap::{Args, ValueEnum};
+use clap_complete::ArgValueCandidates;
+use grep_regex::RegexMatcherBuilder;
+use grep_searcher::SearcherBuilder;
+
+use crate::{batch, completions, remote::Remote, CliError};
+
+#[derive(Args)]
+pub struct SearchArgs {
+    /// Regular expression searched in the books
+    #[arg(required_unless_present = "patterns_file")]
+    pattern: Option<String>,
+    /// Searches each line of this file (`-` reads stdin) instead of
+    /// PATTERN and prints a JSON record per pattern, one per line
+    #[arg(long, value_name = "PATH", conflicts_with_all = ["pattern", "format"])]
+    patterns_file: Option<PathBuf>,
+    /// Patterns of --patterns-file searched at the same time [default: 1]
+    #[arg(
+        short,
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..),
+        conflicts_with = "pattern"
+    )]
+    jobs: Option<u32>,
+    /// Only searches books with this tag (repeat it for more tags)
+    #[arg(long = "include", value_name = "TAG", add = ArgValueCandidates::new(completions::tags))]
+    include: Vec<String>,
+    /// Whether books need all or any of the included tags
+    #[arg(long, value_enum, default_value_t = Mode::All)]
+    include_mode: Mode,
+    /// Skips books with this tag (repeat it for more tags)
+    #[arg(long = "exclude", value_name = "TAG", add = ArgValueCandidates::new(completions::tags))]
+    exclude: Vec<String>,
+    /// Whether books are skipped with all or any of the excluded tags
+    #[arg(long, value_enum, default_value_t = Mode::Any)]
+    exclude_mode: Mode,
+    /// Lines printed before each match
+    #[arg(short = 'B', long, value_name = "LINES", default_value_t = 0)]
+    before: usize,
+    /// Lines printed after each match
+    #[arg(short = 'A', long, value_name = "LINES", default_value_t = 0)]
+    after: usize,
+    /// Lines printed before and after each match
+    #[arg(short = 'C', long, value_name = "LINES")]
+    context: Option<usize>,
+    /// Ignores the case of the letters
+    #[arg(short = 'i', long)]
+    ignore_case: bool,
+    /// Ignores the case unless the pattern has uppercase letters
+    #[arg(short = 'S', long)]
+    smart_case: bool,
+    /// "vituperio" matches "vitupério" and vice-versa
+    #[arg(long)]
+    ignore_accents: bool,
+    #[arg(long, value_enum, default_value_t = Format::Grep)]
+    format: Format,
+}
+
+/// [FilterMode] of the command line.
+#[derive(Clone, Copy, ValueEnum)]
+enum Mode {
+    All,
+    Any,
+}
+
+impl From<Mode> for FilterMode {
+    fn from(mode: Mode) -> Self {
+        match mode {
+            Mode::All => FilterMode::All,
+            Mode::Any => FilterMode::Any,
+        }
+    }
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum Format {
+    /// `title:line:text` for matches and `title-line-text` for context
+    Grep,
+    /// An array with the books that matched and their lines
+    Json,
+}
+
+/// Book of the JSON output.
+#[derive(serde::Serialize)]
+pub struct JsonBook<'a> {
+    title: &'a str,
+    score: f64,
+    match_count: usize,
+    lines: &'a [ResultLine],
+}
+
+impl<'a> From<&'a SearchResults> for JsonBook<'a> {
+    fn from(result: &'a SearchResults) -> Self {
+        JsonBook {
+            title: &result.title,
+            score: result.score,
+            match_count: result.match_count(),
+            lines: &result.lines,
+        }
+    }
+}
+
+impl SearchArgs {
+    /// PATTERN (which is only missing with --patterns-file).
+    fn pattern(&self) -> String {
+        self.pattern.clone().unwrap_or_default()
+    }
+
+    /// Filters and options of the search.
+    fn context(&self) -> HistoryContext {
+        let tags = |tags: &[String]| tags.iter().cloned().collect::<HashSet<String>>();
+        HistoryContext {
+            include_mode: self.include_mode.into(),
+            include_tags: tags(&self.include),
+            exclude_mode: self.exclude_mode.into(),
+            exclude_tags: tags(&self.exclude),
+            case_insensitive: self.ignore_case,
+            case_smart: self.smart_case,
+            ignore_accents: self.ignore_accents,
+            before_context: self.context.unwrap_or(self.before),
+            after_context: self.context.unwrap_or(self.after),
+        }
+    }
+}
+
+/// Searches the books and writes the results to `out`.
+/// Like grep, it exits with 1 when nothing matches.
+pub fn run(
+    root: &mut RootBookDir,
+    args: &SearchArgs,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let context = args.context();
+    if let Some(path) = &args.patterns_file {
+        let patterns = batch::read_patterns(path, io::stdin())?;
+        let jobs = args.jobs.unwrap_or(1);
+        let config = root.config().clone();
+        // the other workers need their own connections
+        let pool = match (&root.connection, &config.database_url) {
+            (Some(_), Some(url)) if jobs > 1 => Some(
+                DbPool::builder()
+                    .max_size(jobs - 1)
+                    .connection_timeout(Duration::from_secs(5))
+                    .build_unchecked(DbConnectionManager::new(url)),
+            ),
+            _ => None,
+        };
+        let mut connections = vec![];
+        if let Some(pool) = &pool {
+            // fewer workers are used if the database refuses connections
+            while let Ok(connection) = pool.get() {
+                connections.push(connection);
+                if connections.len() + 1 == jobs as usize {
+                    break;
+                }
+            }
+        }
+        let first = match root.connection.as_deref_mut() {
+            Some(connection) => RootBookDir::new(config.clone(), connection),
+            None => RootBookDir::without_database(config.clone()),
+        };
+        let others: Vec<RootBookDir> = match &pool {
+            Some(_) => connections
+                .iter_mut()
+                .map(|connection| RootBookDir::new(config.clone(), connection))
+                .collect(),
+            None => (1..jobs)
+                .map(|_| RootBookDir::without_database(config.clone()))
+                .collect(),
+        };
+        let workers = std::iter::once(first).chain(others).collect();
+        let search = |root: &mut RootBookDir, pattern| search(root, pattern, &context);
+        return batch::run(workers, search, &patterns, out);
+    }
+    let results = search(root, args.pattern(), &context)?;
+    write_results(args.format, &results, &context, out)
+}
+
+/// Like [run], on the library of `remote`.
+pub fn run_remote(
+    remote: &Remote,
+    args: &SearchArgs,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let context = args.context();
+    if let Some(path) = &args.patterns_file {
+        let patterns = batch::read_patterns(path, io::stdin())?;
+        let workers = vec![remote; args.jobs.unwrap_or(1) as usize];
+        let search = |remote: &mut &Remote, pattern| remote.search(pattern, &context);
+        return batch::run(workers, search, &patterns, out);
+    }
+    let results = remote.search(args.pattern(), &context)?;
+    write_results(args.format, &results, &context, out)
+}
+
+/// Searches `pattern` with the filters and options of `context`.
+/// Only the books that matched are returned.
+pub fn search(
+    root: &mut RootBookDir,
+    pattern: String,
+    context: &HistoryContext,
+) -> Result<Vec<SearchResults>, BookrabError> {
+    let searcher = SearcherBuilder::new()
+        .before_context(context.before_context)
+        .after_context(context.after_context)
+        .line_number(true)
+        .build();
+    let mut matcher_builder = RegexMatcherBuilder::new();
+    matcher_builder
+        .case_insensitive(context.case_insensitive)
+        .case_smart(context.case_smart);
+    let options = SearchOptions {
+        ignore_accents: context.ignore_accents,
+        history: HistoryContext {
+            case_insensitive: context.case_insensitive,
+            case_smart: context.case_smart,
+            ..Default::default()
+        },
+        ..Default::default()
+    };
+    let results = root.search_by_tags(
+        &Include {
+            mode: context.include_mode.clone(),
+            tags: context.include_tags.clone(),
+        },
+        &Exclude {
+            mode: context.exclude_mode.clone(),
+            tags: context.exclude_tags.clone(),
+        },
+        pattern,
+        searcher,
+        matcher_builder,
+        &options,
+    )?;
+    Ok(results
+        .into_iter()
+        .filter(|result| !result.results.is_empty())
+        .collect())
+}
+
+/// Writes `results` (of a search with `context`) in `format`.
+/// Exits with 1 when there are no results.
+pub fn write_results(
+    format: Format,
+    results: &[SearchResults],
+    context: &HistoryContext,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    match format {
+        Format::Grep => write_grep(
+            results,
+            context.before_context > 0 || context.after_context > 0,
+            out,
+        )?,
+        Format::Json => {
+            let books: Vec<JsonBook> = results.iter().map(JsonBook::from).collect();
+            serde_json::to_writer_pretty(&mut *out, &books).map_err(std::io::Error::from)?;
+            writeln!(out)?;
+        }
+    }
+    Ok(if results.is_empty() {
+        ExitCode::from(1)
+    } else {
+        ExitCode::SUCCESS
+    })
+}
+
+/// Writes the lines of `results` like `grep -Hn`, with `--` between
+/// the snippets if `separate` is set.
+fn write_grep(
+    results: &[SearchResults],
+    separate: bool,
+    out: &mut impl Write,
+) -> std::io::Result<()> {
+    let mut first = true;
+    for result in results {
+        // each line of the snippets is a line of `lines`
+        let mut lines = result.lines.iter();
+        for snippet in &result.results {
+            if separate && !first {
+                writeln!(out, "--")?;
+            }
+            first = false;
+            for line in lines.by_ref().take(snippet.lines().count()) {
+                let separator = if line.kind == LineKind::Match {
+                    ':'
+                } else {
+                    '-'
+                };
+                match line.line_number {
+                    Some(number) => writeln!(
+                        out,
+                        "{}{separator}{number}{separator}{}",
+                        result.title, line.text
+                    )?,
+                    None => writeln!(out, "{}{separator}{}", result.title, line.text)?,
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env::temp_dir, fs};
+
+    use bookrab_core::books::test_utils::{random_string, root_for_tag_tests, DBCONNECTION};
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: SearchArgs,
+    }
+
+    fn search(args: &[&str]) -> (ExitCode, String) {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = root_for_tag_tests(connection);
+        let cli = Cli::parse_from([&["search"], args].concat());
+        let mut out = vec![];
+        let code = run(&mut root, &cli.args, &mut out).unwrap();
+        (code, String::from_utf8(out).unwrap())
+    }
+
+    #[test]
+    fn grep_output() {
+        let (code, out) = search(&["barões"]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert_eq!(out, "2:1:As armas e os barões assinalados,\n");
+
+        // "1" is the only book with the tag "d"
+        let (code, out) = search(&["armas", "--include", "d", "-A", "1"]);
+        assert_eq!(code, ExitCode::SUCCESS);
+        assert!(out.starts_with("1:"));
+        assert!(out.lines().any(|line| line.starts_with("1-")));
+        assert!(!out.contains("\n2:"));
+    }
+
+    #[test]
+    fn json_output() {
+        let (_, out) = search(&["barões", "--format", "json"]);
+        let books: serde_json::Value = serde_json::from_str(&out).unwrap();
+        assert_eq!(books[0]["title"], "2");
+        assert_eq!(books[0]["match_count"], 1);
+        assert_eq!(books[0]["lines"][0]["line_number"], 1);
+        assert_eq!(books[0]["lines"][0]["kind"], "match");
+    }
+
+    #[test]
+    fn nothing_matches() {
+        let (code, out) = search(&["barões", "--exclude", "a"]);
+        assert_eq!(code, ExitCode::from(1));
+        assert!(out.is_empty());
+    }
+
+    #[test]
+    fn patterns_file() {
+        let path = temp_dir().join(format!("bookrab-patterns-{}", random_string()));
+        fs::write(&path, "barões\n\n(\nzzzqqq\n").unwrap();
+        for jobs in ["1", "3"] {
+            let (code, out) = search(&["--patterns-file", path.to_str().unwrap(), "-j", jobs]);
+            // "(" isn't a valid regex
+            assert_eq!(code, ExitCode::from(2));
+            let records: Vec<serde_json::Value> = out
+                .lines()
+                .map(|line| serde_json::from_str(line).unwrap())
+                .collect();
+            assert_eq!(records.len(), 3);
+            assert_eq!(records[0]["pattern"], "barões");
+            assert_eq!(records[0]["match_count"], 1);
+            assert_eq!(records[0]["books"][0]["title"], "2");
+            assert_eq!(records[1]["pattern"], "(");
+            assert!(records[1]["error"]["RegexProblem"].is_object());
+            assert_eq!(records[2]["match_count"], 0);
+            assert_eq!(records[2]["books"], serde_json::json!([]));
+        }
+        fs::remove_file(path).unwrap();
+
+        let cli = Cli::try_parse_from(["search", "x", "--patterns-file", "p"]);
+        assert!(cli.is_err());
+        assert!(Cli::try_parse_from(["search", "x", "-j", "2"]).is_err());
+    }
+
+    #[test]
+    fn separators_between_snippets() {
+        let result = |title: &str, snippets: &[&str], numbers: &[(u64, LineKind)]| SearchResults {
+            title: title.to_string(),
+            results: snippets.iter().map(|s| s.to_string()).collect(),
+            score: 0.0,
+            duration_ms: 0,
+            lines: numbers
+                .iter()
+                .map(|(number, kind)| ResultLine {
+                    line_number: Some(*number),
+                    byte_offset: 0,
+                    text: format!("line {number}"),
+                    spans: vec![],
+                    kind: *kind,
+                })
+                .collect(),
+        };
+        let results = [
+            result(
+                "a",
+                &["x\ny\n", "z\n"],
+                &[
+                    (1, LineKind::Match),
+                    (2, LineKind::After),
+                    (9, LineKind::Match),
+                ],
+            ),
+            result("b", &["w\n"], &[(3, LineKind::Match)]),
+        ];
+        let mut out = vec![];
+        write_grep(&results, true, &mut out).unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "a:1:line 1\na-2-line 2\n--\na:9:line 9\n--\nb:3:line 3\n"
+        );
+    }
+}
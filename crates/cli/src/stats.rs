@@ -0,0 +1,189 @@
+//! `bookrab stats`: size of the library (see [RootBookDir::text_stats]).
+//! With `--server`, the statistics of the server are used, which
+//! don't count words (see [bookrab_client::Client::admin_stats]).
+use std::io::Write;
+use std::process::ExitCode;
+
+use bookrab_core::books::{stats::LanguageCount, RootBookDir};
+use clap::Args;
+
+use crate::{list, remote, remote::Remote, CliError};
+
+#[derive(Args)]
+pub struct StatsArgs {
+    /// Prints the books, size and words of each tag (most used first)
+    #[arg(long)]
+    per_tag: bool,
+    #[arg(long, value_enum, default_value_t = list::Format::Table)]
+    format: list::Format,
+}
+
+/// Totals of the JSON output.
+#[derive(serde::Serialize)]
+struct Summary {
+    books: usize,
+    size: u64,
+    /// `None` with `--server`
+    words: Option<u64>,
+    languages: Vec<LanguageCount>,
+}
+
+/// Writes the statistics of the library of `root` to `out`.
+/// Every text is read to count its words.
+pub fn run(
+    root: &mut RootBookDir,
+    args: &StatsArgs,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let stats = root.text_stats()?;
+    if args.per_tag {
+        let rows = stats
+            .tags
+            .iter()
+            .map(|tag| {
+                vec![
+                    tag.tag.clone(),
+                    tag.books.to_string(),
+                    tag.size.to_string(),
+                    tag.words.to_string(),
+                ]
+            })
+            .collect();
+        list::write_rows(
+            args.format,
+            &["tag", "books", "size", "words"],
+            rows,
+            &stats.tags,
+            out,
+        )?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let summary = Summary {
+        books: stats.books,
+        size: stats.size,
+        words: Some(stats.words),
+        languages: root.library_stats()?.languages,
+    };
+    write_summary(args, &summary, out)
+}
+
+/// Like [run], with the statistics of `remote`. The size is the one of
+/// the texts (or of the book folders when the server has no database)
+/// and `--per-tag` only counts books.
+pub fn run_remote(
+    remote: &Remote,
+    args: &StatsArgs,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let stats = remote.stats()?;
+    if args.per_tag {
+        let rows = stats
+            .library
+            .tags
+            .iter()
+            .map(|tag| vec![tag.tag.clone(), tag.count.to_string()])
+            .collect();
+        let counts: Vec<_> = stats
+            .library
+            .tags
+            .iter()
+            .map(|tag| serde_json::json!({ "tag": tag.tag, "books": tag.count }))
+            .collect();
+        list::write_rows(args.format, &["tag", "books"], rows, &counts, out)?;
+        return Ok(ExitCode::SUCCESS);
+    }
+    let summary = Summary {
+        books: stats.library.books,
+        size: stats.storage.text_bytes.unwrap_or(stats.storage.disk_bytes),
+        words: None,
+        languages: stats
+            .library
+            .languages
+            .into_iter()
+            .map(|count| LanguageCount {
+                language: count.language.and_then(remote::language),
+                count: count.count,
+            })
+            .collect(),
+    };
+    write_summary(args, &summary, out)
+}
+
+fn write_summary(
+    args: &StatsArgs,
+    summary: &Summary,
+    out: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let languages: Vec<String> = summary
+        .languages
+        .iter()
+        .map(|count| {
+            let code = count.language.map(list::language_code);
+            format!("{}:{}", code.as_deref().unwrap_or("?"), count.count)
+        })
+        .collect();
+    let row = vec![
+        summary.books.to_string(),
+        summary.size.to_string(),
+        summary
+            .words
+            .map(|words| words.to_string())
+            .unwrap_or_else(|| "?".to_string()),
+        languages.join(","),
+    ];
+    list::write_rows(
+        args.format,
+        &["books", "size", "words", "languages"],
+        vec![row],
+        summary,
+        out,
+    )?;
+    Ok(ExitCode::SUCCESS)
+}
+
+#[cfg(test)]
+mod tests {
+    use bookrab_core::books::test_utils::{create_book_dir, s, DBCONNECTION};
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: StatsArgs,
+    }
+
+    fn stats(root: &mut RootBookDir, args: &[&str]) -> String {
+        let cli = Cli::parse_from([&["stats"], args].concat());
+        let mut out = vec![];
+        run(root, &cli.args, &mut out).unwrap();
+        String::from_utf8(out).unwrap()
+    }
+
+    #[test]
+    fn totals_and_tags() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = create_book_dir(connection);
+        root.upload("1", "As armas e os", s(vec!["a", "b"]))
+            .unwrap();
+        root.upload("2", "barões", s(vec!["a"])).unwrap();
+
+        let table = stats(&mut root, &[]);
+        let lines: Vec<&str> = table.lines().collect();
+        assert!(lines[0].starts_with("BOOKS  SIZE  WORDS  LANGUAGES"));
+        assert!(lines[1].starts_with("2      20    5"));
+
+        let json: serde_json::Value =
+            serde_json::from_str(&stats(&mut root, &["--format", "json"])).unwrap();
+        assert_eq!(json["words"], 5);
+        let languages = json["languages"].as_array().unwrap();
+        let counts: u64 = languages.iter().map(|l| l["count"].as_u64().unwrap()).sum();
+        assert_eq!(counts, 2);
+
+        assert_eq!(
+            stats(&mut root, &["--per-tag", "--format", "csv"]),
+            "tag,books,size,words\na,2,20,5\nb,1,13,4\n"
+        );
+    }
+}
@@ -0,0 +1,319 @@
+//! `bookrab upload`: uploads files as books, converting them like the
+//! REST API does (see [import]).
+use std::collections::HashSet;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use bookrab_core::{
+    books::{
+        import::{self, SourceFormat},
+        validation::FieldError,
+        RootBookDir,
+    },
+    errors::BookrabError,
+};
+use clap::Args;
+use clap_complete::ArgValueCandidates;
+
+use crate::{completions, error_details, remote::Remote, CliError};
+
+#[derive(Args)]
+pub struct UploadArgs {
+    /// Files uploaded (globs like "books/*.epub" are expanded)
+    #[arg(required = true, value_name = "PATH")]
+    paths: Vec<String>,
+    /// Title of the book (only with a single file). By default, it's the
+    /// name of the file, with the `.txt` extension if it was converted
+    #[arg(long)]
+    title: Option<String>,
+    /// Tags of the books, separated by commas
+    #[arg(long, value_name = "TAGS", value_delimiter = ',', add = ArgValueCandidates::new(completions::tags))]
+    tags: Vec<String>,
+    /// Book folder of the configuration where new books are uploaded
+    /// (by default, the one of `upload_book_path` or `book_path`)
+    #[arg(long, value_name = "PATH")]
+    root: Option<PathBuf>,
+}
+
+/// Uploads every file of `args`, writing the progress to `out` and
+/// the files that couldn't be uploaded to `err`. Failed files don't stop
+/// the others, but the command exits with 2 if there is any.
+pub fn run(
+    root: &mut RootBookDir,
+    args: &UploadArgs,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let Some(folder) = &args.root else {
+        let store = |title: &str, txt: &str, tags: &HashSet<String>| {
+            root.upload(title, txt, tags.clone()).map(|_| ())
+        };
+        return upload_all(args, store, out, err);
+    };
+    let mut config = root.config().clone();
+    let same = |a: &Path, b: &Path| match (fs::canonicalize(a), fs::canonicalize(b)) {
+        (Ok(a), Ok(b)) => a == b,
+        _ => a == b,
+    };
+    let Some(folder) = config
+        .book_folders()
+        .into_iter()
+        .find(|book_folder| same(book_folder, folder))
+    else {
+        let message = format!(
+            "{} isn't a book folder of the configuration",
+            folder.display()
+        );
+        return Err(BookrabError::InvalidFields {
+            error: (),
+            errors: vec![FieldError::new("root", message)],
+        }
+        .into());
+    };
+    config.upload_book_path = Some(folder);
+    let mut root = match root.connection.as_deref_mut() {
+        Some(connection) => RootBookDir::new(config, connection),
+        None => RootBookDir::without_database(config),
+    };
+    let store = |title: &str, txt: &str, tags: &HashSet<String>| {
+        root.upload(title, txt, tags.clone()).map(|_| ())
+    };
+    upload_all(args, store, out, err)
+}
+
+/// Like [run], to the library of `remote`.
+pub fn run_remote(
+    remote: &Remote,
+    args: &UploadArgs,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    if args.root.is_some() {
+        return Err(CliError::NotRemote("upload --root"));
+    }
+    upload_all(
+        args,
+        |title, txt, tags| remote.upload(title, txt, tags),
+        out,
+        err,
+    )
+}
+
+/// Uploads the files of `args` with `store` (which receives the
+/// title, the text and the tags of each book).
+fn upload_all(
+    args: &UploadArgs,
+    mut store: impl FnMut(&str, &str, &HashSet<String>) -> Result<(), BookrabError>,
+    out: &mut impl Write,
+    err: &mut impl Write,
+) -> Result<ExitCode, CliError> {
+    let mut failed = 0;
+    let mut paths = vec![];
+    for pattern in &args.paths {
+        match expand(pattern) {
+            Ok(expanded) => paths.extend(expanded),
+            Err(e) => {
+                writeln!(err, "bookrab: {pattern}: {e}")?;
+                failed += 1;
+            }
+        }
+    }
+    if args.title.is_some() && paths.len() > 1 {
+        writeln!(err, "bookrab: --title only works with a single file")?;
+        return Ok(ExitCode::from(2));
+    }
+    let tags: HashSet<String> = args
+        .tags
+        .iter()
+        .map(|tag| tag.trim().to_string())
+        .filter(|tag| !tag.is_empty())
+        .collect();
+    for (index, path) in paths.iter().enumerate() {
+        write!(out, "[{}/{}] {}", index + 1, paths.len(), path.display())?;
+        out.flush()?;
+        let uploaded = read_book(path, args.title.as_deref())
+            .and_then(|(title, txt)| store(&title, &txt, &tags).map(|_| title));
+        match uploaded {
+            Ok(title) => writeln!(out, " -> {title}")?,
+            Err(e) => {
+                writeln!(out, " failed")?;
+                writeln!(
+                    err,
+                    "bookrab: couldn't upload {}: {}",
+                    path.display(),
+                    error_details(&e)
+                )?;
+                failed += 1;
+            }
+        }
+    }
+    if failed > 0 {
+        writeln!(err, "bookrab: {failed} file(s) couldn't be uploaded")?;
+        return Ok(ExitCode::from(2));
+    }
+    Ok(ExitCode::SUCCESS)
+}
+
+/// Files of `pattern`. Patterns without glob characters are taken
+/// as they are, so that missing files are reported when they are read.
+fn expand(pattern: &str) -> Result<Vec<PathBuf>, String> {
+    if !pattern.contains(['*', '?', '[']) {
+        return Ok(vec![PathBuf::from(pattern)]);
+    }
+    let paths = glob::glob(pattern).map_err(|e| format!("invalid glob ({e})"))?;
+    let mut files = vec![];
+    for path in paths {
+        let path = path.map_err(|e| e.to_string())?;
+        if path.is_file() {
+            files.push(path);
+        }
+    }
+    if files.is_empty() {
+        return Err("no files matched".to_string());
+    }
+    Ok(files)
+}
+
+/// Title and text of the book of the file of `path`.
+/// Files without a known extension are treated as plain text.
+fn read_book(path: &Path, title: Option<&str>) -> Result<(String, String), BookrabError> {
+    let file_name = match path.file_name().map(|name| name.to_str()) {
+        Some(Some(name)) => name.to_string(),
+        _ => {
+            return Err(BookrabError::NotUnicode {
+                error: (),
+                what: path.to_string_lossy().to_string(),
+            })
+        }
+    };
+    let bytes = fs::read(path).map_err(|e| BookrabError::CouldntReadFile {
+        error: (),
+        path: path.to_path_buf(),
+        err: e,
+    })?;
+    let format = SourceFormat::from_file_name(&file_name).unwrap_or(SourceFormat::Text);
+    let txt = import::convert(format, &file_name, bytes)?;
+    let title = match (title, format) {
+        (Some(title), _) => title.to_string(),
+        (None, SourceFormat::Text) => file_name,
+        (None, _) => import::converted_title(&file_name),
+    };
+    Ok((title, txt))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::env::temp_dir;
+
+    use bookrab_core::{
+        books::test_utils::{create_book_dir, random_string, s, DBCONNECTION},
+        config::BookrabConfig,
+    };
+    use clap::Parser;
+
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cli {
+        #[command(flatten)]
+        args: UploadArgs,
+    }
+
+    /// Folder with the files of `files` (name and contents).
+    fn files(files: &[(&str, &str)]) -> PathBuf {
+        let dir = temp_dir().join(format!("bookrab-cli-upload-{}", random_string()));
+        fs::create_dir_all(&dir).unwrap();
+        for (name, contents) in files {
+            fs::write(dir.join(name), contents).unwrap();
+        }
+        dir
+    }
+
+    fn upload(root: &mut RootBookDir, args: &[&str]) -> (ExitCode, String, String) {
+        let cli = Cli::parse_from([&["upload"], args].concat());
+        let (mut out, mut err) = (vec![], vec![]);
+        let code = run(root, &cli.args, &mut out, &mut err).unwrap();
+        (
+            code,
+            String::from_utf8(out).unwrap(),
+            String::from_utf8(err).unwrap(),
+        )
+    }
+
+    #[test]
+    fn upload_with_title_and_tags() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = create_book_dir(connection);
+        let dir = files(&[("lusiadas.md", "# Canto I\n\nAs armas e os barões")]);
+        let path = dir.join("lusiadas.md");
+        let (code, out, err) = upload(
+            &mut root,
+            &[
+                path.to_str().unwrap(),
+                "--title",
+                "Os Lusíadas",
+                "--tags",
+                "a,b",
+            ],
+        );
+        assert_eq!(code, ExitCode::SUCCESS, "{err}");
+        assert_eq!(out, format!("[1/1] {} -> Os Lusíadas\n", path.display()));
+        let books = root.list().unwrap();
+        assert_eq!(books.len(), 1);
+        assert_eq!(books[0].title, "Os Lusíadas");
+        assert_eq!(books[0].tags, s(vec!["a", "b"]));
+        assert!(!root.text("Os Lusíadas").unwrap().contains('#'));
+    }
+
+    #[test]
+    fn globs_and_errors_of_each_file() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = create_book_dir(connection);
+        let dir = files(&[("1.txt", "As armas"), ("2.md", "e os barões")]);
+        // isn't UTF-8
+        fs::write(dir.join("3.txt"), [0xff, 0xfe]).unwrap();
+        let glob = dir.join("*").to_str().unwrap().to_string();
+        let missing = dir.join("missing.txt").to_str().unwrap().to_string();
+        let (code, out, err) = upload(&mut root, &[&glob, &missing]);
+        assert_eq!(code, ExitCode::from(2));
+        assert_eq!(out.lines().count(), 4);
+        assert!(out.contains("2.md -> 2.txt"));
+        assert!(err.contains("3.txt"));
+        assert!(err.contains("missing.txt"));
+        let mut titles: Vec<String> = root.list().unwrap().into_iter().map(|b| b.title).collect();
+        titles.sort();
+        assert_eq!(titles, ["1.txt", "2.txt"]);
+
+        let (code, _, err) = upload(&mut root, &[&glob, "--title", "x"]);
+        assert_eq!(code, ExitCode::from(2));
+        assert!(err.contains("--title"));
+    }
+
+    #[test]
+    fn upload_to_another_folder() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let notes = create_book_dir(connection).config().book_path.clone();
+        let config = BookrabConfig {
+            book_paths: vec![notes.clone()],
+            ..create_book_dir(connection).config().clone()
+        };
+        let mut root = RootBookDir::new(config, connection);
+        let dir = files(&[("1.txt", "As armas")]);
+        let path = dir.join("1.txt");
+        let (code, _, err) = upload(
+            &mut root,
+            &[path.to_str().unwrap(), "--root", notes.to_str().unwrap()],
+        );
+        assert_eq!(code, ExitCode::SUCCESS, "{err}");
+        assert!(notes.join("1.txt").join("txt").exists());
+        assert_eq!(root.text("1.txt").unwrap(), "As armas");
+
+        let cli = Cli::parse_from(["upload", path.to_str().unwrap(), "--root", "/elsewhere"]);
+        assert!(matches!(
+            run(&mut root, &cli.args, &mut vec![], &mut vec![]),
+            Err(CliError::Bookrab(BookrabError::InvalidFields { .. }))
+        ));
+    }
+}
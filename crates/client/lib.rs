@@ -0,0 +1,358 @@
+//! Typed client of the bookrab REST API.
+//!
+//! ```no_run
+//! use bookrab_client::{Client, SearchRequest};
+//!
+//! let client = Client::new("http://localhost:8000");
+//! client.upload("lusiadas.txt", "As armas e os barões assinalados", &["camoes"])?;
+//! for book in client.search(&SearchRequest::new("armas"))?.items {
+//!     println!("{}: {:?}", book.title, book.results);
+//! }
+//! # Ok::<(), bookrab_client::ClientError>(())
+//! ```
+pub mod types;
+
+use std::io::{BufRead, BufReader, Read, Write};
+
+use reqwest::{
+    blocking::{multipart, RequestBuilder, Response},
+    StatusCode,
+};
+use serde::de::DeserializeOwned;
+use serde_json::json;
+pub use types::*;
+
+/// Errors of the client.
+#[derive(Debug, thiserror::Error)]
+pub enum ClientError {
+    /// The server couldn't be reached or its response couldn't be read.
+    #[error("request failed: {0}")]
+    Http(#[from] reqwest::Error),
+    /// The server answered with an error. The body is the error
+    /// of the API (e.g. `{"InexistentBook": {...}}`) or, when it isn't
+    /// JSON, its text.
+    #[error("the server answered {status}: {body}")]
+    Api {
+        status: StatusCode,
+        body: serde_json::Value,
+    },
+    /// A line of a streamed response couldn't be read.
+    #[error("couldn't read the response: {0}")]
+    Stream(String),
+}
+
+impl ClientError {
+    /// Description of the error for users. Errors of the API only
+    /// keep their short message (e.g. `the server answered 401
+    /// Unauthorized: E0024: authentication required.`).
+    pub fn reason(&self) -> String {
+        match self {
+            ClientError::Api { status, body } => {
+                let message = body
+                    .as_object()
+                    .and_then(|variant| variant.values().next())
+                    .and_then(|fields| fields.get("error"))
+                    .and_then(|message| message.as_str())
+                    .map(str::to_string)
+                    .unwrap_or_else(|| body.to_string());
+                format!("the server answered {status}: {message}")
+            }
+            ClientError::Http(_) | ClientError::Stream(_) => self.to_string(),
+        }
+    }
+}
+
+/// Books (or history entries) asked in each request by the
+/// methods that fetch every page (e.g. [Client::list_all]).
+pub const PAGE_SIZE: usize = 100;
+
+/// `segment` escaped to be part of a path (titles can have
+/// slashes, question marks and so on).
+fn path_segment(segment: &str) -> String {
+    segment
+        .bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => {
+                (byte as char).to_string()
+            }
+            _ => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+/// Client of a bookrab server.
+#[derive(Clone, Debug)]
+pub struct Client {
+    /// e.g. `http://localhost:8000`
+    base_url: String,
+    /// Sent as a bearer token (see [Client::login])
+    token: Option<String>,
+    http: reqwest::blocking::Client,
+}
+
+impl Client {
+    pub fn new(base_url: impl Into<String>) -> Client {
+        Client {
+            base_url: base_url.into().trim_end_matches('/').to_string(),
+            token: None,
+            http: reqwest::blocking::Client::new(),
+        }
+    }
+
+    /// e.g. `http://localhost:8000`
+    pub fn base_url(&self) -> &str {
+        &self.base_url
+    }
+
+    /// Uses a token obtained before (see [Client::login]).
+    pub fn with_token(mut self, token: impl Into<String>) -> Client {
+        self.token = Some(token.into());
+        self
+    }
+
+    fn request(&self, method: reqwest::Method, path: &str) -> RequestBuilder {
+        let request = self
+            .http
+            .request(method, format!("{}{}", self.base_url, path));
+        match &self.token {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Sends `request` and turns error statuses into [ClientError::Api].
+    fn send(request: RequestBuilder) -> Result<Response, ClientError> {
+        let response = request.send()?;
+        let status = response.status();
+        if status.is_success() {
+            return Ok(response);
+        }
+        let text = response.text()?;
+        let body = serde_json::from_str(&text).unwrap_or(serde_json::Value::String(text));
+        Err(ClientError::Api { status, body })
+    }
+
+    fn json<T: DeserializeOwned>(request: RequestBuilder) -> Result<T, ClientError> {
+        Ok(Self::send(request)?.json()?)
+    }
+
+    /// Logs in and uses the token in the next requests.
+    pub fn login(&mut self, username: &str, password: &str) -> Result<Token, ClientError> {
+        let token: Token = Self::json(
+            self.request(reqwest::Method::POST, "/v1/auth/login")
+                .json(&json!({ "username": username, "password": password })),
+        )?;
+        self.token = Some(token.token.clone());
+        Ok(token)
+    }
+
+    /// Uploads a book. `title` is the name of the file, as if it was
+    /// uploaded by a browser.
+    pub fn upload(&self, title: &str, txt: &str, tags: &[&str]) -> Result<(), ClientError> {
+        let book = multipart::Part::text(txt.to_string())
+            .file_name(title.to_string())
+            .mime_str("text/plain")?;
+        let tags = multipart::Part::text(serde_json::to_string(tags).unwrap())
+            .mime_str("application/json")?;
+        let form = multipart::Form::new().part("book", book).part("tags", tags);
+        Self::send(
+            self.request(reqwest::Method::POST, "/v1/books/upload")
+                .multipart(form),
+        )?;
+        Ok(())
+    }
+
+    /// Lists one page of books.
+    pub fn list(&self, query: &ListQuery) -> Result<Page<Book>, ClientError> {
+        Self::json(
+            self.request(reqwest::Method::GET, "/v1/books/list")
+                .query(query),
+        )
+    }
+
+    /// Every book that matches `query`, one page after the other
+    /// (its `limit` and `offset` are ignored).
+    pub fn list_all(&self, query: &ListQuery) -> Result<Vec<Book>, ClientError> {
+        let mut books = vec![];
+        let mut offset = Some(0);
+        while let Some(current) = offset {
+            let page = self.list(&ListQuery {
+                limit: Some(PAGE_SIZE),
+                offset: Some(current),
+                ..query.clone()
+            })?;
+            books.extend(page.items);
+            offset = page.next;
+        }
+        Ok(books)
+    }
+
+    /// Searches one page of books (ordered by relevance).
+    pub fn search(&self, request: &SearchRequest) -> Result<Page<BookResults>, ClientError> {
+        Self::json(
+            self.request(reqwest::Method::POST, "/v1/books/search")
+                .json(request),
+        )
+    }
+
+    /// Searches every book (ordered by relevance) in a single request,
+    /// whose results are read while the server sends them. Limits of
+    /// `request` are ignored, since there are no pages.
+    pub fn search_all(&self, request: &SearchRequest) -> Result<Vec<BookResults>, ClientError> {
+        self.search_streaming(request, |_| true)
+    }
+
+    /// Like [Client::search_all], but `on_book` receives each book as soon
+    /// as the server searched it. When it returns `false`, the rest of the
+    /// response is dropped and only the books received so far are returned.
+    pub fn search_streaming(
+        &self,
+        request: &SearchRequest,
+        mut on_book: impl FnMut(&BookResults) -> bool,
+    ) -> Result<Vec<BookResults>, ClientError> {
+        let response = Self::send(
+            self.request(reqwest::Method::POST, "/v1/books/search")
+                .header(reqwest::header::ACCEPT, "application/x-ndjson")
+                .json(request),
+        )?;
+        let mut books = vec![];
+        for line in BufReader::new(response).lines() {
+            let line = line.map_err(|e| ClientError::Stream(e.to_string()))?;
+            if line.is_empty() {
+                continue;
+            }
+            let value: serde_json::Value =
+                serde_json::from_str(&line).map_err(|e| ClientError::Stream(e.to_string()))?;
+            let book: BookResults = match serde_json::from_value(value.clone()) {
+                Ok(v) => v,
+                // the search failed after the response started
+                Err(_) => {
+                    return Err(ClientError::Api {
+                        status: StatusCode::INTERNAL_SERVER_ERROR,
+                        body: value,
+                    })
+                }
+            };
+            let keep_going = on_book(&book);
+            books.push(book);
+            if !keep_going {
+                break;
+            }
+        }
+        books.sort_by(|a, b| b.score.total_cmp(&a.score));
+        Ok(books)
+    }
+
+    /// Lists one page of the search history.
+    pub fn history(&self, query: &HistoryQuery) -> Result<HistoryPage, ClientError> {
+        Self::json(
+            self.request(reqwest::Method::GET, "/v1/history/list")
+                .query(query),
+        )
+    }
+
+    /// Entries of the history that match `query` (newest first, unless
+    /// it says otherwise). Only `limit` entries are asked if it is set,
+    /// otherwise every page is fetched.
+    pub fn history_all(
+        &self,
+        query: &HistoryQuery,
+        limit: Option<i64>,
+    ) -> Result<Vec<HistoryEntry>, ClientError> {
+        let mut query = HistoryQuery {
+            size: Some(limit.unwrap_or(PAGE_SIZE as i64)),
+            ..query.clone()
+        };
+        let mut entries = vec![];
+        loop {
+            let page = self.history(&query)?;
+            entries.extend(page.entries);
+            match page.next {
+                Some(cursor) if limit.is_none() => query = query.after(&cursor),
+                _ => break,
+            }
+        }
+        Ok(entries)
+    }
+
+    /// Lists the tags with the number of books that have them
+    /// (most used first).
+    pub fn tags(&self) -> Result<Vec<TagCount>, ClientError> {
+        Self::json(self.request(reqwest::Method::GET, "/v1/books/tags"))
+    }
+
+    /// Size, number of tags and checksum of a book
+    /// (`None` if there is no such book).
+    pub fn info(&self, title: &str) -> Result<Option<BookInfo>, ClientError> {
+        let path = format!("/v1/books/{}", path_segment(title));
+        let request = self.request(reqwest::Method::HEAD, &path);
+        let response = match Self::send(request) {
+            Ok(response) => response,
+            Err(ClientError::Api { status, .. }) if status == StatusCode::NOT_FOUND => {
+                return Ok(None)
+            }
+            Err(e) => return Err(e),
+        };
+        let header = |name: &str| {
+            response
+                .headers()
+                .get(name)
+                .and_then(|value| value.to_str().ok())
+                .unwrap_or_default()
+                .to_string()
+        };
+        Ok(Some(BookInfo {
+            size: header("x-book-size").parse().unwrap_or(0),
+            tag_count: header("x-book-tag-count").parse().unwrap_or(0),
+            checksum: header("x-book-checksum"),
+        }))
+    }
+
+    /// Changes the title of a book and returns the renamed book.
+    pub fn rename(&self, title: &str, new_title: &str) -> Result<Book, ClientError> {
+        let path = format!("/v1/books/{}/rename", path_segment(title));
+        Self::json(
+            self.request(reqwest::Method::POST, &path)
+                .json(&json!({ "new_title": new_title })),
+        )
+    }
+
+    /// Deletes a book (its search history is kept).
+    pub fn delete(&self, title: &str) -> Result<(), ClientError> {
+        let path = format!("/v1/books/{}", path_segment(title));
+        Self::send(self.request(reqwest::Method::DELETE, &path))?;
+        Ok(())
+    }
+
+    /// Statistics of the library and of the disk of the server.
+    pub fn admin_stats(&self) -> Result<AdminStats, ClientError> {
+        Self::json(self.request(reqwest::Method::GET, "/v1/admin/stats"))
+    }
+
+    /// Writes the whole library (and the search history, if `history`
+    /// is set) to `writer` as a `.tar.gz`, while it is downloaded.
+    /// Returns the size of the archive.
+    pub fn export(&self, history: bool, writer: &mut impl Write) -> Result<u64, ClientError> {
+        let mut response = Self::send(
+            self.request(reqwest::Method::GET, "/v1/admin/export")
+                .query(&[("history", history)]),
+        )?;
+        Ok(response.copy_to(writer)?)
+    }
+
+    /// Restores an archive made by [Client::export],
+    /// sending it while it is read.
+    pub fn import(
+        &self,
+        archive: impl Read + Send + 'static,
+    ) -> Result<ImportSummary, ClientError> {
+        let archive = multipart::Part::reader(archive)
+            .file_name("library.tar.gz")
+            .mime_str("application/gzip")?;
+        let form = multipart::Form::new().part("archive", archive);
+        Self::json(
+            self.request(reqwest::Method::POST, "/v1/admin/import")
+                .multipart(form),
+        )
+    }
+}
@@ -0,0 +1,284 @@
+//! Requests and responses of the REST API.
+//! They mirror the JSON of the server, so this crate
+//! doesn't depend on `bookrab-core` (and on Postgresql).
+
+use std::collections::HashSet;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Language {
+    #[serde(rename = "pt")]
+    Portuguese,
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "es")]
+    Spanish,
+    #[serde(rename = "fr")]
+    French,
+    #[serde(rename = "it")]
+    Italian,
+    #[serde(rename = "de")]
+    German,
+}
+
+/// Whether books need all or any of the tags.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum FilterMode {
+    All,
+    #[default]
+    Any,
+}
+
+/// Format of the results of a search.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResultFormat {
+    /// Matched lines with their context
+    #[default]
+    Lines,
+    /// Keyword in context (see [SearchRequest::kwic_width])
+    Kwic,
+}
+
+/// Order of history entries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum HistoryOrder {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+}
+
+/// A book with its metadata.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Book {
+    pub title: String,
+    pub tags: HashSet<String>,
+    pub language: Option<Language>,
+}
+
+/// Number of books that have a tag.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// Metadata of a book that isn't in [Book].
+#[derive(Clone, Debug, PartialEq)]
+pub struct BookInfo {
+    /// Size of the text (in bytes)
+    pub size: u64,
+    pub tag_count: usize,
+    /// SHA-256 of the text (in hexadecimal)
+    pub checksum: String,
+}
+
+/// One page of a listing.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Number of items in all pages
+    pub total: usize,
+    /// Offset of the next page (`None` if this is the last page)
+    pub next: Option<usize>,
+}
+
+/// Results of a search in a book.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct BookResults {
+    pub title: String,
+    /// Matches are marked with `[matched]` and `[/matched]`
+    pub results: Vec<String>,
+    pub score: f64,
+    pub duration_ms: u64,
+    /// Whether `results` was cut by [SearchRequest::max_matches_per_book]
+    pub truncated: bool,
+}
+
+/// Parameters of a search. Fields that are `None` use the defaults of the server.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct SearchRequest {
+    pub pattern: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub after_context: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub before_context: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub case_insensitive: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub case_smart: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ignore_accents: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub include_mode: Option<FilterMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_tags: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exclude_mode: Option<FilterMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub format: Option<ResultFormat>,
+    /// Characters on each side of a match in [ResultFormat::Kwic]
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub kwic_width: Option<usize>,
+    /// Number of books per page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    /// Number of books to skip
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+    /// Maximum number of results of each book
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_matches_per_book: Option<usize>,
+}
+
+impl SearchRequest {
+    /// Searches `pattern` in every book with the defaults of the server.
+    pub fn new(pattern: impl Into<String>) -> Self {
+        SearchRequest {
+            pattern: pattern.into(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Filters of the book listing.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct ListQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub limit: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub offset: Option<usize>,
+}
+
+/// Filters of the history listing.
+#[derive(Clone, Debug, Default, PartialEq, Serialize)]
+pub struct HistoryQuery {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub order: Option<HistoryOrder>,
+    /// Only entries made at or after this date
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<NaiveDateTime>,
+    /// Only entries made at or before this date
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<NaiveDateTime>,
+    /// Only entries whose pattern contains this string
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pattern: Option<String>,
+    /// Only entries of this book
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Cursor returned by the previous page (see [HistoryQuery::after])
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor_date: Option<NaiveDateTime>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cursor_id: Option<i32>,
+    /// Number of entries per page
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub size: Option<i64>,
+}
+
+impl HistoryQuery {
+    /// Same query for the page that starts after `cursor`.
+    pub fn after(&self, cursor: &HistoryCursor) -> Self {
+        HistoryQuery {
+            cursor_date: Some(cursor.date),
+            cursor_id: Some(cursor.id),
+            ..self.clone()
+        }
+    }
+}
+
+/// A search made in a book.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct HistoryEntry {
+    pub id: i32,
+    pub title: String,
+    pub pattern: String,
+    pub date: NaiveDateTime,
+    pub include_mode: FilterMode,
+    pub include_tags: Vec<String>,
+    pub exclude_mode: FilterMode,
+    pub exclude_tags: Vec<String>,
+    pub case_insensitive: bool,
+    pub case_smart: bool,
+    pub ignore_accents: bool,
+    pub before_context: i32,
+    pub after_context: i32,
+    pub match_count: i32,
+    pub duration_ms: i64,
+    /// Number of times this search was made in a row
+    pub hits: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct HistoryCursor {
+    pub date: NaiveDateTime,
+    pub id: i32,
+}
+
+/// One page of the history.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct HistoryPage {
+    pub entries: Vec<HistoryEntry>,
+    /// Cursor of the next page (`None` if this is the last page)
+    pub next: Option<HistoryCursor>,
+}
+
+/// Token returned by [crate::Client::login].
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct Token {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// What [crate::Client::import] restored.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct ImportSummary {
+    /// Books that were uploaded (existing books with
+    /// the same title are replaced)
+    pub books: usize,
+    pub history_entries: usize,
+}
+
+/// Number of books in a language.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct LanguageCount {
+    /// `None` for books whose language is unknown
+    pub language: Option<Language>,
+    pub count: usize,
+}
+
+/// Contents of the library.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct LibraryStats {
+    pub books: usize,
+    /// Most common languages first
+    pub languages: Vec<LanguageCount>,
+    /// Most used tags first
+    pub tags: Vec<TagCount>,
+}
+
+/// Space used by the library.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct StorageUsage {
+    /// Size of the texts according to the catalog
+    /// (`None` when the server has no database)
+    pub text_bytes: Option<u64>,
+    /// Size of the files in the book folders
+    pub disk_bytes: u64,
+}
+
+/// Statistics returned by [crate::Client::admin_stats].
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+pub struct AdminStats {
+    pub library: LibraryStats,
+    pub storage: StorageUsage,
+}
@@ -0,0 +1,126 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+use regex_syntax::hir::{
+    Capture, Class, ClassUnicode, ClassUnicodeRange, Hir, HirKind, Repetition,
+};
+use unicode_normalization::UnicodeNormalization;
+
+lazy_static! {
+    /// Maps characters without diacritics to all of their
+    /// variants (e.g. 'a' => ['a', 'à', 'á', 'â', ...]).
+    /// Only latin characters are taken into account.
+    static ref VARIANTS: HashMap<char, Vec<char>> = {
+        let mut variants: HashMap<char, Vec<char>> = HashMap::new();
+        for c in '\u{00C0}'..='\u{024F}' {
+            let base = strip(c);
+            if base != c {
+                variants.entry(base).or_insert_with(|| vec![base]).push(c);
+            }
+        }
+        variants
+    };
+}
+
+/// Removes diacritics from a character ('é' => 'e').
+fn strip(c: char) -> char {
+    c.nfd().next().unwrap_or(c)
+}
+
+/// Class that matches `c` with or without diacritics.
+fn variants_class(c: char) -> Option<ClassUnicode> {
+    let variants = VARIANTS.get(&strip(c))?;
+    Some(ClassUnicode::new(
+        variants.iter().map(|v| ClassUnicodeRange::new(*v, *v)),
+    ))
+}
+
+/// Makes every character of `hir` match its variants.
+fn fold_hir(hir: Hir) -> Hir {
+    match hir.into_kind() {
+        HirKind::Literal(literal) => {
+            let literal = String::from_utf8_lossy(&literal.0).to_string();
+            Hir::concat(
+                literal
+                    .chars()
+                    .map(|c| match variants_class(c) {
+                        Some(class) => Hir::class(Class::Unicode(class)),
+                        None => Hir::literal(c.to_string().into_bytes()),
+                    })
+                    .collect(),
+            )
+        }
+        HirKind::Class(Class::Unicode(mut class)) => {
+            let mut extra = ClassUnicode::empty();
+            for c in VARIANTS.values().flatten() {
+                let in_class = class
+                    .ranges()
+                    .iter()
+                    .any(|range| range.start() <= *c && *c <= range.end());
+                if in_class {
+                    extra.union(&variants_class(*c).unwrap());
+                }
+            }
+            class.union(&extra);
+            Hir::class(Class::Unicode(class))
+        }
+        HirKind::Repetition(repetition) => Hir::repetition(Repetition {
+            sub: Box::new(fold_hir(*repetition.sub)),
+            ..repetition
+        }),
+        HirKind::Capture(capture) => Hir::capture(Capture {
+            sub: Box::new(fold_hir(*capture.sub)),
+            ..capture
+        }),
+        HirKind::Concat(hirs) => Hir::concat(hirs.into_iter().map(fold_hir).collect()),
+        HirKind::Alternation(hirs) => Hir::alternation(hirs.into_iter().map(fold_hir).collect()),
+        HirKind::Class(class) => Hir::class(class),
+        HirKind::Look(look) => Hir::look(look),
+        HirKind::Empty => Hir::empty(),
+    }
+}
+
+/// Rewrites `pattern` so that it ignores diacritics,
+/// i.e. "vituperio" matches "vitupério" and vice-versa.
+/// If the pattern is invalid, it is returned as it is (the
+/// matcher will complain about it later).
+pub fn fold_accents(pattern: &str) -> String {
+    match regex_syntax::Parser::new().parse(pattern) {
+        Ok(hir) => fold_hir(hir).to_string(),
+        Err(_) => pattern.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::fold_accents;
+    use grep_matcher::Matcher;
+    use grep_regex::RegexMatcher;
+
+    fn is_match(pattern: &str, haystack: &str) -> bool {
+        RegexMatcher::new(&fold_accents(pattern))
+            .unwrap()
+            .is_match(haystack.as_bytes())
+            .unwrap()
+    }
+
+    #[test]
+    fn fold_literals() {
+        assert!(is_match("vituperio", "desonra e vitupério,"));
+        assert!(is_match("vitupério", "desonra e vituperio,"));
+        assert!(is_match("vitupêrio", "desonra e vitupério,"));
+        assert!(!is_match("vitupario", "desonra e vitupério,"));
+    }
+
+    #[test]
+    fn fold_classes_and_repetitions() {
+        assert!(is_match(r"\bvisib[a-z]l\b", "Obedece o visíbil"));
+        assert!(is_match(r"(c|x)o(r|s)a(ç|c)ao", "coração"));
+        assert!(is_match(r"\bAgua+", "Água"));
+    }
+
+    #[test]
+    fn invalid_pattern() {
+        assert_eq!(fold_accents("("), "(");
+    }
+}
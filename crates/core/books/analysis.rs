@@ -0,0 +1,229 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{BufRead, BufReader},
+    path::PathBuf,
+};
+
+use super::{language::Analyzer, BookListElement, Exclude, Include, RootBookDir};
+use crate::errors::BookrabError;
+
+/// Number of times a word appears in a set of books.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct WordFrequency {
+    pub word: String,
+    pub count: usize,
+}
+
+/// Number of times a sequence of `n` words appears in a set of books.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct NGramFrequency {
+    /// Words separated by a single space
+    pub ngram: String,
+    pub count: usize,
+}
+
+/// Turns a map of counts into the `top` most frequent entries.
+/// Ties are broken alphabetically so that the result is stable.
+fn top_counts(counts: HashMap<String, usize>, top: usize) -> Vec<(String, usize)> {
+    let mut frequencies: Vec<(String, usize)> = counts.into_iter().collect();
+    frequencies.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    frequencies.truncate(top);
+    frequencies
+}
+
+impl RootBookDir<'_> {
+    /// Computes the `top` most frequent words in the books
+    /// that respect some tag constraint. See [RootBookDir::list_by_tags].
+    pub fn word_frequencies(
+        &mut self,
+        include: &Include,
+        exclude: &Exclude,
+        top: usize,
+    ) -> Result<Vec<WordFrequency>, BookrabError> {
+        self.word_frequencies_filtered(|book| include.matches(book) && !exclude.matches(book), top)
+    }
+
+    /// Computes the `top` most frequent words in the books
+    /// for which `predicate` returns `true`. See [RootBookDir::list_filtered].
+    /// Words are split and folded according to the language of each book.
+    pub fn word_frequencies_filtered(
+        &mut self,
+        predicate: impl Fn(&BookListElement) -> bool,
+        top: usize,
+    ) -> Result<Vec<WordFrequency>, BookrabError> {
+        let counts = self.count_ngrams(predicate, 1)?;
+        Ok(top_counts(counts, top)
+            .into_iter()
+            .map(|(word, count)| WordFrequency { word, count })
+            .collect())
+    }
+
+    /// Computes the `top` most frequent sequences of `n` words
+    /// (bigrams, trigrams...) in the books that respect some tag
+    /// constraint. See [RootBookDir::list_by_tags].
+    pub fn ngram_frequencies(
+        &mut self,
+        include: &Include,
+        exclude: &Exclude,
+        n: usize,
+        top: usize,
+    ) -> Result<Vec<NGramFrequency>, BookrabError> {
+        self.ngram_frequencies_filtered(
+            |book| include.matches(book) && !exclude.matches(book),
+            n,
+            top,
+        )
+    }
+
+    /// Computes the `top` most frequent sequences of `n` words
+    /// in the books for which `predicate` returns `true`.
+    /// See [RootBookDir::list_filtered].
+    pub fn ngram_frequencies_filtered(
+        &mut self,
+        predicate: impl Fn(&BookListElement) -> bool,
+        n: usize,
+        top: usize,
+    ) -> Result<Vec<NGramFrequency>, BookrabError> {
+        let counts = self.count_ngrams(predicate, n)?;
+        Ok(top_counts(counts, top)
+            .into_iter()
+            .map(|(ngram, count)| NGramFrequency { ngram, count })
+            .collect())
+    }
+
+    /// Counts sequences of `n` words in the books for which `predicate`
+    /// returns `true`. Books are read line by line, so only the
+    /// counts are kept in memory (not the texts).
+    /// Sequences don't cross the boundaries between books.
+    fn count_ngrams(
+        &mut self,
+        predicate: impl Fn(&BookListElement) -> bool,
+        n: usize,
+    ) -> Result<HashMap<String, usize>, BookrabError> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        if n == 0 {
+            return Ok(counts);
+        }
+        for book in self.list_filtered(predicate)? {
+            // the book may be in any of the book folders (or in the database)
+            let file = self.reader(&book.title)?;
+            let analyzer = book
+                .language
+                .map(|language| language.analyzer())
+                .unwrap_or_else(Analyzer::default);
+            let mut window: VecDeque<String> = VecDeque::with_capacity(n);
+            for line in BufReader::new(file).lines() {
+                let line = match line {
+                    Ok(v) => v,
+                    Err(e) => {
+                        return Err(BookrabError::CouldntReadFile {
+                            error: (),
+                            path: PathBuf::from(&book.title),
+                            err: e,
+                        })
+                    }
+                };
+                for token in analyzer.tokenize(&line) {
+                    if window.len() == n {
+                        window.pop_front();
+                    }
+                    window.push_back(analyzer.fold(token));
+                    if window.len() == n {
+                        let ngram = Vec::from(window.clone()).join(" ");
+                        *counts.entry(ngram).or_default() += 1;
+                    }
+                }
+            }
+        }
+        Ok(counts)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::test_utils::{root_for_tag_tests, s, DBCONNECTION};
+    use crate::books::FilterMode;
+
+    fn w(word: &str, count: usize) -> WordFrequency {
+        WordFrequency {
+            word: word.to_string(),
+            count,
+        }
+    }
+
+    #[test]
+    fn frequencies_by_tags() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = root_for_tag_tests(connection);
+        // only "1"
+        let frequencies = book_dir.word_frequencies(
+            &Include {
+                mode: FilterMode::All,
+                tags: s(vec!["d"]),
+            },
+            &Exclude::default(),
+            3,
+        )?;
+        assert_eq!(frequencies, vec![w("e", 9), w("que", 8), w("o", 5)]);
+        Ok(())
+    }
+
+    #[test]
+    fn frequencies_of_a_single_book() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = root_for_tag_tests(connection);
+        let frequencies = book_dir.word_frequencies_filtered(|book| book.title == "4", 2)?;
+        // ties are ordered alphabetically
+        assert_eq!(frequencies, vec![w("o", 8), w("que", 8)]);
+        Ok(())
+    }
+
+    #[test]
+    fn bigrams_by_tags() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = root_for_tag_tests(connection);
+        // only "1"
+        let frequencies = book_dir.ngram_frequencies(
+            &Include {
+                mode: FilterMode::All,
+                tags: s(vec!["d"]),
+            },
+            &Exclude::default(),
+            2,
+            4,
+        )?;
+        assert_eq!(
+            frequencies,
+            vec![
+                NGramFrequency {
+                    ngram: "o que".to_string(),
+                    count: 2
+                },
+                NGramFrequency {
+                    ngram: "todo o".to_string(),
+                    count: 2
+                },
+                NGramFrequency {
+                    ngram: "ver como".to_string(),
+                    count: 2
+                },
+                NGramFrequency {
+                    ngram: "a cujo".to_string(),
+                    count: 1
+                }
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn no_ngrams() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = root_for_tag_tests(connection);
+        assert!(book_dir
+            .ngram_frequencies_filtered(|_| true, 0, 10)?
+            .is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,353 @@
+//! Whole libraries in a single `.tar.gz`, so that they can be
+//! backed up or moved to another server (see [RootBookDir::export]).
+//!
+//! The archive has a `manifest.json` with the metadata of every book,
+//! the texts in `books/<n>.txt` (titles aren't used as paths, since
+//! they can be anything) and, optionally, the history in `history.jsonl`
+//! (one [JsonHistoryEntry] per line).
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, BufReader, Read, Write},
+    path::PathBuf,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use flate2::{read::GzDecoder, write::GzEncoder, Compression};
+
+use crate::errors::BookrabError;
+
+use super::{
+    history_backend::{history_backend, JsonHistoryEntry},
+    language::Language,
+    validation::title_errors,
+    BookListElement, RootBookDir,
+};
+
+const MANIFEST_PATH: &str = "manifest.json";
+const HISTORY_PATH: &str = "history.jsonl";
+/// Changes whenever the layout of the archive changes.
+const ARCHIVE_VERSION: u32 = 1;
+
+/// Metadata of a book in the archive.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct ArchivedBook {
+    title: String,
+    tags: HashSet<String>,
+    language: Option<Language>,
+    /// Path of the text inside of the archive
+    path: String,
+}
+
+/// Contents of `manifest.json`.
+#[derive(Debug, serde::Deserialize, serde::Serialize)]
+struct Manifest {
+    version: u32,
+    books: Vec<ArchivedBook>,
+}
+
+/// What [RootBookDir::import] restored.
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+pub struct ImportSummary {
+    /// Books that were uploaded (existing books with
+    /// the same title are replaced)
+    pub books: usize,
+    pub history_entries: usize,
+    /// The books of [ImportSummary::books], so that callers can
+    /// tell others about them (e.g. the webhooks of the REST API)
+    #[serde(skip)]
+    pub uploaded: Vec<BookListElement>,
+}
+
+fn invalid_archive(reason: impl ToString) -> BookrabError {
+    BookrabError::InvalidArchive {
+        error: (),
+        reason: reason.to_string(),
+    }
+}
+
+/// Appends a file with `data` to the archive.
+fn append<W: Write>(
+    builder: &mut tar::Builder<W>,
+    path: &str,
+    data: &[u8],
+    mtime: u64,
+) -> Result<(), BookrabError> {
+    let mut header = tar::Header::new_gnu();
+    header.set_size(data.len() as u64);
+    header.set_mode(0o644);
+    header.set_mtime(mtime);
+    header.set_cksum();
+    builder
+        .append_data(&mut header, path, data)
+        .map_err(|err| BookrabError::CouldntWriteFile {
+            error: (),
+            path: PathBuf::from(path),
+            err,
+        })
+}
+
+impl RootBookDir<'_> {
+    /// Writes every book (text, tags and language) to `writer` as a
+    /// `.tar.gz`. The history goes along when `history` is set.
+    /// Texts are read one at a time, so the library is never
+    /// loaded into memory at once.
+    pub fn export(&mut self, writer: impl Write, history: bool) -> Result<(), BookrabError> {
+        let mtime = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|duration| duration.as_secs())
+            .unwrap_or(0);
+        let mut builder = tar::Builder::new(GzEncoder::new(writer, Compression::default()));
+        let books: Vec<ArchivedBook> = self
+            .list()?
+            .into_iter()
+            .enumerate()
+            .map(|(index, book)| ArchivedBook {
+                title: book.title,
+                tags: book.tags,
+                language: book.language,
+                path: format!("books/{index}.txt"),
+            })
+            .collect();
+        // the manifest comes first, so that imports don't have to
+        // keep the texts until they know their titles
+        let manifest = Manifest {
+            version: ARCHIVE_VERSION,
+            books,
+        };
+        let json = serde_json::to_vec_pretty(&manifest).unwrap();
+        append(&mut builder, MANIFEST_PATH, &json, mtime)?;
+        for book in &manifest.books {
+            let txt = self.text(&book.title)?;
+            append(&mut builder, &book.path, txt.as_bytes(), mtime)?;
+        }
+        if history {
+            let mut lines = String::new();
+            for entry in history_backend(&self.config, self.connection.as_deref_mut()).export()? {
+                // entries are made of strings, numbers and sets of strings
+                lines.push_str(&serde_json::to_string(&entry).unwrap());
+                lines.push('\n');
+            }
+            append(&mut builder, HISTORY_PATH, lines.as_bytes(), mtime)?;
+        }
+        let finished = builder
+            .into_inner()
+            .and_then(|encoder| encoder.finish())
+            .and_then(|mut writer| writer.flush());
+        finished.map_err(|err| BookrabError::CouldntWriteFile {
+            error: (),
+            path: PathBuf::from(MANIFEST_PATH),
+            err,
+        })
+    }
+
+    /// Restores an archive made by [RootBookDir::export]. Books with the
+    /// same title as existing ones replace them and history entries are
+    /// appended to the history. Books are uploaded as soon as they are
+    /// read, so a broken archive may be partially imported.
+    pub fn import(&mut self, reader: impl Read) -> Result<ImportSummary, BookrabError> {
+        let mut archive = tar::Archive::new(GzDecoder::new(reader));
+        let mut books: Option<HashMap<String, ArchivedBook>> = None;
+        let mut summary = ImportSummary::default();
+        for entry in archive.entries().map_err(invalid_archive)? {
+            let mut entry = entry.map_err(invalid_archive)?;
+            let path = entry.path().map_err(invalid_archive)?;
+            let path = path.to_string_lossy().to_string();
+            if path == MANIFEST_PATH {
+                let manifest: Manifest =
+                    serde_json::from_reader(&mut entry).map_err(invalid_archive)?;
+                if manifest.version != ARCHIVE_VERSION {
+                    return Err(invalid_archive(format!(
+                        "unsupported version: {}",
+                        manifest.version
+                    )));
+                }
+                // titles name folders, so nothing is imported when
+                // one of them would leave the library (e.g. `../x`)
+                for book in &manifest.books {
+                    if let Some(problem) = title_errors("title", &book.title).pop() {
+                        return Err(invalid_archive(format!(
+                            "the title {:?} {}",
+                            book.title, problem.message
+                        )));
+                    }
+                }
+                books = Some(
+                    manifest
+                        .books
+                        .into_iter()
+                        .map(|book| (book.path.clone(), book))
+                        .collect(),
+                );
+            } else if path == HISTORY_PATH {
+                let entries = BufReader::new(&mut entry)
+                    .lines()
+                    .filter(|line| !matches!(line, Ok(line) if line.trim().is_empty()))
+                    .map(|line| {
+                        let line = line.map_err(invalid_archive)?;
+                        serde_json::from_str::<JsonHistoryEntry>(&line).map_err(invalid_archive)
+                    })
+                    .collect::<Result<Vec<_>, _>>()?;
+                history_backend(&self.config, self.connection.as_deref_mut()).restore(&entries)?;
+                summary.history_entries += entries.len();
+            } else if let Some(book) = books
+                .as_mut()
+                .ok_or_else(|| invalid_archive("the manifest should come first"))?
+                .remove(&path)
+            {
+                let mut txt = String::new();
+                entry.read_to_string(&mut txt).map_err(|e| match e.kind() {
+                    io::ErrorKind::InvalidData => BookrabError::NotUnicode {
+                        error: (),
+                        what: book.title.clone(),
+                    },
+                    _ => invalid_archive(e),
+                })?;
+                self.upload(&book.title, &txt, book.tags.clone())?
+                    .set_language(&book.title, book.language)?;
+                summary.books += 1;
+                summary.uploaded.push(BookListElement {
+                    title: book.title,
+                    tags: book.tags,
+                    language: book.language,
+                });
+            }
+        }
+        match books {
+            None => Err(invalid_archive("there is no manifest.json")),
+            Some(missing) if !missing.is_empty() => {
+                let mut paths: Vec<String> = missing.into_keys().collect();
+                paths.sort();
+                Err(invalid_archive(format!(
+                    "missing texts: {}",
+                    paths.join(", ")
+                )))
+            }
+            Some(_) => Ok(summary),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        books::{
+            history::HistoryContext,
+            test_utils::{create_book_dir, s, DBCONNECTION, LUSIADAS1},
+        },
+        config::HistoryBackendKind,
+        database::DbConnection,
+    };
+    use chrono::NaiveDate;
+
+    /// Folder with its own history, so that other tests don't get in the way.
+    fn book_dir_with_history(connection: &mut DbConnection) -> RootBookDir<'_> {
+        let mut book_dir = create_book_dir(connection);
+        book_dir.config.history_backend = HistoryBackendKind::JsonFile;
+        book_dir.config.history_file = Some(book_dir.config.book_path.with_extension("jsonl"));
+        book_dir
+    }
+
+    #[test]
+    fn export_and_import() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let entry = JsonHistoryEntry {
+            date: NaiveDate::from_ymd_opt(2024, 1, 1)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap(),
+            title: "2".into(),
+            pattern: "armas".into(),
+            context: HistoryContext::default(),
+            results: vec!["As [matched]armas[/matched]".into()],
+            match_count: 1,
+            duration_ms: 3,
+        };
+        let (with_history, without_history, books) = {
+            let mut book_dir = book_dir_with_history(connection);
+            book_dir.upload("os lusíadas", LUSIADAS1, s(vec!["a", "b"]))?;
+            book_dir.upload("2", "As armas", s(vec![]))?;
+            book_dir.set_language("2", None)?;
+            history_backend(&book_dir.config, None).restore(std::slice::from_ref(&entry))?;
+            let (mut with_history, mut without_history) = (vec![], vec![]);
+            book_dir.export(&mut with_history, true)?;
+            book_dir.export(&mut without_history, false)?;
+            (with_history, without_history, book_dir.list()?)
+        };
+
+        let mut book_dir = book_dir_with_history(connection);
+        let summary = book_dir.import(with_history.as_slice())?;
+        assert_eq!(summary.books, 2);
+        assert_eq!(summary.history_entries, 1);
+        let mut uploaded = summary.uploaded;
+        uploaded.sort_by(|a, b| a.title.cmp(&b.title));
+        assert_eq!(uploaded, books);
+        assert_eq!(book_dir.list()?, books);
+        assert_eq!(book_dir.text("os lusíadas")?, LUSIADAS1);
+        assert_eq!(
+            history_backend(&book_dir.config, None).export()?,
+            vec![entry]
+        );
+
+        let mut book_dir = book_dir_with_history(connection);
+        let summary = book_dir.import(without_history.as_slice())?;
+        assert_eq!(summary.history_entries, 0);
+        assert_eq!(book_dir.list()?, books);
+        Ok(())
+    }
+
+    #[test]
+    fn import_rejects_broken_archives() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        assert!(matches!(
+            book_dir.import("not an archive".as_bytes()),
+            Err(BookrabError::InvalidArchive { .. })
+        ));
+        // an empty archive has no manifest
+        let mut archive = vec![];
+        tar::Builder::new(GzEncoder::new(&mut archive, Compression::default()))
+            .into_inner()
+            .unwrap()
+            .finish()
+            .unwrap();
+        assert!(matches!(
+            book_dir.import(archive.as_slice()),
+            Err(BookrabError::InvalidArchive { .. })
+        ));
+    }
+
+    #[test]
+    fn import_rejects_titles_outside_of_the_library() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        let manifest = Manifest {
+            version: ARCHIVE_VERSION,
+            books: ["1", "../../2/books/x"]
+                .into_iter()
+                .enumerate()
+                .map(|(index, title)| ArchivedBook {
+                    title: title.into(),
+                    tags: s(vec![]),
+                    language: None,
+                    path: format!("books/{index}.txt"),
+                })
+                .collect(),
+        };
+        let mut archive = vec![];
+        let mut builder = tar::Builder::new(GzEncoder::new(&mut archive, Compression::default()));
+        let json = serde_json::to_vec(&manifest).unwrap();
+        append(&mut builder, MANIFEST_PATH, &json, 0)?;
+        for book in &manifest.books {
+            append(&mut builder, &book.path, b"As armas", 0)?;
+        }
+        builder.into_inner().unwrap().finish().unwrap();
+        assert!(matches!(
+            book_dir.import(archive.as_slice()),
+            Err(BookrabError::InvalidArchive { .. })
+        ));
+        // not even the valid titles are imported
+        assert!(book_dir.list()?.is_empty());
+        Ok(())
+    }
+}
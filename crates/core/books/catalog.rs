@@ -0,0 +1,335 @@
+use std::collections::HashSet;
+
+use chrono::Local;
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    config::BookrabConfig,
+    database::{
+        catalog::{CatalogEntry, NewCatalogEntry},
+        with_backend, DbConnection,
+    },
+    errors::BookrabError,
+    schema,
+};
+
+use super::{language::Language, RootBookDir};
+
+/// Order of [BookCatalog::list].
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum CatalogOrder {
+    #[default]
+    Title,
+    /// Books whose text changed last come first
+    RecentlyUpdated,
+}
+
+/// What [BookCatalog::record] did with a book.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub enum CatalogChange {
+    /// The book wasn't in the catalog
+    Added,
+    /// The text of the book changed
+    Updated,
+    /// The text of the book is the same as before
+    Unchanged,
+}
+
+/// Result of [RootBookDir::sync_catalog].
+#[derive(Clone, Debug, Default, PartialEq, serde::Serialize)]
+pub struct CatalogSync {
+    pub added: Vec<String>,
+    pub updated: Vec<String>,
+    /// Books that are in the catalog, but not in the storage anymore
+    pub removed: Vec<String>,
+}
+
+/// Metadata of a book. See [RootBookDir::book_info].
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct BookInfo {
+    pub title: String,
+    pub tags: HashSet<String>,
+    pub language: Option<Language>,
+    /// SHA-256 of the text (in hexadecimal, see [checksum])
+    pub checksum: String,
+    /// Size of the text (in bytes)
+    pub size: u64,
+}
+
+/// SHA-256 of `txt` (in hexadecimal).
+pub fn checksum(txt: &str) -> String {
+    Sha256::digest(txt.as_bytes())
+        .iter()
+        .map(|byte| format!("{byte:02x}"))
+        .collect()
+}
+
+/// Table with the checksum, size and dates of every book.
+/// It is kept by [RootBookDir::upload], so that books can be listed
+/// and sorted without reading the storage and changes can be detected
+/// without reading the texts again.
+pub struct BookCatalog<'a> {
+    pub config: BookrabConfig,
+    /// Connection to the database
+    pub connection: &'a mut DbConnection,
+}
+
+impl BookCatalog<'_> {
+    pub fn new(config: BookrabConfig, connection: &mut DbConnection) -> BookCatalog<'_> {
+        BookCatalog { config, connection }
+    }
+
+    /// Returns all entries in the given order.
+    pub fn list(self, order: CatalogOrder) -> Result<Vec<CatalogEntry>, BookrabError> {
+        use schema::book_catalog::columns;
+        let query = schema::book_catalog::table
+            .select(CatalogEntry::as_select())
+            .filter(columns::user_id.eq(self.config.owner()))
+            .into_boxed();
+        let query = match order {
+            CatalogOrder::Title => query.order(columns::title.asc()),
+            CatalogOrder::RecentlyUpdated => {
+                query.order((columns::updated_at.desc(), columns::title.asc()))
+            }
+        };
+        Ok(query.load(self.connection)?)
+    }
+
+    /// Returns the entry of `title` (if any).
+    pub fn get(self, title: &str) -> Result<Option<CatalogEntry>, BookrabError> {
+        Ok(schema::book_catalog::table
+            .find((self.config.owner(), title))
+            .select(CatalogEntry::as_select())
+            .first(self.connection)
+            .optional()?)
+    }
+
+    /// Moves the entry of `title` to `new_title`, replacing a stale entry
+    /// of `new_title` (if any). Returns whether `title` had an entry.
+    pub fn rename(self, title: &str, new_title: &str) -> Result<bool, BookrabError> {
+        use schema::book_catalog::columns;
+        let owner = self.config.owner();
+        self.connection.transaction(|connection| {
+            diesel::delete(schema::book_catalog::table.find((owner, new_title)))
+                .execute(connection)?;
+            let updated = diesel::update(schema::book_catalog::table.find((owner, title)))
+                .set(columns::title.eq(new_title))
+                .execute(connection)?;
+            Ok(updated > 0)
+        })
+    }
+
+    /// Stores the checksum and size of `txt` as the text of `title`.
+    /// The update date only changes when the text changes.
+    pub fn record(self, title: &str, txt: &str) -> Result<CatalogChange, BookrabError> {
+        use schema::book_catalog::columns;
+        let checksum = checksum(txt);
+        let size = i64::try_from(txt.len()).unwrap_or(i64::MAX);
+        // inserting first avoids races between uploads of a new book
+        let entry = NewCatalogEntry {
+            title,
+            checksum: &checksum,
+            size,
+            user_id: self.config.owner(),
+        };
+        let inserted = with_backend!(&mut *self.connection, |connection| {
+            diesel::insert_into(schema::book_catalog::table)
+                .values(&entry)
+                .on_conflict_do_nothing()
+                .execute(connection)
+        })?;
+        if inserted > 0 {
+            return Ok(CatalogChange::Added);
+        }
+        let updated = diesel::update(
+            schema::book_catalog::table
+                .find((self.config.owner(), title))
+                .filter(columns::checksum.ne(&checksum)),
+        )
+        .set((
+            columns::checksum.eq(&checksum),
+            columns::size.eq(size),
+            columns::updated_at.eq(Local::now().naive_local()),
+        ))
+        .execute(self.connection)?;
+        if updated > 0 {
+            Ok(CatalogChange::Updated)
+        } else {
+            Ok(CatalogChange::Unchanged)
+        }
+    }
+
+    /// Removes `title` from the catalog. Returns whether it was there.
+    pub fn remove(self, title: &str) -> Result<bool, BookrabError> {
+        let deleted =
+            diesel::delete(schema::book_catalog::table.find((self.config.owner(), title)))
+                .execute(self.connection)?;
+        Ok(deleted > 0)
+    }
+}
+
+impl RootBookDir<'_> {
+    /// Metadata of `title` (`None` if there is no such book).
+    /// The checksum and the size come from the catalog, so the text is
+    /// only read when there is no database or the book isn't cataloged.
+    pub fn book_info(&mut self, title: &str) -> Result<Option<BookInfo>, BookrabError> {
+        let Some(book) = self.get_by_title(title.to_string())? else {
+            return Ok(None);
+        };
+        let config = self.config.clone();
+        let entry = match self.connection.as_deref_mut() {
+            Some(connection) => BookCatalog::new(config, connection).get(title)?,
+            None => None,
+        };
+        let (checksum, size) = match entry {
+            Some(entry) => (entry.checksum, u64::try_from(entry.size).unwrap_or(0)),
+            None => {
+                let txt = self.text(title)?;
+                (checksum(&txt), txt.len() as u64)
+            }
+        };
+        Ok(Some(BookInfo {
+            title: book.title,
+            tags: book.tags,
+            language: book.language,
+            checksum,
+            size,
+        }))
+    }
+
+    /// Makes the catalog match the storage: books that were changed
+    /// outside of [RootBookDir::upload] (e.g. edited by hand) are
+    /// recorded again and books that don't exist anymore are removed.
+    /// Every text is read, so this is meant to be run rarely.
+    pub fn sync_catalog(&mut self) -> Result<CatalogSync, BookrabError> {
+        if self.connection.is_none() {
+            return Err(BookrabError::DatabaseUnavailable {
+                error: (),
+                operation: "sync the book catalog".to_string(),
+            });
+        }
+        let mut sync = CatalogSync::default();
+        let titles: HashSet<String> = self.list()?.into_iter().map(|book| book.title).collect();
+        for title in &titles {
+            let txt = self.text(title)?;
+            let connection = self.connection.as_deref_mut().unwrap();
+            match BookCatalog::new(self.config.clone(), connection).record(title, &txt)? {
+                CatalogChange::Added => sync.added.push(title.clone()),
+                CatalogChange::Updated => sync.updated.push(title.clone()),
+                CatalogChange::Unchanged => {}
+            }
+        }
+        let connection = self.connection.as_deref_mut().unwrap();
+        for entry in BookCatalog::new(self.config.clone(), connection).list(CatalogOrder::Title)? {
+            if !titles.contains(&entry.title) {
+                BookCatalog::new(self.config.clone(), connection).remove(&entry.title)?;
+                sync.removed.push(entry.title);
+            }
+        }
+        sync.added.sort();
+        sync.updated.sort();
+        Ok(sync)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::test_utils::{create_book_dir, random_string, s, DBCONNECTION};
+    use std::fs;
+
+    #[test]
+    fn book_info() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        // other tests don't use this title
+        let title = random_string();
+        book_dir.upload(&title, "As armas", s(vec!["a", "b"]))?;
+        let info = book_dir.book_info(&title)?.unwrap();
+        assert_eq!(info.size, 8);
+        assert_eq!(info.checksum, checksum("As armas"));
+        assert_eq!(info.tags, s(vec!["a", "b"]));
+        assert_eq!(book_dir.book_info(&random_string())?, None);
+
+        // without the catalog, the text is read
+        let config = book_dir.config.clone();
+        let mut book_dir = RootBookDir::without_database(config);
+        assert_eq!(book_dir.book_info(&title)?, Some(info));
+        Ok(())
+    }
+
+    #[test]
+    fn upload_keeps_the_catalog() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        // other tests don't use this title
+        let title = random_string();
+        book_dir.upload(&title, "As armas", s(vec![]))?;
+        let config = book_dir.config.clone();
+        let connection = book_dir.connection.as_deref_mut().unwrap();
+        let first = BookCatalog::new(config.clone(), connection)
+            .get(&title)?
+            .unwrap();
+        assert_eq!(first.size, 8);
+        assert_eq!(first.checksum, checksum("As armas"));
+        assert_eq!(first.uploaded_at, first.updated_at);
+
+        // same text, only the tags change
+        book_dir.upload(&title, "As armas", s(vec!["a"]))?;
+        let connection = book_dir.connection.as_deref_mut().unwrap();
+        let second = BookCatalog::new(config.clone(), connection)
+            .get(&title)?
+            .unwrap();
+        assert_eq!(second, first);
+
+        book_dir.upload(&title, "As armas e os barões", s(vec!["a"]))?;
+        let connection = book_dir.connection.as_deref_mut().unwrap();
+        let third = BookCatalog::new(config.clone(), connection)
+            .get(&title)?
+            .unwrap();
+        assert_eq!(third.size, "As armas e os barões".len() as i64);
+        assert_ne!(third.checksum, first.checksum);
+        assert_eq!(third.uploaded_at, first.uploaded_at);
+        assert!(third.updated_at > first.updated_at);
+
+        let entries = BookCatalog::new(config, connection).list(CatalogOrder::RecentlyUpdated)?;
+        assert!(entries.iter().any(|entry| entry.title == title));
+        assert!(entries
+            .windows(2)
+            .all(|pair| pair[0].updated_at >= pair[1].updated_at));
+        Ok(())
+    }
+
+    #[test]
+    fn sync_with_the_filesystem() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = create_book_dir(connection).config;
+        // `sync_catalog` removes the books of the other tests,
+        // so nothing is committed
+        connection.test_transaction::<_, BookrabError, _>(|connection| {
+            let mut book_dir = RootBookDir::new(config.clone(), connection);
+            book_dir.upload("edited", "As armas", s(vec![]))?;
+            book_dir.upload("deleted", "As armas", s(vec![]))?;
+            book_dir.upload("same", "As armas", s(vec![]))?;
+            fs::write(config.book_path.join("edited").join("txt"), "Os barões").unwrap();
+            fs::remove_dir_all(config.book_path.join("deleted")).unwrap();
+            fs::create_dir_all(config.book_path.join("new")).unwrap();
+            fs::write(config.book_path.join("new").join("txt"), "").unwrap();
+
+            let sync = book_dir.sync_catalog()?;
+            assert_eq!(sync.added, vec!["new"]);
+            assert_eq!(sync.updated, vec!["edited"]);
+            assert!(sync.removed.contains(&"deleted".to_string()));
+
+            let connection = book_dir.connection.as_deref_mut().unwrap();
+            let titles: Vec<String> = BookCatalog::new(config.clone(), connection)
+                .list(CatalogOrder::Title)?
+                .into_iter()
+                .map(|entry| entry.title)
+                .collect();
+            assert_eq!(titles, vec!["edited", "new", "same"]);
+            Ok(())
+        });
+    }
+}
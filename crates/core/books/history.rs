@@ -1,32 +1,261 @@
-use diesel::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+use chrono::{Local, NaiveDate, NaiveDateTime, TimeDelta};
+use diesel::{
+    prelude::*,
+    sql_types::{BigInt, Date, Double, Integer, Varchar},
+};
+use grep_matcher::Matcher;
+use grep_regex::RegexMatcherBuilder;
 
 use crate::{
     config::BookrabConfig,
     database::{
         history::{NewResult, NewSearchHistoryEntry, SearchHistoryEntry},
-        PgPooledConnection,
+        with_backend, DbConnection, MultiBackend,
     },
     errors::BookrabError,
     schema,
 };
 
-use super::SearchResults;
+use super::{
+    history_backend::JsonHistoryEntry,
+    utils::{tags_from_db, tags_to_db},
+    FilterMode, SearchResults,
+};
+
+/// Filters and options of a search, stored in the history
+/// so that the search can be reproduced exactly.
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct HistoryContext {
+    pub include_mode: FilterMode,
+    pub include_tags: HashSet<String>,
+    pub exclude_mode: FilterMode,
+    pub exclude_tags: HashSet<String>,
+    pub case_insensitive: bool,
+    pub case_smart: bool,
+    pub ignore_accents: bool,
+    pub before_context: usize,
+    pub after_context: usize,
+}
+
+impl SearchHistoryEntry {
+    /// Filters and options used in the search of this entry.
+    pub fn context(&self) -> HistoryContext {
+        HistoryContext {
+            include_mode: FilterMode::from_db(&self.include_mode),
+            include_tags: tags_from_db(&self.include_tags),
+            exclude_mode: FilterMode::from_db(&self.exclude_mode),
+            exclude_tags: tags_from_db(&self.exclude_tags),
+            case_insensitive: self.case_insensitive,
+            case_smart: self.case_smart,
+            ignore_accents: self.ignore_accents,
+            before_context: self.before_context.max(0) as usize,
+            after_context: self.after_context.max(0) as usize,
+        }
+    }
+}
+
+/// Order of history entries.
+#[derive(Clone, Copy, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+pub enum HistoryOrder {
+    #[default]
+    NewestFirst,
+    OldestFirst,
+}
+
+/// Restricts the entries returned by [SearchHistory::get_history].
+/// Fields that are `None` don't restrict anything.
+#[derive(Clone, Debug, Default, serde::Deserialize)]
+pub struct HistoryFilter {
+    #[serde(default)]
+    pub order: HistoryOrder,
+    /// Only entries made at or after this date
+    pub from: Option<NaiveDateTime>,
+    /// Only entries made at or before this date
+    pub to: Option<NaiveDateTime>,
+    /// Only entries whose pattern contains this string
+    pub pattern: Option<String>,
+    /// Only entries of this book
+    pub title: Option<String>,
+    /// Maximum number of entries (ignored by [SearchHistory::get_history_page])
+    pub limit: Option<i64>,
+    /// Number of entries to skip (ignored by [SearchHistory::get_history_page])
+    pub offset: Option<i64>,
+}
+
+/// Position of an entry in the history. Pages continue
+/// right after the entry pointed by the cursor.
+#[derive(Clone, Copy, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct HistoryCursor {
+    pub date: NaiveDateTime,
+    pub id: i32,
+}
+
+impl From<&SearchHistoryEntry> for HistoryCursor {
+    fn from(entry: &SearchHistoryEntry) -> Self {
+        HistoryCursor {
+            date: entry.date,
+            id: entry.id,
+        }
+    }
+}
+
+/// Page of history entries. See [SearchHistory::get_history_page].
+#[derive(Debug, serde::Serialize)]
+pub struct HistoryPage {
+    pub entries: Vec<SearchHistoryEntry>,
+    /// Cursor of the next page (`None` if this is the last page)
+    pub next: Option<HistoryCursor>,
+}
+
+/// Escapes the wildcards of a LIKE pattern.
+fn escape_like(s: &str) -> String {
+    s.replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_")
+}
+
+/// Results of a history entry that match a pattern.
+/// See [SearchHistory::search_results].
+#[derive(Debug, serde::Serialize)]
+pub struct HistoryResultMatch {
+    pub entry: SearchHistoryEntry,
+    /// Matches of the old pattern are unmarked and
+    /// matches of the new one are marked with `[matched]`.
+    pub results: Vec<String>,
+}
+
+/// Removes `[matched]` tags from a stored result and marks
+/// the matches of `matcher` instead.
+/// Returns `None` if nothing matches.
+fn remark<M: Matcher>(matcher: &M, result: &str) -> Result<Option<String>, M::Error> {
+    let txt = result.replace("[matched]", "").replace("[/matched]", "");
+    let mut marked = String::new();
+    let mut last = 0;
+    let mut found = false;
+    matcher.find_iter(txt.as_bytes(), |m| {
+        found = true;
+        marked.push_str(&txt[last..m.start()]);
+        marked.push_str("[matched]");
+        marked.push_str(&txt[m.start()..m.end()]);
+        marked.push_str("[/matched]");
+        last = m.end();
+        true
+    })?;
+    if !found {
+        return Ok(None);
+    }
+    marked.push_str(&txt[last..]);
+    Ok(Some(marked))
+}
+
+/// Number of history entries with a given pattern.
+#[derive(Clone, Debug, PartialEq, QueryableByName, serde::Serialize)]
+pub struct PatternCount {
+    #[diesel(sql_type = Varchar)]
+    pub pattern: String,
+    #[diesel(sql_type = BigInt)]
+    pub count: i64,
+    /// Matches found in all of the entries
+    #[diesel(sql_type = BigInt)]
+    pub match_count: i64,
+    /// Average time spent searching a book with the pattern
+    #[diesel(sql_type = Double)]
+    pub avg_duration_ms: f64,
+}
+
+/// Number of history entries made in a given day.
+#[derive(Clone, Debug, PartialEq, QueryableByName, serde::Serialize)]
+pub struct DailyCount {
+    #[diesel(sql_type = Date)]
+    pub day: NaiveDate,
+    #[diesel(sql_type = BigInt)]
+    pub count: i64,
+}
+
+/// Number of history entries in which a book had results.
+#[derive(Clone, Debug, PartialEq, QueryableByName, serde::Serialize)]
+pub struct BookCount {
+    #[diesel(sql_type = Varchar)]
+    pub title: String,
+    #[diesel(sql_type = BigInt)]
+    pub count: i64,
+}
+
+/// Aggregates of the history. See [SearchHistory::stats].
+/// Every book searched creates an entry, so searching
+/// 3 books counts as 3 searches. Repeated searches are
+/// counted as many times as they were made (see [SearchHistoryEntry::hits]).
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct HistoryStats {
+    /// Most searched patterns
+    pub top_patterns: Vec<PatternCount>,
+    /// Entries per day (oldest first)
+    pub searches_per_day: Vec<DailyCount>,
+    /// Books that matched most often
+    pub top_books: Vec<BookCount>,
+}
+
+/// Queries of [SearchHistory::stats]. They take the owner as the
+/// first parameter and the number of patterns or books as the second.
+struct StatsQueries {
+    top_patterns: &'static str,
+    searches_per_day: &'static str,
+    top_books: &'static str,
+}
+
+const POSTGRESQL_STATS: StatsQueries = StatsQueries {
+    top_patterns: "SELECT pattern, SUM(hits)::BIGINT AS count,
+         SUM(match_count * hits)::BIGINT AS match_count,
+         AVG(duration_ms)::FLOAT8 AS avg_duration_ms FROM search_history
+         WHERE user_id = $1
+         GROUP BY pattern ORDER BY count DESC, pattern ASC LIMIT $2",
+    searches_per_day: "SELECT date::date AS day, SUM(hits)::BIGINT AS count FROM search_history
+         WHERE user_id = $1
+         GROUP BY day ORDER BY day ASC",
+    // an entry matched if it has at least one result
+    top_books: "SELECT title, SUM(hits)::BIGINT AS count FROM search_history
+         WHERE user_id = $1
+         AND EXISTS (SELECT 1 FROM search_results WHERE search_history_id = search_history.id)
+         GROUP BY title ORDER BY count DESC, title ASC LIMIT $2",
+};
+
+#[cfg(feature = "sqlite")]
+const SQLITE_STATS: StatsQueries = StatsQueries {
+    top_patterns: "SELECT pattern, SUM(hits) AS count,
+         SUM(match_count * hits) AS match_count,
+         CAST(AVG(duration_ms) AS REAL) AS avg_duration_ms FROM search_history
+         WHERE user_id = ?
+         GROUP BY pattern ORDER BY count DESC, pattern ASC LIMIT ?",
+    searches_per_day: "SELECT date(date) AS day, SUM(hits) AS count FROM search_history
+         WHERE user_id = ?
+         GROUP BY day ORDER BY day ASC",
+    top_books: "SELECT title, SUM(hits) AS count FROM search_history
+         WHERE user_id = ?
+         AND EXISTS (SELECT 1 FROM search_results WHERE search_history_id = search_history.id)
+         GROUP BY title ORDER BY count DESC, title ASC LIMIT ?",
+};
 
 pub struct SearchHistory<'a> {
     pub config: BookrabConfig,
-    /// Connection to Postgresql
-    pub connection: &'a mut PgPooledConnection,
+    /// Connection to the database
+    pub connection: &'a mut DbConnection,
 }
 
 impl<'a> SearchHistory<'a> {
-    pub fn new(config: BookrabConfig, connection: &mut PgPooledConnection) -> SearchHistory {
+    pub fn new(config: BookrabConfig, connection: &mut DbConnection) -> SearchHistory<'_> {
         SearchHistory { config, connection }
     }
 
     /// Returns entire history.
     pub fn get_entire_history(self) -> Result<Vec<SearchHistoryEntry>, BookrabError> {
         match schema::search_history::table
-            .order(schema::search_history::columns::date.asc())
+            .filter(schema::search_history::columns::user_id.eq(self.config.owner()))
+            .order((
+                schema::search_history::columns::date.asc(),
+                schema::search_history::columns::id.asc(),
+            ))
             .load::<SearchHistoryEntry>(self.connection)
         {
             Ok(v) => Ok(v),
@@ -34,43 +263,502 @@ impl<'a> SearchHistory<'a> {
         }
     }
 
-    /// Appends a history entry to Postgresql table.
-    /// It returns ownership of the results.
-    pub fn register_history(
+    /// Builds a query with the restrictions and the order of `filter`
+    /// over the entries of `owner` (limit and offset are left to the caller).
+    fn filtered_query(
+        owner: i32,
+        filter: &HistoryFilter,
+    ) -> schema::search_history::BoxedQuery<'_, MultiBackend> {
+        use schema::search_history::columns;
+        let mut query = schema::search_history::table
+            .filter(columns::user_id.eq(owner))
+            .into_boxed();
+        if let Some(from) = filter.from {
+            query = query.filter(columns::date.ge(from));
+        }
+        if let Some(to) = filter.to {
+            query = query.filter(columns::date.le(to));
+        }
+        if let Some(pattern) = &filter.pattern {
+            query = query.filter(
+                columns::pattern
+                    .like(format!("%{}%", escape_like(pattern)))
+                    .escape('\\'),
+            );
+        }
+        if let Some(title) = &filter.title {
+            query = query.filter(columns::title.eq(title));
+        }
+        match filter.order {
+            HistoryOrder::NewestFirst => query.order((columns::date.desc(), columns::id.desc())),
+            HistoryOrder::OldestFirst => query.order((columns::date.asc(), columns::id.asc())),
+        }
+    }
+
+    /// Returns history entries that satisfy `filter`.
+    pub fn get_history(
         self,
-        pattern: String,
-        results: &'a Vec<SearchResults>,
-    ) -> Result<&'a Vec<SearchResults>, BookrabError> {
+        filter: &HistoryFilter,
+    ) -> Result<Vec<SearchHistoryEntry>, BookrabError> {
+        let mut query = Self::filtered_query(self.config.owner(), filter);
+        if let Some(limit) = filter.limit {
+            query = query.limit(limit);
+        }
+        if let Some(offset) = filter.offset {
+            query = query.offset(offset);
+        }
+        match query.load::<SearchHistoryEntry>(self.connection) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the entry with `id` (`None` if there is no such entry
+    /// or it belongs to another user).
+    pub fn get_entry(self, id: i32) -> Result<Option<SearchHistoryEntry>, BookrabError> {
+        use schema::search_history::columns;
+        Ok(schema::search_history::table
+            .filter(columns::user_id.eq(self.config.owner()))
+            .find(id)
+            .first::<SearchHistoryEntry>(self.connection)
+            .optional()?)
+    }
+
+    /// Returns up to `size` entries that satisfy `filter` and come after
+    /// `cursor` (or from the start if there is no cursor).
+    /// Unlike offsets, cursors are cheap no matter how deep the page is
+    /// and pages don't shift when new searches are registered.
+    pub fn get_history_page(
+        self,
+        filter: &HistoryFilter,
+        cursor: Option<&HistoryCursor>,
+        size: i64,
+    ) -> Result<HistoryPage, BookrabError> {
+        use schema::search_history::columns;
+        let mut query = Self::filtered_query(self.config.owner(), filter);
+        if let Some(cursor) = cursor {
+            query = match filter.order {
+                HistoryOrder::NewestFirst => query.filter(
+                    columns::date
+                        .lt(cursor.date)
+                        .or(columns::date.eq(cursor.date).and(columns::id.lt(cursor.id))),
+                ),
+                HistoryOrder::OldestFirst => query.filter(
+                    columns::date
+                        .gt(cursor.date)
+                        .or(columns::date.eq(cursor.date).and(columns::id.gt(cursor.id))),
+                ),
+            };
+        }
+        let size = size.max(0);
+        // one more entry tells us whether there is a next page
+        let mut entries = query
+            .limit(size + 1)
+            .load::<SearchHistoryEntry>(self.connection)?;
+        let next = if entries.len() as i64 > size {
+            entries.truncate(size as usize);
+            entries.last().map(HistoryCursor::from)
+        } else {
+            None
+        };
+        Ok(HistoryPage { entries, next })
+    }
+
+    /// Searches `pattern` in the results stored in the entries that
+    /// satisfy `filter` (e.g. every past result mentioning "Taprobana").
+    /// Entries without matching results are left out.
+    pub fn search_results(
+        self,
+        pattern: &str,
+        matcher_builder: &RegexMatcherBuilder,
+        filter: &HistoryFilter,
+    ) -> Result<Vec<HistoryResultMatch>, BookrabError> {
+        use schema::search_results::columns;
+        let matcher = matcher_builder.build(pattern)?;
         let connection = self.connection;
-        for search_result in results {
-            let in_db_history = diesel::insert_into(crate::schema::search_history::table)
-                .values(NewSearchHistoryEntry {
-                    pattern: &pattern,
-                    title: &search_result.title,
-                })
-                .returning(SearchHistoryEntry::as_returning())
-                .get_result(connection)?;
-
-            let mut search_result_vec = vec![];
-            for single_result in search_result.results.iter() {
-                search_result_vec.push(NewResult {
-                    search_history_id: in_db_history.id,
-                    result: single_result.as_str(),
-                })
+        let mut query = Self::filtered_query(self.config.owner(), filter);
+        if let Some(limit) = filter.limit {
+            query = query.limit(limit);
+        }
+        if let Some(offset) = filter.offset {
+            query = query.offset(offset);
+        }
+        let entries = query.load::<SearchHistoryEntry>(connection)?;
+        let ids: Vec<i32> = entries.iter().map(|entry| entry.id).collect();
+        let rows = schema::search_results::table
+            .select((columns::search_history_id, columns::result))
+            .filter(columns::search_history_id.eq_any(&ids))
+            .order(columns::id.asc())
+            .load::<(i32, String)>(connection)?;
+        let mut results: HashMap<i32, Vec<String>> = HashMap::new();
+        for (id, result) in rows {
+            // a RegexMatcher never fails
+            if let Ok(Some(marked)) = remark(&matcher, &result) {
+                results.entry(id).or_default().push(marked);
             }
-            diesel::insert_into(crate::schema::search_results::table)
-                .values(search_result_vec)
+        }
+        Ok(entries
+            .into_iter()
+            .filter_map(|entry| {
+                let results = results.remove(&entry.id)?;
+                Some(HistoryResultMatch { entry, results })
+            })
+            .collect())
+    }
+
+    /// Computes aggregates of the entire history.
+    /// `top` limits the number of patterns and books.
+    pub fn stats(self, top: i64) -> Result<HistoryStats, BookrabError> {
+        let owner = self.config.owner();
+        let sql = match self.connection {
+            DbConnection::Postgresql(_) => &POSTGRESQL_STATS,
+            #[cfg(feature = "sqlite")]
+            DbConnection::Sqlite(_) => &SQLITE_STATS,
+        };
+        let connection = self.connection;
+        let top_patterns = diesel::sql_query(sql.top_patterns)
+            .bind::<Integer, _>(owner)
+            .bind::<BigInt, _>(top)
+            .load::<PatternCount>(connection)?;
+        let searches_per_day = diesel::sql_query(sql.searches_per_day)
+            .bind::<Integer, _>(owner)
+            .load::<DailyCount>(connection)?;
+        let top_books = diesel::sql_query(sql.top_books)
+            .bind::<Integer, _>(owner)
+            .bind::<BigInt, _>(top)
+            .load::<BookCount>(connection)?;
+        Ok(HistoryStats {
+            top_patterns,
+            searches_per_day,
+            top_books,
+        })
+    }
+
+    /// Deletes every history entry of the user and its results.
+    /// Both tables are cleared in a single transaction,
+    /// so they are never left half cleared.
+    pub fn clear(self) -> Result<(), BookrabError> {
+        use schema::search_history::columns;
+        let owner = self.config.owner();
+        self.connection.transaction(|connection| {
+            let entries = schema::search_history::table
+                .select(columns::id)
+                .filter(columns::user_id.eq(owner));
+            diesel::delete(
+                schema::search_results::table
+                    .filter(schema::search_results::columns::search_history_id.eq_any(entries)),
+            )
+            .execute(connection)?;
+            diesel::delete(schema::search_history::table.filter(columns::user_id.eq(owner)))
                 .execute(connection)?;
+            Ok(())
+        })
+    }
+
+    /// Makes the entries of `title` refer to `new_title`.
+    /// Returns the number of changed entries.
+    pub fn rename_book(self, title: &str, new_title: &str) -> Result<usize, BookrabError> {
+        use schema::search_history::columns;
+        Ok(diesel::update(
+            schema::search_history::table
+                .filter(columns::user_id.eq(self.config.owner()))
+                .filter(columns::title.eq(title)),
+        )
+        .set(columns::title.eq(new_title))
+        .execute(self.connection)?)
+    }
+
+    /// Deletes the entries of the user that exceed the retention policy of the config
+    /// (see [BookrabConfig::history_max_age_days] and
+    /// [BookrabConfig::history_max_entries]) along with their results.
+    /// Returns the number of deleted entries.
+    pub fn prune(self) -> Result<usize, BookrabError> {
+        prune(&self.config, self.connection)
+    }
+
+    /// Every entry of the user with its results (oldest first).
+    /// See [super::history_backend::HistoryBackend::export].
+    pub fn export(self) -> Result<Vec<JsonHistoryEntry>, BookrabError> {
+        use schema::search_results::columns;
+        let connection = self.connection;
+        let entries = SearchHistory::new(self.config, connection).get_entire_history()?;
+        let ids: Vec<i32> = entries.iter().map(|entry| entry.id).collect();
+        let rows = schema::search_results::table
+            .select((columns::search_history_id, columns::result))
+            .filter(columns::search_history_id.eq_any(&ids))
+            .order(columns::id.asc())
+            .load::<(i32, String)>(connection)?;
+        let mut results: HashMap<i32, Vec<String>> = HashMap::new();
+        for (id, result) in rows {
+            results.entry(id).or_default().push(result);
         }
+        Ok(entries
+            .into_iter()
+            .map(|entry| JsonHistoryEntry {
+                date: entry.date,
+                context: entry.context(),
+                results: results.remove(&entry.id).unwrap_or_default(),
+                match_count: entry.match_count.max(0) as usize,
+                duration_ms: entry.duration_ms.max(0) as u64,
+                title: entry.title,
+                pattern: entry.pattern,
+            })
+            .collect())
+    }
+
+    /// Appends `entries` to the history of the user, keeping their dates.
+    /// Nothing is stored if any of them fails.
+    pub fn restore(self, entries: &[JsonHistoryEntry]) -> Result<(), BookrabError> {
+        use schema::search_history::columns;
+        let owner = self.config.owner();
+        self.connection
+            .transaction::<_, BookrabError, _>(|connection| {
+                for entry in entries {
+                    let include_tags = tags_to_db(&entry.context.include_tags);
+                    let exclude_tags = tags_to_db(&entry.context.exclude_tags);
+                    let id = diesel::insert_into(schema::search_history::table)
+                        .values((
+                            NewSearchHistoryEntry {
+                                pattern: &entry.pattern,
+                                title: &entry.title,
+                                include_mode: entry.context.include_mode.to_db(),
+                                include_tags: &include_tags,
+                                exclude_mode: entry.context.exclude_mode.to_db(),
+                                exclude_tags: &exclude_tags,
+                                case_insensitive: entry.context.case_insensitive,
+                                case_smart: entry.context.case_smart,
+                                ignore_accents: entry.context.ignore_accents,
+                                before_context: i32::try_from(entry.context.before_context)
+                                    .unwrap_or(i32::MAX),
+                                after_context: i32::try_from(entry.context.after_context)
+                                    .unwrap_or(i32::MAX),
+                                match_count: i32::try_from(entry.match_count).unwrap_or(i32::MAX),
+                                duration_ms: i64::try_from(entry.duration_ms).unwrap_or(i64::MAX),
+                                user_id: owner,
+                            },
+                            columns::date.eq(entry.date),
+                        ))
+                        .returning(columns::id)
+                        .get_result::<i32>(connection)?;
+                    let results: Vec<NewResult> = entry
+                        .results
+                        .iter()
+                        .map(|result| NewResult {
+                            search_history_id: id,
+                            result: result.as_str(),
+                        })
+                        .collect();
+                    with_backend!(connection, |connection| {
+                        diesel::insert_into(schema::search_results::table)
+                            .values(&results)
+                            .execute(connection)
+                    })?;
+                }
+                Ok(())
+            })
+    }
+
+    /// Appends a history entry to the database.
+    /// If the last search of the owner is the same search (same
+    /// pattern, context, books and results), its entries are reused
+    /// instead (their hits are incremented and their date is updated),
+    /// so that repeating a search doesn't spam the history.
+    /// Everything is written in a single transaction, so that
+    /// nothing is stored if any part of it fails.
+    /// It returns ownership of the results.
+    pub fn register_history(
+        self,
+        pattern: String,
+        context: &HistoryContext,
+        results: &'a [SearchResults],
+    ) -> Result<&'a [SearchResults], BookrabError> {
+        use schema::search_history::columns;
+        let include_tags = tags_to_db(&context.include_tags);
+        let exclude_tags = tags_to_db(&context.exclude_tags);
+        let config = &self.config;
+        let date = Local::now().naive_local();
+        // a failure in the middle (e.g. when inserting the results)
+        // must not leave entries without their results
+        self.connection
+            .transaction::<_, BookrabError, _>(|connection| {
+                if let Some(previous) =
+                    repeated_search(connection, config.owner(), &pattern, context, results)?
+                {
+                    for search_result in results {
+                        diesel::update(
+                            schema::search_history::table.find(previous[&search_result.title]),
+                        )
+                        .set((
+                            columns::hits.eq(columns::hits + 1),
+                            columns::date.eq(date),
+                            columns::duration_ms
+                                .eq(i64::try_from(search_result.duration_ms).unwrap_or(i64::MAX)),
+                        ))
+                        .execute(connection)?;
+                    }
+                    prune(config, connection)?;
+                    return Ok(());
+                }
+                for search_result in results {
+                    let id = diesel::insert_into(crate::schema::search_history::table)
+                        .values((
+                            NewSearchHistoryEntry {
+                                pattern: &pattern,
+                                title: &search_result.title,
+                                include_mode: context.include_mode.to_db(),
+                                include_tags: &include_tags,
+                                exclude_mode: context.exclude_mode.to_db(),
+                                exclude_tags: &exclude_tags,
+                                case_insensitive: context.case_insensitive,
+                                case_smart: context.case_smart,
+                                ignore_accents: context.ignore_accents,
+                                before_context: i32::try_from(context.before_context)
+                                    .unwrap_or(i32::MAX),
+                                after_context: i32::try_from(context.after_context)
+                                    .unwrap_or(i32::MAX),
+                                match_count: i32::try_from(search_result.match_count())
+                                    .unwrap_or(i32::MAX),
+                                duration_ms: i64::try_from(search_result.duration_ms)
+                                    .unwrap_or(i64::MAX),
+                                user_id: config.owner(),
+                            },
+                            columns::date.eq(date),
+                        ))
+                        .returning(columns::id)
+                        .get_result::<i32>(connection)?;
+
+                    let mut search_result_vec = vec![];
+                    for single_result in search_result.results.iter() {
+                        search_result_vec.push(NewResult {
+                            search_history_id: id,
+                            result: single_result.as_str(),
+                        })
+                    }
+                    with_backend!(connection, |connection| {
+                        diesel::insert_into(crate::schema::search_results::table)
+                            .values(&search_result_vec)
+                            .execute(connection)
+                    })?;
+                }
+                prune(config, connection)?;
+                Ok(())
+            })?;
         Ok(results)
     }
 }
 
+/// Returns the ids (by title) of the entries of the last search of
+/// `owner` if it is the same search as `results`, i.e. it has the
+/// same pattern and context, and the same books with the same results.
+/// The entries of a search share its date, so the last search is
+/// found through its newest entry.
+fn repeated_search(
+    connection: &mut DbConnection,
+    owner: i32,
+    pattern: &str,
+    context: &HistoryContext,
+    results: &[SearchResults],
+) -> Result<Option<HashMap<String, i32>>, BookrabError> {
+    use schema::search_history::columns;
+    let last = schema::search_history::table
+        .filter(columns::user_id.eq(owner))
+        .order((columns::date.desc(), columns::id.desc()))
+        .select(SearchHistoryEntry::as_select())
+        .first(connection)
+        .optional()?;
+    let last = match last {
+        Some(v) if v.pattern == pattern && v.context() == *context => v,
+        _ => return Ok(None),
+    };
+    let entries: Vec<(i32, String)> = schema::search_history::table
+        .filter(columns::user_id.eq(owner))
+        .filter(columns::date.eq(last.date))
+        .filter(columns::pattern.eq(pattern))
+        .select((columns::id, columns::title))
+        .load(connection)?;
+    if entries.len() != results.len() {
+        return Ok(None);
+    }
+    let ids: Vec<i32> = entries.iter().map(|(id, _)| *id).collect();
+    let rows: Vec<(i32, String)> = schema::search_results::table
+        .filter(schema::search_results::columns::search_history_id.eq_any(&ids))
+        .order(schema::search_results::columns::id.asc())
+        .select((
+            schema::search_results::columns::search_history_id,
+            schema::search_results::columns::result,
+        ))
+        .load(connection)?;
+    let mut previous_results: HashMap<i32, Vec<String>> = HashMap::new();
+    for (id, result) in rows {
+        previous_results.entry(id).or_default().push(result);
+    }
+    let previous: HashMap<String, i32> =
+        entries.into_iter().map(|(id, title)| (title, id)).collect();
+    for search_result in results {
+        let Some(id) = previous.get(&search_result.title) else {
+            return Ok(None);
+        };
+        let previous_result = previous_results.get(id).map(Vec::as_slice).unwrap_or(&[]);
+        if previous_result != search_result.results.as_slice() {
+            return Ok(None);
+        }
+    }
+    Ok(Some(previous))
+}
+
+/// See [SearchHistory::prune].
+fn prune(config: &BookrabConfig, connection: &mut DbConnection) -> Result<usize, BookrabError> {
+    use schema::search_history::columns;
+    if config.history_max_age_days.is_none() && config.history_max_entries.is_none() {
+        return Ok(0);
+    }
+    connection.transaction(|connection| {
+        let mut stale: Vec<i32> = vec![];
+        if let Some(days) = config.history_max_age_days {
+            let limit = Local::now().naive_local() - TimeDelta::days(days.into());
+            stale.extend(
+                schema::search_history::table
+                    .select(columns::id)
+                    .filter(columns::user_id.eq(config.owner()))
+                    .filter(columns::date.lt(limit))
+                    .load::<i32>(connection)?,
+            );
+        }
+        if let Some(entries) = config.history_max_entries {
+            stale.extend(
+                schema::search_history::table
+                    .select(columns::id)
+                    .filter(columns::user_id.eq(config.owner()))
+                    .order((columns::date.desc(), columns::id.desc()))
+                    .offset(entries.into())
+                    .load::<i32>(connection)?,
+            );
+        }
+        diesel::delete(
+            schema::search_results::table
+                .filter(schema::search_results::columns::search_history_id.eq_any(&stale)),
+        )
+        .execute(connection)?;
+        let deleted =
+            diesel::delete(schema::search_history::table.filter(columns::id.eq_any(&stale)))
+                .execute(connection)?;
+        Ok(deleted)
+    })
+}
+
 #[cfg(test)]
 mod tests {
-    use super::SearchHistory;
-    use crate::books::test_utils::create_book_dir;
+    use super::{HistoryContext, HistoryFilter, HistoryOrder, JsonHistoryEntry, SearchHistory};
     use crate::books::test_utils::DBCONNECTION;
+    use crate::books::test_utils::{create_book_dir, random_string};
+    use crate::books::SearchResults;
+    use crate::database::DbConnection;
+    use crate::errors::BookrabError;
+    use chrono::NaiveDate;
+    use diesel::{Connection, RunQueryDsl};
+    use grep_regex::RegexMatcherBuilder;
+    use rand::Rng;
+    use std::collections::HashSet;
     #[test]
     fn get_entire_history() {
         //TODO: actually test this
@@ -80,4 +768,542 @@ mod tests {
         let history = SearchHistory::new(config, connection);
         history.get_entire_history().unwrap();
     }
+
+    #[test]
+    fn filtered_history() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = create_book_dir(connection).config;
+        // the database is shared between tests, so we need
+        // a pattern that no other test uses
+        let pattern = format!("filtered_{}%", random_string());
+        let results = vec![
+            SearchResults::new("a_book".to_string()),
+            SearchResults::new("another_book".to_string()),
+        ];
+        let history = SearchHistory::new(config.clone(), connection);
+        history
+            .register_history(pattern.clone(), &HistoryContext::default(), &results)
+            .unwrap();
+
+        let history = SearchHistory::new(config.clone(), connection);
+        let entries = history
+            .get_history(&HistoryFilter {
+                pattern: Some(pattern.clone()),
+                order: HistoryOrder::OldestFirst,
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].title, "a_book");
+
+        let history = SearchHistory::new(config.clone(), connection);
+        let entries = history
+            .get_history(&HistoryFilter {
+                pattern: Some(pattern.clone()),
+                title: Some("another_book".to_string()),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].pattern, pattern);
+
+        let history = SearchHistory::new(config.clone(), connection);
+        let entry = history.get_entry(entries[0].id).unwrap().unwrap();
+        assert_eq!(entry.title, "another_book");
+        let history = SearchHistory::new(config.for_user(i32::MAX), connection);
+        assert!(history.get_entry(entries[0].id).unwrap().is_none());
+
+        let history = SearchHistory::new(config.clone(), connection);
+        let entries = history
+            .get_history(&HistoryFilter {
+                pattern: Some(pattern.clone()),
+                offset: Some(1),
+                limit: Some(5),
+                ..Default::default()
+            })
+            .unwrap();
+        // newest first
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].title, "a_book");
+
+        // `%` and `_` are not wildcards
+        let history = SearchHistory::new(config, connection);
+        let entries = history
+            .get_history(&HistoryFilter {
+                pattern: Some(pattern.replace('_', "%")),
+                ..Default::default()
+            })
+            .unwrap();
+        assert!(entries.is_empty());
+    }
+
+    #[test]
+    fn paginated_history() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = create_book_dir(connection).config;
+        let pattern = format!("paginated_{}", random_string());
+        let results: Vec<SearchResults> =
+            (0..5).map(|i| SearchResults::new(i.to_string())).collect();
+        let history = SearchHistory::new(config.clone(), connection);
+        history
+            .register_history(pattern.clone(), &HistoryContext::default(), &results)
+            .unwrap();
+
+        let filter = HistoryFilter {
+            pattern: Some(pattern),
+            ..Default::default()
+        };
+        let mut titles = vec![];
+        let mut cursor = None;
+        loop {
+            let history = SearchHistory::new(config.clone(), connection);
+            let page = history
+                .get_history_page(&filter, cursor.as_ref(), 2)
+                .unwrap();
+            assert!(page.entries.len() <= 2);
+            titles.extend(page.entries.into_iter().map(|entry| entry.title));
+            cursor = page.next;
+            if cursor.is_none() {
+                break;
+            }
+        }
+        assert_eq!(titles, vec!["4", "3", "2", "1", "0"]);
+
+        let history = SearchHistory::new(config, connection);
+        let page = history
+            .get_history_page(
+                &HistoryFilter {
+                    order: HistoryOrder::OldestFirst,
+                    ..filter
+                },
+                None,
+                5,
+            )
+            .unwrap();
+        assert_eq!(page.entries[0].title, "0");
+        assert_eq!(page.next, None);
+    }
+
+    #[test]
+    fn export_and_restore_history() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        // a user of its own, so that other entries don't get in the way
+        let user_id = rand::thread_rng().gen_range(1_000..i32::MAX);
+        let config = create_book_dir(connection).config.for_user(user_id);
+        let entries: Vec<JsonHistoryEntry> = (0..2)
+            .map(|day| JsonHistoryEntry {
+                date: NaiveDate::from_ymd_opt(2024, 1, day + 1)
+                    .unwrap()
+                    .and_hms_opt(0, 0, 0)
+                    .unwrap(),
+                title: format!("book {day}"),
+                pattern: "armas".to_string(),
+                context: HistoryContext {
+                    include_tags: HashSet::from(["a".to_string()]),
+                    after_context: 2,
+                    ..Default::default()
+                },
+                results: vec!["[matched]armas[/matched]".to_string(), "other".to_string()],
+                match_count: 1,
+                duration_ms: 4,
+            })
+            .collect();
+        // other tests use the same database, so nothing is committed
+        connection.test_transaction::<_, BookrabError, _>(|connection| {
+            SearchHistory::new(config.clone(), connection).restore(&entries)?;
+            assert_eq!(
+                SearchHistory::new(config.clone(), connection).export()?,
+                entries
+            );
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn prune_history() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut config = create_book_dir(connection).config;
+        let pattern = random_string();
+        let results: Vec<SearchResults> =
+            (0..3).map(|i| SearchResults::new(i.to_string())).collect();
+        // other tests use the same database, so nothing is committed
+        connection.test_transaction::<_, BookrabError, _>(|connection| {
+            // keeps other tests from adding entries until the rollback
+            // (in the same order as `clear`, so that they don't deadlock)
+            diesel::sql_query("LOCK TABLE search_results, search_history IN EXCLUSIVE MODE")
+                .execute(connection)?;
+            SearchHistory::new(config.clone(), connection)
+                .register_history(pattern.clone(), &HistoryContext::default(), &results)?;
+            diesel::sql_query(
+                "UPDATE search_history SET date = NOW() - interval '10 days' WHERE pattern = $1 AND title = '0'",
+            )
+            .bind::<diesel::sql_types::Text, _>(&pattern)
+            .execute(connection)?;
+
+            config.history_max_age_days = Some(5);
+            SearchHistory::new(config.clone(), connection).prune()?;
+            let titles: Vec<String> = SearchHistory::new(config.clone(), connection)
+                .get_entire_history()?
+                .into_iter()
+                .filter(|entry| entry.pattern == pattern)
+                .map(|entry| entry.title)
+                .collect();
+            assert_eq!(titles, vec!["1", "2"]);
+
+            config.history_max_age_days = None;
+            config.history_max_entries = Some(1);
+            assert!(SearchHistory::new(config.clone(), connection).prune()? >= 1);
+            let entries = SearchHistory::new(config.clone(), connection).get_entire_history()?;
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].title, "2");
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn history_stats() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = create_book_dir(connection).config;
+        let result = |title: &str, results: Vec<&str>| SearchResults {
+            title: title.to_string(),
+            results: results.into_iter().map(String::from).collect(),
+            score: 0.0,
+            duration_ms: 6,
+            lines: vec![],
+        };
+        // other tests use the same database, so nothing is committed
+        connection.test_transaction::<_, BookrabError, _>(|connection| {
+            SearchHistory::new(config.clone(), connection).clear()?;
+            SearchHistory::new(config.clone(), connection).register_history(
+                "armas".to_string(),
+                &HistoryContext::default(),
+                &[
+                    result("1", vec!["[matched]armas[/matched]"]),
+                    result(
+                        "2",
+                        vec!["[matched]armas[/matched]", "[matched]armas[/matched]"],
+                    ),
+                ],
+            )?;
+            SearchHistory::new(config.clone(), connection).register_history(
+                "armas".to_string(),
+                &HistoryContext::default(),
+                &[result("2", vec!["[matched]armas[/matched]"])],
+            )?;
+            SearchHistory::new(config.clone(), connection).register_history(
+                "Tomé".to_string(),
+                &HistoryContext::default(),
+                &[result("1", vec![])],
+            )?;
+
+            let stats = SearchHistory::new(config.clone(), connection).stats(10)?;
+            let patterns: Vec<(&str, i64, i64)> = stats
+                .top_patterns
+                .iter()
+                .map(|p| (p.pattern.as_str(), p.count, p.match_count))
+                .collect();
+            assert_eq!(patterns, vec![("armas", 3, 4), ("Tomé", 1, 0)]);
+            assert_eq!(stats.top_patterns[0].avg_duration_ms, 6.0);
+            let books: Vec<(&str, i64)> = stats
+                .top_books
+                .iter()
+                .map(|b| (b.title.as_str(), b.count))
+                .collect();
+            assert_eq!(books, vec![("2", 2), ("1", 1)]);
+            assert_eq!(stats.searches_per_day.len(), 1);
+            assert_eq!(stats.searches_per_day[0].count, 4);
+
+            let stats = SearchHistory::new(config.clone(), connection).stats(1)?;
+            assert_eq!(stats.top_patterns.len(), 1);
+            assert_eq!(stats.top_books.len(), 1);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn search_history_results() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = create_book_dir(connection).config;
+        let pattern = format!("Taprobana_{}", random_string());
+        let results = vec![
+            SearchResults {
+                title: "2".to_string(),
+                results: vec![
+                    "Passaram ainda além da [matched]Taprobana[/matched],\n".to_string(),
+                    "Que da ocidental praia [matched]Lusitana[/matched],\n".to_string(),
+                ],
+                score: 0.0,
+                duration_ms: 0,
+                lines: vec![],
+            },
+            SearchResults {
+                title: "3".to_string(),
+                results: vec!["Que assi lho ensinou Cristo, e ele o prova.\n".to_string()],
+                score: 0.0,
+                duration_ms: 0,
+                lines: vec![],
+            },
+        ];
+        SearchHistory::new(config.clone(), connection)
+            .register_history(pattern.clone(), &HistoryContext::default(), &results)
+            .unwrap();
+
+        let filter = HistoryFilter {
+            pattern: Some(pattern),
+            ..Default::default()
+        };
+        let matches = SearchHistory::new(config.clone(), connection)
+            .search_results(
+                "taprobana",
+                RegexMatcherBuilder::new().case_insensitive(true),
+                &filter,
+            )
+            .unwrap();
+        assert_eq!(matches.len(), 1);
+        assert_eq!(matches[0].entry.title, "2");
+        assert_eq!(
+            matches[0].results,
+            vec!["Passaram ainda além da [matched]Taprobana[/matched],\n"]
+        );
+
+        // the old tags don't get in the way
+        let matches = SearchHistory::new(config.clone(), connection)
+            .search_results(r"matched|a L", &RegexMatcherBuilder::new(), &filter)
+            .unwrap();
+        assert_eq!(
+            matches[0].results,
+            vec!["Que da ocidental prai[matched]a L[/matched]usitana,\n"]
+        );
+
+        assert!(SearchHistory::new(config, connection)
+            .search_results("(", &RegexMatcherBuilder::new(), &filter)
+            .is_err());
+    }
+
+    #[test]
+    fn clear_history() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = create_book_dir(connection).config;
+        // other tests use the same database, so nothing is committed
+        connection.test_transaction::<_, BookrabError, _>(|connection| {
+            let history = SearchHistory::new(config.clone(), connection);
+            history.register_history(
+                random_string(),
+                &HistoryContext::default(),
+                &[SearchResults {
+                    title: "a_book".to_string(),
+                    results: vec!["result".to_string()],
+                    score: 0.0,
+                    duration_ms: 0,
+                    lines: vec![],
+                }],
+            )?;
+            SearchHistory::new(config.clone(), connection).clear()?;
+            let history = SearchHistory::new(config.clone(), connection);
+            assert!(history.get_entire_history()?.is_empty());
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn users_have_their_own_history() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = create_book_dir(connection).config;
+        let user_config = config.for_user(1);
+        let pattern = random_string();
+        let results = [SearchResults::new("a_book".to_string())];
+        connection.test_transaction::<_, BookrabError, _>(|connection| {
+            SearchHistory::new(config.clone(), connection).register_history(
+                pattern.clone(),
+                &HistoryContext::default(),
+                &results,
+            )?;
+            SearchHistory::new(user_config.clone(), connection).register_history(
+                pattern.clone(),
+                &HistoryContext::default(),
+                &results,
+            )?;
+            let filter = HistoryFilter {
+                pattern: Some(pattern.clone()),
+                ..Default::default()
+            };
+            // the same search of different users is not deduplicated
+            let entries =
+                SearchHistory::new(user_config.clone(), connection).get_history(&filter)?;
+            assert_eq!(entries.len(), 1);
+            assert_eq!(entries[0].hits, 1);
+
+            SearchHistory::new(user_config.clone(), connection).clear()?;
+            let history = SearchHistory::new(user_config.clone(), connection);
+            assert!(history.get_entire_history()?.is_empty());
+            let entries = SearchHistory::new(config.clone(), connection).get_history(&filter)?;
+            assert_eq!(entries.len(), 1);
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn failed_registration_is_rolled_back() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = create_book_dir(connection).config;
+        // nobody else searches this
+        let pattern = random_string();
+        let results = vec![
+            SearchResults {
+                title: random_string(),
+                results: vec!["[matched]armas[/matched]".to_string()],
+                score: 0.0,
+                duration_ms: 0,
+                lines: vec![],
+            },
+            SearchResults {
+                title: random_string(),
+                // Postgresql doesn't accept NUL in text
+                results: vec!["[matched]armas[/matched]\0".to_string()],
+                score: 0.0,
+                duration_ms: 0,
+                lines: vec![],
+            },
+        ];
+        assert!(SearchHistory::new(config.clone(), connection)
+            .register_history(pattern.clone(), &HistoryContext::default(), &results)
+            .is_err());
+        let entries = SearchHistory::new(config.clone(), connection)
+            .get_history(&HistoryFilter {
+                pattern: Some(pattern.clone()),
+                ..Default::default()
+            })
+            .unwrap();
+        // not even the entry of the first book is kept
+        assert!(entries.is_empty());
+
+        // the connection is still usable
+        SearchHistory::new(config.clone(), connection)
+            .register_history(pattern.clone(), &HistoryContext::default(), &results[..1])
+            .unwrap();
+        let entries = SearchHistory::new(config, connection)
+            .get_history(&HistoryFilter {
+                pattern: Some(pattern),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].title, results[0].title);
+    }
+
+    #[test]
+    fn repeated_searches() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = create_book_dir(connection).config;
+        // nobody else searches this book
+        let title = random_string();
+        let results = |results: Vec<&str>| {
+            vec![SearchResults {
+                title: title.clone(),
+                results: results.into_iter().map(String::from).collect(),
+                score: 0.0,
+                duration_ms: 0,
+                lines: vec![],
+            }]
+        };
+        let register = |connection: &mut DbConnection,
+                        pattern: &str,
+                        context: &HistoryContext,
+                        results: &Vec<SearchResults>| {
+            SearchHistory::new(config.clone(), connection)
+                .register_history(pattern.to_string(), context, results)
+                .unwrap();
+        };
+        let same = results(vec!["[matched]armas[/matched]"]);
+        register(connection, "armas", &HistoryContext::default(), &same);
+        register(connection, "armas", &HistoryContext::default(), &same);
+        register(connection, "armas", &HistoryContext::default(), &same);
+        // different results, pattern or context are new entries
+        let different = results(vec!["[matched]armas[/matched]", "[matched]armas[/matched]"]);
+        register(connection, "armas", &HistoryContext::default(), &different);
+        register(
+            connection,
+            "armas|x",
+            &HistoryContext::default(),
+            &different,
+        );
+        let context = HistoryContext {
+            case_insensitive: true,
+            ..Default::default()
+        };
+        register(connection, "armas|x", &context, &different);
+        // not consecutive anymore
+        register(connection, "armas", &HistoryContext::default(), &same);
+
+        let entries = SearchHistory::new(config, connection)
+            .get_history(&HistoryFilter {
+                title: Some(title.clone()),
+                order: HistoryOrder::OldestFirst,
+                ..Default::default()
+            })
+            .unwrap();
+        let hits: Vec<(&str, i32)> = entries
+            .iter()
+            .map(|entry| (entry.pattern.as_str(), entry.hits))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![
+                ("armas", 3),
+                ("armas", 1),
+                ("armas|x", 1),
+                ("armas|x", 1),
+                ("armas", 1)
+            ]
+        );
+    }
+
+    #[test]
+    fn only_the_last_search_is_repeated() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = create_book_dir(connection).config;
+        let book = |title: &str| SearchResults {
+            title: title.to_string(),
+            results: vec!["[matched]armas[/matched]".to_string()],
+            score: 0.0,
+            duration_ms: 0,
+            lines: vec![],
+        };
+        let (first, second) = (random_string(), random_string());
+        let register = |connection: &mut DbConnection, pattern: &str, results: &[SearchResults]| {
+            SearchHistory::new(config.clone(), connection)
+                .register_history(pattern.to_string(), &HistoryContext::default(), results)
+                .unwrap();
+        };
+        let pattern = random_string();
+        let other_pattern = random_string();
+        // A -> B -> A: the second A isn't merged into the first one
+        register(connection, &pattern, &[book(&first)]);
+        register(connection, &other_pattern, &[book(&second)]);
+        register(connection, &pattern, &[book(&first)]);
+        // the same pattern over other books is another search
+        register(connection, &pattern, &[book(&first), book(&second)]);
+        register(connection, &pattern, &[book(&first), book(&second)]);
+
+        let entries = SearchHistory::new(config, connection)
+            .get_history(&HistoryFilter {
+                pattern: Some(pattern),
+                order: HistoryOrder::OldestFirst,
+                ..Default::default()
+            })
+            .unwrap();
+        let hits: Vec<(&str, i32)> = entries
+            .iter()
+            .map(|entry| (entry.title.as_str(), entry.hits))
+            .collect();
+        assert_eq!(
+            hits,
+            vec![
+                (first.as_str(), 1),
+                (first.as_str(), 1),
+                (first.as_str(), 2),
+                (second.as_str(), 2)
+            ]
+        );
+    }
 }
@@ -0,0 +1,508 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::{self, Write},
+    path::PathBuf,
+};
+
+use chrono::{Local, NaiveDateTime};
+
+use crate::{
+    config::{BookrabConfig, HistoryBackendKind},
+    database::DbConnection,
+    errors::BookrabError,
+};
+
+use super::{
+    history::{HistoryContext, SearchHistory},
+    SearchResults,
+};
+
+/// Storage of the search history.
+/// See [history_backend] to get the one chosen in the config.
+pub trait HistoryBackend {
+    /// Stores the results of a search. See [SearchHistory::register_history].
+    fn register(
+        &mut self,
+        pattern: &str,
+        context: &HistoryContext,
+        results: &[SearchResults],
+    ) -> Result<(), BookrabError>;
+
+    /// Deletes the entire history. See [SearchHistory::clear].
+    fn clear(&mut self) -> Result<(), BookrabError>;
+
+    /// Makes the entries of `title` refer to `new_title`
+    /// (see [super::RootBookDir::rename]).
+    fn rename_book(&mut self, title: &str, new_title: &str) -> Result<(), BookrabError>;
+
+    /// Every entry with its results (oldest first), so that the history
+    /// can be moved to another backend (see [super::RootBookDir::export]).
+    fn export(&mut self) -> Result<Vec<JsonHistoryEntry>, BookrabError>;
+
+    /// Appends `entries` keeping their dates (see [super::RootBookDir::import]).
+    fn restore(&mut self, entries: &[JsonHistoryEntry]) -> Result<(), BookrabError>;
+}
+
+/// Returns the backend chosen in [BookrabConfig::history_backend].
+/// Filtering, pagination and statistics are done by [query_history].
+/// If the SQLite database can't be opened, the error is
+/// returned by the first operation of the backend.
+/// Without a `connection`, the history of the database is disabled.
+pub fn history_backend<'a>(
+    config: &BookrabConfig,
+    connection: Option<&'a mut DbConnection>,
+) -> Box<dyn HistoryBackend + 'a> {
+    match config.history_backend {
+        HistoryBackendKind::Postgres => match connection {
+            Some(connection) => Box::new(SearchHistory::new(config.clone(), connection)),
+            None => Box::new(NoHistory),
+        },
+        HistoryBackendKind::JsonFile => Box::new(JsonFileHistory::new(config.history_file())),
+        #[cfg(feature = "sqlite")]
+        HistoryBackendKind::Sqlite => {
+            match super::history_sqlite::SqliteHistory::new(config.clone()) {
+                Ok(v) => Box::new(v),
+                Err(e) => Box::new(UnavailableHistory(Some(e))),
+            }
+        }
+        HistoryBackendKind::Disabled => Box::new(NoHistory),
+    }
+}
+
+/// Runs `query` on the history of [BookrabConfig::history_backend].
+/// Only the histories stored in a database (the one of `connection`
+/// or the SQLite file) can be filtered, paginated and aggregated,
+/// the others return [BookrabError::HistoryNotQueryable].
+pub fn query_history<T>(
+    config: &BookrabConfig,
+    connection: Option<&mut DbConnection>,
+    query: impl FnOnce(SearchHistory<'_>) -> Result<T, BookrabError>,
+) -> Result<T, BookrabError> {
+    match config.history_backend {
+        HistoryBackendKind::Postgres => match connection {
+            Some(connection) => query(SearchHistory::new(config.clone(), connection)),
+            None => Err(BookrabError::DatabaseUnavailable {
+                error: (),
+                operation: "query the search history".to_string(),
+            }),
+        },
+        #[cfg(feature = "sqlite")]
+        HistoryBackendKind::Sqlite => {
+            query(super::history_sqlite::SqliteHistory::new(config.clone())?.history())
+        }
+        HistoryBackendKind::JsonFile | HistoryBackendKind::Disabled => {
+            Err(BookrabError::HistoryNotQueryable {
+                error: (),
+                backend: format!("{:?}", config.history_backend),
+            })
+        }
+    }
+}
+
+impl HistoryBackend for SearchHistory<'_> {
+    fn register(
+        &mut self,
+        pattern: &str,
+        context: &HistoryContext,
+        results: &[SearchResults],
+    ) -> Result<(), BookrabError> {
+        SearchHistory::new(self.config.clone(), self.connection).register_history(
+            pattern.to_string(),
+            context,
+            results,
+        )?;
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), BookrabError> {
+        SearchHistory::new(self.config.clone(), self.connection).clear()
+    }
+
+    fn rename_book(&mut self, title: &str, new_title: &str) -> Result<(), BookrabError> {
+        SearchHistory::new(self.config.clone(), self.connection).rename_book(title, new_title)?;
+        Ok(())
+    }
+
+    fn export(&mut self) -> Result<Vec<JsonHistoryEntry>, BookrabError> {
+        SearchHistory::new(self.config.clone(), self.connection).export()
+    }
+
+    fn restore(&mut self, entries: &[JsonHistoryEntry]) -> Result<(), BookrabError> {
+        SearchHistory::new(self.config.clone(), self.connection).restore(entries)
+    }
+}
+
+/// Line of the file of [JsonFileHistory]. Other backends also
+/// use it to export their entries (see [HistoryBackend::export]).
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct JsonHistoryEntry {
+    pub date: NaiveDateTime,
+    pub title: String,
+    pub pattern: String,
+    pub context: HistoryContext,
+    pub results: Vec<String>,
+    pub match_count: usize,
+    pub duration_ms: u64,
+}
+
+/// A search of the history, made of the entries it stored (one per book).
+#[derive(Clone, Debug, PartialEq)]
+pub struct PastSearch {
+    pub date: NaiveDateTime,
+    pub pattern: String,
+    pub context: HistoryContext,
+    /// Books that were searched (with or without matches)
+    pub books: usize,
+    pub match_count: usize,
+}
+
+/// Groups the entries of [HistoryBackend::export] by search (newest first).
+/// Entries of the same search share their date, pattern and context.
+pub fn past_searches(mut entries: Vec<JsonHistoryEntry>) -> Vec<PastSearch> {
+    entries.sort_by(|a, b| b.date.cmp(&a.date).then_with(|| a.pattern.cmp(&b.pattern)));
+    let mut searches: Vec<PastSearch> = vec![];
+    for entry in entries {
+        match searches.last_mut() {
+            Some(search)
+                if search.date == entry.date
+                    && search.pattern == entry.pattern
+                    && search.context == entry.context =>
+            {
+                search.books += 1;
+                search.match_count += entry.match_count;
+            }
+            _ => searches.push(PastSearch {
+                date: entry.date,
+                pattern: entry.pattern,
+                context: entry.context,
+                books: 1,
+                match_count: entry.match_count,
+            }),
+        }
+    }
+    searches
+}
+
+/// Stores the history in a file with one JSON entry per line,
+/// so that entries are appended without reading the whole file.
+pub struct JsonFileHistory {
+    path: PathBuf,
+}
+
+impl JsonFileHistory {
+    pub fn new(path: PathBuf) -> Self {
+        JsonFileHistory { path }
+    }
+
+    /// Appends `entries` to the end of the file.
+    fn append(&self, entries: &[JsonHistoryEntry]) -> Result<(), BookrabError> {
+        let mut lines = String::new();
+        for entry in entries {
+            // entries are made of strings, numbers and sets of strings
+            lines.push_str(&serde_json::to_string(entry).unwrap());
+            lines.push('\n');
+        }
+        let written = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&self.path)
+            .and_then(|mut file| file.write_all(lines.as_bytes()));
+        if let Err(e) = written {
+            return Err(BookrabError::CouldntWriteFile {
+                error: (),
+                path: self.path.clone(),
+                err: e,
+            });
+        }
+        Ok(())
+    }
+
+    /// Reads all entries (oldest first).
+    pub fn entries(&self) -> Result<Vec<JsonHistoryEntry>, BookrabError> {
+        let txt = match fs::read_to_string(&self.path) {
+            Ok(v) => v,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+            Err(e) => {
+                return Err(BookrabError::CouldntReadFile {
+                    error: (),
+                    path: self.path.clone(),
+                    err: e,
+                })
+            }
+        };
+        txt.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|e| BookrabError::CouldntReadFile {
+                    error: (),
+                    path: self.path.clone(),
+                    err: io::Error::other(e),
+                })
+            })
+            .collect()
+    }
+}
+
+impl HistoryBackend for JsonFileHistory {
+    fn register(
+        &mut self,
+        pattern: &str,
+        context: &HistoryContext,
+        results: &[SearchResults],
+    ) -> Result<(), BookrabError> {
+        let date = Local::now().naive_local();
+        let entries: Vec<JsonHistoryEntry> = results
+            .iter()
+            .map(|search_result| JsonHistoryEntry {
+                date,
+                title: search_result.title.clone(),
+                pattern: pattern.to_string(),
+                context: context.clone(),
+                results: search_result.results.clone(),
+                match_count: search_result.match_count(),
+                duration_ms: search_result.duration_ms,
+            })
+            .collect();
+        self.append(&entries)
+    }
+
+    fn clear(&mut self) -> Result<(), BookrabError> {
+        match fs::remove_file(&self.path) {
+            Ok(_) => Ok(()),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(BookrabError::CouldntWriteFile {
+                error: (),
+                path: self.path.clone(),
+                err: e,
+            }),
+        }
+    }
+
+    /// Rewrites the whole file (only when the book was searched before).
+    fn rename_book(&mut self, title: &str, new_title: &str) -> Result<(), BookrabError> {
+        let mut entries = self.entries()?;
+        if !entries.iter().any(|entry| entry.title == title) {
+            return Ok(());
+        }
+        let mut lines = String::new();
+        for entry in entries.iter_mut() {
+            if entry.title == title {
+                entry.title = new_title.to_string();
+            }
+            lines.push_str(&serde_json::to_string(&entry).unwrap());
+            lines.push('\n');
+        }
+        if let Err(e) = fs::write(&self.path, lines) {
+            return Err(BookrabError::CouldntWriteFile {
+                error: (),
+                path: self.path.clone(),
+                err: e,
+            });
+        }
+        Ok(())
+    }
+
+    fn export(&mut self) -> Result<Vec<JsonHistoryEntry>, BookrabError> {
+        self.entries()
+    }
+
+    fn restore(&mut self, entries: &[JsonHistoryEntry]) -> Result<(), BookrabError> {
+        self.append(entries)
+    }
+}
+
+/// Backend that couldn't be opened. The error is returned
+/// by the first operation, so that searches still fail loudly.
+#[cfg(feature = "sqlite")]
+struct UnavailableHistory(Option<BookrabError>);
+
+#[cfg(feature = "sqlite")]
+impl UnavailableHistory {
+    fn error(&mut self) -> Result<(), BookrabError> {
+        match self.0.take() {
+            Some(e) => Err(e),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl HistoryBackend for UnavailableHistory {
+    fn register(
+        &mut self,
+        _pattern: &str,
+        _context: &HistoryContext,
+        _results: &[SearchResults],
+    ) -> Result<(), BookrabError> {
+        self.error()
+    }
+
+    fn clear(&mut self) -> Result<(), BookrabError> {
+        self.error()
+    }
+
+    fn rename_book(&mut self, _title: &str, _new_title: &str) -> Result<(), BookrabError> {
+        self.error()
+    }
+
+    fn export(&mut self) -> Result<Vec<JsonHistoryEntry>, BookrabError> {
+        self.error().map(|_| vec![])
+    }
+
+    fn restore(&mut self, _entries: &[JsonHistoryEntry]) -> Result<(), BookrabError> {
+        self.error()
+    }
+}
+
+/// Doesn't store anything.
+pub struct NoHistory;
+
+impl HistoryBackend for NoHistory {
+    fn register(
+        &mut self,
+        _pattern: &str,
+        _context: &HistoryContext,
+        _results: &[SearchResults],
+    ) -> Result<(), BookrabError> {
+        Ok(())
+    }
+
+    fn clear(&mut self) -> Result<(), BookrabError> {
+        Ok(())
+    }
+
+    fn rename_book(&mut self, _title: &str, _new_title: &str) -> Result<(), BookrabError> {
+        Ok(())
+    }
+
+    fn export(&mut self) -> Result<Vec<JsonHistoryEntry>, BookrabError> {
+        Ok(vec![])
+    }
+
+    fn restore(&mut self, _entries: &[JsonHistoryEntry]) -> Result<(), BookrabError> {
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::test_utils::{create_book_dir, random_string, DBCONNECTION, LUSIADAS2};
+    use crate::books::{Exclude, Include, SearchOptions};
+    use grep_regex::RegexMatcherBuilder;
+    use grep_searcher::SearcherBuilder;
+
+    #[test]
+    fn json_file_history() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        let history_file = book_dir.config.book_path.join("..").join(random_string());
+        book_dir.config.history_backend = HistoryBackendKind::JsonFile;
+        book_dir.config.history_file = Some(history_file.clone());
+        book_dir.upload("lusiadas", LUSIADAS2, Default::default())?;
+        book_dir.search_by_tags(
+            &Include {
+                mode: Default::default(),
+                tags: Default::default(),
+            },
+            &Exclude::default(),
+            "Taprobana".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new(),
+            &SearchOptions::default(),
+        )?;
+
+        let history = JsonFileHistory::new(history_file);
+        let entries = history.entries()?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].title, "lusiadas");
+        assert_eq!(entries[0].pattern, "Taprobana");
+        assert_eq!(entries[0].match_count, 1);
+        assert_eq!(
+            entries[0].results,
+            vec!["Passaram ainda além da [matched]Taprobana[/matched],\n"]
+        );
+
+        // the file can't be filtered or aggregated
+        assert!(matches!(
+            query_history(&book_dir.config, None, |history| history.stats(10)),
+            Err(BookrabError::HistoryNotQueryable { .. })
+        ));
+
+        let mut history = history;
+        history.clear()?;
+        assert!(history.entries()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn group_past_searches() {
+        let date = |day| {
+            chrono::NaiveDate::from_ymd_opt(2024, 1, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+        };
+        let entry = |day, title: &str, pattern: &str, match_count| JsonHistoryEntry {
+            date: date(day),
+            title: title.into(),
+            pattern: pattern.into(),
+            context: HistoryContext::default(),
+            results: vec![],
+            match_count,
+            duration_ms: 0,
+        };
+        let searches = past_searches(vec![
+            entry(1, "1", "armas", 2),
+            entry(1, "2", "armas", 3),
+            entry(2, "1", "barões", 1),
+            entry(1, "1", "Taprobana", 0),
+        ]);
+        let summary: Vec<(u32, &str, usize, usize)> = searches
+            .iter()
+            .map(|search| {
+                (
+                    chrono::Datelike::day(&search.date),
+                    search.pattern.as_str(),
+                    search.books,
+                    search.match_count,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (2, "barões", 1, 1),
+                (1, "Taprobana", 1, 0),
+                (1, "armas", 2, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn disabled_history() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        book_dir.config.history_backend = HistoryBackendKind::Disabled;
+        book_dir.upload("lusiadas", LUSIADAS2, Default::default())?;
+        // nobody else searches this
+        let pattern = format!("Taprobana|{}", random_string());
+        book_dir.search(
+            "lusiadas".to_string(),
+            pattern.clone(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new(),
+            &SearchOptions::default(),
+        )?;
+        let entries = SearchHistory::new(
+            book_dir.config.clone(),
+            book_dir.connection.as_deref_mut().unwrap(),
+        )
+        .get_history(&crate::books::history::HistoryFilter {
+            pattern: Some(pattern),
+            ..Default::default()
+        })?;
+        assert!(entries.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,148 @@
+use crate::{
+    config::BookrabConfig,
+    database::{sqlite::establish_sqlite_connection, DbConnection},
+    errors::BookrabError,
+};
+
+use super::{
+    history::{HistoryContext, SearchHistory},
+    history_backend::{HistoryBackend, JsonHistoryEntry},
+    SearchResults,
+};
+
+/// Stores the history in a SQLite file, so that it survives
+/// without a database server. The file has the tables of a SQLite
+/// database (see [crate::database::sqlite]), so its entries are
+/// filtered, paginated and aggregated by [SearchHistory] like the
+/// ones of the database.
+pub struct SqliteHistory {
+    config: BookrabConfig,
+    connection: DbConnection,
+}
+
+impl SqliteHistory {
+    /// Opens (or creates) the database in [BookrabConfig::history_file].
+    pub fn new(config: BookrabConfig) -> Result<Self, BookrabError> {
+        let path = config.history_file();
+        match establish_sqlite_connection(&path) {
+            Ok(connection) => Ok(SqliteHistory {
+                config,
+                connection: DbConnection::Sqlite(connection),
+            }),
+            Err(err) => Err(BookrabError::CouldntConnectToDatabase {
+                error: (),
+                url: path.to_string_lossy().to_string(),
+                err,
+            }),
+        }
+    }
+
+    /// History of the file.
+    pub fn history(&mut self) -> SearchHistory<'_> {
+        SearchHistory::new(self.config.clone(), &mut self.connection)
+    }
+}
+
+impl HistoryBackend for SqliteHistory {
+    fn register(
+        &mut self,
+        pattern: &str,
+        context: &HistoryContext,
+        results: &[SearchResults],
+    ) -> Result<(), BookrabError> {
+        self.history().register(pattern, context, results)
+    }
+
+    fn clear(&mut self) -> Result<(), BookrabError> {
+        HistoryBackend::clear(&mut self.history())
+    }
+
+    fn rename_book(&mut self, title: &str, new_title: &str) -> Result<(), BookrabError> {
+        HistoryBackend::rename_book(&mut self.history(), title, new_title)
+    }
+
+    fn export(&mut self) -> Result<Vec<JsonHistoryEntry>, BookrabError> {
+        HistoryBackend::export(&mut self.history())
+    }
+
+    fn restore(&mut self, entries: &[JsonHistoryEntry]) -> Result<(), BookrabError> {
+        HistoryBackend::restore(&mut self.history(), entries)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::history::HistoryFilter;
+    use crate::books::history_backend::query_history;
+    use crate::books::test_utils::{create_book_dir, random_string, DBCONNECTION, LUSIADAS2};
+    use crate::books::SearchOptions;
+    use crate::config::HistoryBackendKind;
+    use grep_regex::RegexMatcherBuilder;
+    use grep_searcher::SearcherBuilder;
+
+    #[test]
+    fn sqlite_history() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        let history_file = book_dir.config.book_path.join("..").join(random_string());
+        book_dir.config.history_backend = HistoryBackendKind::Sqlite;
+        book_dir.config.history_file = Some(history_file);
+        book_dir.config.history_max_entries = Some(1);
+        book_dir.upload("lusiadas", LUSIADAS2, Default::default())?;
+        let search = |book_dir: &mut crate::books::RootBookDir, pattern: &str| {
+            book_dir.search(
+                "lusiadas".to_string(),
+                pattern.to_string(),
+                SearcherBuilder::new().build(),
+                RegexMatcherBuilder::new(),
+                &SearchOptions::default(),
+            )
+        };
+        search(&mut book_dir, "Taprobana")?;
+        search(&mut book_dir, "Taprobana")?;
+
+        let mut history = SqliteHistory::new(book_dir.config.clone())?;
+        let entries = history.history().get_entire_history()?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].pattern, "Taprobana");
+        assert_eq!(entries[0].hits, 2);
+        assert_eq!(entries[0].match_count, 1);
+        assert_eq!(entries[0].context(), HistoryContext::default());
+        assert_eq!(
+            history.export()?[0].results,
+            vec!["Passaram ainda além da [matched]Taprobana[/matched],\n"]
+        );
+
+        let matches = history.history().search_results(
+            "Taprobana",
+            &RegexMatcherBuilder::new(),
+            &HistoryFilter::default(),
+        )?;
+        assert_eq!(matches[0].entry.pattern, "Taprobana");
+
+        // only the most recent entry is kept
+        search(&mut book_dir, "Ceilão")?;
+        let entries = history.history().get_history(&HistoryFilter::default())?;
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].pattern, "Ceilão");
+        let stats = history.history().stats(10)?;
+        assert_eq!(stats.top_patterns[0].pattern, "Ceilão");
+        // routes query the file without a connection to the database
+        let page = query_history(&book_dir.config, None, |history| {
+            history.get_history_page(
+                &HistoryFilter {
+                    pattern: Some("Cei".to_string()),
+                    ..Default::default()
+                },
+                None,
+                10,
+            )
+        })?;
+        assert_eq!(page.entries[0].pattern, "Ceilão");
+
+        history.clear()?;
+        assert!(history.history().get_entire_history()?.is_empty());
+        Ok(())
+    }
+}
@@ -0,0 +1,441 @@
+//! Converters that turn other formats into the plain text of a book.
+use std::{
+    collections::HashMap,
+    io::{Cursor, Read},
+    path::Path,
+};
+
+use pulldown_cmark::{Event, Parser, TagEnd};
+use serde::{Deserialize, Serialize};
+use zip::ZipArchive;
+
+use crate::errors::BookrabError;
+
+/// Formats that can be uploaded as books.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SourceFormat {
+    /// Stored as it is
+    Text,
+    Markdown,
+    Html,
+    Epub,
+}
+
+impl SourceFormat {
+    /// Format of a MIME type (e.g. `text/markdown`).
+    pub fn from_content_type(content_type: &str) -> Option<SourceFormat> {
+        let essence = content_type.split(';').next().unwrap_or("").trim();
+        match essence.to_lowercase().as_str() {
+            "text/plain" => Some(SourceFormat::Text),
+            "text/markdown" | "text/x-markdown" => Some(SourceFormat::Markdown),
+            "text/html" | "application/xhtml+xml" => Some(SourceFormat::Html),
+            "application/epub+zip" => Some(SourceFormat::Epub),
+            _ => None,
+        }
+    }
+
+    /// Format of a file by its extension (e.g. `book.md`).
+    pub fn from_file_name(file_name: &str) -> Option<SourceFormat> {
+        let extension = Path::new(file_name).extension()?.to_str()?.to_lowercase();
+        match extension.as_str() {
+            "txt" => Some(SourceFormat::Text),
+            "md" | "markdown" => Some(SourceFormat::Markdown),
+            "html" | "htm" | "xhtml" => Some(SourceFormat::Html),
+            "epub" => Some(SourceFormat::Epub),
+            _ => None,
+        }
+    }
+}
+
+/// Title of a book converted from `file_name`: the extension becomes `.txt`
+/// (e.g. `lusiadas.epub` becomes `lusiadas.txt`).
+pub fn converted_title(file_name: &str) -> String {
+    match Path::new(file_name).file_stem() {
+        Some(stem) => format!("{}.txt", stem.to_string_lossy()),
+        None => file_name.to_string(),
+    }
+}
+
+/// Converts the contents of `file_name` from `format` into plain text.
+pub fn convert(
+    format: SourceFormat,
+    file_name: &str,
+    bytes: Vec<u8>,
+) -> Result<String, BookrabError> {
+    let error = |reason: String| BookrabError::CouldntConvertBook {
+        error: (),
+        filename: file_name.to_string(),
+        reason,
+    };
+    let utf8 = |bytes: Vec<u8>| {
+        String::from_utf8(bytes).map_err(|_| BookrabError::NotUnicode {
+            error: (),
+            what: file_name.to_string(),
+        })
+    };
+    match format {
+        SourceFormat::Text => utf8(bytes),
+        SourceFormat::Markdown => Ok(markdown_to_text(&utf8(bytes)?)),
+        SourceFormat::Html => Ok(html_to_text(&utf8(bytes)?)),
+        SourceFormat::Epub => epub_to_text(bytes).map_err(error),
+    }
+}
+
+/// Text of a markdown document, without its markup.
+/// Blocks are separated by empty lines.
+pub fn markdown_to_text(markdown: &str) -> String {
+    let mut txt = String::new();
+    for event in Parser::new(markdown) {
+        match event {
+            Event::Text(text) | Event::Code(text) => txt.push_str(&text),
+            Event::Html(html) | Event::InlineHtml(html) => txt.push_str(&html_to_text(&html)),
+            Event::SoftBreak | Event::HardBreak => txt.push('\n'),
+            Event::End(
+                TagEnd::Paragraph
+                | TagEnd::Heading(_)
+                | TagEnd::CodeBlock
+                | TagEnd::BlockQuote(_)
+                | TagEnd::Item
+                | TagEnd::TableRow,
+            ) => txt.push_str("\n\n"),
+            Event::End(TagEnd::TableCell) => txt.push('\t'),
+            Event::Rule => txt.push_str("\n\n"),
+            _ => {}
+        }
+    }
+    tidy(&txt)
+}
+
+/// Tags whose contents aren't text.
+const SKIPPED_TAGS: &[&str] = &["head", "script", "style", "template"];
+/// Tags that start a new line.
+const BLOCK_TAGS: &[&str] = &[
+    "address",
+    "article",
+    "aside",
+    "blockquote",
+    "br",
+    "dd",
+    "div",
+    "dl",
+    "dt",
+    "figcaption",
+    "figure",
+    "footer",
+    "h1",
+    "h2",
+    "h3",
+    "h4",
+    "h5",
+    "h6",
+    "header",
+    "hr",
+    "li",
+    "main",
+    "nav",
+    "ol",
+    "p",
+    "pre",
+    "section",
+    "table",
+    "tr",
+    "ul",
+];
+
+/// Text of an HTML (or XHTML) document, without its tags.
+/// Block elements (paragraphs, headings, ...) start new lines.
+pub fn html_to_text(html: &str) -> String {
+    let mut txt = String::new();
+    let mut rest = html;
+    let mut skipping: Option<String> = None;
+    let mut preformatted = false;
+    while !rest.is_empty() {
+        if let Some(comment) = rest.strip_prefix("<!--") {
+            rest = comment.split_once("-->").map_or("", |(_, after)| after);
+            continue;
+        }
+        if rest.starts_with('<') {
+            let Some((tag, after)) = rest[1..].split_once('>') else {
+                break;
+            };
+            rest = after;
+            let closing = tag.starts_with('/');
+            let name: String = tag
+                .trim_start_matches('/')
+                .chars()
+                .take_while(|c| c.is_ascii_alphanumeric())
+                .collect::<String>()
+                .to_lowercase();
+            if let Some(skipped) = &skipping {
+                if closing && *skipped == name {
+                    skipping = None;
+                }
+                continue;
+            }
+            if SKIPPED_TAGS.contains(&name.as_str()) && !closing && !tag.ends_with('/') {
+                skipping = Some(name);
+            } else if BLOCK_TAGS.contains(&name.as_str()) {
+                txt.push('\n');
+                if name == "pre" {
+                    preformatted = !closing;
+                }
+            } else if name == "td" || name == "th" {
+                txt.push('\t');
+            }
+            continue;
+        }
+        let end = rest.find('<').unwrap_or(rest.len());
+        if skipping.is_none() {
+            let text = decode_entities(&rest[..end]);
+            if preformatted {
+                txt.push_str(&text);
+            } else {
+                txt.push_str(&collapse_whitespace(&text, txt.ends_with([' ', '\n'])));
+            }
+        }
+        rest = &rest[end..];
+    }
+    tidy(&txt)
+}
+
+/// Turns runs of whitespace into a single space, like browsers do.
+fn collapse_whitespace(text: &str, after_space: bool) -> String {
+    let mut collapsed = String::with_capacity(text.len());
+    let mut after_space = after_space;
+    for c in text.chars() {
+        if c.is_whitespace() {
+            if !after_space {
+                collapsed.push(' ');
+            }
+            after_space = true;
+        } else {
+            collapsed.push(c);
+            after_space = false;
+        }
+    }
+    collapsed
+}
+
+/// Replaces character references (e.g. `&amp;` or `&#233;`).
+fn decode_entities(text: &str) -> String {
+    let mut decoded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('&') {
+        decoded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        let entity = rest[1..]
+            .split_once(';')
+            .filter(|(name, _)| name.len() <= 10)
+            .and_then(|(name, after)| Some((entity(name)?, after)));
+        match entity {
+            Some((c, after)) => {
+                decoded.push(c);
+                rest = after;
+            }
+            None => {
+                decoded.push('&');
+                rest = &rest[1..];
+            }
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+/// Character of a reference without `&` and `;` (e.g. `amp` or `#233`).
+fn entity(name: &str) -> Option<char> {
+    if let Some(hex) = name.strip_prefix("#x").or_else(|| name.strip_prefix("#X")) {
+        return u32::from_str_radix(hex, 16).ok().and_then(char::from_u32);
+    }
+    if let Some(decimal) = name.strip_prefix('#') {
+        return decimal.parse().ok().and_then(char::from_u32);
+    }
+    match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        "mdash" => Some('—'),
+        "ndash" => Some('–'),
+        "hellip" => Some('…'),
+        "laquo" => Some('«'),
+        "raquo" => Some('»'),
+        "lsquo" => Some('‘'),
+        "rsquo" => Some('’'),
+        "ldquo" => Some('“'),
+        "rdquo" => Some('”'),
+        _ => None,
+    }
+}
+
+/// Trims the lines of `txt` and leaves at most one empty line between blocks.
+fn tidy(txt: &str) -> String {
+    let mut tidy = String::with_capacity(txt.len());
+    let mut empty_lines = 0;
+    for line in txt.lines().map(str::trim) {
+        if line.is_empty() {
+            empty_lines += 1;
+            continue;
+        }
+        if !tidy.is_empty() {
+            tidy.push_str(if empty_lines > 0 { "\n\n" } else { "\n" });
+        }
+        empty_lines = 0;
+        tidy.push_str(line);
+    }
+    if !tidy.is_empty() {
+        tidy.push('\n');
+    }
+    tidy
+}
+
+/// Text of the chapters of an EPUB, in reading order (the spine of the package).
+fn epub_to_text(bytes: Vec<u8>) -> Result<String, String> {
+    let mut archive = ZipArchive::new(Cursor::new(bytes)).map_err(|e| e.to_string())?;
+    let mut read = |path: &str| -> Result<String, String> {
+        let mut entry = archive.by_name(path).map_err(|e| format!("{path}: {e}"))?;
+        let mut contents = String::new();
+        entry
+            .read_to_string(&mut contents)
+            .map_err(|e| format!("{path}: {e}"))?;
+        Ok(contents)
+    };
+
+    let container = read("META-INF/container.xml")?;
+    let container = roxmltree::Document::parse(&container).map_err(|e| e.to_string())?;
+    let package_path = container
+        .descendants()
+        .find(|node| node.has_tag_name("rootfile"))
+        .and_then(|node| node.attribute("full-path"))
+        .ok_or("there is no rootfile in META-INF/container.xml")?
+        .to_string();
+    // paths in the package are relative to its folder
+    let folder = match package_path.rsplit_once('/') {
+        Some((folder, _)) => format!("{folder}/"),
+        None => String::new(),
+    };
+
+    let package = read(&package_path)?;
+    let package = roxmltree::Document::parse(&package).map_err(|e| e.to_string())?;
+    let manifest: HashMap<&str, &str> = package
+        .descendants()
+        .filter(|node| node.has_tag_name("item"))
+        .filter_map(|node| Some((node.attribute("id")?, node.attribute("href")?)))
+        .collect();
+    let mut chapters = vec![];
+    for itemref in package
+        .descendants()
+        .filter(|node| node.has_tag_name("itemref"))
+    {
+        let Some(href) = itemref.attribute("idref").and_then(|id| manifest.get(id)) else {
+            continue;
+        };
+        let path = format!("{folder}{}", href.replace("%20", " "));
+        chapters.push(html_to_text(&read(&path)?));
+    }
+    if chapters.is_empty() {
+        return Err(format!("there are no chapters in {package_path}"));
+    }
+    Ok(chapters.join("\n"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::{Cursor, Write};
+
+    use zip::{write::SimpleFileOptions, ZipWriter};
+
+    use super::{convert, converted_title, html_to_text, markdown_to_text, SourceFormat};
+    use crate::errors::BookrabError;
+
+    #[test]
+    fn formats_by_content_type_and_name() {
+        assert_eq!(
+            SourceFormat::from_content_type("text/markdown; charset=utf-8"),
+            Some(SourceFormat::Markdown)
+        );
+        assert_eq!(
+            SourceFormat::from_content_type("application/epub+zip"),
+            Some(SourceFormat::Epub)
+        );
+        assert_eq!(SourceFormat::from_content_type("image/png"), None);
+        assert_eq!(
+            SourceFormat::from_file_name("Os Lusíadas.HTM"),
+            Some(SourceFormat::Html)
+        );
+        assert_eq!(SourceFormat::from_file_name("lusiadas"), None);
+        assert_eq!(converted_title("lusiadas.epub"), "lusiadas.txt");
+    }
+
+    #[test]
+    fn markdown() {
+        let markdown = "# Canto I\n\nAs armas e os *barões* assinalados\nQue da [ocidental](https://pt.wikipedia.org) praia Lusitana\n\n- `Taprobana`\n";
+        assert_eq!(
+            markdown_to_text(markdown),
+            "Canto I\n\nAs armas e os barões assinalados\nQue da ocidental praia Lusitana\n\nTaprobana\n"
+        );
+    }
+
+    #[test]
+    fn html() {
+        let html = r#"<html><head><title>Os Lusíadas</title><style>p { color: red }</style></head>
+            <body><!-- canto I --><h1>Canto&nbsp;I</h1>
+            <p>As armas e os barões   assinalados<br/>Que da ocidental praia
+            Lusitana</p><script>alert("&")</script><p>Por mares &amp; &#233;&#x72;a</p></body></html>"#;
+        assert_eq!(
+            html_to_text(html),
+            "Canto I\n\nAs armas e os barões assinalados\nQue da ocidental praia Lusitana\n\nPor mares & éra\n"
+        );
+    }
+
+    fn epub(chapters: &[(&str, &str)]) -> Vec<u8> {
+        let mut zip = ZipWriter::new(Cursor::new(vec![]));
+        let options = SimpleFileOptions::default();
+        let container = r#"<?xml version="1.0"?>
+            <container xmlns="urn:oasis:names:tc:opendocument:xmlns:container" version="1.0">
+            <rootfiles><rootfile full-path="OEBPS/content.opf" media-type="application/oebps-package+xml"/></rootfiles>
+            </container>"#;
+        zip.start_file("META-INF/container.xml", options).unwrap();
+        zip.write_all(container.as_bytes()).unwrap();
+        let mut manifest = String::new();
+        let mut spine = String::new();
+        // the spine is in reverse order to make sure it is followed
+        for (i, (name, xhtml)) in chapters.iter().enumerate().rev() {
+            manifest.push_str(&format!(
+                r#"<item id="c{i}" href="{name}" media-type="application/xhtml+xml"/>"#
+            ));
+            spine.push_str(&format!(r#"<itemref idref="c{i}"/>"#));
+            zip.start_file(format!("OEBPS/{name}"), options).unwrap();
+            zip.write_all(xhtml.as_bytes()).unwrap();
+        }
+        let package = format!(
+            r#"<?xml version="1.0"?><package xmlns="http://www.idpf.org/2007/opf" version="3.0">
+            <manifest>{manifest}</manifest><spine>{spine}</spine></package>"#
+        );
+        zip.start_file("OEBPS/content.opf", options).unwrap();
+        zip.write_all(package.as_bytes()).unwrap();
+        zip.finish().unwrap().into_inner()
+    }
+
+    #[test]
+    fn epub_follows_the_spine() {
+        let book = epub(&[
+            ("one.xhtml", "<html><body><p>Canto I</p></body></html>"),
+            ("two.xhtml", "<html><body><p>Canto II</p></body></html>"),
+        ]);
+        let txt = convert(SourceFormat::Epub, "lusiadas.epub", book).unwrap();
+        assert_eq!(txt, "Canto II\n\nCanto I\n");
+    }
+
+    #[test]
+    fn broken_epub() {
+        let result = convert(SourceFormat::Epub, "lusiadas.epub", b"not a zip".to_vec());
+        assert!(matches!(
+            result,
+            Err(BookrabError::CouldntConvertBook { .. })
+        ));
+    }
+}
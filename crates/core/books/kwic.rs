@@ -0,0 +1,75 @@
+use std::io;
+
+use grep_matcher::Matcher;
+use grep_searcher::SinkError;
+
+/// Turns every whitespace character into a space, so that
+/// windows are printed in a single line.
+fn flatten(txt: &str) -> String {
+    txt.chars()
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .collect()
+}
+
+/// Builds a keyword-in-context (KWIC) concordance of `txt`.
+/// Every match becomes an entry with `width` characters of context
+/// on each side, padded with spaces so that all matches are aligned:
+/// ```no_compile
+/// " do Céu à [matched]Terra[/matched], enfim de"
+/// "ortais da [matched]Terra[/matched] ao Céu.  "
+/// ```
+pub(crate) fn concordance<M: Matcher>(
+    matcher: &M,
+    txt: &str,
+    width: usize,
+) -> Result<Vec<String>, io::Error> {
+    let mut entries = vec![];
+    matcher
+        .find_iter(txt.as_bytes(), |m| {
+            let left: Vec<char> = txt[..m.start()].chars().rev().take(width).collect();
+            let left: String = left.into_iter().rev().collect();
+            let right: String = txt[m.end()..].chars().take(width).collect();
+            entries.push(format!(
+                "{:>width$}[matched]{}[/matched]{:<width$}",
+                flatten(&left),
+                flatten(&txt[m.start()..m.end()]),
+                flatten(&right),
+            ));
+            true
+        })
+        .map_err(io::Error::error_message)?;
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::concordance;
+    use crate::books::test_utils::LUSIADAS1;
+    use grep_regex::RegexMatcher;
+
+    #[test]
+    fn aligned_windows() {
+        let matcher = RegexMatcher::new(r"\bTerra\b").unwrap();
+        let entries = concordance(&matcher, LUSIADAS1, 10).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                " do Céu à [matched]Terra[/matched], enfim de",
+                "ortais da [matched]Terra[/matched] ao Céu.  ",
+            ]
+        );
+    }
+
+    #[test]
+    fn padding_at_the_edges() {
+        let matcher = RegexMatcher::new(r"A lei|bravas:").unwrap();
+        let entries = concordance(&matcher, LUSIADAS1, 6).unwrap();
+        assert_eq!(
+            entries,
+            vec![
+                "      [matched]A lei[/matched] tenho",
+                "huças [matched]bravas:[/matched]      ",
+            ]
+        );
+    }
+}
@@ -0,0 +1,263 @@
+use std::collections::HashMap;
+
+use regex_syntax::hir::{Capture, Hir, HirKind, Repetition};
+
+/// Language of a book.
+/// It is stored in the book folder as a JSON string (e.g. `"pt"`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Hash, serde::Deserialize, serde::Serialize)]
+pub enum Language {
+    #[serde(rename = "pt")]
+    Portuguese,
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "es")]
+    Spanish,
+    #[serde(rename = "fr")]
+    French,
+    #[serde(rename = "it")]
+    Italian,
+    #[serde(rename = "de")]
+    German,
+}
+
+impl Language {
+    const ALL: [Language; 6] = [
+        Language::Portuguese,
+        Language::English,
+        Language::Spanish,
+        Language::French,
+        Language::Italian,
+        Language::German,
+    ];
+
+    /// Very common words used to guess the language of a text.
+    fn stopwords(&self) -> &'static [&'static str] {
+        match self {
+            Language::Portuguese => &[
+                "que", "não", "os", "do", "da", "em", "um", "uma", "para", "com", "se", "o", "e",
+                "as", "dos", "das", "ao", "mais", "mas", "pera",
+            ],
+            Language::English => &[
+                "the", "and", "of", "to", "in", "is", "that", "it", "was", "he", "for", "with",
+                "as", "his", "on", "be", "at", "by", "i", "you",
+            ],
+            Language::Spanish => &[
+                "que", "el", "la", "los", "las", "del", "en", "y", "un", "una", "por", "con", "no",
+                "se", "su", "al", "lo", "como", "más", "pero",
+            ],
+            Language::French => &[
+                "le", "la", "les", "de", "des", "et", "un", "une", "du", "est", "que", "qui",
+                "dans", "pour", "pas", "sur", "au", "il", "ne", "avec",
+            ],
+            Language::Italian => &[
+                "il", "la", "di", "che", "e", "un", "una", "del", "della", "per", "non", "con",
+                "gli", "le", "si", "nel", "alla", "sono", "ma", "come",
+            ],
+            Language::German => &[
+                "der", "die", "und", "das", "den", "von", "zu", "mit", "ist", "nicht", "sich",
+                "des", "ein", "eine", "auf", "dem", "es", "im", "für", "auch",
+            ],
+        }
+    }
+
+    /// Guesses the language of `txt` by counting stopwords.
+    /// Returns `None` if the text doesn't look like any
+    /// supported language.
+    pub fn detect(txt: &str) -> Option<Language> {
+        let analyzer = Analyzer::default();
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for token in analyzer.tokenize(txt) {
+            *counts.entry(analyzer.fold(token)).or_default() += 1;
+        }
+        let (language, score) = Self::ALL
+            .iter()
+            .map(|language| {
+                let score: usize = language
+                    .stopwords()
+                    .iter()
+                    .map(|word| counts.get(*word).copied().unwrap_or_default())
+                    .sum();
+                (*language, score)
+            })
+            .max_by_key(|(_, score)| *score)?;
+        if score == 0 {
+            return None;
+        }
+        Some(language)
+    }
+
+    /// Tokenization and case-folding rules for this language.
+    pub fn analyzer(&self) -> Analyzer {
+        match self {
+            Language::Portuguese => Analyzer {
+                joiners: &['-'],
+                spellings: &[],
+            },
+            Language::French => Analyzer {
+                joiners: &['-'],
+                spellings: &[("œ", "oe"), ("æ", "ae")],
+            },
+            Language::German => Analyzer {
+                joiners: &['-'],
+                spellings: &[("ß", "ss")],
+            },
+            Language::English => Analyzer {
+                joiners: &['\'', '’'],
+                spellings: &[("æ", "ae")],
+            },
+            Language::Spanish | Language::Italian => Analyzer::default(),
+        }
+    }
+}
+
+/// Splits text into words and folds them so that they
+/// can be compared.
+#[derive(Clone, Debug, Default)]
+pub struct Analyzer {
+    /// Characters that are part of a word when they appear
+    /// between two letters (e.g. `-` in "matá-lo").
+    joiners: &'static [char],
+    /// Letters that are also written in another way
+    /// (e.g. "ß" and "ss" in German).
+    spellings: &'static [(&'static str, &'static str)],
+}
+
+impl Analyzer {
+    /// Iterates over the words of `txt`.
+    pub fn tokenize<'t>(&self, txt: &'t str) -> impl Iterator<Item = &'t str> {
+        let joiners = self.joiners;
+        txt.split(move |c: char| !c.is_alphanumeric() && !joiners.contains(&c))
+            .map(move |token| token.trim_matches(|c| joiners.contains(&c)))
+            .filter(|token| !token.is_empty())
+    }
+
+    /// Folds a word so that "Que" and "que" are the same word
+    /// (and "Straße" and "strasse" in German).
+    pub fn fold(&self, token: &str) -> String {
+        let mut folded = token.to_lowercase();
+        for (letter, spelling) in self.spellings {
+            folded = folded.replace(letter, spelling);
+        }
+        folded
+    }
+
+    /// Rewrites `pattern` so that it matches every spelling of its
+    /// words, i.e. "strasse" matches "Straße" in German and vice-versa.
+    /// If the pattern is invalid, it is returned as it is (the
+    /// matcher will complain about it later).
+    pub fn fold_pattern(&self, pattern: &str) -> String {
+        if self.spellings.is_empty() {
+            return pattern.to_string();
+        }
+        match regex_syntax::Parser::new().parse(pattern) {
+            Ok(hir) => self.fold_hir(hir).to_string(),
+            Err(_) => pattern.to_string(),
+        }
+    }
+
+    /// Makes every literal of `hir` match the other spellings of its letters.
+    fn fold_hir(&self, hir: Hir) -> Hir {
+        match hir.into_kind() {
+            HirKind::Literal(literal) => self.fold_literal(&String::from_utf8_lossy(&literal.0)),
+            HirKind::Repetition(repetition) => Hir::repetition(Repetition {
+                sub: Box::new(self.fold_hir(*repetition.sub)),
+                ..repetition
+            }),
+            HirKind::Capture(capture) => Hir::capture(Capture {
+                sub: Box::new(self.fold_hir(*capture.sub)),
+                ..capture
+            }),
+            HirKind::Concat(hirs) => {
+                Hir::concat(hirs.into_iter().map(|hir| self.fold_hir(hir)).collect())
+            }
+            HirKind::Alternation(hirs) => {
+                Hir::alternation(hirs.into_iter().map(|hir| self.fold_hir(hir)).collect())
+            }
+            HirKind::Class(class) => Hir::class(class),
+            HirKind::Look(look) => Hir::look(look),
+            HirKind::Empty => Hir::empty(),
+        }
+    }
+
+    /// Literal that matches `literal` and its other spellings
+    /// (e.g. "Strasse" => "Stra(?:ss|ß)e").
+    fn fold_literal(&self, literal: &str) -> Hir {
+        let mut hirs = vec![];
+        let mut rest = literal;
+        'outer: while let Some(c) = rest.chars().next() {
+            for (letter, spelling) in self.spellings {
+                for written in [letter, spelling] {
+                    let Some(found) = rest.get(..written.len()) else {
+                        continue;
+                    };
+                    if found.to_lowercase() == *written {
+                        hirs.push(Hir::alternation(vec![
+                            Hir::literal(found.as_bytes()),
+                            Hir::literal(
+                                if written == letter {
+                                    *spelling
+                                } else {
+                                    *letter
+                                }
+                                .as_bytes(),
+                            ),
+                        ]));
+                        rest = &rest[written.len()..];
+                        continue 'outer;
+                    }
+                }
+            }
+            hirs.push(Hir::literal(c.to_string().into_bytes()));
+            rest = &rest[c.len_utf8()..];
+        }
+        Hir::concat(hirs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::test_utils::{LUSIADAS1, LUSIADAS2};
+    use grep_matcher::Matcher;
+    use grep_regex::RegexMatcherBuilder;
+
+    #[test]
+    fn detect_portuguese() {
+        assert_eq!(Language::detect(LUSIADAS1), Some(Language::Portuguese));
+        assert_eq!(Language::detect(LUSIADAS2), Some(Language::Portuguese));
+    }
+
+    #[test]
+    fn detect_nothing() {
+        assert_eq!(Language::detect(""), None);
+        assert_eq!(Language::detect("123 456"), None);
+    }
+
+    #[test]
+    fn portuguese_tokenization() {
+        let analyzer = Language::Portuguese.analyzer();
+        let tokens: Vec<String> = analyzer
+            .tokenize("Deste Deus-Homem, alto e infinito -")
+            .map(|token| analyzer.fold(token))
+            .collect();
+        assert_eq!(tokens, vec!["deste", "deus-homem", "alto", "e", "infinito"]);
+    }
+
+    #[test]
+    fn german_spellings() {
+        let analyzer = Language::German.analyzer();
+        assert_eq!(analyzer.fold("Straße"), "strasse");
+        let matcher = RegexMatcherBuilder::new()
+            .case_insensitive(true)
+            .build(&analyzer.fold_pattern("STRASSE|Fuß"))
+            .unwrap();
+        for text in ["Straße", "strasse", "Fuss", "fuß"] {
+            assert!(matcher.is_match(text.as_bytes()).unwrap(), "{text}");
+        }
+        // other languages keep the pattern
+        assert_eq!(
+            Language::Portuguese.analyzer().fold_pattern("passo"),
+            "passo"
+        );
+    }
+}
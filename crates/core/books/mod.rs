@@ -1,32 +1,96 @@
-mod history;
+mod accents;
+pub mod analysis;
+pub mod archive;
+pub mod catalog;
+pub mod history;
+pub mod history_backend;
+#[cfg(feature = "sqlite")]
+pub mod history_sqlite;
+pub mod import;
+mod kwic;
+pub mod language;
+pub mod reading_progress;
+pub mod saved_searches;
 mod sink;
+pub mod stats;
+pub mod storage;
 pub mod test_utils;
 mod utils;
+pub mod validation;
 
-use crate::{config::BookrabConfig, database::PgPooledConnection};
+use crate::{config::BookrabConfig, database::DbConnection};
+use accents::fold_accents;
+use catalog::BookCatalog;
 use core::str;
 use grep_matcher::Matcher;
 use grep_regex::RegexMatcherBuilder;
 use grep_searcher::Searcher;
-use history::SearchHistory;
-use log::error;
+use history::HistoryContext;
+use history_backend::history_backend;
+use kwic::concordance;
+use language::Language;
+use reading_progress::ReadingProgress;
 use sink::BookSink;
-use std::{collections::HashSet, fs};
+use std::{
+    collections::{HashMap, HashSet},
+    io::{self, BufRead, BufReader, Read},
+    path::PathBuf,
+    time::Instant,
+};
+use storage::{book_storage, BookStorage, FileBookStorage};
 
 use crate::errors::BookrabError;
 
 /// Represents elements returned by the listing
 /// route.
-#[derive(Debug, serde::Deserialize, serde::Serialize, PartialEq)]
+#[derive(Clone, Debug, serde::Deserialize, serde::Serialize, PartialEq)]
 pub struct BookListElement {
     /// Book title
-    title: String,
+    pub title: String,
     /// Book metadata for filtering
-    tags: HashSet<String>,
+    pub tags: HashSet<String>,
+    /// Language of the book (if known)
+    pub language: Option<Language>,
+}
+
+/// Slice of a listing. See [Page::new].
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Number of items in all pages
+    pub total: usize,
+    /// Offset of the next page (`None` if this is the last page)
+    pub next: Option<usize>,
+}
+
+impl<T> Page<T> {
+    /// Keeps up to `limit` of `items`, starting at `offset`.
+    pub fn new(items: Vec<T>, offset: usize, limit: usize) -> Self {
+        let total = items.len();
+        let items: Vec<T> = items.into_iter().skip(offset).take(limit).collect();
+        let end = offset.saturating_add(items.len());
+        Page {
+            next: if end < total && !items.is_empty() {
+                Some(end)
+            } else {
+                None
+            },
+            items,
+            total,
+        }
+    }
+}
+
+/// Number of books that have a tag.
+/// See [RootBookDir::tag_counts].
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
 }
 
 /// Manages the way that books will be filtered by tags.
-#[derive(Clone, Debug, Default, serde::Deserialize)]
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
 pub enum FilterMode {
     /// Grabs books that have all of the tags.
     All,
@@ -35,77 +99,272 @@ pub enum FilterMode {
     Any,
 }
 
+impl FilterMode {
+    /// Checks whether `book_tags` satisfy `tags` according to the mode.
+    fn matches(&self, tags: &HashSet<String>, book_tags: &HashSet<String>) -> bool {
+        match self {
+            FilterMode::Any => !tags.is_disjoint(book_tags),
+            FilterMode::All => tags.is_subset(book_tags),
+        }
+    }
+
+    /// Name of the mode in the database.
+    pub(crate) fn to_db(&self) -> &'static str {
+        match self {
+            FilterMode::All => "All",
+            FilterMode::Any => "Any",
+        }
+    }
+
+    /// Inverse of [FilterMode::to_db]. Unknown names are [FilterMode::Any].
+    pub(crate) fn from_db(mode: &str) -> FilterMode {
+        match mode {
+            "All" => FilterMode::All,
+            _ => FilterMode::Any,
+        }
+    }
+}
+
 /// Excludes matched books
 #[derive(Clone, Debug, Default)]
 pub struct Exclude {
     pub mode: FilterMode,
     pub tags: HashSet<String>,
 }
+impl Exclude {
+    /// Checks whether a book should be excluded.
+    /// No excluded tags = exclude no books.
+    pub fn matches(&self, book: &BookListElement) -> bool {
+        !self.tags.is_empty() && self.mode.matches(&self.tags, &book.tags)
+    }
+}
 /// Include matched books
 #[derive(Clone, Debug)]
 pub struct Include {
     pub mode: FilterMode,
     pub tags: HashSet<String>,
 }
+impl Include {
+    /// Checks whether a book should be included.
+    /// No included tags = include all books.
+    pub fn matches(&self, book: &BookListElement) -> bool {
+        self.tags.is_empty() || self.mode.matches(&self.tags, &book.tags)
+    }
+}
+
+/// Changes to the tags of a book. `set` replaces the tags first,
+/// then `add` and `remove` are applied (in this order).
+#[derive(Clone, Debug, Default, PartialEq, serde::Deserialize, serde::Serialize)]
+#[serde(default)]
+pub struct TagEdit {
+    pub add: HashSet<String>,
+    pub remove: HashSet<String>,
+    pub set: Option<HashSet<String>>,
+}
+impl TagEdit {
+    /// Returns `tags` with the changes applied.
+    pub fn apply(&self, tags: &HashSet<String>) -> HashSet<String> {
+        let mut result = self.set.clone().unwrap_or_else(|| tags.clone());
+        result.extend(self.add.iter().cloned());
+        result.retain(|tag| !self.remove.contains(tag));
+        result
+    }
+}
+
+/// Manages the way that search results are presented.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ResultFormat {
+    /// Matched lines with their context
+    /// (see [Searcher::before_context] and [Searcher::after_context]).
+    #[default]
+    Lines,
+    /// Keyword in context: every match is centered in a window with
+    /// `width` characters on each side. The context configured in the
+    /// [Searcher] is ignored.
+    Concordance { width: usize },
+}
+
+/// Options that change the way a search is made and
+/// that can't be expressed with a [Searcher] or a [RegexMatcherBuilder].
+#[derive(Clone, Debug, Default)]
+pub struct SearchOptions {
+    /// Ignores diacritics in both the pattern and the text,
+    /// i.e. "vituperio" matches "vitupério" and vice-versa.
+    /// The other spellings of the language of each book are
+    /// also ignored (see [language::Analyzer::fold_pattern]), which
+    /// also happens when the case is ignored (see [HistoryContext::case_insensitive]
+    /// and [HistoryContext::case_smart]).
+    pub ignore_accents: bool,
+    /// Format of [SearchResults::results]
+    pub format: ResultFormat,
+    /// Filters and options stored in the history along with the results.
+    /// The context of the [Searcher], [SearchOptions::ignore_accents] and
+    /// the tags of [RootBookDir::search_by_tags] are filled automatically,
+    /// but the way the [RegexMatcherBuilder] was configured has to be
+    /// informed by the caller.
+    pub history: HistoryContext,
+    /// Doesn't store the search in the history, e.g. when a search that
+    /// was already stored runs again to get another page of its results.
+    pub skip_history: bool,
+}
 
 /// Associates search results with the title of a book.
 #[derive(Clone, Debug, PartialEq, serde::Serialize)]
 pub struct SearchResults {
     pub title: String,
     pub results: Vec<String>,
+    /// Relevance of the book for the search (higher is better).
+    /// It is based on the number of matches relative to the size
+    /// of the book and on how close different matches are.
+    pub score: f64,
+    /// Time spent searching the book (in milliseconds)
+    pub duration_ms: u64,
+    /// The lines of [SearchResults::results], without `[matched]` markers
+    /// (empty for [ResultFormat::Concordance]).
+    #[serde(skip)]
+    pub lines: Vec<ResultLine>,
+}
+
+/// Byte range of a match inside of [ResultLine::text].
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct Span {
+    pub start: usize,
+    pub end: usize,
+}
+
+/// A line of a book that matched the search or that is
+/// context of a match.
+#[derive(Clone, Debug, PartialEq, Eq, serde::Serialize)]
+pub struct ResultLine {
+    /// Starting at 1 (`None` if the searcher doesn't count lines)
+    pub line_number: Option<u64>,
+    /// Position of the line in the book (in bytes)
+    pub byte_offset: u64,
+    /// The line, without its line break
+    pub text: String,
+    /// Matches in `text` (empty for context lines)
+    pub spans: Vec<Span>,
+    pub kind: LineKind,
+}
+
+/// Why a [ResultLine] is in the results.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, serde::Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineKind {
+    Match,
+    /// Context before a match
+    Before,
+    /// Context after a match
+    After,
 }
 
 impl SearchResults {
     /// Generates a BookSink instance that can
     /// fill this instance with search results.
-    fn sink<T: Matcher>(&mut self, matcher: T) -> BookSink<T> {
+    fn sink<T: Matcher>(&mut self, matcher: T) -> BookSink<'_, T> {
         BookSink::new(self, matcher)
     }
+    /// Number of matches in [SearchResults::results].
+    pub fn match_count(&self) -> usize {
+        self.results
+            .iter()
+            .map(|result| result.matches("[matched]").count())
+            .sum()
+    }
     fn new(title: String) -> Self {
         SearchResults {
             title,
             results: vec![],
+            score: 0.0,
+            duration_ms: 0,
+            lines: vec![],
         }
     }
 }
 
+/// Progress of [RootBookDir::search_filtered_streaming].
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SearchEvent<'a> {
+    /// A book is about to be searched
+    Started {
+        title: &'a str,
+        /// Position of the book in the listing (starting at 0)
+        index: usize,
+        /// Number of books that are going to be searched
+        total: usize,
+    },
+    /// A book was searched
+    Finished(&'a SearchResults),
+}
+
 /// Represents a root book folder.
 /// In this folder we are going to store texts and metadata
-/// in the way explained bellow:
+/// in the way explained bellow (unless the books are stored in
+/// the database, see [BookrabConfig::book_storage]):
 /// ```no_compile
 /// path/to/root_book_dir/ <= this is the `path` we use in this struct
 /// ├─ book_title1/ <= folder with the book's title as its name
 /// │  ├─ txt <= full text of the book
 /// │  ├─ tags.json <= json in the format `["tag1", "tag2", ...]`
+/// │  ├─ language.json <= json in the format `"pt"` (or `null`)
 /// ├─ book_title2/
 /// │  ├─ txt
 /// │  ├─ tags.json
+/// │  ├─ language.json
 /// ```
 pub struct RootBookDir<'a> {
     config: BookrabConfig,
-    /// Connection to Postgresql
-    pub connection: &'a mut PgPooledConnection,
+    /// Connection to the database (`None` when there is no database,
+    /// see [RootBookDir::without_database])
+    pub connection: Option<&'a mut DbConnection>,
 }
 
 impl<'a> RootBookDir<'a> {
-    const INFO_PATH: &'static str = "tags.json";
-    pub fn new(config: BookrabConfig, connection: &mut PgPooledConnection) -> RootBookDir {
-        RootBookDir { config, connection }
+    pub fn new(config: BookrabConfig, connection: &mut DbConnection) -> RootBookDir<'_> {
+        RootBookDir {
+            config,
+            connection: Some(connection),
+        }
+    }
+
+    /// Works with the books without a database.
+    /// Listing and searching still work, but the history is only
+    /// stored if its backend doesn't need the database
+    /// (see [BookrabConfig::history_backend]) and books stored
+    /// in the database are unavailable.
+    pub fn without_database(config: BookrabConfig) -> RootBookDir<'static> {
+        RootBookDir {
+            config,
+            connection: None,
+        }
+    }
+
+    /// Config of the books.
+    pub fn config(&self) -> &BookrabConfig {
+        &self.config
+    }
+
+    /// Storage chosen in [BookrabConfig::book_storage].
+    fn storage(&mut self) -> Result<Box<dyn BookStorage + '_>, BookrabError> {
+        book_storage(&self.config, self.connection.as_deref_mut())
+    }
+
+    /// Text file of `title` inside of the book folder that holds it
+    /// (see [BookrabConfig::book_folders]), so that errors name it.
+    pub fn txt_path(&self, title: &str) -> Result<PathBuf, BookrabError> {
+        FileBookStorage::with_folders(self.config.book_folders(), self.config.upload_folder())
+            .txt_path(title)
     }
 
     /// Gets book according to its title.
-    pub fn get_by_title(&self, title: String) -> Result<Option<BookListElement>, BookrabError> {
-        let list = self.list()?;
-        let result: Vec<BookListElement> = list
-            .into_iter()
-            .filter(|book| book.title == title)
-            .collect();
+    pub fn get_by_title(&mut self, title: String) -> Result<Option<BookListElement>, BookrabError> {
+        let result = self.list_filtered(|book| book.title == title)?;
         // there are not going to be any duplicates
         Ok(result.into_iter().next())
     }
 
     /// Lists all tags from all books.
-    pub fn all_tags(&self) -> Result<HashSet<String>, BookrabError> {
+    pub fn all_tags(&mut self) -> Result<HashSet<String>, BookrabError> {
         let list = self.list()?;
         let mut result = HashSet::new();
         for elem in list {
@@ -114,168 +373,204 @@ impl<'a> RootBookDir<'a> {
         Ok(result)
     }
 
+    /// Lists all tags with the number of books that have them.
+    /// The most used tags come first (ties are ordered by tag).
+    pub fn tag_counts(&mut self) -> Result<Vec<TagCount>, BookrabError> {
+        let mut counts: HashMap<String, usize> = HashMap::new();
+        for book in self.list()? {
+            for tag in book.tags {
+                *counts.entry(tag).or_default() += 1;
+            }
+        }
+        let mut result: Vec<TagCount> = counts
+            .into_iter()
+            .map(|(tag, count)| TagCount { tag, count })
+            .collect();
+        result.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
+        Ok(result)
+    }
+
+    /// Hash of the titles, tags and languages of every book.
+    /// It changes whenever [RootBookDir::list] changes (texts are not
+    /// read), so clients can tell whether the library changed without
+    /// downloading the listing again.
+    pub fn version(&mut self) -> Result<String, BookrabError> {
+        let mut books = self.list()?;
+        books.sort_by(|a, b| a.title.cmp(&b.title));
+        let mut listing = String::new();
+        for book in books {
+            let mut tags: Vec<String> = book.tags.into_iter().collect();
+            tags.sort();
+            // JSON keeps titles and tags with line breaks apart
+            listing.push_str(&serde_json::to_string(&(book.title, tags, book.language)).unwrap());
+            listing.push('\n');
+        }
+        Ok(catalog::checksum(&listing))
+    }
+
     /// Lists books according to their tags.
     /// No included tags = include all tags.
     /// No excluded tags = exclude no tags.
     /// These apply regardless of the mode of the inclusion/exclusion.
     pub fn list_by_tags(
-        &self,
+        &mut self,
         include: &Include,
         exclude: &Exclude,
+    ) -> Result<Vec<BookListElement>, BookrabError> {
+        self.list_filtered(|book| include.matches(book) && !exclude.matches(book))
+    }
+
+    /// Lists books for which `predicate` returns `true`.
+    /// Use this when [Include] and [Exclude] aren't expressive enough
+    /// (e.g. filtering by title or combining several tag sets).
+    pub fn list_filtered(
+        &mut self,
+        predicate: impl Fn(&BookListElement) -> bool,
     ) -> Result<Vec<BookListElement>, BookrabError> {
         let list = self.list()?;
-        let result = list
-            .into_iter()
-            .filter(|book| {
-                let includes = if !include.tags.is_empty() {
-                    match include.mode {
-                        FilterMode::Any => !include
-                            .tags
-                            .intersection(&book.tags)
-                            .collect::<Vec<&String>>()
-                            .is_empty(),
-                        FilterMode::All => {
-                            include.tags.union(&book.tags).collect::<Vec<_>>().len()
-                                == book.tags.len()
-                        }
-                    }
-                } else {
-                    true
-                };
-                let excludes = if !exclude.tags.is_empty() {
-                    match exclude.mode {
-                        FilterMode::Any => !exclude
-                            .tags
-                            .intersection(&book.tags)
-                            .collect::<Vec<&String>>()
-                            .is_empty(),
-                        FilterMode::All => {
-                            exclude.tags.union(&book.tags).collect::<Vec<_>>().len()
-                                == book.tags.len()
-                        }
-                    }
-                } else {
-                    false
-                };
-                includes && !excludes
-            })
-            .collect();
-        Ok(result)
+        Ok(list.into_iter().filter(|book| predicate(book)).collect())
     }
 
     /// Lists all books in the form of [BookListElement]
-    pub fn list(&self) -> Result<Vec<BookListElement>, BookrabError> {
-        let books_dir = match fs::read_dir(&self.config.book_path) {
-            Ok(v) => v,
-            Err(e) => {
-                error!("{e:#?}");
-                return Err(BookrabError::CouldntReadDir {
-                    error: (),
-                    path: self.config.book_path.clone(),
-                    err: e,
-                });
-            }
-        };
-        let mut result = vec![];
-        for book_dir_res in books_dir {
-            let book_dir = match book_dir_res {
-                Ok(v) => v,
-                Err(e) => {
-                    return Err(BookrabError::CouldntReadChild {
-                        error: (),
-                        parent: self.config.book_path.clone(),
-                        err: e,
-                    })
-                }
-            };
-            let book_title = book_dir.file_name().to_str().unwrap().to_string();
-
-            // extract metadata
-            let tags_path = book_dir.path().join(Self::INFO_PATH);
-            let tags_contents = if tags_path.exists() {
-                match fs::read_to_string(&tags_path) {
-                    Ok(v) => v,
-                    Err(e) => {
-                        return Err(BookrabError::CouldntReadFile {
-                            error: (),
-                            path: tags_path,
-                            err: e,
-                        })
-                    }
-                }
-            } else {
-                let _ = fs::write(&tags_path, "[]");
-                "[]".to_string()
-            };
-            let tags: HashSet<String> = match serde_json::from_str(tags_contents.as_str()) {
-                Ok(v) => v,
-                Err(e) => {
-                    return Err(BookrabError::InvalidTags {
-                        error: (),
-                        tags: tags_contents,
-                        path: tags_path,
-                        err: e,
-                    })
-                }
-            };
-
-            result.push(BookListElement {
-                title: book_title,
-                tags,
-            });
-        }
-
-        Ok(result)
+    pub fn list(&mut self) -> Result<Vec<BookListElement>, BookrabError> {
+        self.storage()?.list()
     }
 
     /// Uploads a single book.
     /// If the book is already there (i.e root_dir/title exists),
     /// the txt and tags are updated.
+    /// The language of the book is detected from `txt`
+    /// (see [RootBookDir::set_language] to set it manually).
+    /// The book is also recorded in the [BookCatalog] (if there is a database).
     pub fn upload(
-        &self,
+        &mut self,
         title: &str,
         txt: &str,
         tags: HashSet<String>,
-    ) -> Result<&Self, BookrabError> {
-        // create book directory if it doesn't exist
-        let book_path = &self.config.book_path.join(title);
-        if let Err(e) = fs::create_dir_all(book_path) {
-            if e.kind() != std::io::ErrorKind::AlreadyExists {
-                return Err(BookrabError::CouldntCreateDir {
+    ) -> Result<&mut Self, BookrabError> {
+        validation::check_title("title", title)?;
+        self.storage()?.upload(title, txt, &tags)?;
+        if let Some(connection) = self.connection.as_deref_mut() {
+            BookCatalog::new(self.config.clone(), connection).record(title, txt)?;
+        }
+        self.set_language(title, Language::detect(txt))
+    }
+
+    /// Reads the full text of a book.
+    pub fn text(&mut self, title: &str) -> Result<String, BookrabError> {
+        self.storage()?.text(title)
+    }
+
+    /// Opens the text of a book, so that it can be read in chunks.
+    /// With [crate::config::BookStorageKind::Postgres], the whole text
+    /// is still loaded at once.
+    pub fn reader(&mut self, title: &str) -> Result<Box<dyn Read + Send>, BookrabError> {
+        self.storage()?.reader(title)
+    }
+
+    /// Returns at most `count` lines of a book, starting at line `start`
+    /// (line numbers start at 1, like the ones of [ResultLine]).
+    /// Line terminators are removed and only the returned
+    /// lines are kept in memory (see [RootBookDir::reader]).
+    pub fn get_lines(
+        &mut self,
+        title: &str,
+        start: u64,
+        count: usize,
+    ) -> Result<Vec<String>, BookrabError> {
+        let reader = BufReader::new(self.reader(title)?);
+        let lines = reader
+            .lines()
+            .skip(start.saturating_sub(1) as usize)
+            .take(count)
+            .collect::<Result<_, _>>();
+        lines.or_else(|e| {
+            Err(match e.kind() {
+                io::ErrorKind::InvalidData => BookrabError::NotUnicode {
+                    error: (),
+                    what: title.to_string(),
+                },
+                _ => BookrabError::CouldntReadFile {
                     error: (),
-                    path: book_path.to_owned(),
+                    path: self.txt_path(title)?,
                     err: e,
-                });
-            }
-        }
-        // write text
-        let txt_path = book_path.join("txt");
-        if let Err(e) = fs::write(&txt_path, txt) {
-            return Err(BookrabError::CouldntWriteFile {
-                error: (),
-                path: txt_path,
-                err: e,
-            });
-        };
+                },
+            })
+        })
+    }
 
-        // write metadata
-        let tags_str =
-            serde_json::to_string(&tags).expect("BookTags could not be converted to string");
-        let tags_path = book_path.join(Self::INFO_PATH);
-        if let Err(e) = fs::write(&tags_path, tags_str) {
-            return Err(BookrabError::CouldntWriteFile {
+    /// Changes the tags of an existing book without uploading it again.
+    /// Returns the book with the new tags.
+    pub fn edit_tags(
+        &mut self,
+        title: &str,
+        edit: &TagEdit,
+    ) -> Result<BookListElement, BookrabError> {
+        let Some(mut book) = self.get_by_title(title.to_string())? else {
+            return Err(BookrabError::InexistentBook {
                 error: (),
-                path: tags_path,
-                err: e,
+                path: self.txt_path(title)?,
             });
         };
+        book.tags = edit.apply(&book.tags);
+        self.storage()?.set_tags(title, &book.tags)?;
+        Ok(book)
+    }
+
+    /// Sets the language of an existing book.
+    /// `None` means that the language is unknown.
+    pub fn set_language(
+        &mut self,
+        title: &str,
+        language: Option<Language>,
+    ) -> Result<&mut Self, BookrabError> {
+        self.storage()?.set_language(title, language)?;
         Ok(self)
     }
 
+    /// Changes the title of an existing book. The [BookCatalog], the
+    /// history entries and the [ReadingProgress] of the book are moved
+    /// to the new title.
+    /// Fails if there is already a book called `new_title`.
+    /// Returns the renamed book.
+    pub fn rename(
+        &mut self,
+        title: &str,
+        new_title: &str,
+    ) -> Result<BookListElement, BookrabError> {
+        validation::check_title("new_title", new_title)?;
+        self.storage()?.rename(title, new_title)?;
+        if let Some(connection) = self.connection.as_deref_mut() {
+            BookCatalog::new(self.config.clone(), connection).rename(title, new_title)?;
+            ReadingProgress::new(self.config.clone(), connection).rename(title, new_title)?;
+        }
+        history_backend(&self.config, self.connection.as_deref_mut())
+            .rename_book(title, new_title)?;
+        match self.get_by_title(new_title.to_string())? {
+            Some(book) => Ok(book),
+            None => Err(BookrabError::InexistentBook {
+                error: (),
+                path: self.txt_path(new_title)?,
+            }),
+        }
+    }
+
+    /// Removes a book, its entry in the [BookCatalog] and its
+    /// [ReadingProgress]. The history of its searches is kept.
+    pub fn delete(&mut self, title: &str) -> Result<(), BookrabError> {
+        self.storage()?.delete(title)?;
+        if let Some(connection) = self.connection.as_deref_mut() {
+            BookCatalog::new(self.config.clone(), connection).remove(title)?;
+            ReadingProgress::new(self.config.clone(), connection).forget(title)?;
+        }
+        Ok(())
+    }
+
     /// Searches stuff in a single book.
     /// The search is configurable via parameters passed
     /// to the searcher (after_context, for example) or to the
     /// matcher (case_insensitive, for example).
+    /// This also generates a history entry.
     pub fn search(
         &mut self,
         title: String,
@@ -283,31 +578,93 @@ impl<'a> RootBookDir<'a> {
         // because there is no way to extract the pattern from a
         // RegexMatcher (AFAIK).
         pattern: String,
+        searcher: Searcher,
+        matcher_builder: RegexMatcherBuilder,
+        options: &SearchOptions,
+    ) -> Result<SearchResults, BookrabError> {
+        let language = self
+            .get_by_title(title.clone())?
+            .and_then(|book| book.language);
+        let results = self.search_book(
+            title,
+            language,
+            &pattern,
+            searcher.clone(),
+            matcher_builder,
+            options,
+        )?;
+        let results_vec = vec![results];
+        if !options.skip_history {
+            let context = HistoryContext {
+                ignore_accents: options.ignore_accents,
+                before_context: searcher.before_context(),
+                after_context: searcher.after_context(),
+                ..options.history.clone()
+            };
+            history_backend(&self.config, self.connection.as_deref_mut()).register(
+                &pattern,
+                &context,
+                &results_vec,
+            )?;
+        }
+        Ok(results_vec.into_iter().next().unwrap())
+    }
+
+    /// Same as [RootBookDir::search], but without history.
+    /// `language` is the one of the book, whose analyzer folds the
+    /// pattern when [SearchOptions::ignore_accents] is set or the
+    /// case is ignored.
+    fn search_book(
+        &mut self,
+        title: String,
+        language: Option<Language>,
+        pattern: &str,
         mut searcher: Searcher,
         matcher_builder: RegexMatcherBuilder,
+        options: &SearchOptions,
     ) -> Result<SearchResults, BookrabError> {
-        let matcher = matcher_builder.build(pattern.as_str())?;
+        let start = Instant::now();
+        let context = &options.history;
+        let pattern = if options.ignore_accents || context.case_insensitive || context.case_smart {
+            language
+                .map(|language| language.analyzer())
+                .unwrap_or_default()
+                .fold_pattern(pattern)
+        } else {
+            pattern.to_string()
+        };
+        let matcher = if options.ignore_accents {
+            matcher_builder.build(fold_accents(&pattern).as_str())?
+        } else {
+            matcher_builder.build(&pattern)?
+        };
         let mut results = SearchResults::new(title.clone());
-        let book_path = self.config.book_path.join(title).join("txt");
-        let sink = &mut results.sink(matcher);
-        if book_path.exists() {
-            if let Err(e) = searcher.search_path(sink.matcher.clone(), &book_path, sink) {
+        if let ResultFormat::Concordance { width } = &options.format {
+            let txt = self.text(&title)?;
+            results.results = match concordance(&matcher, &txt, *width) {
+                Ok(v) => v,
+                Err(e) => {
+                    return Err(BookrabError::GrepSearchError {
+                        error: (),
+                        path: self.txt_path(&title)?,
+                        err: e,
+                    })
+                }
+            };
+            results.score = results.results.len() as f64 * 10_000.0 / txt.len().max(1) as f64;
+        } else {
+            let reader = self.storage()?.reader(&title)?;
+            let sink = &mut results.sink(matcher);
+            if let Err(e) = searcher.search_reader(sink.matcher.clone(), reader, sink) {
                 return Err(BookrabError::GrepSearchError {
                     error: (),
-                    path: book_path,
+                    path: self.txt_path(&title)?,
                     err: e,
                 });
             };
-        } else {
-            return Err(BookrabError::InexistentBook {
-                error: (),
-                path: book_path,
-            });
         }
-        let results_vec = vec![results];
-        let search_history = SearchHistory::new(self.config.clone(), self.connection);
-        let res = search_history.register_history(pattern, &results_vec)?;
-        Ok(res.first().unwrap().to_owned())
+        results.duration_ms = start.elapsed().as_millis() as u64;
+        Ok(results)
     }
 
     /// Searches stuff in all books that respect some
@@ -320,33 +677,115 @@ impl<'a> RootBookDir<'a> {
         pattern: String,
         searcher: Searcher,
         matcher_builder: RegexMatcherBuilder,
+        options: &SearchOptions,
+    ) -> Result<Vec<SearchResults>, BookrabError> {
+        let options = SearchOptions {
+            history: HistoryContext {
+                include_mode: include.mode.clone(),
+                include_tags: include.tags.clone(),
+                exclude_mode: exclude.mode.clone(),
+                exclude_tags: exclude.tags.clone(),
+                ..options.history.clone()
+            },
+            ..options.clone()
+        };
+        self.search_filtered(
+            |book| include.matches(book) && !exclude.matches(book),
+            pattern,
+            searcher,
+            matcher_builder,
+            &options,
+        )
+    }
+
+    /// Searches stuff in all books for which `predicate`
+    /// returns `true`. See [RootBookDir::list_filtered].
+    /// Results are ordered by [SearchResults::score].
+    /// This also generates history entries.
+    pub fn search_filtered(
+        &mut self,
+        predicate: impl Fn(&BookListElement) -> bool,
+        pattern: String,
+        searcher: Searcher,
+        matcher_builder: RegexMatcherBuilder,
+        options: &SearchOptions,
     ) -> Result<Vec<SearchResults>, BookrabError> {
-        let book_list = self.list_by_tags(include, exclude)?;
+        self.search_filtered_streaming(
+            predicate,
+            pattern,
+            searcher,
+            matcher_builder,
+            options,
+            |_| true,
+        )
+    }
+
+    /// Same as [RootBookDir::search_filtered], but `on_event` is called
+    /// before and after each book is searched (in the order of the listing),
+    /// so that callers can show progress and results before all books are searched.
+    /// If `on_event` returns `false`, the remaining books are skipped and
+    /// only the results found so far are returned (and stored in the history).
+    pub fn search_filtered_streaming(
+        &mut self,
+        predicate: impl Fn(&BookListElement) -> bool,
+        pattern: String,
+        searcher: Searcher,
+        matcher_builder: RegexMatcherBuilder,
+        options: &SearchOptions,
+        mut on_event: impl FnMut(SearchEvent) -> bool,
+    ) -> Result<Vec<SearchResults>, BookrabError> {
+        let book_list = self.list_filtered(predicate)?;
+        let total = book_list.len();
         let mut search_results = vec![];
-        for book in book_list {
+        for (index, book) in book_list.into_iter().enumerate() {
             let title = book.title;
-            let single_search = self.search(
+            if !on_event(SearchEvent::Started {
+                title: &title,
+                index,
+                total,
+            }) {
+                break;
+            }
+            let single_search = self.search_book(
                 title,
-                pattern.clone(),
+                book.language,
+                &pattern,
                 searcher.clone(),
                 matcher_builder.clone(),
+                options,
+            )?;
+            let keep_going = on_event(SearchEvent::Finished(&single_search));
+            search_results.push(single_search);
+            if !keep_going {
+                break;
+            }
+        }
+        search_results.sort_by(|a, b| b.score.total_cmp(&a.score));
+        if !options.skip_history {
+            let context = HistoryContext {
+                ignore_accents: options.ignore_accents,
+                before_context: searcher.before_context(),
+                after_context: searcher.after_context(),
+                ..options.history.clone()
+            };
+            history_backend(&self.config, self.connection.as_deref_mut()).register(
+                &pattern,
+                &context,
+                &search_results,
             )?;
-            search_results.push(single_search.to_owned());
         }
-        let search_history = SearchHistory::new(self.config.clone(), self.connection);
-        let res = search_history.register_history(pattern, &search_results)?;
-        Ok(res.to_owned())
+        Ok(search_results)
     }
 }
 
 #[cfg(test)]
 mod tests {
-    use std::borrow::Borrow;
 
+    use crate::books::storage::FileBookStorage;
     use crate::books::test_utils::DBCONNECTION;
-    use crate::books::RootBookDir;
     use grep_regex::RegexMatcherBuilder;
     use grep_searcher::SearcherBuilder;
+    use std::fs;
     use test_utils::{basic_metadata, create_book_dir, root_for_tag_tests, s, LUSIADAS1};
 
     use super::*;
@@ -354,7 +793,7 @@ mod tests {
     #[test]
     fn basic_uploading() -> Result<(), anyhow::Error> {
         let connection = &mut DBCONNECTION.get().unwrap();
-        let book_dir = create_book_dir(connection);
+        let mut book_dir = create_book_dir(connection);
         let expected_text = "As armas e os barões assinalados";
         book_dir
             .upload("lusiadas", expected_text, basic_metadata())
@@ -367,7 +806,7 @@ mod tests {
                 .config
                 .book_path
                 .join("lusiadas")
-                .join(RootBookDir::INFO_PATH),
+                .join(FileBookStorage::INFO_PATH),
         )
         .expect("couldnt read info (file not created?)");
         let tags: HashSet<String> = serde_json::from_str(&tags_txt).unwrap();
@@ -378,7 +817,7 @@ mod tests {
     #[test]
     fn overwriting_with_upload() -> Result<(), anyhow::Error> {
         let connection = &mut DBCONNECTION.get().unwrap();
-        let book_dir = create_book_dir(connection);
+        let mut book_dir = create_book_dir(connection);
         let expected_text = "As armas e os barões assinalados";
         book_dir
             .upload(
@@ -398,7 +837,7 @@ mod tests {
                 .config
                 .book_path
                 .join("lusiadas")
-                .join(RootBookDir::INFO_PATH),
+                .join(FileBookStorage::INFO_PATH),
         )
         .expect("couldnt read info (file not created?)");
         let tags: HashSet<String> = serde_json::from_str(&tags_txt).unwrap();
@@ -409,7 +848,7 @@ mod tests {
     #[test]
     fn basic_listing() -> Result<(), anyhow::Error> {
         let connection = &mut DBCONNECTION.get().unwrap();
-        let book_dir = create_book_dir(connection);
+        let mut book_dir = create_book_dir(connection);
         book_dir.upload("lusiadas", "", basic_metadata()).unwrap();
         let body = book_dir.list().unwrap();
         assert_eq!(body.len(), 1);
@@ -418,6 +857,7 @@ mod tests {
             BookListElement {
                 title: "lusiadas".to_string(),
                 tags: basic_metadata(),
+                language: None,
             }
         );
         Ok(())
@@ -426,7 +866,7 @@ mod tests {
     #[test]
     fn list_two_items() -> Result<(), anyhow::Error> {
         let connection = &mut DBCONNECTION.get().unwrap();
-        let book_dir = create_book_dir(connection);
+        let mut book_dir = create_book_dir(connection);
         book_dir.upload("lusiadas", "", basic_metadata()).unwrap();
         book_dir.upload("sonetos", "", basic_metadata()).unwrap();
 
@@ -437,6 +877,7 @@ mod tests {
             BookListElement {
                 title: "lusiadas".to_string(),
                 tags: basic_metadata(),
+                language: None,
             }
         );
         assert_eq!(
@@ -444,6 +885,7 @@ mod tests {
             BookListElement {
                 title: "sonetos".to_string(),
                 tags: basic_metadata(),
+                language: None,
             }
         );
         Ok(())
@@ -452,13 +894,13 @@ mod tests {
     #[test]
     fn list_invalid_metadata() -> Result<(), BookrabError> {
         let connection = &mut DBCONNECTION.get().unwrap();
-        let book_dir = create_book_dir(connection);
+        let mut book_dir = create_book_dir(connection);
         book_dir.upload("lusiadas", "", basic_metadata()).unwrap();
         let metadata_path = book_dir
             .config
             .book_path
             .join("lusiadas")
-            .join(RootBookDir::INFO_PATH);
+            .join(FileBookStorage::INFO_PATH);
         fs::write(&metadata_path, "meeeeeeeeeeeeeeeeeeeessed up").unwrap();
 
         if let BookrabError::InvalidTags {
@@ -477,10 +919,96 @@ mod tests {
         Ok(())
     }
 
+    #[test]
+    fn language_detection_and_override() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        book_dir.upload("lusiadas", LUSIADAS1, basic_metadata())?;
+        let book = book_dir.get_by_title("lusiadas".to_string())?.unwrap();
+        assert_eq!(book.language, Some(Language::Portuguese));
+
+        book_dir.set_language("lusiadas", Some(Language::Spanish))?;
+        let book = book_dir.get_by_title("lusiadas".to_string())?.unwrap();
+        assert_eq!(book.language, Some(Language::Spanish));
+        Ok(())
+    }
+
+    #[test]
+    fn search_with_the_analyzer_of_the_book() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        let txt = "Die Straße ist lang und der Weg ist weit, aber wir gehen auf der Straße.\n";
+        book_dir.upload("strasse", txt, basic_metadata())?;
+        assert_eq!(
+            book_dir
+                .get_by_title("strasse".to_string())?
+                .unwrap()
+                .language,
+            Some(Language::German)
+        );
+        let options = SearchOptions {
+            ignore_accents: true,
+            ..Default::default()
+        };
+        let search = |book_dir: &mut RootBookDir| {
+            book_dir.search(
+                "strasse".to_string(),
+                "strasse".to_string(),
+                SearcherBuilder::new().build(),
+                RegexMatcherBuilder::new().case_insensitive(true).clone(),
+                &options,
+            )
+        };
+        assert_eq!(
+            search(&mut book_dir)?.results,
+            vec![
+                "Die [matched]Straße[/matched] ist lang und der Weg ist weit, \
+                 aber wir gehen auf der [matched]Straße[/matched].\n"
+            ]
+        );
+        let results = book_dir.search_filtered(
+            |_| true,
+            "STRASSE".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new().case_insensitive(true).clone(),
+            &options,
+        )?;
+        assert_eq!(results[0].match_count(), 2);
+        // ignoring the case is enough
+        let results = book_dir.search(
+            "strasse".to_string(),
+            "strasse".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new().case_insensitive(true).clone(),
+            &SearchOptions {
+                history: HistoryContext {
+                    case_insensitive: true,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+        )?;
+        assert_eq!(results.match_count(), 2);
+        // but not matching it exactly
+        let results = book_dir.search(
+            "strasse".to_string(),
+            "strasse".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new(),
+            &SearchOptions::default(),
+        )?;
+        assert!(results.results.is_empty());
+
+        // "ss" and "ß" are different letters in other languages
+        book_dir.set_language("strasse", Some(Language::Portuguese))?;
+        assert!(search(&mut book_dir)?.results.is_empty());
+        Ok(())
+    }
+
     #[test]
     fn all_tags() -> Result<(), BookrabError> {
         let connection = &mut DBCONNECTION.get().unwrap();
-        let book_dir = root_for_tag_tests(connection);
+        let mut book_dir = root_for_tag_tests(connection);
         let tags = book_dir.all_tags()?;
         assert_eq!(
             tags,
@@ -493,9 +1021,101 @@ mod tests {
         );
         Ok(())
     }
+    #[test]
+    fn streaming_search() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = root_for_tag_tests(connection);
+        let mut events = vec![];
+        let results = book_dir.search_filtered_streaming(
+            |book| book.title != "4",
+            r"\bpadeceu\b".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new(),
+            &SearchOptions::default(),
+            |event| {
+                events.push(match event {
+                    SearchEvent::Started {
+                        title,
+                        index,
+                        total,
+                    } => {
+                        format!("started {title} {index}/{total}")
+                    }
+                    SearchEvent::Finished(results) => format!("finished {}", results.title),
+                });
+                true
+            },
+        )?;
+        assert_eq!(
+            events,
+            vec![
+                "started 1 0/3",
+                "finished 1",
+                "started 2 1/3",
+                "finished 2",
+                "started 3 2/3",
+                "finished 3"
+            ]
+        );
+        assert_eq!(results.len(), 3);
+
+        // stops after the second book
+        let mut finished = vec![];
+        let results = book_dir.search_filtered_streaming(
+            |_| true,
+            r"\bpadeceu\b".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new(),
+            &SearchOptions::default(),
+            |event| {
+                if let SearchEvent::Finished(results) = event {
+                    finished.push(results.title.clone());
+                }
+                finished.len() < 2
+            },
+        )?;
+        assert_eq!(finished, vec!["1", "2"]);
+        assert_eq!(results.len(), 2);
+        Ok(())
+    }
+    #[test]
+    fn pages() {
+        let page = Page::new(vec![1, 2, 3, 4, 5], 1, 2);
+        assert_eq!(page.items, vec![2, 3]);
+        assert_eq!(page.total, 5);
+        assert_eq!(page.next, Some(3));
+        let page = Page::new(vec![1, 2, 3, 4, 5], 3, 2);
+        assert_eq!(page.items, vec![4, 5]);
+        assert_eq!(page.next, None);
+        let page = Page::new(vec![1, 2, 3], 10, 2);
+        assert!(page.items.is_empty());
+        assert_eq!(page.next, None);
+        // a limit of 0 would never reach the end
+        assert_eq!(Page::new(vec![1, 2, 3], 0, 0).next, None);
+    }
+    #[test]
+    fn tag_counts() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = root_for_tag_tests(connection);
+        let counts: Vec<(String, usize)> = book_dir
+            .tag_counts()?
+            .into_iter()
+            .map(|tag_count| (tag_count.tag, tag_count.count))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![
+                ("a".to_string(), 4),
+                ("b".to_string(), 3),
+                ("c".to_string(), 2),
+                ("d".to_string(), 1)
+            ]
+        );
+        Ok(())
+    }
     macro_rules! test_filter {
         ($include:expr, $exclude: expr, $expected: expr, $connection: expr) => {{
-            let book_dir = root_for_tag_tests($connection);
+            let mut book_dir = root_for_tag_tests($connection);
             let books = book_dir.list_by_tags($include, $exclude).unwrap();
 
             let expected = $expected;
@@ -651,7 +1271,7 @@ mod tests {
     #[test]
     fn get_by_title() -> Result<(), BookrabError> {
         let connection = &mut DBCONNECTION.get().unwrap();
-        let book_dir = create_book_dir(connection);
+        let mut book_dir = create_book_dir(connection);
         book_dir.upload("lusiadas", "", basic_metadata()).unwrap();
         let book = book_dir.get_by_title("lusiadas".to_string())?.unwrap();
         assert_eq!(
@@ -659,11 +1279,54 @@ mod tests {
             BookListElement {
                 title: "lusiadas".to_string(),
                 tags: basic_metadata(),
+                language: None,
             }
         );
         Ok(())
     }
 
+    #[test]
+    fn get_lines() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        book_dir.upload("lusiadas", LUSIADAS1, basic_metadata())?;
+        assert_eq!(
+            book_dir.get_lines("lusiadas", 2, 2)?,
+            vec![
+                "Obedece o visíbil e ínvisíbil",
+                "Aquele que criou todo o Hemisfério,"
+            ]
+        );
+        assert_eq!(
+            book_dir.get_lines("lusiadas", 0, 1)?,
+            vec!["A lei tenho daquele, a cujo império"]
+        );
+        assert_eq!(
+            book_dir.get_lines("lusiadas", 10_000, 5)?,
+            Vec::<String>::new()
+        );
+        assert!(matches!(
+            book_dir.get_lines("nope", 1, 1),
+            Err(BookrabError::InexistentBook { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn list_filtered() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = root_for_tag_tests(connection);
+        let books = book_dir.list_filtered(|book| book.tags.len() % 2 == 0 && book.title != "1")?;
+        assert_eq!(
+            books
+                .into_iter()
+                .map(|book| book.title)
+                .collect::<HashSet<_>>(),
+            s(vec!["3"])
+        );
+        Ok(())
+    }
+
     macro_rules! test_search {
         ($name:ident, $searcher: expr, $pattern: expr, $matcher_builder: expr, $expected_results: expr) => {
             #[test]
@@ -679,6 +1342,7 @@ mod tests {
                         $pattern,
                         $searcher,
                         $matcher_builder.clone(),
+                        &SearchOptions::default(),
                     )
                     .unwrap();
                 assert_eq!(result.title, "lusiadas");
@@ -747,6 +1411,59 @@ mod tests {
         vec!["E que do Céu à Terra, enfim desceu,\n[matched]Por[/matched] subir os mortais da Terra ao Céu.\n\n", "Cumprido esse desejo te seria;\nComo amigo as verás; [matched]por[/matched]que eu me obrigo,\nQue nunca as queiras ver como inimigo.\n"]
     );
 
+    #[test]
+    fn search_ignoring_accents() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        book_dir.upload("lusiadas", LUSIADAS1, basic_metadata())?;
+        let options = SearchOptions {
+            ignore_accents: true,
+            ..Default::default()
+        };
+        let result = book_dir.search(
+            String::from("lusiadas"),
+            r"vituperio|invisibil".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new(),
+            &options,
+        )?;
+        assert_eq!(
+            result.results,
+            vec![
+                "Obedece o visíbil e [matched]ínvisíbil[/matched]\n",
+                "Que padeceu desonra e [matched]vitupério[/matched],\n"
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn concordance_search() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        book_dir.upload("lusiadas", LUSIADAS1, basic_metadata())?;
+        let options = SearchOptions {
+            format: ResultFormat::Concordance { width: 8 },
+            ..Default::default()
+        };
+        let result = book_dir.search(
+            String::from("lusiadas"),
+            r"\bCéu\b".to_string(),
+            SearcherBuilder::new().after_context(3).build(),
+            RegexMatcherBuilder::new(),
+            &options,
+        )?;
+        assert_eq!(
+            result.results,
+            vec![
+                " que do [matched]Céu[/matched] à Terra",
+                "erra ao [matched]Céu[/matched].  Deste",
+            ]
+        );
+        assert!(result.score > 0.0);
+        Ok(())
+    }
+
     #[test]
     fn search_by_tags() -> Result<(), anyhow::Error> {
         let include = &Include {
@@ -772,8 +1489,19 @@ mod tests {
                 r"\bpor\w*?".to_string(),
                 searcher,
                 matcher_builder.clone(),
+                &SearchOptions::default(),
             )
             .unwrap();
+        // scores are tested in `ranked_search_by_tags`
+        let search_results: Vec<SearchResults> = search_results
+            .into_iter()
+            .map(|result| SearchResults {
+                score: 0.0,
+                duration_ms: 0,
+                lines: vec![],
+                ..result
+            })
+            .collect();
         assert_eq!(search_results,
         vec![
     SearchResults {
@@ -783,6 +1511,9 @@ mod tests {
             "De África e de Ásia andaram devastando;\nE aqueles, que [matched]por[/matched] obras valerosas\nSe vão da lei da morte libertando;\n".to_string(),
             "Cantando espalharei [matched]por[/matched] toda parte,\nSe a tanto me ajudar o engenho e arte.\n".to_string(),
         ],
+        score: 0.0,
+        duration_ms: 0,
+        lines: vec![],
     },
     SearchResults {
         title: String::from("3"),
@@ -791,9 +1522,403 @@ mod tests {
             "Pera onde faça um sumptuoso templo\nQue ficasse aos futuros [matched]por[/matched] exemplo.\n\n".to_string(),
             "A gente ficou disto alvoraçada;\nOs Brâmenes o têm [matched]por[/matched] cousa nova;\nVendo os milagres, vendo a santidade,\n".to_string(),
         ],
+        score: 0.0,
+        duration_ms: 0,
+        lines: vec![],
     },
 ]
     );
         Ok(())
     }
+
+    #[test]
+    fn ranked_search_by_tags() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = root_for_tag_tests(connection);
+        let search_results = book_dir.search_by_tags(
+            &Include {
+                mode: FilterMode::Any,
+                tags: s(vec![]),
+            },
+            &Exclude::default(),
+            r"armas|barões".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new(),
+            &SearchOptions::default(),
+        )?;
+        let titles: Vec<&str> = search_results
+            .iter()
+            .map(|result| result.title.as_str())
+            .collect();
+        // "2" has both words in the same line
+        assert_eq!(titles, vec!["2", "1", "3", "4"]);
+        assert!(search_results[0].score > search_results[1].score);
+        assert!(search_results[1].score > 0.0);
+        assert_eq!(search_results[2].score, 0.0);
+        Ok(())
+    }
+
+    #[test]
+    fn upload_outside_of_the_library() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        let outside = format!("../{}", test_utils::random_string());
+        for title in [outside.as_str(), "", "..", "a/b"] {
+            assert!(matches!(
+                book_dir.upload(title, "As armas", s(vec![])),
+                Err(BookrabError::InvalidFields { .. })
+            ));
+        }
+        assert!(!book_dir.config.book_path.join(&outside).exists());
+        // nothing was written in the root of the library
+        assert!(!book_dir.config.book_path.join("txt").exists());
+        assert!(book_dir.list()?.is_empty());
+        Ok(())
+    }
+
+    #[test]
+    fn search_without_history() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        book_dir.config.history_backend = crate::config::HistoryBackendKind::JsonFile;
+        book_dir.config.history_file = Some(book_dir.config.book_path.with_extension("jsonl"));
+        book_dir.upload("1", "As armas e os barões", basic_metadata())?;
+        let search = |book_dir: &mut RootBookDir, skip_history: bool| {
+            book_dir.search_filtered(
+                |_| true,
+                "armas".to_string(),
+                SearcherBuilder::new().build(),
+                RegexMatcherBuilder::new(),
+                &SearchOptions {
+                    skip_history,
+                    ..Default::default()
+                },
+            )
+        };
+        let history_len = |book_dir: &mut RootBookDir| {
+            history_backend(&book_dir.config, None)
+                .export()
+                .map(|entries| entries.len())
+        };
+        assert_eq!(search(&mut book_dir, true)?.len(), 1);
+        assert_eq!(history_len(&mut book_dir)?, 0);
+        search(&mut book_dir, false)?;
+        assert_eq!(history_len(&mut book_dir)?, 1);
+        Ok(())
+    }
+
+    #[test]
+    fn titles_outside_of_the_library() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        // `..` would be the folder of the user, which has a `txt`
+        let user_id = rand::random::<u16>() as i32 + 1_000;
+        let config = crate::config::ensure_config_works(
+            create_book_dir(connection).config.for_user(user_id),
+        )
+        .unwrap();
+        let user_folder = config.book_path.parent().unwrap().to_path_buf();
+        fs::write(user_folder.join("txt"), "secret").unwrap();
+        let mut book_dir = RootBookDir::new(config, connection);
+        let invalid = |result: Result<(), BookrabError>| {
+            matches!(result, Err(BookrabError::InvalidFields { .. }))
+        };
+
+        assert!(invalid(book_dir.text("..").map(|_| ())));
+        // downloads read the book with a reader
+        assert!(invalid(book_dir.reader("..").map(|_| ())));
+        assert!(invalid(book_dir.get_lines("..", 1, 10).map(|_| ())));
+        assert!(invalid(book_dir.rename("..", "x").map(|_| ())));
+        let edit = TagEdit {
+            set: Some(s(vec!["a"])),
+            ..TagEdit::default()
+        };
+        assert!(invalid(book_dir.edit_tags("..", &edit).map(|_| ())));
+        assert!(invalid(book_dir.set_language("..", None).map(|_| ())));
+        assert!(invalid(book_dir.delete("..")));
+
+        assert_eq!(
+            fs::read_to_string(user_folder.join("txt")).unwrap(),
+            "secret"
+        );
+        assert!(!user_folder.join(FileBookStorage::INFO_PATH).exists());
+        assert!(!user_folder.join(FileBookStorage::LANGUAGE_PATH).exists());
+        assert!(!user_folder.with_file_name("x").exists());
+        fs::remove_dir_all(user_folder).unwrap();
+        Ok(())
+    }
+
+    #[test]
+    fn delete() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        // the catalog is shared by the tests
+        let title = test_utils::random_string();
+        book_dir.upload(&title, "As armas", s(vec!["a"]))?;
+        book_dir.upload("2", "e os barões", s(vec![]))?;
+        ReadingProgress::new(
+            book_dir.config.clone(),
+            book_dir.connection.as_deref_mut().unwrap(),
+        )
+        .save(&title, 1)?;
+        book_dir.delete(&title)?;
+        let titles: Vec<String> = book_dir.list()?.into_iter().map(|b| b.title).collect();
+        assert_eq!(titles, vec!["2"]);
+        assert_eq!(book_dir.book_info(&title)?, None);
+        let catalog = BookCatalog::new(
+            book_dir.config.clone(),
+            book_dir.connection.as_deref_mut().unwrap(),
+        );
+        assert_eq!(catalog.get(&title)?, None);
+        let progress = ReadingProgress::new(
+            book_dir.config.clone(),
+            book_dir.connection.as_deref_mut().unwrap(),
+        );
+        assert_eq!(progress.get(&title)?, None);
+        assert!(matches!(
+            book_dir.delete(&title),
+            Err(BookrabError::InexistentBook { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn rename() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        // the catalog, the history and the reading progress are shared by the tests
+        let (title, new_title) = (test_utils::random_string(), test_utils::random_string());
+        book_dir.upload(&title, "As armas", s(vec!["a"]))?;
+        book_dir.upload("2", "e os barões", s(vec![]))?;
+        book_dir.search(
+            title.clone(),
+            "armas".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new(),
+            &SearchOptions::default(),
+        )?;
+        ReadingProgress::new(
+            book_dir.config.clone(),
+            book_dir.connection.as_deref_mut().unwrap(),
+        )
+        .save(&title, 7)?;
+
+        let book = book_dir.rename(&title, &new_title)?;
+        assert_eq!(book.title, new_title);
+        assert_eq!(book.tags, s(vec!["a"]));
+        assert_eq!(book_dir.text(&new_title)?, "As armas");
+        assert!(book_dir.get_by_title(title.clone())?.is_none());
+        assert!(matches!(
+            book_dir.rename(&new_title, "2"),
+            Err(BookrabError::BookAlreadyExists { .. })
+        ));
+        assert!(matches!(
+            book_dir.rename(&title, "3"),
+            Err(BookrabError::InexistentBook { .. })
+        ));
+        // the book can't be moved out of the library
+        let outside = format!("../{}", test_utils::random_string());
+        assert!(matches!(
+            book_dir.rename(&new_title, &outside),
+            Err(BookrabError::InvalidFields { .. })
+        ));
+        assert!(!book_dir.config.book_path.join(&outside).exists());
+        assert_eq!(book_dir.text(&new_title)?, "As armas");
+
+        let config = book_dir.config.clone();
+        let connection = book_dir.connection.as_deref_mut().unwrap();
+        assert!(BookCatalog::new(config.clone(), connection)
+            .get(&title)?
+            .is_none());
+        assert_eq!(
+            BookCatalog::new(config.clone(), connection)
+                .get(&new_title)?
+                .unwrap()
+                .size,
+            8
+        );
+        assert_eq!(
+            ReadingProgress::new(config.clone(), connection).get(&title)?,
+            None
+        );
+        assert_eq!(
+            ReadingProgress::new(config.clone(), connection)
+                .get(&new_title)?
+                .unwrap()
+                .line,
+            7
+        );
+        let titles: Vec<String> = history::SearchHistory::new(config, connection)
+            .get_entire_history()?
+            .into_iter()
+            .map(|entry| entry.title)
+            .filter(|entry_title| [&title, &new_title].contains(&entry_title))
+            .collect();
+        assert_eq!(titles, vec![new_title]);
+        Ok(())
+    }
+
+    #[test]
+    fn structured_search_results() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        book_dir.upload("1", "Canto primeiro\r\nAs armas e os barões\n", s(vec![]))?;
+        let search_results = book_dir.search_by_tags(
+            &Include {
+                mode: FilterMode::Any,
+                tags: s(vec![]),
+            },
+            &Exclude::default(),
+            r"armas|barões".to_string(),
+            SearcherBuilder::new().before_context(1).build(),
+            RegexMatcherBuilder::new(),
+            &SearchOptions::default(),
+        )?;
+        assert_eq!(
+            search_results[0].lines,
+            vec![
+                ResultLine {
+                    line_number: Some(1),
+                    byte_offset: 0,
+                    text: "Canto primeiro".to_string(),
+                    spans: vec![],
+                    kind: LineKind::Before,
+                },
+                ResultLine {
+                    line_number: Some(2),
+                    byte_offset: 16,
+                    text: "As armas e os barões".to_string(),
+                    spans: vec![Span { start: 3, end: 8 }, Span { start: 14, end: 21 }],
+                    kind: LineKind::Match,
+                },
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn search_context_in_history() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = root_for_tag_tests(connection);
+        // nobody else searches this
+        let pattern = format!("armas|{}", test_utils::random_string());
+        let options = SearchOptions {
+            ignore_accents: true,
+            history: HistoryContext {
+                case_insensitive: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        book_dir.search_by_tags(
+            &Include {
+                mode: FilterMode::All,
+                tags: s(vec!["a", "b"]),
+            },
+            &Exclude {
+                mode: FilterMode::Any,
+                tags: s(vec!["d"]),
+            },
+            pattern.clone(),
+            SearcherBuilder::new().before_context(1).build(),
+            RegexMatcherBuilder::new().case_insensitive(true).clone(),
+            &options,
+        )?;
+        let entries = history::SearchHistory::new(
+            book_dir.config.clone(),
+            book_dir.connection.as_deref_mut().unwrap(),
+        )
+        .get_history(&history::HistoryFilter {
+            pattern: Some(pattern),
+            ..Default::default()
+        })?;
+        assert!(!entries.is_empty());
+        for entry in entries {
+            assert_eq!(
+                entry.context(),
+                HistoryContext {
+                    include_mode: FilterMode::All,
+                    include_tags: s(vec!["a", "b"]),
+                    exclude_mode: FilterMode::Any,
+                    exclude_tags: s(vec!["d"]),
+                    case_insensitive: true,
+                    case_smart: false,
+                    ignore_accents: true,
+                    before_context: 1,
+                    after_context: 0,
+                }
+            );
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn editing_tags() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        book_dir.upload("lusiadas", LUSIADAS1, s(vec!["a", "b"]))?;
+        let edit = TagEdit {
+            add: s(vec!["c"]),
+            remove: s(vec!["x"]),
+            set: Some(s(vec!["x", "y"])),
+        };
+        let book = book_dir.edit_tags("lusiadas", &edit)?;
+        assert_eq!(book.tags, s(vec!["c", "y"]));
+        assert_eq!(book.language, Some(Language::Portuguese));
+        assert_eq!(book_dir.get_by_title("lusiadas".to_string())?, Some(book));
+        assert!(matches!(
+            book_dir.edit_tags("inexistent", &TagEdit::default()),
+            Err(BookrabError::InexistentBook { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn library_version() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        let empty = book_dir.version()?;
+        book_dir.upload("lusiadas", LUSIADAS1, s(vec!["a"]))?;
+        let uploaded = book_dir.version()?;
+        assert_ne!(uploaded, empty);
+        assert_eq!(book_dir.version()?, uploaded);
+        book_dir.edit_tags(
+            "lusiadas",
+            &TagEdit {
+                add: s(vec!["b"]),
+                ..Default::default()
+            },
+        )?;
+        assert_ne!(book_dir.version()?, uploaded);
+        Ok(())
+    }
+
+    #[test]
+    fn without_database() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = root_for_tag_tests(connection).config;
+        let mut root = RootBookDir::without_database(config.clone());
+        assert_eq!(root.list()?.len(), 4);
+        let result = root.search(
+            "1".to_string(),
+            r"\bpadeceu\b".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new(),
+            &SearchOptions::default(),
+        )?;
+        assert_eq!(result.results.len(), 1);
+        assert!(matches!(
+            root.run_saved_search("whatever"),
+            Err(BookrabError::DatabaseUnavailable { .. })
+        ));
+
+        let mut root = RootBookDir::without_database(BookrabConfig {
+            book_storage: crate::config::BookStorageKind::Postgres,
+            ..config
+        });
+        assert!(matches!(
+            root.list(),
+            Err(BookrabError::DatabaseUnavailable { .. })
+        ));
+        Ok(())
+    }
 }
@@ -0,0 +1,150 @@
+use chrono::{Local, NaiveDateTime};
+use diesel::{prelude::*, upsert::excluded};
+
+use crate::{
+    config::BookrabConfig,
+    database::{reading_progress::ReadingProgressRow, with_backend, DbConnection},
+    errors::BookrabError,
+    schema,
+};
+
+/// Where the reading of a book stopped.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct ReadingPosition {
+    pub title: String,
+    /// First line shown when the book was left (line numbers start at 1)
+    pub line: u64,
+    pub updated_at: NaiveDateTime,
+}
+
+impl From<ReadingProgressRow> for ReadingPosition {
+    fn from(row: ReadingProgressRow) -> Self {
+        ReadingPosition {
+            title: row.title,
+            line: row.line.max(1) as u64,
+            updated_at: row.updated_at,
+        }
+    }
+}
+
+pub struct ReadingProgress<'a> {
+    pub config: BookrabConfig,
+    /// Connection to the database
+    pub connection: &'a mut DbConnection,
+}
+
+impl ReadingProgress<'_> {
+    pub fn new(config: BookrabConfig, connection: &mut DbConnection) -> ReadingProgress<'_> {
+        ReadingProgress { config, connection }
+    }
+
+    /// Stores that the reading of `title` stopped at `line`.
+    pub fn save(self, title: &str, line: u64) -> Result<(), BookrabError> {
+        use schema::reading_progress::columns;
+        let line = i64::try_from(line).unwrap_or(i64::MAX);
+        let row = (
+            columns::user_id.eq(self.config.owner()),
+            columns::title.eq(title),
+            columns::line.eq(line),
+            columns::updated_at.eq(Local::now().naive_local()),
+        );
+        with_backend!(self.connection, |connection| {
+            diesel::insert_into(schema::reading_progress::table)
+                .values(row)
+                .on_conflict((columns::user_id, columns::title))
+                .do_update()
+                .set((
+                    columns::line.eq(excluded(columns::line)),
+                    columns::updated_at.eq(excluded(columns::updated_at)),
+                ))
+                .execute(connection)
+        })?;
+        Ok(())
+    }
+
+    /// Returns where the reading of `title` stopped (if it was started).
+    pub fn get(self, title: &str) -> Result<Option<ReadingPosition>, BookrabError> {
+        let row = schema::reading_progress::table
+            .find((self.config.owner(), title))
+            .select(ReadingProgressRow::as_select())
+            .first(self.connection)
+            .optional()?;
+        Ok(row.map(ReadingPosition::from))
+    }
+
+    /// Returns the positions of every book, the most recently read first.
+    pub fn list(self) -> Result<Vec<ReadingPosition>, BookrabError> {
+        use schema::reading_progress::columns;
+        let rows = schema::reading_progress::table
+            .select(ReadingProgressRow::as_select())
+            .filter(columns::user_id.eq(self.config.owner()))
+            .order((columns::updated_at.desc(), columns::title.asc()))
+            .load(self.connection)?;
+        Ok(rows.into_iter().map(ReadingPosition::from).collect())
+    }
+
+    /// Forgets the position of `title` (nothing happens if there is none).
+    pub fn forget(self, title: &str) -> Result<(), BookrabError> {
+        diesel::delete(schema::reading_progress::table.find((self.config.owner(), title)))
+            .execute(self.connection)?;
+        Ok(())
+    }
+
+    /// Moves the position of `title` to `new_title` (nothing happens
+    /// if there is none). An old position of `new_title` is replaced.
+    pub fn rename(self, title: &str, new_title: &str) -> Result<(), BookrabError> {
+        use schema::reading_progress::columns;
+        let owner = self.config.owner();
+        self.connection.transaction(|connection| {
+            diesel::delete(schema::reading_progress::table.find((owner, new_title)))
+                .execute(connection)?;
+            diesel::update(schema::reading_progress::table.find((owner, title)))
+                .set(columns::title.eq(new_title))
+                .execute(connection)?;
+            Ok(())
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::test_utils::{random_string, root_for_tag_tests, DBCONNECTION};
+
+    #[test]
+    fn save_list_and_forget() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = root_for_tag_tests(connection).config;
+        let (first, second) = (random_string(), random_string());
+        assert_eq!(
+            ReadingProgress::new(config.clone(), connection).get(&first)?,
+            None
+        );
+
+        ReadingProgress::new(config.clone(), connection).save(&first, 10)?;
+        ReadingProgress::new(config.clone(), connection).save(&second, 5)?;
+        // saving again moves the position and makes it the most recent
+        ReadingProgress::new(config.clone(), connection).save(&first, 42)?;
+        let position = ReadingProgress::new(config.clone(), connection)
+            .get(&first)?
+            .unwrap();
+        assert_eq!(position.line, 42);
+        let titles: Vec<String> = ReadingProgress::new(config.clone(), connection)
+            .list()?
+            .into_iter()
+            .map(|position| position.title)
+            .filter(|title| *title == first || *title == second)
+            .collect();
+        assert_eq!(titles, vec![first.clone(), second.clone()]);
+
+        // positions belong to a user
+        let other = config.for_user(-1);
+        assert_eq!(ReadingProgress::new(other, connection).get(&first)?, None);
+
+        for title in [&first, &second] {
+            ReadingProgress::new(config.clone(), connection).forget(title)?;
+        }
+        assert_eq!(ReadingProgress::new(config, connection).get(&first)?, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,315 @@
+use std::collections::HashSet;
+
+use diesel::{prelude::*, upsert::excluded};
+use grep_regex::RegexMatcherBuilder;
+use grep_searcher::{Searcher, SearcherBuilder};
+
+use crate::{
+    config::BookrabConfig,
+    database::{saved_searches::SavedSearchRow, with_backend, DbConnection},
+    errors::BookrabError,
+    schema,
+};
+
+use super::{
+    history::HistoryContext,
+    utils::{tags_from_db, tags_to_db},
+    Exclude, FilterMode, Include, RootBookDir, SearchOptions, SearchResults,
+};
+
+/// A search stored under a name, so that it can be run again
+/// without typing the pattern and choosing the tags.
+#[derive(Clone, Debug, PartialEq, serde::Deserialize, serde::Serialize)]
+pub struct SavedSearch {
+    pub name: String,
+    pub pattern: String,
+    #[serde(default)]
+    pub include_mode: FilterMode,
+    #[serde(default)]
+    pub include_tags: HashSet<String>,
+    #[serde(default)]
+    pub exclude_mode: FilterMode,
+    #[serde(default)]
+    pub exclude_tags: HashSet<String>,
+    #[serde(default)]
+    pub case_insensitive: bool,
+    #[serde(default)]
+    pub case_smart: bool,
+    #[serde(default)]
+    pub ignore_accents: bool,
+    #[serde(default)]
+    pub before_context: usize,
+    #[serde(default)]
+    pub after_context: usize,
+}
+
+impl SavedSearch {
+    pub fn include(&self) -> Include {
+        Include {
+            mode: self.include_mode.clone(),
+            tags: self.include_tags.clone(),
+        }
+    }
+
+    pub fn exclude(&self) -> Exclude {
+        Exclude {
+            mode: self.exclude_mode.clone(),
+            tags: self.exclude_tags.clone(),
+        }
+    }
+
+    pub fn searcher(&self) -> Searcher {
+        SearcherBuilder::new()
+            .before_context(self.before_context)
+            .after_context(self.after_context)
+            .build()
+    }
+
+    pub fn matcher_builder(&self) -> RegexMatcherBuilder {
+        let mut builder = RegexMatcherBuilder::new();
+        builder
+            .case_insensitive(self.case_insensitive)
+            .case_smart(self.case_smart);
+        builder
+    }
+
+    pub fn options(&self) -> SearchOptions {
+        SearchOptions {
+            ignore_accents: self.ignore_accents,
+            history: HistoryContext {
+                case_insensitive: self.case_insensitive,
+                case_smart: self.case_smart,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+}
+
+impl From<&SavedSearch> for SavedSearchRow {
+    fn from(search: &SavedSearch) -> Self {
+        SavedSearchRow {
+            name: search.name.clone(),
+            pattern: search.pattern.clone(),
+            include_mode: search.include_mode.to_db().to_string(),
+            include_tags: tags_to_db(&search.include_tags),
+            exclude_mode: search.exclude_mode.to_db().to_string(),
+            exclude_tags: tags_to_db(&search.exclude_tags),
+            case_insensitive: search.case_insensitive,
+            case_smart: search.case_smart,
+            ignore_accents: search.ignore_accents,
+            before_context: i32::try_from(search.before_context).unwrap_or(i32::MAX),
+            after_context: i32::try_from(search.after_context).unwrap_or(i32::MAX),
+        }
+    }
+}
+
+impl From<SavedSearchRow> for SavedSearch {
+    fn from(row: SavedSearchRow) -> Self {
+        SavedSearch {
+            name: row.name,
+            pattern: row.pattern,
+            include_mode: FilterMode::from_db(&row.include_mode),
+            include_tags: tags_from_db(&row.include_tags),
+            exclude_mode: FilterMode::from_db(&row.exclude_mode),
+            exclude_tags: tags_from_db(&row.exclude_tags),
+            case_insensitive: row.case_insensitive,
+            case_smart: row.case_smart,
+            ignore_accents: row.ignore_accents,
+            before_context: row.before_context.max(0) as usize,
+            after_context: row.after_context.max(0) as usize,
+        }
+    }
+}
+
+pub struct SavedSearches<'a> {
+    pub config: BookrabConfig,
+    /// Connection to the database
+    pub connection: &'a mut DbConnection,
+}
+
+impl SavedSearches<'_> {
+    pub fn new(config: BookrabConfig, connection: &mut DbConnection) -> SavedSearches<'_> {
+        SavedSearches { config, connection }
+    }
+
+    /// Stores `search`. A search with the same name is replaced.
+    pub fn save(self, search: &SavedSearch) -> Result<(), BookrabError> {
+        use schema::saved_searches::columns;
+        let row = (
+            SavedSearchRow::from(search),
+            columns::user_id.eq(self.config.owner()),
+        );
+        with_backend!(self.connection, |connection| {
+            diesel::insert_into(schema::saved_searches::table)
+                .values(&row)
+                .on_conflict((columns::user_id, columns::name))
+                .do_update()
+                .set((
+                    columns::pattern.eq(excluded(columns::pattern)),
+                    columns::include_mode.eq(excluded(columns::include_mode)),
+                    columns::include_tags.eq(excluded(columns::include_tags)),
+                    columns::exclude_mode.eq(excluded(columns::exclude_mode)),
+                    columns::exclude_tags.eq(excluded(columns::exclude_tags)),
+                    columns::case_insensitive.eq(excluded(columns::case_insensitive)),
+                    columns::case_smart.eq(excluded(columns::case_smart)),
+                    columns::ignore_accents.eq(excluded(columns::ignore_accents)),
+                    columns::before_context.eq(excluded(columns::before_context)),
+                    columns::after_context.eq(excluded(columns::after_context)),
+                ))
+                .execute(connection)
+        })?;
+        Ok(())
+    }
+
+    /// Returns the search stored under `name`.
+    pub fn get(self, name: &str) -> Result<SavedSearch, BookrabError> {
+        match schema::saved_searches::table
+            .find((self.config.owner(), name))
+            .select(SavedSearchRow::as_select())
+            .first(self.connection)
+            .optional()?
+        {
+            Some(row) => Ok(row.into()),
+            None => Err(BookrabError::InexistentSavedSearch {
+                error: (),
+                name: name.to_string(),
+            }),
+        }
+    }
+
+    /// Returns all saved searches ordered by name.
+    pub fn list(self) -> Result<Vec<SavedSearch>, BookrabError> {
+        let rows = schema::saved_searches::table
+            .select(SavedSearchRow::as_select())
+            .filter(schema::saved_searches::columns::user_id.eq(self.config.owner()))
+            .order(schema::saved_searches::columns::name.asc())
+            .load(self.connection)?;
+        Ok(rows.into_iter().map(SavedSearch::from).collect())
+    }
+
+    /// Deletes the search stored under `name`.
+    pub fn delete(self, name: &str) -> Result<(), BookrabError> {
+        let deleted =
+            diesel::delete(schema::saved_searches::table.find((self.config.owner(), name)))
+                .execute(self.connection)?;
+        if deleted == 0 {
+            return Err(BookrabError::InexistentSavedSearch {
+                error: (),
+                name: name.to_string(),
+            });
+        }
+        Ok(())
+    }
+}
+
+impl RootBookDir<'_> {
+    /// Runs the search stored under `name`.
+    /// See [RootBookDir::search_by_tags].
+    pub fn run_saved_search(&mut self, name: &str) -> Result<Vec<SearchResults>, BookrabError> {
+        let Some(connection) = self.connection.as_deref_mut() else {
+            return Err(BookrabError::DatabaseUnavailable {
+                error: (),
+                operation: "run a saved search".to_string(),
+            });
+        };
+        let search = SavedSearches::new(self.config.clone(), connection).get(name)?;
+        self.search_by_tags(
+            &search.include(),
+            &search.exclude(),
+            search.pattern.clone(),
+            search.searcher(),
+            search.matcher_builder(),
+            &search.options(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::test_utils::{random_string, root_for_tag_tests, s, DBCONNECTION};
+
+    fn saved_search(name: &str) -> SavedSearch {
+        SavedSearch {
+            name: name.to_string(),
+            pattern: "ARMAS".to_string(),
+            include_mode: FilterMode::All,
+            include_tags: s(vec!["a", "b"]),
+            exclude_mode: FilterMode::Any,
+            exclude_tags: s(vec!["c"]),
+            case_insensitive: true,
+            case_smart: false,
+            ignore_accents: false,
+            before_context: 0,
+            after_context: 0,
+        }
+    }
+
+    #[test]
+    fn save_get_and_delete() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = root_for_tag_tests(connection).config;
+        let name = random_string();
+        let mut search = saved_search(&name);
+        SavedSearches::new(config.clone(), connection).save(&search)?;
+        assert_eq!(
+            SavedSearches::new(config.clone(), connection).get(&name)?,
+            search
+        );
+
+        // saving again replaces the search
+        search.pattern = "Tomé".to_string();
+        search.exclude_tags = HashSet::new();
+        SavedSearches::new(config.clone(), connection).save(&search)?;
+        assert_eq!(
+            SavedSearches::new(config.clone(), connection).get(&name)?,
+            search
+        );
+        assert!(SavedSearches::new(config.clone(), connection)
+            .list()?
+            .contains(&search));
+
+        SavedSearches::new(config.clone(), connection).delete(&name)?;
+        assert!(matches!(
+            SavedSearches::new(config.clone(), connection).get(&name),
+            Err(BookrabError::InexistentSavedSearch { .. })
+        ));
+        assert!(matches!(
+            SavedSearches::new(config, connection).delete(&name),
+            Err(BookrabError::InexistentSavedSearch { .. })
+        ));
+        Ok(())
+    }
+
+    #[test]
+    fn run_saved_search() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut root = root_for_tag_tests(connection);
+        let name = random_string();
+        SavedSearches::new(root.config.clone(), root.connection.as_deref_mut().unwrap())
+            .save(&saved_search(&name))?;
+        // "1" and "2" have the tag "c"
+        let results = root.run_saved_search(&name)?;
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].title, "3");
+        assert!(results[0].results.is_empty());
+
+        let mut search = saved_search(&name);
+        search.exclude_tags = HashSet::new();
+        SavedSearches::new(root.config.clone(), root.connection.as_deref_mut().unwrap())
+            .save(&search)?;
+        let results = root.run_saved_search(&name)?;
+        let mut titles: Vec<&str> = results
+            .iter()
+            .filter(|result| !result.results.is_empty())
+            .map(|result| result.title.as_str())
+            .collect();
+        titles.sort();
+        assert_eq!(titles, vec!["1", "2"]);
+
+        SavedSearches::new(root.config.clone(), root.connection.as_deref_mut().unwrap())
+            .delete(&name)?;
+        Ok(())
+    }
+}
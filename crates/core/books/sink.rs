@@ -1,10 +1,10 @@
 use super::{
     utils::{find_iter_at_in_context_single_line, from_utf8},
-    SearchResults,
+    LineKind, ResultLine, SearchResults, Span,
 };
 use grep_matcher::{Match, Matcher};
 use grep_searcher::{Searcher, Sink, SinkContextKind};
-use std::io;
+use std::{collections::HashSet, io};
 
 /// Sink to be used in book searches.
 /// It doesn't support passthru.
@@ -13,6 +13,11 @@ pub struct BookSink<'a, T: Matcher> {
     pub(crate) matcher: T,
     matches: Vec<Match>,
     after_context_id: usize,
+    /// Number of matches found so far (used to compute the score)
+    match_count: usize,
+    /// Number of lines with at least two distinct matches
+    /// (used to compute the score)
+    close_lines: usize,
 }
 
 impl<T: Matcher> BookSink<'_, T> {
@@ -48,12 +53,14 @@ impl<T: Matcher> BookSink<'_, T> {
     }
 
     /// Creates new [BookSink] instance from [SearchResults] instance
-    pub fn new(results: &mut SearchResults, matcher: T) -> BookSink<T> {
+    pub fn new(results: &mut SearchResults, matcher: T) -> BookSink<'_, T> {
         BookSink {
             results,
             matcher,
             matches: vec![],
             after_context_id: 0,
+            match_count: 0,
+            close_lines: 0,
         }
     }
     /// Pushes string to the last entry in `self.results.results`.
@@ -70,6 +77,36 @@ impl<T: Matcher> BookSink<'_, T> {
         self.results.results.push(current_result);
         Ok(())
     }
+
+    /// Pushes a line to `self.results.lines`.
+    fn push_line(
+        &mut self,
+        line_number: Option<u64>,
+        byte_offset: u64,
+        text: &str,
+        kind: LineKind,
+    ) {
+        let spans = if kind == LineKind::Match {
+            self.matches
+                .iter()
+                .map(|m| Span {
+                    start: m.start(),
+                    end: m.end(),
+                })
+                .collect()
+        } else {
+            vec![]
+        };
+        let text = text.strip_suffix('\n').unwrap_or(text);
+        let text = text.strip_suffix('\r').unwrap_or(text);
+        self.results.lines.push(ResultLine {
+            line_number,
+            byte_offset,
+            text: text.to_string(),
+            spans,
+            kind,
+        });
+    }
 }
 impl<T: Matcher> Sink for BookSink<'_, T> {
     type Error = std::io::Error;
@@ -88,6 +125,15 @@ impl<T: Matcher> Sink for BookSink<'_, T> {
         // here we add [matched] [/matched] around the search result.
         self.record_matches(searcher, mat.buffer(), mat.bytes_range_in_buffer())?;
         let raw_result = from_utf8(mat.bytes())?;
+        self.match_count += self.matches.len();
+        let distinct_matches: HashSet<&str> = self
+            .matches
+            .iter()
+            .map(|m| &raw_result[m.start()..m.end()])
+            .collect();
+        if distinct_matches.len() > 1 {
+            self.close_lines += 1;
+        }
         let mut result_with_matched_tags = String::from(raw_result);
         let opening_tag = "[matched]";
         let closing_tag = "[/matched]";
@@ -106,6 +152,12 @@ impl<T: Matcher> Sink for BookSink<'_, T> {
             );
         }
         self.push_to_last_entry(result_with_matched_tags.as_str())?;
+        self.push_line(
+            mat.line_number(),
+            mat.absolute_byte_offset(),
+            raw_result,
+            LineKind::Match,
+        );
         if searcher.after_context() == 0 {
             self.results.results.push("".to_string());
         }
@@ -130,7 +182,18 @@ impl<T: Matcher> Sink for BookSink<'_, T> {
         // second contextual line => results == ["match context1 context2", ""] <= observe the empty string
         // another match => results = ["match context1 context2", "another match"]
         // and so on.
-        self.push_to_last_entry(from_utf8(context.bytes())?)?;
+        let line = from_utf8(context.bytes())?;
+        self.push_to_last_entry(line)?;
+        let kind = match context.kind() {
+            SinkContextKind::Before => LineKind::Before,
+            _ => LineKind::After,
+        };
+        self.push_line(
+            context.line_number(),
+            context.absolute_byte_offset(),
+            line,
+            kind,
+        );
         if let SinkContextKind::After = context.kind() {
             self.after_context_id += 1;
             if self.after_context_id == searcher.after_context() {
@@ -144,8 +207,17 @@ impl<T: Matcher> Sink for BookSink<'_, T> {
     fn finish(
         &mut self,
         _searcher: &Searcher,
-        _: &grep_searcher::SinkFinish,
+        finish: &grep_searcher::SinkFinish,
     ) -> Result<(), Self::Error> {
+        // The score is the number of matches per 10kB of text.
+        // Lines where different matches are close to each other
+        // (e.g. `armas|barões` matching both words in the same line)
+        // make the score higher.
+        if self.match_count > 0 {
+            let density = self.match_count as f64 * 10_000.0 / finish.byte_count().max(1) as f64;
+            let proximity = 1.0 + self.close_lines as f64 / self.match_count as f64;
+            self.results.score = density * proximity;
+        }
         // If the last element of `results` is an empty string,
         // (I believe this is always the case) then remove it.
         if self
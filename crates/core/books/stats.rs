@@ -0,0 +1,318 @@
+use std::{cmp::Reverse, collections::HashSet, fs, io, path::Path};
+
+use crate::{database::catalog::CatalogEntry, errors::BookrabError};
+
+use super::{
+    catalog::{BookCatalog, CatalogOrder},
+    language::Language,
+    RootBookDir, TagCount,
+};
+
+/// Number of books in a language.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct LanguageCount {
+    /// `None` for books whose language is unknown
+    pub language: Option<Language>,
+    pub count: usize,
+}
+
+/// Contents of the library. See [RootBookDir::library_stats].
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct LibraryStats {
+    pub books: usize,
+    /// Most common languages first
+    pub languages: Vec<LanguageCount>,
+    /// Most used tags first (see [RootBookDir::tag_counts])
+    pub tags: Vec<TagCount>,
+}
+
+/// Size of the texts of the books with a tag. See [RootBookDir::text_stats].
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct TagTextStats {
+    pub tag: String,
+    pub books: usize,
+    /// Size of the texts (in bytes)
+    pub size: u64,
+    /// Words of the texts (separated by whitespace)
+    pub words: u64,
+}
+
+/// Size of the texts of the library. See [RootBookDir::text_stats].
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct TextStats {
+    pub books: usize,
+    /// Size of the texts (in bytes)
+    pub size: u64,
+    /// Words of the texts (separated by whitespace)
+    pub words: u64,
+    /// Most used tags first (like [RootBookDir::tag_counts])
+    pub tags: Vec<TagTextStats>,
+}
+
+/// How well the catalog matches the storage. See [RootBookDir::index_status].
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct IndexStatus {
+    /// Number of books in the catalog
+    pub entries: usize,
+    /// Books that are not in the catalog (e.g. copied by hand to the book folder)
+    pub missing: Vec<String>,
+    /// Books in the catalog that are not in the storage anymore
+    pub orphaned: Vec<String>,
+}
+
+impl IndexStatus {
+    /// Whether the catalog matches the storage
+    /// (otherwise see [RootBookDir::sync_catalog]).
+    pub fn in_sync(&self) -> bool {
+        self.missing.is_empty() && self.orphaned.is_empty()
+    }
+}
+
+/// Space used by the library. See [RootBookDir::storage_usage].
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct StorageUsage {
+    /// Size of the texts according to the catalog
+    /// (`None` without a database)
+    pub text_bytes: Option<u64>,
+    /// Size of the files in the book folders (texts, tags and languages)
+    pub disk_bytes: u64,
+}
+
+/// Size of the files inside of `path` (recursively).
+fn disk_usage(path: &Path) -> io::Result<u64> {
+    let mut total = 0;
+    for entry in fs::read_dir(path)? {
+        let entry = entry?;
+        let metadata = entry.metadata()?;
+        if metadata.is_dir() {
+            total += disk_usage(&entry.path())?;
+        } else {
+            total += metadata.len();
+        }
+    }
+    Ok(total)
+}
+
+impl RootBookDir<'_> {
+    /// Counts the books by language and by tag.
+    pub fn library_stats(&mut self) -> Result<LibraryStats, BookrabError> {
+        let books = self.list()?;
+        let mut languages: Vec<LanguageCount> = vec![];
+        for book in &books {
+            match languages
+                .iter_mut()
+                .find(|count| count.language == book.language)
+            {
+                Some(count) => count.count += 1,
+                None => languages.push(LanguageCount {
+                    language: book.language,
+                    count: 1,
+                }),
+            }
+        }
+        languages.sort_by_key(|count| Reverse(count.count));
+        Ok(LibraryStats {
+            books: books.len(),
+            languages,
+            tags: self.tag_counts()?,
+        })
+    }
+
+    /// Measures the texts of the books, in total and by tag.
+    /// Every text is read, so this takes a while in large libraries.
+    pub fn text_stats(&mut self) -> Result<TextStats, BookrabError> {
+        let books = self.list()?;
+        let mut stats = TextStats {
+            books: books.len(),
+            size: 0,
+            words: 0,
+            tags: vec![],
+        };
+        for book in &books {
+            let txt = self.text(&book.title)?;
+            let size = txt.len() as u64;
+            let words = txt.split_whitespace().count() as u64;
+            stats.size += size;
+            stats.words += words;
+            for tag in &book.tags {
+                let index = match stats.tags.iter().position(|stats| &stats.tag == tag) {
+                    Some(index) => index,
+                    None => {
+                        stats.tags.push(TagTextStats {
+                            tag: tag.clone(),
+                            books: 0,
+                            size: 0,
+                            words: 0,
+                        });
+                        stats.tags.len() - 1
+                    }
+                };
+                let tag_stats = &mut stats.tags[index];
+                tag_stats.books += 1;
+                tag_stats.size += size;
+                tag_stats.words += words;
+            }
+        }
+        stats
+            .tags
+            .sort_by(|a, b| b.books.cmp(&a.books).then_with(|| a.tag.cmp(&b.tag)));
+        Ok(stats)
+    }
+
+    /// Entries of the catalog (`None` without a database).
+    fn catalog_entries(&mut self) -> Result<Option<Vec<CatalogEntry>>, BookrabError> {
+        let config = self.config.clone();
+        match self.connection.as_deref_mut() {
+            Some(connection) => Ok(Some(
+                BookCatalog::new(config, connection).list(CatalogOrder::Title)?,
+            )),
+            None => Ok(None),
+        }
+    }
+
+    /// Compares the titles in the catalog with the titles in the storage
+    /// (texts are not read). Returns `None` without a database.
+    pub fn index_status(&mut self) -> Result<Option<IndexStatus>, BookrabError> {
+        let Some(entries) = self.catalog_entries()? else {
+            return Ok(None);
+        };
+        let stored: HashSet<String> = self.list()?.into_iter().map(|book| book.title).collect();
+        let cataloged: HashSet<&String> = entries.iter().map(|entry| &entry.title).collect();
+        let mut missing: Vec<String> = stored
+            .iter()
+            .filter(|title| !cataloged.contains(title))
+            .cloned()
+            .collect();
+        missing.sort();
+        let orphaned = entries
+            .iter()
+            .filter(|entry| !stored.contains(&entry.title))
+            .map(|entry| entry.title.clone())
+            .collect();
+        Ok(Some(IndexStatus {
+            entries: entries.len(),
+            missing,
+            orphaned,
+        }))
+    }
+
+    /// Measures the texts (with the catalog) and the book folder.
+    pub fn storage_usage(&mut self) -> Result<StorageUsage, BookrabError> {
+        let text_bytes = self.catalog_entries()?.map(|entries| {
+            entries
+                .iter()
+                .map(|entry| u64::try_from(entry.size).unwrap_or(0))
+                .sum()
+        });
+        let mut disk_bytes = 0;
+        for folder in self.config.book_folders() {
+            disk_bytes += disk_usage(&folder).map_err(|err| BookrabError::CouldntReadDir {
+                error: (),
+                path: folder.clone(),
+                err,
+            })?;
+        }
+        Ok(StorageUsage {
+            text_bytes,
+            disk_bytes,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        books::test_utils::{create_book_dir, root_for_tag_tests, s, DBCONNECTION},
+        config::ensure_config_works,
+    };
+    use rand::Rng;
+
+    #[test]
+    fn library_stats() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = root_for_tag_tests(connection);
+        let stats = book_dir.library_stats()?;
+        assert_eq!(stats.books, 4);
+        assert_eq!(stats.languages.iter().map(|l| l.count).sum::<usize>(), 4);
+        assert_eq!(
+            stats.tags[0],
+            TagCount {
+                tag: "a".into(),
+                count: 4
+            }
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn text_stats() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let mut book_dir = create_book_dir(connection);
+        let (first, second) = ("As armas e os barões", "assinalados,\nQue da");
+        book_dir.upload("1", first, s(vec!["a", "b"]))?;
+        book_dir.upload("2", second, s(vec!["a"]))?;
+        let (first, both) = (first.len() as u64, (first.len() + second.len()) as u64);
+        let stats = book_dir.text_stats()?;
+        assert_eq!(stats.books, 2);
+        assert_eq!(stats.words, 8);
+        assert_eq!(stats.size, both);
+        let tag = |tag: &str, books, size, words| TagTextStats {
+            tag: tag.into(),
+            books,
+            size,
+            words,
+        };
+        assert_eq!(
+            stats.tags,
+            vec![tag("a", 2, both, 8), tag("b", 1, first, 5)]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn index_status_and_storage_usage() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        // the catalog of each user is shared by all of their folders
+        let user_id = rand::thread_rng().gen_range(1_000..i32::MAX);
+        let config =
+            ensure_config_works(create_book_dir(connection).config.for_user(user_id)).unwrap();
+        let mut book_dir = RootBookDir::new(config, connection);
+        book_dir.upload("1", "As armas", s(vec![]))?;
+        // a book that was copied by hand
+        let folder = book_dir.config.book_path.join("2");
+        fs::create_dir(&folder).unwrap();
+        fs::write(folder.join("txt"), "e os barões").unwrap();
+        fs::write(folder.join("tags.json"), "[]").unwrap();
+
+        let status = book_dir.index_status()?.unwrap();
+        assert_eq!(status.entries, 1);
+        assert_eq!(status.missing, vec!["2".to_string()]);
+        assert!(status.orphaned.is_empty());
+        assert!(!status.in_sync());
+
+        let usage = book_dir.storage_usage()?;
+        assert_eq!(usage.text_bytes, Some(8));
+        assert!(usage.disk_bytes >= 8 + "e os barões".len() as u64);
+
+        book_dir.sync_catalog()?;
+        assert!(book_dir.index_status()?.unwrap().in_sync());
+
+        let config = book_dir.config.clone();
+        let connection = book_dir.connection.unwrap();
+        for title in ["1", "2"] {
+            BookCatalog::new(config.clone(), connection).remove(title)?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn index_status_without_database() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = create_book_dir(connection).config;
+        let mut book_dir = RootBookDir::without_database(config);
+        assert_eq!(book_dir.index_status()?, None);
+        assert_eq!(book_dir.storage_usage()?.text_bytes, None);
+        Ok(())
+    }
+}
@@ -0,0 +1,764 @@
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, Cursor, Read},
+    path::{Path, PathBuf},
+};
+
+use diesel::{prelude::*, upsert::excluded};
+use log::error;
+
+use crate::{
+    config::{BookStorageKind, BookrabConfig},
+    database::{books::BookRow, tags::TagList, with_backend, DbConnection},
+    errors::BookrabError,
+    schema,
+};
+
+use super::{
+    language::Language,
+    utils::{tags_from_db, tags_to_db},
+    validation::check_title,
+    BookListElement,
+};
+
+/// Place where the texts and metadata of the books are kept.
+/// See [book_storage] to get the one chosen in the config.
+pub trait BookStorage {
+    /// Lists all books (ordered by title).
+    fn list(&mut self) -> Result<Vec<BookListElement>, BookrabError>;
+
+    /// Stores a book. If it already exists, its text and tags are replaced.
+    fn upload(
+        &mut self,
+        title: &str,
+        txt: &str,
+        tags: &HashSet<String>,
+    ) -> Result<(), BookrabError>;
+
+    /// Reads the full text of a book.
+    fn text(&mut self, title: &str) -> Result<String, BookrabError>;
+
+    /// Opens the text of a book, so that it can be searched
+    /// without being loaded at once when possible.
+    fn reader(&mut self, title: &str) -> Result<Box<dyn Read + Send>, BookrabError>;
+
+    /// Sets the language of an existing book.
+    fn set_language(&mut self, title: &str, language: Option<Language>)
+        -> Result<(), BookrabError>;
+
+    /// Replaces the tags of an existing book.
+    fn set_tags(&mut self, title: &str, tags: &HashSet<String>) -> Result<(), BookrabError>;
+
+    /// Changes the title of an existing book. Fails if there is
+    /// already a book called `new_title`.
+    fn rename(&mut self, title: &str, new_title: &str) -> Result<(), BookrabError>;
+
+    /// Removes an existing book (its text and its metadata).
+    fn delete(&mut self, title: &str) -> Result<(), BookrabError>;
+}
+
+/// Returns the storage chosen in [BookrabConfig::book_storage].
+/// Books stored in the database need a `connection`.
+pub fn book_storage<'a>(
+    config: &BookrabConfig,
+    connection: Option<&'a mut DbConnection>,
+) -> Result<Box<dyn BookStorage + 'a>, BookrabError> {
+    match (&config.book_storage, connection) {
+        (BookStorageKind::Filesystem, _) => Ok(Box::new(FileBookStorage::with_folders(
+            config.book_folders(),
+            config.upload_folder(),
+        ))),
+        (BookStorageKind::Postgres, Some(connection)) => {
+            Ok(Box::new(DbBookStorage::new(config.owner(), connection)))
+        }
+        (BookStorageKind::Postgres, None) => Err(BookrabError::DatabaseUnavailable {
+            error: (),
+            operation: "access books stored in the database".to_string(),
+        }),
+    }
+}
+
+/// Keeps every book in a folder inside of one of the book folders
+/// (see [BookrabConfig::book_folders]). See [super::RootBookDir] for the layout.
+pub struct FileBookStorage {
+    /// Folders whose books are listed (when a title is in
+    /// several of them, the one of the first folder is used)
+    book_paths: Vec<PathBuf>,
+    /// Folder where new books are uploaded
+    upload_path: PathBuf,
+}
+
+impl FileBookStorage {
+    pub(crate) const INFO_PATH: &'static str = "tags.json";
+    pub(crate) const LANGUAGE_PATH: &'static str = "language.json";
+
+    pub fn new(book_path: PathBuf) -> Self {
+        FileBookStorage::with_folders(vec![book_path.clone()], book_path)
+    }
+
+    /// Books of every folder of `book_paths`. New books go to `upload_path`.
+    pub fn with_folders(book_paths: Vec<PathBuf>, upload_path: PathBuf) -> Self {
+        FileBookStorage {
+            book_paths,
+            upload_path,
+        }
+    }
+
+    /// Folder of the book `title`: the one inside of the first book
+    /// folder that has it or, for new books, inside of the upload folder.
+    /// Every path of a book comes from here, so titles that aren't
+    /// folder names (e.g. `..`) fail here (see [check_title]).
+    fn book_dir(&self, title: &str) -> Result<PathBuf, BookrabError> {
+        check_title("title", title)?;
+        Ok(self
+            .book_paths
+            .iter()
+            .map(|book_path| book_path.join(title))
+            .find(|book_dir| book_dir.join("txt").exists())
+            .unwrap_or_else(|| self.upload_path.join(title)))
+    }
+
+    pub(crate) fn txt_path(&self, title: &str) -> Result<PathBuf, BookrabError> {
+        Ok(self.book_dir(title)?.join("txt"))
+    }
+
+    fn write_tags(&self, title: &str, tags: &HashSet<String>) -> Result<(), BookrabError> {
+        let tags_str =
+            serde_json::to_string(tags).expect("BookTags could not be converted to string");
+        let tags_path = self.book_dir(title)?.join(Self::INFO_PATH);
+        if let Err(e) = fs::write(&tags_path, tags_str) {
+            return Err(BookrabError::CouldntWriteFile {
+                error: (),
+                path: tags_path,
+                err: e,
+            });
+        };
+        Ok(())
+    }
+
+    /// Books of the folder `book_path`.
+    fn list_folder(&self, book_path: &Path) -> Result<Vec<BookListElement>, BookrabError> {
+        let books_dir = match fs::read_dir(book_path) {
+            Ok(v) => v,
+            Err(e) => {
+                error!("{e:#?}");
+                return Err(BookrabError::CouldntReadDir {
+                    error: (),
+                    path: book_path.to_path_buf(),
+                    err: e,
+                });
+            }
+        };
+        let mut result = vec![];
+        for book_dir_res in books_dir {
+            let book_dir = match book_dir_res {
+                Ok(v) => v,
+                Err(e) => {
+                    return Err(BookrabError::CouldntReadChild {
+                        error: (),
+                        parent: book_path.to_path_buf(),
+                        err: e,
+                    })
+                }
+            };
+            let book_title = book_dir.file_name().to_str().unwrap().to_string();
+
+            // extract metadata
+            let tags_path = book_dir.path().join(Self::INFO_PATH);
+            let tags_contents = if tags_path.exists() {
+                match fs::read_to_string(&tags_path) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        return Err(BookrabError::CouldntReadFile {
+                            error: (),
+                            path: tags_path,
+                            err: e,
+                        })
+                    }
+                }
+            } else {
+                let _ = fs::write(&tags_path, "[]");
+                "[]".to_string()
+            };
+            let tags: HashSet<String> = match serde_json::from_str(tags_contents.as_str()) {
+                Ok(v) => v,
+                Err(e) => {
+                    return Err(BookrabError::InvalidTags {
+                        error: (),
+                        tags: tags_contents,
+                        path: tags_path,
+                        err: e,
+                    })
+                }
+            };
+
+            let language_path = book_dir.path().join(Self::LANGUAGE_PATH);
+            let language = if language_path.exists() {
+                let language_contents = match fs::read_to_string(&language_path) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        return Err(BookrabError::CouldntReadFile {
+                            error: (),
+                            path: language_path,
+                            err: e,
+                        })
+                    }
+                };
+                match serde_json::from_str(language_contents.as_str()) {
+                    Ok(v) => v,
+                    Err(e) => {
+                        return Err(BookrabError::InvalidLanguage {
+                            error: (),
+                            language: language_contents,
+                            path: language_path,
+                            err: e,
+                        })
+                    }
+                }
+            } else {
+                None
+            };
+
+            result.push(BookListElement {
+                title: book_title,
+                tags,
+                language,
+            });
+        }
+        Ok(result)
+    }
+}
+
+impl BookStorage for FileBookStorage {
+    fn list(&mut self) -> Result<Vec<BookListElement>, BookrabError> {
+        let mut titles = HashSet::new();
+        let mut result = vec![];
+        for book_path in &self.book_paths {
+            for book in self.list_folder(book_path)? {
+                if titles.insert(book.title.clone()) {
+                    result.push(book);
+                }
+            }
+        }
+        // `read_dir` doesn't guarantee any order
+        result.sort_by(|a, b| a.title.cmp(&b.title));
+        Ok(result)
+    }
+
+    fn upload(
+        &mut self,
+        title: &str,
+        txt: &str,
+        tags: &HashSet<String>,
+    ) -> Result<(), BookrabError> {
+        // create book directory if it doesn't exist
+        let book_path = &self.book_dir(title)?;
+        if let Err(e) = fs::create_dir_all(book_path) {
+            if e.kind() != std::io::ErrorKind::AlreadyExists {
+                return Err(BookrabError::CouldntCreateDir {
+                    error: (),
+                    path: book_path.to_owned(),
+                    err: e,
+                });
+            }
+        }
+        // write text
+        let txt_path = book_path.join("txt");
+        if let Err(e) = fs::write(&txt_path, txt) {
+            return Err(BookrabError::CouldntWriteFile {
+                error: (),
+                path: txt_path,
+                err: e,
+            });
+        };
+
+        // write metadata
+        self.write_tags(title, tags)
+    }
+
+    fn text(&mut self, title: &str) -> Result<String, BookrabError> {
+        let txt_path = self.txt_path(title)?;
+        if !txt_path.exists() {
+            return Err(BookrabError::InexistentBook {
+                error: (),
+                path: txt_path,
+            });
+        }
+        match fs::read_to_string(&txt_path) {
+            Ok(v) => Ok(v),
+            Err(e) => Err(BookrabError::CouldntReadFile {
+                error: (),
+                path: txt_path,
+                err: e,
+            }),
+        }
+    }
+
+    fn reader(&mut self, title: &str) -> Result<Box<dyn Read + Send>, BookrabError> {
+        let txt_path = self.txt_path(title)?;
+        match File::open(&txt_path) {
+            Ok(v) => Ok(Box::new(v)),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => Err(BookrabError::InexistentBook {
+                error: (),
+                path: txt_path,
+            }),
+            Err(e) => Err(BookrabError::CouldntReadFile {
+                error: (),
+                path: txt_path,
+                err: e,
+            }),
+        }
+    }
+
+    fn set_language(
+        &mut self,
+        title: &str,
+        language: Option<Language>,
+    ) -> Result<(), BookrabError> {
+        let book_path = self.book_dir(title)?;
+        if !book_path.exists() {
+            return Err(BookrabError::InexistentBook {
+                error: (),
+                path: book_path,
+            });
+        }
+        let language_str =
+            serde_json::to_string(&language).expect("Language could not be converted to string");
+        let language_path = book_path.join(Self::LANGUAGE_PATH);
+        if let Err(e) = fs::write(&language_path, language_str) {
+            return Err(BookrabError::CouldntWriteFile {
+                error: (),
+                path: language_path,
+                err: e,
+            });
+        };
+        Ok(())
+    }
+
+    fn set_tags(&mut self, title: &str, tags: &HashSet<String>) -> Result<(), BookrabError> {
+        let book_dir = self.book_dir(title)?;
+        if !book_dir.join("txt").exists() {
+            return Err(BookrabError::InexistentBook {
+                error: (),
+                path: book_dir,
+            });
+        }
+        self.write_tags(title, tags)
+    }
+
+    fn rename(&mut self, title: &str, new_title: &str) -> Result<(), BookrabError> {
+        let book_dir = self.book_dir(title)?;
+        if !book_dir.join("txt").exists() {
+            return Err(BookrabError::InexistentBook {
+                error: (),
+                path: book_dir,
+            });
+        }
+        // the book stays in its folder
+        let new_path = book_dir.with_file_name(new_title);
+        if new_path.exists() || self.txt_path(new_title)?.exists() {
+            return Err(BookrabError::BookAlreadyExists {
+                error: (),
+                title: new_title.to_string(),
+            });
+        }
+        if let Err(e) = fs::rename(&book_dir, &new_path) {
+            return Err(BookrabError::CouldntWriteFile {
+                error: (),
+                path: new_path,
+                err: e,
+            });
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, title: &str) -> Result<(), BookrabError> {
+        let path = self.book_dir(title)?;
+        if !path.join("txt").exists() {
+            return Err(BookrabError::InexistentBook { error: (), path });
+        }
+        if let Err(e) = fs::remove_dir_all(&path) {
+            return Err(BookrabError::CouldntWriteFile {
+                error: (),
+                path,
+                err: e,
+            });
+        }
+        Ok(())
+    }
+}
+
+/// Keeps the books in the `books` table of the database,
+/// so that the server doesn't need a persistent disk.
+/// Only the books of one user are seen (see [BookrabConfig::for_user]).
+pub struct DbBookStorage<'a> {
+    user_id: i32,
+    connection: &'a mut DbConnection,
+}
+
+impl DbBookStorage<'_> {
+    pub fn new(user_id: i32, connection: &mut DbConnection) -> DbBookStorage<'_> {
+        DbBookStorage {
+            user_id,
+            connection,
+        }
+    }
+}
+
+/// Turns the language of a row into a [Language].
+/// Errors use the title as the path, since there are no files.
+fn language_from_db(
+    title: &str,
+    language: Option<String>,
+) -> Result<Option<Language>, BookrabError> {
+    let Some(language) = language else {
+        return Ok(None);
+    };
+    match serde_json::from_value(serde_json::Value::String(language.clone())) {
+        Ok(v) => Ok(Some(v)),
+        Err(e) => Err(BookrabError::InvalidLanguage {
+            error: (),
+            language,
+            path: PathBuf::from(title),
+            err: e,
+        }),
+    }
+}
+
+/// Code of `language` (e.g. `pt`).
+fn language_to_db(language: Option<Language>) -> Option<String> {
+    // languages are serialized as strings
+    language.map(|language| {
+        serde_json::to_value(language)
+            .unwrap()
+            .as_str()
+            .unwrap()
+            .to_string()
+    })
+}
+
+impl BookStorage for DbBookStorage<'_> {
+    fn list(&mut self) -> Result<Vec<BookListElement>, BookrabError> {
+        use schema::books::columns;
+        let rows: Vec<(String, TagList, Option<String>)> = schema::books::table
+            .select((columns::title, columns::tags, columns::language))
+            .filter(columns::user_id.eq(self.user_id))
+            .order(columns::title.asc())
+            .load(self.connection)?;
+        rows.into_iter()
+            .map(|(title, tags, language)| {
+                Ok(BookListElement {
+                    language: language_from_db(&title, language)?,
+                    tags: tags_from_db(&tags),
+                    title,
+                })
+            })
+            .collect()
+    }
+
+    fn upload(
+        &mut self,
+        title: &str,
+        txt: &str,
+        tags: &HashSet<String>,
+    ) -> Result<(), BookrabError> {
+        use schema::books::columns;
+        let row = BookRow {
+            title: title.to_string(),
+            txt: txt.to_string(),
+            tags: tags_to_db(tags),
+            language: None,
+            user_id: self.user_id,
+        };
+        with_backend!(self.connection, |connection| {
+            diesel::insert_into(schema::books::table)
+                .values(&row)
+                .on_conflict((columns::user_id, columns::title))
+                .do_update()
+                .set((
+                    columns::txt.eq(excluded(columns::txt)),
+                    columns::tags.eq(excluded(columns::tags)),
+                ))
+                .execute(connection)
+        })?;
+        Ok(())
+    }
+
+    fn text(&mut self, title: &str) -> Result<String, BookrabError> {
+        use schema::books::columns;
+        match schema::books::table
+            .find((self.user_id, title))
+            .select(columns::txt)
+            .first(self.connection)
+            .optional()?
+        {
+            Some(v) => Ok(v),
+            None => Err(BookrabError::InexistentBook {
+                error: (),
+                path: PathBuf::from(title),
+            }),
+        }
+    }
+
+    fn reader(&mut self, title: &str) -> Result<Box<dyn Read + Send>, BookrabError> {
+        Ok(Box::new(Cursor::new(self.text(title)?)))
+    }
+
+    fn set_language(
+        &mut self,
+        title: &str,
+        language: Option<Language>,
+    ) -> Result<(), BookrabError> {
+        use schema::books::columns;
+        let updated = diesel::update(schema::books::table.find((self.user_id, title)))
+            .set(columns::language.eq(language_to_db(language)))
+            .execute(self.connection)?;
+        if updated == 0 {
+            return Err(BookrabError::InexistentBook {
+                error: (),
+                path: PathBuf::from(title),
+            });
+        }
+        Ok(())
+    }
+
+    fn set_tags(&mut self, title: &str, tags: &HashSet<String>) -> Result<(), BookrabError> {
+        use schema::books::columns;
+        let updated = diesel::update(schema::books::table.find((self.user_id, title)))
+            .set(columns::tags.eq(tags_to_db(tags)))
+            .execute(self.connection)?;
+        if updated == 0 {
+            return Err(BookrabError::InexistentBook {
+                error: (),
+                path: PathBuf::from(title),
+            });
+        }
+        Ok(())
+    }
+
+    fn rename(&mut self, title: &str, new_title: &str) -> Result<(), BookrabError> {
+        use schema::books::columns;
+        let taken = schema::books::table
+            .find((self.user_id, new_title))
+            .select(columns::title)
+            .first::<String>(self.connection)
+            .optional()?;
+        if taken.is_some() {
+            return Err(BookrabError::BookAlreadyExists {
+                error: (),
+                title: new_title.to_string(),
+            });
+        }
+        let updated = diesel::update(schema::books::table.find((self.user_id, title)))
+            .set(columns::title.eq(new_title))
+            .execute(self.connection)?;
+        if updated == 0 {
+            return Err(BookrabError::InexistentBook {
+                error: (),
+                path: PathBuf::from(title),
+            });
+        }
+        Ok(())
+    }
+
+    fn delete(&mut self, title: &str) -> Result<(), BookrabError> {
+        let deleted = diesel::delete(schema::books::table.find((self.user_id, title)))
+            .execute(self.connection)?;
+        if deleted == 0 {
+            return Err(BookrabError::InexistentBook {
+                error: (),
+                path: PathBuf::from(title),
+            });
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::test_utils::{create_book_dir, s, DBCONNECTION, LUSIADAS1};
+    use crate::books::{RootBookDir, SearchOptions, TagEdit};
+    use diesel::Connection;
+    use grep_regex::RegexMatcherBuilder;
+    use grep_searcher::SearcherBuilder;
+
+    #[test]
+    fn books_in_postgres() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = BookrabConfig {
+            book_storage: BookStorageKind::Postgres,
+            ..create_book_dir(connection).config.clone()
+        };
+        // nothing is committed, so other tests don't see these books
+        connection.test_transaction::<_, BookrabError, _>(|connection| {
+            let mut root = RootBookDir::new(config.clone(), connection);
+            root.upload("lusiadas", "whatever", s(vec!["a"]))?;
+            root.upload("lusiadas", LUSIADAS1, s(vec!["b", "c"]))?;
+            root.upload("sonetos", "", s(vec![]))?;
+            assert_eq!(
+                root.list()?,
+                vec![
+                    BookListElement {
+                        title: "lusiadas".to_string(),
+                        tags: s(vec!["b", "c"]),
+                        language: Some(Language::Portuguese),
+                    },
+                    BookListElement {
+                        title: "sonetos".to_string(),
+                        tags: s(vec![]),
+                        language: None,
+                    }
+                ]
+            );
+            assert_eq!(root.text("lusiadas")?, LUSIADAS1);
+            // nothing was written to the book folder
+            assert!(fs::read_dir(&config.book_path).unwrap().next().is_none());
+
+            let result = root.search(
+                "lusiadas".to_string(),
+                r"\bpadeceu\b".to_string(),
+                SearcherBuilder::new().build(),
+                RegexMatcherBuilder::new(),
+                &SearchOptions::default(),
+            )?;
+            assert_eq!(
+                result.results,
+                vec!["Que [matched]padeceu[/matched] desonra e vitupério,\n"]
+            );
+            assert!(matches!(
+                root.text("inexistent"),
+                Err(BookrabError::InexistentBook { .. })
+            ));
+            assert!(matches!(
+                root.set_language("inexistent", None),
+                Err(BookrabError::InexistentBook { .. })
+            ));
+
+            let edit = TagEdit {
+                add: s(vec!["d"]),
+                remove: s(vec!["b"]),
+                set: None,
+            };
+            assert_eq!(root.edit_tags("lusiadas", &edit)?.tags, s(vec!["c", "d"]));
+            assert_eq!(
+                root.get_by_title("lusiadas".to_string())?.unwrap().tags,
+                s(vec!["c", "d"])
+            );
+
+            assert!(matches!(
+                root.rename("lusiadas", "sonetos"),
+                Err(BookrabError::BookAlreadyExists { .. })
+            ));
+            assert_eq!(
+                root.rename("lusiadas", "os lusíadas")?.tags,
+                s(vec!["c", "d"])
+            );
+            assert_eq!(root.text("os lusíadas")?, LUSIADAS1);
+            assert!(matches!(
+                root.text("lusiadas"),
+                Err(BookrabError::InexistentBook { .. })
+            ));
+
+            root.delete("os lusíadas")?;
+            assert_eq!(root.get_by_title("os lusíadas".to_string())?, None);
+            assert!(matches!(
+                root.delete("os lusíadas"),
+                Err(BookrabError::InexistentBook { .. })
+            ));
+            Ok(())
+        });
+    }
+
+    #[test]
+    fn several_book_folders() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let public = create_book_dir(connection).config.clone();
+        let notes = create_book_dir(connection).config.book_path.clone();
+        let mut root = RootBookDir::new(public.clone(), connection);
+        root.upload("lusiadas", LUSIADAS1, s(vec!["a"]))?;
+        root.upload("sonetos", "Alma minha gentil", s(vec![]))?;
+        let config = BookrabConfig {
+            book_paths: vec![notes.clone()],
+            upload_book_path: Some(notes.clone()),
+            ..public.clone()
+        };
+        let mut root = RootBookDir::new(config, connection);
+        root.upload("notas", "padeceu", s(vec!["b"]))?;
+        // existing books are replaced where they are
+        root.upload("sonetos", "Alma minha", s(vec![]))?;
+        assert!(notes.join("notas").join("txt").exists());
+        assert_eq!(
+            fs::read_to_string(public.book_path.join("sonetos").join("txt")).unwrap(),
+            "Alma minha"
+        );
+
+        // the book of the first folder hides the one of the others
+        fs::create_dir(notes.join("lusiadas")).unwrap();
+        fs::write(notes.join("lusiadas").join("txt"), "hidden").unwrap();
+        let titles: Vec<String> = root.list()?.into_iter().map(|book| book.title).collect();
+        assert_eq!(titles, ["lusiadas", "notas", "sonetos"]);
+        assert_eq!(root.text("lusiadas")?, LUSIADAS1);
+
+        let results = root.search_filtered(
+            |_| true,
+            r"\bpadeceu\b".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new(),
+            &SearchOptions::default(),
+        )?;
+        let matched: Vec<&str> = results
+            .iter()
+            .filter(|result| !result.results.is_empty())
+            .map(|result| result.title.as_str())
+            .collect();
+        assert_eq!(matched.len(), 2);
+        assert!(matched.contains(&"lusiadas") && matched.contains(&"notas"));
+
+        // books stay in their folders
+        root.rename("notas", "diario")?;
+        assert!(notes.join("diario").join("txt").exists());
+        assert!(matches!(
+            root.rename("diario", "sonetos"),
+            Err(BookrabError::BookAlreadyExists { .. })
+        ));
+        root.delete("diario")?;
+        assert!(!notes.join("diario").exists());
+        Ok(())
+    }
+
+    #[test]
+    fn users_have_their_own_books() {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = BookrabConfig {
+            book_storage: BookStorageKind::Postgres,
+            ..create_book_dir(connection).config.clone()
+        };
+        let user_config = config.for_user(1);
+        assert!(user_config.book_path.ends_with("users/1/books"));
+        connection.test_transaction::<_, BookrabError, _>(|connection| {
+            RootBookDir::new(config.clone(), connection).upload(
+                "lusiadas",
+                LUSIADAS1,
+                s(vec!["a"]),
+            )?;
+            let mut root = RootBookDir::new(user_config.clone(), connection);
+            assert!(matches!(
+                root.text("lusiadas"),
+                Err(BookrabError::InexistentBook { .. })
+            ));
+            root.upload("lusiadas", "As armas", s(vec!["b"]))?;
+            assert_eq!(root.text("lusiadas")?, "As armas");
+            assert_eq!(root.list()?.len(), 1);
+
+            let mut root = RootBookDir::new(config.clone(), connection);
+            assert_eq!(root.text("lusiadas")?, LUSIADAS1);
+            assert_eq!(
+                root.get_by_title("lusiadas".to_string())?.unwrap().tags,
+                s(vec!["a"])
+            );
+            Ok(())
+        });
+    }
+}
@@ -1,6 +1,8 @@
-use std::io;
+use std::{collections::HashSet, io};
 
 use grep_matcher::Match;
+
+use crate::database::tags::TagList;
 use {
     grep_matcher::Matcher,
     grep_searcher::{Searcher, SinkError},
@@ -53,3 +55,16 @@ pub(crate) fn from_utf8(bytes: &[u8]) -> Result<&str, std::io::Error> {
         Err(err) => Err(std::io::Error::error_message(err)),
     }
 }
+
+/// Turns tags into the list stored in the database.
+/// They are sorted, so that the stored list doesn't depend on the hasher.
+pub(crate) fn tags_to_db(tags: &HashSet<String>) -> TagList {
+    let mut tags: Vec<Option<String>> = tags.iter().cloned().map(Some).collect();
+    tags.sort();
+    TagList(tags)
+}
+
+/// Turns the list stored in the database into tags.
+pub(crate) fn tags_from_db(tags: &TagList) -> HashSet<String> {
+    tags.0.iter().flatten().cloned().collect()
+}
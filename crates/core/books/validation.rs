@@ -0,0 +1,239 @@
+use grep_regex::RegexMatcherBuilder;
+
+use crate::errors::BookrabError;
+
+/// Location of a problem inside of a pattern.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct PatternSpan {
+    /// Offset (in bytes) where the problem starts
+    pub start: usize,
+    /// Offset (in bytes) where the problem ends
+    pub end: usize,
+    /// Line where the problem starts (starting at 1)
+    pub line: usize,
+    /// Column where the problem starts (starting at 1)
+    pub column: usize,
+}
+
+/// Result of the validation of a pattern.
+/// See [validate_pattern].
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct PatternDiagnostics {
+    /// Whether the pattern can be used in a search
+    pub valid: bool,
+    /// Short description of the problem
+    pub message: Option<String>,
+    /// Where the problem is (not every problem has a location)
+    pub span: Option<PatternSpan>,
+}
+
+impl From<&regex_syntax::ast::Span> for PatternSpan {
+    fn from(span: &regex_syntax::ast::Span) -> Self {
+        PatternSpan {
+            start: span.start.offset,
+            end: span.end.offset,
+            line: span.start.line,
+            column: span.start.column,
+        }
+    }
+}
+
+/// Checks whether `pattern` can be built by `matcher_builder`.
+/// Unlike [grep_regex::Error], the diagnostics say where
+/// the problem is, so clients can point at it while the user types.
+pub fn validate_pattern(
+    pattern: &str,
+    matcher_builder: &RegexMatcherBuilder,
+) -> PatternDiagnostics {
+    let err = match matcher_builder.build(pattern) {
+        Ok(_) => {
+            return PatternDiagnostics {
+                valid: true,
+                message: None,
+                span: None,
+            }
+        }
+        Err(e) => e,
+    };
+    // grep_regex doesn't tell us where the error is,
+    // so we parse the pattern again to find out.
+    let (message, span) = match regex_syntax::Parser::new().parse(pattern) {
+        Err(regex_syntax::Error::Parse(e)) => (e.kind().to_string(), Some(e.span().into())),
+        Err(regex_syntax::Error::Translate(e)) => (e.kind().to_string(), Some(e.span().into())),
+        _ => (err.to_string(), None),
+    };
+    PatternDiagnostics {
+        valid: false,
+        message: Some(message),
+        span,
+    }
+}
+
+/// Problem with a field of a request (see [crate::errors::BookrabError::InvalidFields]).
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: impl ToString, message: impl ToString) -> Self {
+        FieldError {
+            field: field.to_string(),
+            message: message.to_string(),
+        }
+    }
+}
+
+/// Problems with the pattern of a search: it can't be empty
+/// and must be built by `matcher_builder` (see [validate_pattern]).
+pub fn pattern_errors(pattern: &str, matcher_builder: &RegexMatcherBuilder) -> Vec<FieldError> {
+    if pattern.is_empty() {
+        return vec![FieldError::new("pattern", "can't be empty")];
+    }
+    let diagnostics = validate_pattern(pattern, matcher_builder);
+    match diagnostics.message {
+        Some(message) if !diagnostics.valid => vec![FieldError::new("pattern", message)],
+        _ => vec![],
+    }
+}
+
+/// Problems with the title of a book. Titles name the folder of the
+/// book, so they can't be empty, `.` or `..` nor have slashes or NUL
+/// (e.g. `../2/books/x` would write in the folder of another user).
+pub fn title_errors(field: &str, title: &str) -> Vec<FieldError> {
+    let message = match title {
+        "" => "can't be empty",
+        "." | ".." => "can't be . or ..",
+        _ if title.contains(['/', '\\']) => "can't have slashes",
+        _ if title.contains('\0') => "can't have NUL characters",
+        _ => return vec![],
+    };
+    vec![FieldError::new(field, message)]
+}
+
+/// Fails with [BookrabError::InvalidFields] when `title` can't be the
+/// title of a book (see [title_errors]).
+pub fn check_title(field: &str, title: &str) -> Result<(), BookrabError> {
+    let errors = title_errors(field, title);
+    if errors.is_empty() {
+        return Ok(());
+    }
+    Err(BookrabError::InvalidFields { error: (), errors })
+}
+
+/// Field of a request that serde couldn't deserialize. Its messages only name
+/// the field when it is missing, unknown or duplicated; when a value is the
+/// problem (e.g. ``unknown variant `Allx` ``), the field with that value is
+/// looked for in `fields`. `None` when the field can't be found.
+pub fn field_of_serde_error<'a>(
+    message: &str,
+    fields: impl IntoIterator<Item = (&'a str, &'a str)>,
+) -> Option<String> {
+    let quoted = |prefix: &str| {
+        let rest = &message[message.find(prefix)? + prefix.len()..];
+        rest.split('`').next()
+    };
+    for prefix in ["missing field `", "unknown field `", "duplicate field `"] {
+        if let Some(field) = quoted(prefix) {
+            return Some(field.to_string());
+        }
+    }
+    let value = quoted("unknown variant `")?;
+    fields
+        .into_iter()
+        .find(|(_, v)| *v == value)
+        .map(|(field, _)| field.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_pattern() {
+        let diagnostics = validate_pattern(r"\bpor\w*?", &RegexMatcherBuilder::new());
+        assert!(diagnostics.valid);
+        assert_eq!(diagnostics.message, None);
+        assert_eq!(diagnostics.span, None);
+    }
+
+    #[test]
+    fn unclosed_group() {
+        let diagnostics = validate_pattern("armas (e", &RegexMatcherBuilder::new());
+        assert!(!diagnostics.valid);
+        assert_eq!(diagnostics.message, Some("unclosed group".to_string()));
+        assert_eq!(
+            diagnostics.span,
+            Some(PatternSpan {
+                start: 6,
+                end: 7,
+                line: 1,
+                column: 7
+            })
+        );
+    }
+
+    #[test]
+    fn error_without_location() {
+        let diagnostics = validate_pattern(
+            "a\nb",
+            RegexMatcherBuilder::new().line_terminator(Some(b'\n')),
+        );
+        assert!(!diagnostics.valid);
+        assert!(diagnostics.message.is_some());
+        assert_eq!(diagnostics.span, None);
+    }
+
+    #[test]
+    fn pattern_problems() {
+        let builder = RegexMatcherBuilder::new();
+        assert_eq!(pattern_errors("armas", &builder), vec![]);
+        assert_eq!(
+            pattern_errors("", &builder),
+            vec![FieldError::new("pattern", "can't be empty")]
+        );
+        assert_eq!(
+            pattern_errors("armas (e", &builder),
+            vec![FieldError::new("pattern", "unclosed group")]
+        );
+    }
+
+    #[test]
+    fn title_problems() {
+        assert_eq!(title_errors("title", "Os Lusíadas.txt"), vec![]);
+        assert_eq!(title_errors("title", "..."), vec![]);
+        for title in ["", ".", "..", "../../2/books/x", "a\\b", "a/b", "a\0b"] {
+            assert_eq!(title_errors("title", title).len(), 1, "{title:?}");
+        }
+        assert!(matches!(
+            check_title("new_title", ".."),
+            Err(BookrabError::InvalidFields { .. })
+        ));
+    }
+
+    #[test]
+    fn fields_of_serde_errors() {
+        let fields = [("pattern", "armas"), ("include_mode", "Allx")];
+        assert_eq!(
+            field_of_serde_error("missing field `pattern`", fields),
+            Some("pattern".to_string())
+        );
+        assert_eq!(
+            field_of_serde_error(
+                "unknown variant `Allx`, expected `All` or `Any` at line 1 column 20",
+                fields
+            ),
+            Some("include_mode".to_string())
+        );
+        assert_eq!(
+            field_of_serde_error("invalid digit found in string", fields),
+            None
+        );
+        // values that aren't in the request don't match anything
+        assert_eq!(
+            field_of_serde_error("unknown variant `Nonex`, expected `All`", fields),
+            None
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use diesel::{
+    prelude::{Insertable, Queryable},
+    AsChangeset, Selectable,
+};
+
+use crate::{database::tags::TagList, schema::books};
+
+#[derive(Debug, Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = books)]
+#[diesel(check_for_backend(crate::database::MultiBackend))]
+pub struct BookRow {
+    pub title: String,
+    pub txt: String,
+    pub tags: TagList,
+    /// Code of the language (e.g. `pt`)
+    pub language: Option<String>,
+    pub user_id: i32,
+}
@@ -0,0 +1,30 @@
+use chrono::NaiveDateTime;
+use diesel::{
+    prelude::{Insertable, Queryable},
+    Selectable,
+};
+
+use crate::schema::book_catalog;
+
+#[derive(Insertable)]
+#[diesel(table_name = book_catalog)]
+pub struct NewCatalogEntry<'a> {
+    pub title: &'a str,
+    pub checksum: &'a str,
+    pub size: i64,
+    pub user_id: i32,
+}
+
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable, serde::Serialize)]
+#[diesel(table_name = book_catalog)]
+#[diesel(check_for_backend(crate::database::MultiBackend))]
+pub struct CatalogEntry {
+    pub title: String,
+    /// SHA-256 of the text (in hexadecimal)
+    pub checksum: String,
+    /// Size of the text (in bytes)
+    pub size: i64,
+    pub uploaded_at: NaiveDateTime,
+    /// Last time the text changed
+    pub updated_at: NaiveDateTime,
+}
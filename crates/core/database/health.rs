@@ -0,0 +1,125 @@
+use std::fs;
+
+use diesel::RunQueryDsl;
+
+use crate::config::BookrabConfig;
+
+use super::DbPool;
+
+/// Result of a single check of [health_check].
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct CheckResult {
+    pub ok: bool,
+    /// Description of the problem (when `ok` is `false`)
+    pub error: Option<String>,
+}
+
+impl CheckResult {
+    fn from_result<E: std::fmt::Display>(result: Result<(), E>) -> Self {
+        match result {
+            Ok(_) => CheckResult {
+                ok: true,
+                error: None,
+            },
+            Err(e) => CheckResult {
+                ok: false,
+                error: Some(e.to_string()),
+            },
+        }
+    }
+}
+
+/// Result of [health_check]. The book folder and the database are
+/// reported separately, so that one problem doesn't hide the other.
+#[derive(Clone, Debug, PartialEq, serde::Serialize)]
+pub struct HealthReport {
+    pub book_path: CheckResult,
+    pub database: CheckResult,
+}
+
+impl HealthReport {
+    /// Whether everything works.
+    pub fn ok(&self) -> bool {
+        self.book_path.ok && self.database.ok
+    }
+}
+
+/// Checks whether the book folders can be read.
+pub fn check_book_path(config: &BookrabConfig) -> CheckResult {
+    let result = config.book_folders().iter().try_for_each(|folder| {
+        fs::read_dir(folder)
+            .map(|_| ())
+            .map_err(|e| format!("{}: {e}", folder.display()))
+    });
+    CheckResult::from_result(result)
+}
+
+/// Checks whether a connection can be taken from `pool`
+/// and whether the database answers a trivial query.
+/// Without a pool (i.e. no database is configured) there is nothing to check.
+pub fn check_database(pool: Option<&DbPool>) -> CheckResult {
+    let Some(pool) = pool else {
+        return CheckResult::from_result(Ok::<(), String>(()));
+    };
+    let result = pool
+        .get()
+        .map_err(|e| e.to_string())
+        .and_then(|mut connection| {
+            diesel::sql_query("SELECT 1")
+                .execute(&mut connection)
+                .map(|_| ())
+                .map_err(|e| e.to_string())
+        });
+    CheckResult::from_result(result)
+}
+
+/// Checks the book folder and the database.
+pub fn health_check(config: &BookrabConfig, pool: Option<&DbPool>) -> HealthReport {
+    HealthReport {
+        book_path: check_book_path(config),
+        database: check_database(pool),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::test_utils::{random_string, DBCONNECTION};
+    use crate::database::DbConnectionManager;
+    use std::time::Duration;
+
+    #[test]
+    fn healthy() {
+        let config = BookrabConfig {
+            book_path: std::env::temp_dir(),
+            ..Default::default()
+        };
+        let report = health_check(&config, Some(&DBCONNECTION));
+        assert!(report.ok());
+        assert_eq!(report.book_path.error, None);
+        assert_eq!(report.database.error, None);
+    }
+
+    #[test]
+    fn problems_are_reported_separately() {
+        let config = BookrabConfig {
+            book_path: std::env::temp_dir().join(random_string()),
+            ..Default::default()
+        };
+        let pool = DbPool::builder()
+            .connection_timeout(Duration::from_millis(100))
+            .build_unchecked(DbConnectionManager::new(
+                "postgres://nobody@localhost:1/nothing",
+            ));
+
+        let report = health_check(&config, Some(&DBCONNECTION));
+        assert!(!report.ok());
+        assert!(!report.book_path.ok);
+        assert!(report.book_path.error.is_some());
+        assert!(report.database.ok);
+
+        let report = health_check(&config, Some(&pool));
+        assert!(!report.database.ok);
+        assert!(report.database.error.is_some());
+    }
+}
@@ -4,13 +4,28 @@ use diesel::{
     Selectable,
 };
 
-use crate::schema::{search_history, search_results};
+use crate::{
+    database::tags::TagList,
+    schema::{search_history, search_results},
+};
 
 #[derive(Insertable)]
 #[diesel(table_name = search_history)]
 pub struct NewSearchHistoryEntry<'a> {
     pub title: &'a str,
     pub pattern: &'a str,
+    pub include_mode: &'a str,
+    pub include_tags: &'a TagList,
+    pub exclude_mode: &'a str,
+    pub exclude_tags: &'a TagList,
+    pub case_insensitive: bool,
+    pub case_smart: bool,
+    pub ignore_accents: bool,
+    pub before_context: i32,
+    pub after_context: i32,
+    pub match_count: i32,
+    pub duration_ms: i64,
+    pub user_id: i32,
 }
 
 #[derive(Insertable)]
@@ -20,19 +35,35 @@ pub struct NewResult<'a> {
     pub result: &'a str,
 }
 
-#[derive(Debug, Queryable, Selectable)]
+#[derive(Debug, Queryable, Selectable, serde::Serialize)]
 #[diesel(table_name=crate::schema::search_history)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(check_for_backend(crate::database::MultiBackend))]
 pub struct SearchHistoryEntry {
     pub id: i32,
     pub title: String,
     pub pattern: String,
     pub date: NaiveDateTime,
+    pub include_mode: String,
+    pub include_tags: TagList,
+    pub exclude_mode: String,
+    pub exclude_tags: TagList,
+    pub case_insensitive: bool,
+    pub case_smart: bool,
+    pub ignore_accents: bool,
+    pub before_context: i32,
+    pub after_context: i32,
+    pub match_count: i32,
+    pub duration_ms: i64,
+    /// Number of times this search was made in a row
+    pub hits: i32,
+    /// Owner of the entry (see [crate::config::BookrabConfig::for_user])
+    #[serde(skip_serializing)]
+    pub user_id: i32,
 }
 
 #[derive(Debug, Queryable, Selectable)]
 #[diesel(table_name=crate::schema::search_results)]
-#[diesel(check_for_backend(diesel::pg::Pg))]
+#[diesel(check_for_backend(crate::database::MultiBackend))]
 pub struct SearchResult {
     pub id: i32,
     pub search_history_id: i32,
@@ -1,6 +1,85 @@
 use diesel::pg::PgConnection;
-use diesel::r2d2::{ConnectionManager, Pool, PooledConnection};
+use diesel::r2d2::{self, Pool, PooledConnection, R2D2Connection};
+#[cfg(feature = "sqlite")]
+use diesel::sqlite::SqliteConnection;
+use diesel::{Connection, ConnectionResult};
+pub mod books;
+pub mod catalog;
+mod health;
 pub mod history;
+pub mod reading_progress;
+pub mod saved_searches;
+#[cfg(feature = "sqlite")]
+pub mod sqlite;
+pub mod tags;
+pub mod users;
 
-pub type PgPool = Pool<ConnectionManager<PgConnection>>;
-pub type PgPooledConnection = PooledConnection<ConnectionManager<PgConnection>>;
+pub use health::{check_book_path, check_database, health_check, CheckResult, HealthReport};
+
+/// Connection to the database of [crate::config::BookrabConfig::database_url].
+/// Queries that both databases understand are written once against it;
+/// the rest match on the variant (see [crate::books::history::SearchHistory::stats]).
+#[derive(diesel::MultiConnection)]
+pub enum DbConnection {
+    Postgresql(PgConnection),
+    /// Used for `sqlite://` URLs (see [sqlite::establish_sqlite_connection])
+    #[cfg(feature = "sqlite")]
+    Sqlite(SqliteConnection),
+}
+
+/// Runs `$body` with `$connection` bound to the connection inside of a
+/// [DbConnection]. It is for the queries that [MultiBackend] can't build,
+/// like upserts and batch inserts, which both databases run the same way,
+/// so the body is written once and compiled for each database.
+macro_rules! with_backend {
+    ($db:expr, |$connection:ident| $body:expr) => {
+        match $db {
+            $crate::database::DbConnection::Postgresql($connection) => $body,
+            #[cfg(feature = "sqlite")]
+            $crate::database::DbConnection::Sqlite($connection) => $body,
+        }
+    };
+}
+pub(crate) use with_backend;
+
+/// Opens a connection to `url`. The database is chosen by the scheme
+/// of the URL, instead of trying each one until a connection works.
+pub fn establish_connection(url: &str) -> ConnectionResult<DbConnection> {
+    #[cfg(feature = "sqlite")]
+    if let Some(path) = sqlite::sqlite_path(url) {
+        return sqlite::establish_sqlite_connection(path).map(DbConnection::Sqlite);
+    }
+    PgConnection::establish(url).map(DbConnection::Postgresql)
+}
+
+/// Makes the connections of a [DbPool] with [establish_connection].
+#[derive(Clone, Debug)]
+pub struct DbConnectionManager {
+    url: String,
+}
+
+impl DbConnectionManager {
+    pub fn new(url: impl Into<String>) -> Self {
+        DbConnectionManager { url: url.into() }
+    }
+}
+
+impl r2d2::ManageConnection for DbConnectionManager {
+    type Connection = DbConnection;
+    type Error = r2d2::Error;
+
+    fn connect(&self) -> Result<DbConnection, r2d2::Error> {
+        establish_connection(&self.url).map_err(r2d2::Error::ConnectionError)
+    }
+
+    fn is_valid(&self, connection: &mut DbConnection) -> Result<(), r2d2::Error> {
+        connection.ping().map_err(r2d2::Error::QueryError)
+    }
+
+    fn has_broken(&self, connection: &mut DbConnection) -> bool {
+        std::thread::panicking() || connection.is_broken()
+    }
+}
+
+pub type DbPool = Pool<DbConnectionManager>;
+pub type DbPooledConnection = PooledConnection<DbConnectionManager>;
@@ -0,0 +1,13 @@
+use chrono::NaiveDateTime;
+use diesel::{prelude::Queryable, Selectable};
+
+use crate::schema::reading_progress;
+
+#[derive(Debug, Queryable, Selectable)]
+#[diesel(table_name = reading_progress)]
+#[diesel(check_for_backend(crate::database::MultiBackend))]
+pub struct ReadingProgressRow {
+    pub title: String,
+    pub line: i64,
+    pub updated_at: NaiveDateTime,
+}
@@ -0,0 +1,23 @@
+use diesel::{
+    prelude::{Insertable, Queryable},
+    AsChangeset, Selectable,
+};
+
+use crate::{database::tags::TagList, schema::saved_searches};
+
+#[derive(Debug, Queryable, Selectable, Insertable, AsChangeset)]
+#[diesel(table_name = saved_searches)]
+#[diesel(check_for_backend(crate::database::MultiBackend))]
+pub struct SavedSearchRow {
+    pub name: String,
+    pub pattern: String,
+    pub include_mode: String,
+    pub include_tags: TagList,
+    pub exclude_mode: String,
+    pub exclude_tags: TagList,
+    pub case_insensitive: bool,
+    pub case_smart: bool,
+    pub ignore_accents: bool,
+    pub before_context: i32,
+    pub after_context: i32,
+}
@@ -0,0 +1,155 @@
+use std::path::Path;
+
+use diesel::{
+    connection::SimpleConnection, sql_types::Integer, sqlite::SqliteConnection, Connection,
+    ConnectionError, ConnectionResult, QueryableByName, RunQueryDsl,
+};
+
+/// Migrations of the SQLite database, in order. They create the tables of
+/// [crate::schema], but tags are stored as JSON arrays (see [super::tags::Tags]).
+/// `PRAGMA user_version` counts the ones that already ran.
+const MIGRATIONS: &[&str] = &[
+    include_str!("../migrations_sqlite/2026-10-17-140512_create_history/up.sql"),
+    include_str!("../migrations_sqlite/2026-10-18-091500_create_library/up.sql"),
+];
+
+/// Path of the database of a `sqlite://` URL
+/// (e.g. `sqlite:///var/lib/bookrab/bookrab.sqlite3` or `sqlite://bookrab.sqlite3`).
+pub fn sqlite_path(url: &str) -> Option<&Path> {
+    url.strip_prefix("sqlite://").map(Path::new)
+}
+
+#[derive(QueryableByName)]
+struct UserVersion {
+    #[diesel(sql_type = Integer)]
+    user_version: i32,
+}
+
+/// Opens (or creates) the SQLite database in `path`
+/// and runs the migrations it is missing.
+pub fn establish_sqlite_connection(path: &Path) -> ConnectionResult<SqliteConnection> {
+    let mut connection = SqliteConnection::establish(&path.to_string_lossy())?;
+    migrate(&mut connection).map_err(ConnectionError::CouldntSetupConfiguration)?;
+    Ok(connection)
+}
+
+fn migrate(connection: &mut SqliteConnection) -> diesel::QueryResult<()> {
+    // several connections of a pool may write at the same time
+    connection.batch_execute("PRAGMA foreign_keys = ON; PRAGMA busy_timeout = 5000;")?;
+    connection.immediate_transaction(|connection| {
+        let version = diesel::sql_query("PRAGMA user_version")
+            .get_result::<UserVersion>(connection)?
+            .user_version;
+        let done = usize::try_from(version).unwrap_or_default();
+        for migration in MIGRATIONS.iter().skip(done) {
+            connection.batch_execute(migration)?;
+        }
+        connection.batch_execute(&format!("PRAGMA user_version = {}", MIGRATIONS.len()))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashSet;
+
+    use grep_regex::RegexMatcherBuilder;
+    use grep_searcher::SearcherBuilder;
+
+    use crate::books::catalog::{BookCatalog, CatalogOrder};
+    use crate::books::history::{HistoryFilter, SearchHistory};
+    use crate::books::reading_progress::ReadingProgress;
+    use crate::books::saved_searches::{SavedSearch, SavedSearches};
+    use crate::books::test_utils::{random_string, s, LUSIADAS2};
+    use crate::books::{RootBookDir, SearchOptions};
+    use crate::config::{ensure_config_works, BookStorageKind, BookrabConfig};
+    use crate::database::{DbConnectionManager, DbPool};
+    use crate::errors::BookrabError;
+    use crate::users::Users;
+
+    /// The whole library works with a SQLite file instead of Postgresql.
+    #[test]
+    fn sqlite_database() -> Result<(), BookrabError> {
+        let folder = std::env::temp_dir().join(format!("bookrab-test-{}", random_string()));
+        let url = format!("sqlite://{}", folder.join("bookrab.sqlite3").display());
+        let config = ensure_config_works(BookrabConfig {
+            book_path: folder.join("books"),
+            database_url: Some(url.clone()),
+            book_storage: BookStorageKind::Postgres,
+            ..Default::default()
+        })
+        .unwrap();
+        let pool = DbPool::builder()
+            .max_size(2)
+            .build(DbConnectionManager::new(url))
+            .unwrap();
+        let connection = &mut pool.get().unwrap();
+
+        let user = Users::new(config.clone(), connection).register("camoes", "tagus123")?;
+        let config = config.for_user(user.id);
+        assert_eq!(
+            Users::new(config.clone(), connection)
+                .authenticate("camoes", "tagus123")?
+                .id,
+            user.id
+        );
+
+        let mut book_dir = RootBookDir::new(config.clone(), connection);
+        book_dir.upload("lusiadas", LUSIADAS2, s(vec!["epic", "pt"]))?;
+        let books = book_dir.list()?;
+        assert_eq!(books[0].tags, s(vec!["epic", "pt"]));
+        let results = book_dir.search(
+            "lusiadas".to_string(),
+            "Taprobana".to_string(),
+            SearcherBuilder::new().build(),
+            RegexMatcherBuilder::new(),
+            &SearchOptions::default(),
+        )?;
+        assert_eq!(results.match_count(), 1);
+
+        let history = SearchHistory::new(config.clone(), connection);
+        let page = history.get_history_page(
+            &HistoryFilter {
+                pattern: Some("Tapro".to_string()),
+                ..Default::default()
+            },
+            None,
+            10,
+        )?;
+        assert_eq!(page.entries.len(), 1);
+        assert_eq!(page.entries[0].title, "lusiadas");
+        let stats = SearchHistory::new(config.clone(), connection).stats(10)?;
+        assert_eq!(stats.top_books[0].title, "lusiadas");
+        assert_eq!(stats.searches_per_day[0].count, 1);
+
+        let catalog = BookCatalog::new(config.clone(), connection).list(CatalogOrder::Title)?;
+        assert_eq!(catalog[0].size, LUSIADAS2.len() as i64);
+
+        let search = SavedSearch {
+            name: "ilha".to_string(),
+            pattern: "Taprobana".to_string(),
+            include_mode: Default::default(),
+            include_tags: s(vec!["epic"]),
+            exclude_mode: Default::default(),
+            exclude_tags: HashSet::new(),
+            case_insensitive: true,
+            case_smart: false,
+            ignore_accents: false,
+            before_context: 1,
+            after_context: 0,
+        };
+        SavedSearches::new(config.clone(), connection).save(&search)?;
+        SavedSearches::new(config.clone(), connection).save(&search)?;
+        assert_eq!(
+            SavedSearches::new(config.clone(), connection).list()?,
+            vec![search]
+        );
+
+        ReadingProgress::new(config.clone(), connection).save("lusiadas", 3)?;
+        ReadingProgress::new(config.clone(), connection).save("lusiadas", 5)?;
+        let position = ReadingProgress::new(config.clone(), connection).get("lusiadas")?;
+        assert_eq!(position.map(|position| position.line), Some(5));
+
+        std::fs::remove_dir_all(folder).unwrap();
+        Ok(())
+    }
+}
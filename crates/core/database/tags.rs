@@ -0,0 +1,72 @@
+use diesel::{
+    backend::Backend,
+    deserialize::{self, FromSql, FromSqlRow},
+    expression::AsExpression,
+    pg::{Pg, PgValue},
+    serialize::{self, IsNull, Output, ToSql},
+    sql_types::{Array, HasSqlType, Nullable, SqlType, Text},
+};
+
+use super::MultiBackend;
+
+/// SQL type of the tags of a row. Postgresql stores them in a `TEXT[]`
+/// and SQLite, which has no arrays, in a JSON array (e.g. `["epic","pt"]`).
+#[derive(Clone, Copy, Debug, Default, SqlType)]
+#[diesel(postgres_type(oid = 1009, array_oid = 1009))]
+#[cfg_attr(feature = "sqlite", diesel(sqlite_type(name = "Text")))]
+pub struct Tags;
+
+/// Tags of a row (see [crate::books::utils::tags_to_db]).
+#[derive(Clone, Debug, Default, PartialEq, AsExpression, FromSqlRow, serde::Serialize)]
+#[diesel(sql_type = Tags)]
+#[serde(transparent)]
+pub struct TagList(pub Vec<Option<String>>);
+
+impl HasSqlType<Tags> for MultiBackend {
+    fn metadata(lookup: &mut Self::MetadataLookup) -> Self::TypeMetadata {
+        MultiBackend::lookup_sql_type::<Tags>(lookup)
+    }
+}
+
+impl FromSql<Tags, MultiBackend> for TagList {
+    fn from_sql(bytes: <MultiBackend as Backend>::RawValue<'_>) -> deserialize::Result<Self> {
+        bytes.from_sql::<Self, Tags>()
+    }
+}
+
+impl ToSql<Tags, MultiBackend> for TagList {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, MultiBackend>) -> serialize::Result {
+        out.set_value((Tags, self));
+        Ok(IsNull::No)
+    }
+}
+
+impl FromSql<Tags, Pg> for TagList {
+    fn from_sql(bytes: PgValue<'_>) -> deserialize::Result<Self> {
+        <Vec<Option<String>> as FromSql<Array<Nullable<Text>>, Pg>>::from_sql(bytes).map(TagList)
+    }
+}
+
+impl ToSql<Tags, Pg> for TagList {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, Pg>) -> serialize::Result {
+        <Vec<Option<String>> as ToSql<Array<Nullable<Text>>, Pg>>::to_sql(&self.0, out)
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl FromSql<Tags, diesel::sqlite::Sqlite> for TagList {
+    fn from_sql(
+        bytes: <diesel::sqlite::Sqlite as Backend>::RawValue<'_>,
+    ) -> deserialize::Result<Self> {
+        let json = <String as FromSql<Text, diesel::sqlite::Sqlite>>::from_sql(bytes)?;
+        Ok(TagList(serde_json::from_str(&json)?))
+    }
+}
+
+#[cfg(feature = "sqlite")]
+impl ToSql<Tags, diesel::sqlite::Sqlite> for TagList {
+    fn to_sql<'b>(&'b self, out: &mut Output<'b, '_, diesel::sqlite::Sqlite>) -> serialize::Result {
+        out.set_value(serde_json::to_string(&self.0)?);
+        Ok(IsNull::No)
+    }
+}
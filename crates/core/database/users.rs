@@ -0,0 +1,26 @@
+use chrono::NaiveDateTime;
+use diesel::{
+    prelude::{Insertable, Queryable},
+    Selectable,
+};
+
+use crate::schema::users;
+
+#[derive(Insertable)]
+#[diesel(table_name = users)]
+pub struct NewUser<'a> {
+    pub username: &'a str,
+    pub password_hash: &'a str,
+}
+
+#[derive(Clone, Debug, PartialEq, Queryable, Selectable, serde::Serialize)]
+#[diesel(table_name = users)]
+#[diesel(check_for_backend(crate::database::MultiBackend))]
+pub struct User {
+    pub id: i32,
+    pub username: String,
+    /// Argon2 hash in the PHC string format
+    #[serde(skip_serializing)]
+    pub password_hash: String,
+    pub created_at: NaiveDateTime,
+}
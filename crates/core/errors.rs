@@ -11,7 +11,10 @@ macro_rules! edddd {
 }
 edddd!(e0001, "E0001: could not save file permanently.");
 edddd!(e0002, "E0002: could not create directory.");
-edddd!(e0003, "E0003: file should have 'text/plain' content type.");
+edddd!(
+    e0003,
+    "E0003: file should be plain text, markdown, html or epub."
+);
 edddd!(e0004, "E0004: could not write tags.");
 edddd!(e0005, "E0005: one of your book folders is messed up.");
 edddd!(e0006, "E0006: couldnt read child of your book folder.");
@@ -26,6 +29,31 @@ edddd!(
     "E0013: couldn't search file (even though it exists)."
 );
 edddd!(e0015, "E0015: database error.");
+edddd!(e0016, "E0016: invalid language.");
+edddd!(e0017, "E0017: this operation must be confirmed.");
+edddd!(e0018, "E0018: saved search doesnt exist.");
+edddd!(e0019, "E0019: couldnt connect to the database.");
+edddd!(e0020, "E0020: this operation needs a database.");
+edddd!(e0021, "E0021: username is taken.");
+edddd!(e0022, "E0022: wrong username or password.");
+edddd!(e0023, "E0023: invalid password.");
+edddd!(e0024, "E0024: authentication required.");
+edddd!(e0025, "E0025: couldnt hash password.");
+edddd!(e0026, "E0026: upload is too large.");
+edddd!(e0027, "E0027: couldnt convert book to plain text.");
+edddd!(e0028, "E0028: there is already a book with this title.");
+edddd!(e0029, "E0029: invalid library archive.");
+edddd!(e0030, "E0030: some fields of the request are invalid.");
+edddd!(
+    e0031,
+    "E0031: idempotency key cant be used for this request."
+);
+edddd!(e0032, "E0032: request to the bookrab server failed.");
+edddd!(e0033, "E0033: history entry doesnt exist.");
+edddd!(
+    e0034,
+    "E0034: this history backend cant be filtered or aggregated."
+);
 
 fn format_error<S: Serializer, D: Debug>(err: &D, s: S) -> Result<S::Ok, S::Error> {
     s.serialize_str(format!("{:#?}", err).as_str())
@@ -54,7 +82,7 @@ pub enum BookrabError {
     },
 
     /// Responds with [`E0003_MSG`]
-    /// You shoud've inputed a text file.
+    /// You shoud've inputed a text file (or a format of [crate::books::import]).
     ShouldBeTextPlain {
         #[serde(serialize_with = "e0003")]
         error: (),
@@ -163,12 +191,187 @@ pub enum BookrabError {
         #[serde(serialize_with = "format_error")]
         err: diesel::result::Error,
     },
+
+    /// Responds with [`E0016_MSG`]
+    /// Invalid language inside book folder.
+    InvalidLanguage {
+        #[serde(serialize_with = "e0016")]
+        error: (),
+        language: String,
+        path: PathBuf,
+        #[serde(serialize_with = "format_error")]
+        err: serde_json::error::Error,
+    },
+
+    /// Responds with [`E0017_MSG`]
+    /// A destructive operation was requested without confirmation.
+    UnconfirmedOperation {
+        #[serde(serialize_with = "e0017")]
+        error: (),
+        operation: String,
+    },
+
+    /// Responds with [`E0018_MSG`]
+    /// There is no saved search with this name.
+    InexistentSavedSearch {
+        #[serde(serialize_with = "e0018")]
+        error: (),
+        name: String,
+    },
+
+    /// Responds with [`E0019_MSG`]
+    /// Connection to the database failed.
+    CouldntConnectToDatabase {
+        #[serde(serialize_with = "e0019")]
+        error: (),
+        url: String,
+        #[serde(serialize_with = "format_error")]
+        err: diesel::ConnectionError,
+    },
+
+    /// Responds with [`E0020_MSG`]
+    /// The operation needs a database, but none is configured.
+    DatabaseUnavailable {
+        #[serde(serialize_with = "e0020")]
+        error: (),
+        operation: String,
+    },
+
+    /// Responds with [`E0021_MSG`]
+    /// There is already a user with this name.
+    UsernameTaken {
+        #[serde(serialize_with = "e0021")]
+        error: (),
+        username: String,
+    },
+
+    /// Responds with [`E0022_MSG`]
+    /// Login failed.
+    InvalidCredentials {
+        #[serde(serialize_with = "e0022")]
+        error: (),
+        username: String,
+    },
+
+    /// Responds with [`E0023_MSG`]
+    /// The password (or username) doesn't follow the rules.
+    InvalidPassword {
+        #[serde(serialize_with = "e0023")]
+        error: (),
+        reason: String,
+    },
+
+    /// Responds with [`E0024_MSG`]
+    /// The token is missing, invalid or expired.
+    Unauthorized {
+        #[serde(serialize_with = "e0024")]
+        error: (),
+        reason: String,
+    },
+
+    /// Responds with [`E0025_MSG`]
+    /// Argon2 couldn't hash or parse a password hash.
+    CouldntHashPassword {
+        #[serde(serialize_with = "e0025")]
+        error: (),
+        #[serde(serialize_with = "format_error")]
+        err: argon2::password_hash::Error,
+    },
+
+    /// Responds with [`E0026_MSG`]
+    /// The upload is bigger than [crate::config::BookrabConfig::max_upload_bytes]
+    /// (or its fields kept in memory are bigger than
+    /// [crate::config::BookrabConfig::max_upload_memory_bytes]).
+    UploadTooLarge {
+        #[serde(serialize_with = "e0026")]
+        error: (),
+        /// Limit that was exceeded (in bytes)
+        limit: usize,
+    },
+
+    /// Responds with [`E0027_MSG`]
+    /// The book is broken (e.g. an EPUB without chapters).
+    CouldntConvertBook {
+        #[serde(serialize_with = "e0027")]
+        error: (),
+        filename: String,
+        reason: String,
+    },
+
+    /// Responds with [`E0028_MSG`]
+    /// A book can't take the title of another one (e.g. when renamed).
+    BookAlreadyExists {
+        #[serde(serialize_with = "e0028")]
+        error: (),
+        title: String,
+    },
+
+    /// Responds with [`E0029_MSG`]
+    /// The archive isn't a `.tar.gz` made by
+    /// [crate::books::RootBookDir::export].
+    InvalidArchive {
+        #[serde(serialize_with = "e0029")]
+        error: (),
+        reason: String,
+    },
+
+    /// Responds with [`E0030_MSG`]
+    /// The request couldn't be read or has values that don't make sense
+    /// (e.g. an empty pattern). Every problem is listed.
+    InvalidFields {
+        #[serde(serialize_with = "e0030")]
+        error: (),
+        errors: Vec<crate::books::validation::FieldError>,
+    },
+
+    /// Responds with [`E0031_MSG`]
+    /// A request with the same `Idempotency-Key` is still running
+    /// or the key was used for a different request.
+    IdempotencyKeyConflict {
+        #[serde(serialize_with = "e0031")]
+        error: (),
+        key: String,
+        reason: String,
+    },
+
+    /// Responds with [`E0032_MSG`]
+    /// A request to another bookrab server (e.g. by the remote mode
+    /// of the TUI) failed or was answered with an error.
+    RemoteRequestFailed {
+        #[serde(serialize_with = "e0032")]
+        error: (),
+        url: String,
+        reason: String,
+    },
+
+    /// Responds with [`E0033_MSG`]
+    /// There is no entry with this id in the history of the user.
+    InexistentHistoryEntry {
+        #[serde(serialize_with = "e0033")]
+        error: (),
+        id: i32,
+    },
+
+    /// Responds with [`E0034_MSG`]
+    /// Only the histories stored in a database (see
+    /// [crate::config::HistoryBackendKind]) can be listed with
+    /// filters, paginated and aggregated.
+    HistoryNotQueryable {
+        #[serde(serialize_with = "e0034")]
+        error: (),
+        backend: String,
+    },
 }
 impl From<grep_regex::Error> for BookrabError {
     fn from(err: grep_regex::Error) -> Self {
         BookrabError::RegexProblem { error: (), err }
     }
 }
+impl From<argon2::password_hash::Error> for BookrabError {
+    fn from(err: argon2::password_hash::Error) -> Self {
+        BookrabError::CouldntHashPassword { error: (), err }
+    }
+}
 impl From<diesel::result::Error> for BookrabError {
     fn from(err: diesel::result::Error) -> Self {
         BookrabError::DatabaseError { error: (), err }
@@ -3,3 +3,4 @@ pub mod config;
 pub mod database;
 pub mod errors;
 pub mod schema;
+pub mod users;
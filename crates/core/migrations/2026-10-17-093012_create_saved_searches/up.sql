@@ -0,0 +1,13 @@
+CREATE TABLE saved_searches (
+  name VARCHAR PRIMARY KEY,
+  pattern VARCHAR NOT NULL,
+  include_mode VARCHAR NOT NULL,
+  include_tags TEXT[] NOT NULL,
+  exclude_mode VARCHAR NOT NULL,
+  exclude_tags TEXT[] NOT NULL,
+  case_insensitive BOOLEAN NOT NULL,
+  case_smart BOOLEAN NOT NULL,
+  ignore_accents BOOLEAN NOT NULL,
+  before_context INT NOT NULL,
+  after_context INT NOT NULL
+);
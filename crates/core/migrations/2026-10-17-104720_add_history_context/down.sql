@@ -0,0 +1,10 @@
+ALTER TABLE search_history
+  DROP COLUMN include_mode,
+  DROP COLUMN include_tags,
+  DROP COLUMN exclude_mode,
+  DROP COLUMN exclude_tags,
+  DROP COLUMN case_insensitive,
+  DROP COLUMN case_smart,
+  DROP COLUMN ignore_accents,
+  DROP COLUMN before_context,
+  DROP COLUMN after_context;
@@ -0,0 +1,10 @@
+ALTER TABLE search_history
+  ADD COLUMN include_mode VARCHAR NOT NULL DEFAULT 'Any',
+  ADD COLUMN include_tags TEXT[] NOT NULL DEFAULT '{}',
+  ADD COLUMN exclude_mode VARCHAR NOT NULL DEFAULT 'Any',
+  ADD COLUMN exclude_tags TEXT[] NOT NULL DEFAULT '{}',
+  ADD COLUMN case_insensitive BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN case_smart BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN ignore_accents BOOLEAN NOT NULL DEFAULT FALSE,
+  ADD COLUMN before_context INT NOT NULL DEFAULT 0,
+  ADD COLUMN after_context INT NOT NULL DEFAULT 0;
@@ -0,0 +1,3 @@
+ALTER TABLE search_history
+  DROP COLUMN match_count,
+  DROP COLUMN duration_ms;
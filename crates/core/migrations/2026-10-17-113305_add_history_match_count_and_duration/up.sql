@@ -0,0 +1,3 @@
+ALTER TABLE search_history
+  ADD COLUMN match_count INT NOT NULL DEFAULT 0,
+  ADD COLUMN duration_ms BIGINT NOT NULL DEFAULT 0;
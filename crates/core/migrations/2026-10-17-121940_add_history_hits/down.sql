@@ -0,0 +1 @@
+ALTER TABLE search_history DROP COLUMN hits;
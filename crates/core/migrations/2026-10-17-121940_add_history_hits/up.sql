@@ -0,0 +1 @@
+ALTER TABLE search_history ADD COLUMN hits INT NOT NULL DEFAULT 1;
@@ -0,0 +1,6 @@
+CREATE TABLE books (
+  title VARCHAR PRIMARY KEY,
+  txt TEXT NOT NULL,
+  tags TEXT[] NOT NULL DEFAULT '{}',
+  language VARCHAR
+);
@@ -0,0 +1,9 @@
+CREATE TABLE book_catalog (
+  title VARCHAR PRIMARY KEY,
+  checksum VARCHAR NOT NULL,
+  size BIGINT NOT NULL,
+  uploaded_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX book_catalog_updated_at ON book_catalog (updated_at);
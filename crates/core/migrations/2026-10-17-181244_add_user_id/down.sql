@@ -0,0 +1,20 @@
+-- titles and names are only unique within a user
+DELETE FROM book_catalog WHERE user_id <> 0;
+ALTER TABLE book_catalog DROP CONSTRAINT book_catalog_pkey;
+ALTER TABLE book_catalog DROP COLUMN user_id;
+ALTER TABLE book_catalog ADD PRIMARY KEY (title);
+
+DELETE FROM books WHERE user_id <> 0;
+ALTER TABLE books DROP CONSTRAINT books_pkey;
+ALTER TABLE books DROP COLUMN user_id;
+ALTER TABLE books ADD PRIMARY KEY (title);
+
+DELETE FROM saved_searches WHERE user_id <> 0;
+ALTER TABLE saved_searches DROP CONSTRAINT saved_searches_pkey;
+ALTER TABLE saved_searches DROP COLUMN user_id;
+ALTER TABLE saved_searches ADD PRIMARY KEY (name);
+
+DELETE FROM search_results WHERE search_history_id IN (SELECT id FROM search_history WHERE user_id <> 0);
+DELETE FROM search_history WHERE user_id <> 0;
+DROP INDEX search_history_user_id_date;
+ALTER TABLE search_history DROP COLUMN user_id;
@@ -0,0 +1,15 @@
+-- 0 is the shared library, used when authentication is disabled
+ALTER TABLE search_history ADD COLUMN user_id INT NOT NULL DEFAULT 0;
+CREATE INDEX search_history_user_id_date ON search_history (user_id, date);
+
+ALTER TABLE saved_searches ADD COLUMN user_id INT NOT NULL DEFAULT 0;
+ALTER TABLE saved_searches DROP CONSTRAINT saved_searches_pkey;
+ALTER TABLE saved_searches ADD PRIMARY KEY (user_id, name);
+
+ALTER TABLE books ADD COLUMN user_id INT NOT NULL DEFAULT 0;
+ALTER TABLE books DROP CONSTRAINT books_pkey;
+ALTER TABLE books ADD PRIMARY KEY (user_id, title);
+
+ALTER TABLE book_catalog ADD COLUMN user_id INT NOT NULL DEFAULT 0;
+ALTER TABLE book_catalog DROP CONSTRAINT book_catalog_pkey;
+ALTER TABLE book_catalog ADD PRIMARY KEY (user_id, title);
@@ -0,0 +1,8 @@
+-- where each user stopped reading each book
+CREATE TABLE reading_progress (
+  user_id INT NOT NULL DEFAULT 0,
+  title VARCHAR NOT NULL,
+  line BIGINT NOT NULL,
+  updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  PRIMARY KEY (user_id, title)
+);
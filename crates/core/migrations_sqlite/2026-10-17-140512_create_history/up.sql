@@ -0,0 +1,25 @@
+-- Tags are stored as JSON arrays because SQLite has no array type.
+CREATE TABLE IF NOT EXISTS search_history (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  title TEXT NOT NULL,
+  pattern TEXT NOT NULL,
+  date TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP,
+  include_mode TEXT NOT NULL DEFAULT 'Any',
+  include_tags TEXT NOT NULL DEFAULT '[]',
+  exclude_mode TEXT NOT NULL DEFAULT 'Any',
+  exclude_tags TEXT NOT NULL DEFAULT '[]',
+  case_insensitive BOOLEAN NOT NULL DEFAULT FALSE,
+  case_smart BOOLEAN NOT NULL DEFAULT FALSE,
+  ignore_accents BOOLEAN NOT NULL DEFAULT FALSE,
+  before_context INTEGER NOT NULL DEFAULT 0,
+  after_context INTEGER NOT NULL DEFAULT 0,
+  match_count INTEGER NOT NULL DEFAULT 0,
+  duration_ms BIGINT NOT NULL DEFAULT 0,
+  hits INTEGER NOT NULL DEFAULT 1
+);
+
+CREATE TABLE IF NOT EXISTS search_results (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  search_history_id INTEGER NOT NULL REFERENCES search_history(id) ON DELETE CASCADE,
+  result TEXT NOT NULL
+);
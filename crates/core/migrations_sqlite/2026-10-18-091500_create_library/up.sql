@@ -0,0 +1,57 @@
+-- The rest of the tables of the Postgresql migrations, so that a SQLite
+-- file can replace the whole database. Dates are in local time, like
+-- the ones written by bookrab.
+ALTER TABLE search_history ADD COLUMN user_id INTEGER NOT NULL DEFAULT 0;
+CREATE INDEX search_history_user_id_date ON search_history (user_id, date);
+
+CREATE TABLE saved_searches (
+  user_id INTEGER NOT NULL DEFAULT 0,
+  name TEXT NOT NULL,
+  pattern TEXT NOT NULL,
+  include_mode TEXT NOT NULL,
+  include_tags TEXT NOT NULL,
+  exclude_mode TEXT NOT NULL,
+  exclude_tags TEXT NOT NULL,
+  case_insensitive BOOLEAN NOT NULL,
+  case_smart BOOLEAN NOT NULL,
+  ignore_accents BOOLEAN NOT NULL,
+  before_context INTEGER NOT NULL,
+  after_context INTEGER NOT NULL,
+  PRIMARY KEY (user_id, name)
+);
+
+CREATE TABLE books (
+  user_id INTEGER NOT NULL DEFAULT 0,
+  title TEXT NOT NULL,
+  txt TEXT NOT NULL,
+  tags TEXT NOT NULL DEFAULT '[]',
+  language TEXT,
+  PRIMARY KEY (user_id, title)
+);
+
+CREATE TABLE book_catalog (
+  user_id INTEGER NOT NULL DEFAULT 0,
+  title TEXT NOT NULL,
+  checksum TEXT NOT NULL,
+  size BIGINT NOT NULL,
+  uploaded_at TIMESTAMP NOT NULL DEFAULT (datetime('now', 'localtime')),
+  updated_at TIMESTAMP NOT NULL DEFAULT (datetime('now', 'localtime')),
+  PRIMARY KEY (user_id, title)
+);
+
+CREATE INDEX book_catalog_updated_at ON book_catalog (updated_at);
+
+CREATE TABLE users (
+  id INTEGER PRIMARY KEY AUTOINCREMENT NOT NULL,
+  username TEXT NOT NULL UNIQUE,
+  password_hash TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT (datetime('now', 'localtime'))
+);
+
+CREATE TABLE reading_progress (
+  user_id INTEGER NOT NULL DEFAULT 0,
+  title TEXT NOT NULL,
+  line BIGINT NOT NULL,
+  updated_at TIMESTAMP NOT NULL DEFAULT (datetime('now', 'localtime')),
+  PRIMARY KEY (user_id, title)
+);
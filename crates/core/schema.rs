@@ -1,11 +1,80 @@
 // @generated automatically by Diesel CLI.
 
 diesel::table! {
+    book_catalog (user_id, title) {
+        title -> Varchar,
+        checksum -> Varchar,
+        size -> Int8,
+        uploaded_at -> Timestamp,
+        updated_at -> Timestamp,
+        user_id -> Int4,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use crate::database::tags::Tags;
+
+    books (user_id, title) {
+        title -> Varchar,
+        txt -> Text,
+        tags -> Tags,
+        language -> Nullable<Varchar>,
+        user_id -> Int4,
+    }
+}
+
+diesel::table! {
+    reading_progress (user_id, title) {
+        user_id -> Int4,
+        title -> Varchar,
+        line -> Int8,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use crate::database::tags::Tags;
+
+    saved_searches (user_id, name) {
+        name -> Varchar,
+        pattern -> Varchar,
+        include_mode -> Varchar,
+        include_tags -> Tags,
+        exclude_mode -> Varchar,
+        exclude_tags -> Tags,
+        case_insensitive -> Bool,
+        case_smart -> Bool,
+        ignore_accents -> Bool,
+        before_context -> Int4,
+        after_context -> Int4,
+        user_id -> Int4,
+    }
+}
+
+diesel::table! {
+    use diesel::sql_types::*;
+    use crate::database::tags::Tags;
+
     search_history (id) {
         id -> Int4,
         title -> Varchar,
         pattern -> Varchar,
         date -> Timestamp,
+        include_mode -> Varchar,
+        include_tags -> Tags,
+        exclude_mode -> Varchar,
+        exclude_tags -> Tags,
+        case_insensitive -> Bool,
+        case_smart -> Bool,
+        ignore_accents -> Bool,
+        before_context -> Int4,
+        after_context -> Int4,
+        match_count -> Int4,
+        duration_ms -> Int8,
+        hits -> Int4,
+        user_id -> Int4,
     }
 }
 
@@ -17,6 +86,22 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    users (id) {
+        id -> Int4,
+        username -> Varchar,
+        password_hash -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::joinable!(search_results -> search_history (search_history_id));
 
-diesel::allow_tables_to_appear_in_same_query!(search_history, search_results,);
+diesel::allow_tables_to_appear_in_same_query!(
+    books,
+    reading_progress,
+    saved_searches,
+    search_history,
+    search_results,
+    users,
+);
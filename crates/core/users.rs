@@ -0,0 +1,143 @@
+use argon2::{
+    password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, PasswordVerifier, SaltString},
+    Argon2,
+};
+use diesel::{
+    prelude::*,
+    result::{DatabaseErrorKind, Error as DieselError},
+};
+
+use crate::{
+    config::BookrabConfig,
+    database::{
+        users::{NewUser, User},
+        DbConnection,
+    },
+    errors::BookrabError,
+    schema,
+};
+
+/// Passwords shorter than this are rejected by [Users::register].
+pub const MIN_PASSWORD_LENGTH: usize = 8;
+
+/// Accounts of the people that use the REST API.
+/// Passwords are stored as Argon2 hashes.
+pub struct Users<'a> {
+    pub config: BookrabConfig,
+    /// Connection to the database
+    pub connection: &'a mut DbConnection,
+}
+
+impl Users<'_> {
+    pub fn new(config: BookrabConfig, connection: &mut DbConnection) -> Users<'_> {
+        Users { config, connection }
+    }
+
+    /// Creates a user.
+    pub fn register(self, username: &str, password: &str) -> Result<User, BookrabError> {
+        if username.trim().is_empty() {
+            return Err(BookrabError::InvalidPassword {
+                error: (),
+                reason: "the username is empty".to_string(),
+            });
+        }
+        if password.chars().count() < MIN_PASSWORD_LENGTH {
+            return Err(BookrabError::InvalidPassword {
+                error: (),
+                reason: format!("the password has less than {MIN_PASSWORD_LENGTH} characters"),
+            });
+        }
+        let salt = SaltString::generate(&mut OsRng);
+        let password_hash = Argon2::default()
+            .hash_password(password.as_bytes(), &salt)?
+            .to_string();
+        let inserted = diesel::insert_into(schema::users::table)
+            .values(NewUser {
+                username,
+                password_hash: &password_hash,
+            })
+            .returning(schema::users::all_columns)
+            .get_result::<User>(self.connection);
+        match inserted {
+            Ok(v) => Ok(v),
+            Err(DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _)) => {
+                Err(BookrabError::UsernameTaken {
+                    error: (),
+                    username: username.to_string(),
+                })
+            }
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the user if `password` is right.
+    pub fn authenticate(self, username: &str, password: &str) -> Result<User, BookrabError> {
+        let invalid = || BookrabError::InvalidCredentials {
+            error: (),
+            username: username.to_string(),
+        };
+        let user = schema::users::table
+            .filter(schema::users::columns::username.eq(username))
+            .select(User::as_select())
+            .first(self.connection)
+            .optional()?
+            .ok_or_else(invalid)?;
+        let hash = PasswordHash::new(&user.password_hash)?;
+        match Argon2::default().verify_password(password.as_bytes(), &hash) {
+            Ok(_) => Ok(user),
+            Err(argon2::password_hash::Error::Password) => Err(invalid()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    /// Returns the user with `id` (if any).
+    pub fn get(self, id: i32) -> Result<Option<User>, BookrabError> {
+        Ok(schema::users::table
+            .find(id)
+            .select(User::as_select())
+            .first(self.connection)
+            .optional()?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::books::test_utils::{random_string, DBCONNECTION};
+
+    #[test]
+    fn register_and_authenticate() -> Result<(), BookrabError> {
+        let connection = &mut DBCONNECTION.get().unwrap();
+        let config = BookrabConfig::default();
+        // other tests don't use this name
+        let username = random_string();
+        let user = Users::new(config.clone(), connection).register(&username, "As armas")?;
+        assert_eq!(user.username, username);
+        assert_ne!(user.password_hash, "As armas");
+
+        let authenticated =
+            Users::new(config.clone(), connection).authenticate(&username, "As armas")?;
+        assert_eq!(authenticated, user);
+        assert_eq!(
+            Users::new(config.clone(), connection).get(user.id)?,
+            Some(user)
+        );
+        assert!(matches!(
+            Users::new(config.clone(), connection).authenticate(&username, "Os barões"),
+            Err(BookrabError::InvalidCredentials { .. })
+        ));
+        assert!(matches!(
+            Users::new(config.clone(), connection).authenticate(&random_string(), "As armas"),
+            Err(BookrabError::InvalidCredentials { .. })
+        ));
+        assert!(matches!(
+            Users::new(config.clone(), connection).register(&username, "whatever"),
+            Err(BookrabError::UsernameTaken { .. })
+        ));
+        assert!(matches!(
+            Users::new(config, connection).register(&random_string(), "short"),
+            Err(BookrabError::InvalidPassword { .. })
+        ));
+        Ok(())
+    }
+}
@@ -0,0 +1,68 @@
+//! Files under `/static`. They are built into the binary, so the
+//! server works from any folder, but [BookrabConfig::static_dir]
+//! can override them without rebuilding.
+use std::path::{Component, Path, PathBuf};
+
+use actix_files::NamedFile;
+use actix_web::{
+    get,
+    http::header::{self, EntityTag},
+    web, HttpRequest, HttpResponse,
+};
+use bookrab_core::config::BookrabConfig;
+use rust_embed::Embed;
+
+use crate::{config::ensure_confy_works, views::books::not_modified};
+
+#[derive(Embed)]
+#[folder = "static/"]
+#[exclude = ".gitkeep"]
+struct Assets;
+
+/// `path` inside of `dir`, unless it could escape it (e.g. `../config.toml`).
+fn override_path(dir: &Path, path: &str) -> Option<PathBuf> {
+    let path = Path::new(path);
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    Some(dir.join(path)).filter(|path| path.is_file())
+}
+
+/// Serves a file of [BookrabConfig::static_dir] or, if it isn't
+/// there, a built-in one.
+#[get("/static/{path:.*}")]
+pub async fn assets(req: HttpRequest, path: web::Path<String>) -> HttpResponse {
+    let config: BookrabConfig = ensure_confy_works();
+    if let Some(file) = config
+        .static_dir
+        .as_deref()
+        .and_then(|dir| override_path(dir, &path))
+    {
+        return match NamedFile::open(file) {
+            Ok(file) => file.into_response(&req),
+            Err(e) => HttpResponse::InternalServerError().body(e.to_string()),
+        };
+    }
+    let Some(file) = Assets::get(&path) else {
+        return HttpResponse::NotFound().finish();
+    };
+    let etag = EntityTag::new_strong(
+        file.metadata
+            .sha256_hash()
+            .iter()
+            .map(|byte| format!("{byte:02x}"))
+            .collect(),
+    );
+    if not_modified(&req, &etag) {
+        return HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .finish();
+    }
+    HttpResponse::Ok()
+        .content_type(file.metadata.mimetype())
+        .insert_header(header::ETag(etag))
+        .body(file.data.into_owned())
+}
@@ -0,0 +1,144 @@
+use actix_web::{
+    body::{BoxBody, MessageBody},
+    dev::{ServiceRequest, ServiceResponse},
+    http::header::AUTHORIZATION,
+    middleware::Next,
+    HttpMessage, HttpResponse,
+};
+use bookrab_core::{config::BookrabConfig, database::users::User, errors::BookrabError};
+use chrono::{DateTime, TimeDelta, Utc};
+use jsonwebtoken::{decode, encode, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+
+use crate::{config::ensure_confy_works, errors::ApiError};
+
+/// Versions of the API whose routes need a token.
+const PROTECTED_PREFIXES: &[&str] = &["/v1/", "/v2/"];
+
+/// Routes under `/v1` that don't need a token.
+const PUBLIC_PREFIXES: &[&str] = &["/v1/auth/", "/v1/redoc"];
+
+/// Contents of a token.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Claims {
+    /// Id of the user
+    pub sub: i32,
+    pub username: String,
+    /// Expiration (seconds since the epoch)
+    pub exp: i64,
+}
+
+/// User that made the request. It is available in the extensions
+/// of the request when authentication is enabled.
+#[derive(Clone, Debug)]
+pub struct AuthenticatedUser {
+    pub id: i32,
+    pub username: String,
+}
+
+/// Token returned by the login route.
+#[derive(Debug, Serialize)]
+pub struct Token {
+    pub token: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+/// Secret of the config, or an error if authentication is disabled.
+fn secret(config: &BookrabConfig) -> Result<&str, BookrabError> {
+    match &config.jwt_secret {
+        Some(v) => Ok(v),
+        None => Err(BookrabError::Unauthorized {
+            error: (),
+            reason: "authentication is disabled (there is no jwt_secret in the config)".into(),
+        }),
+    }
+}
+
+/// Makes a token for `user`.
+pub fn issue_token(config: &BookrabConfig, user: &User) -> Result<Token, BookrabError> {
+    let hours = config.jwt_expiration_hours.unwrap_or(24);
+    let expires_at = Utc::now() + TimeDelta::hours(hours.into());
+    let claims = Claims {
+        sub: user.id,
+        username: user.username.clone(),
+        exp: expires_at.timestamp(),
+    };
+    let token = encode(
+        &Header::default(),
+        &claims,
+        &EncodingKey::from_secret(secret(config)?.as_bytes()),
+    )
+    // HMAC accepts keys of any size
+    .expect("couldn't sign token");
+    Ok(Token { token, expires_at })
+}
+
+/// Checks the signature and the expiration of `token`.
+pub fn verify_token(config: &BookrabConfig, token: &str) -> Result<Claims, BookrabError> {
+    match decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(secret(config)?.as_bytes()),
+        &Validation::default(),
+    ) {
+        Ok(v) => Ok(v.claims),
+        Err(e) => Err(BookrabError::Unauthorized {
+            error: (),
+            reason: e.to_string(),
+        }),
+    }
+}
+
+/// Token of the request: the `Authorization: Bearer` header or, since
+/// browsers can't set headers in websockets, the `access_token` query parameter.
+fn request_token(req: &ServiceRequest) -> Option<String> {
+    if let Some(header) = req.headers().get(AUTHORIZATION) {
+        return header
+            .to_str()
+            .ok()
+            .and_then(|header| header.strip_prefix("Bearer "))
+            .map(|token| token.trim().to_string());
+    }
+    req.query_string()
+        .split('&')
+        .find_map(|pair| pair.strip_prefix("access_token="))
+        .map(|token| token.to_string())
+}
+
+/// Middleware that rejects requests to `/v1` and `/v2` without a valid token
+/// (only when there is a `jwt_secret` in the config).
+pub async fn authenticate(
+    req: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    let config = ensure_confy_works();
+    let path = req.path();
+    let public = !PROTECTED_PREFIXES
+        .iter()
+        .any(|prefix| path.starts_with(prefix))
+        || PUBLIC_PREFIXES
+            .iter()
+            .any(|prefix| path.starts_with(prefix));
+    if config.jwt_secret.is_none() || public {
+        return Ok(next.call(req).await?.map_into_boxed_body());
+    }
+    let claims = match request_token(&req) {
+        Some(token) => verify_token(&config, &token),
+        None => Err(BookrabError::Unauthorized {
+            error: (),
+            reason: "there is no bearer token in the request".into(),
+        }),
+    };
+    match claims {
+        Ok(claims) => {
+            req.extensions_mut().insert(AuthenticatedUser {
+                id: claims.sub,
+                username: claims.username,
+            });
+            Ok(next.call(req).await?.map_into_boxed_body())
+        }
+        Err(e) => {
+            let response: HttpResponse = ApiError(e).into();
+            Ok(req.into_response(response))
+        }
+    }
+}
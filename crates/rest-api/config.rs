@@ -1,8 +1,150 @@
-use bookrab_core::config::{ensure_config_works, BookrabConfig};
+use std::{
+    env,
+    path::PathBuf,
+    sync::{OnceLock, RwLock},
+};
 
-/// Loads the configuration file and makes sure it works.
-pub fn ensure_confy_works<'a>() -> BookrabConfig {
-    let config: BookrabConfig = confy::load("bookrab", None).unwrap();
-    ensure_config_works(&config);
-    config
+use actix_web::{
+    dev::Payload, error::InternalError, FromRequest, HttpMessage, HttpRequest, HttpResponse,
+};
+use bookrab_core::{
+    config::{ensure_config_works, BookrabConfig, ConfigError},
+    errors::BookrabError,
+};
+use futures::future::{err, ok, Ready};
+
+use crate::{auth::AuthenticatedUser, errors::ApiError};
+
+/// Configuration file given by `--config`.
+static CONFIG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Uses `path` instead of the default configuration file, so that
+/// several servers can run with different libraries.
+pub fn use_config_file(path: PathBuf) {
+    let _ = CONFIG_FILE.set(path);
+}
+
+/// Configuration file given by `--config` or `BOOKRAB_CONFIG`
+/// (`None` means the default one of confy).
+pub fn config_file() -> Option<PathBuf> {
+    CONFIG_FILE
+        .get()
+        .cloned()
+        .or_else(|| env::var_os("BOOKRAB_CONFIG").map(PathBuf::from))
+}
+
+/// `--config <path>` (or `--config=<path>`) of the command line.
+pub fn config_flag(mut args: impl Iterator<Item = String>) -> Result<Option<PathBuf>, String> {
+    let mut path = None;
+    while let Some(arg) = args.next() {
+        if let Some(value) = arg.strip_prefix("--config=") {
+            path = Some(PathBuf::from(value));
+        } else if arg == "--config" {
+            let value = args.next().ok_or("--config needs the path of a file")?;
+            path = Some(PathBuf::from(value));
+        } else {
+            return Err(format!("unknown argument {arg}"));
+        }
+    }
+    Ok(path)
+}
+
+/// Why the server can't start with the configuration file.
+#[derive(Debug, thiserror::Error)]
+pub enum StartupError {
+    #[error("couldn't read the configuration: {0}")]
+    Unreadable(#[from] confy::ConfyError),
+    #[error("bad configuration: {0}")]
+    Invalid(#[from] ConfigError),
+}
+
+/// Path of the configuration file (see [config_file]).
+pub fn config_path() -> Result<PathBuf, confy::ConfyError> {
+    match config_file() {
+        Some(path) => Ok(path),
+        None => confy::get_configuration_file_path("bookrab", None),
+    }
+}
+
+/// Loads the configuration file as it is (the book folders aren't created).
+pub fn load_config() -> Result<BookrabConfig, confy::ConfyError> {
+    match config_file() {
+        Some(path) => confy::load_path(path),
+        None => confy::load("bookrab", None),
+    }
+}
+
+/// Loads the configuration file and makes sure it works
+/// (see [ensure_config_works]).
+pub fn check_config() -> Result<BookrabConfig, StartupError> {
+    Ok(ensure_config_works(load_config()?)?)
+}
+
+/// Config used by the routes (see [use_config]).
+static CURRENT: RwLock<Option<BookrabConfig>> = RwLock::new(None);
+
+/// Makes the routes use `config`, which must work (see [check_config]).
+/// It is replaced when the configuration file changes (see [crate::reload]).
+pub fn use_config(config: BookrabConfig) {
+    *CURRENT.write().unwrap() = Some(config);
+}
+
+/// Config used by the routes. Before [use_config], the configuration
+/// file is loaded and checked.
+pub fn ensure_confy_works() -> BookrabConfig {
+    if let Some(config) = CURRENT.read().unwrap().as_ref() {
+        return config.clone();
+    }
+    check_config().expect("the configuration was checked when the server started")
+}
+
+/// Error of the config of a user (e.g. their folder couldn't be created).
+pub fn user_config_error(error: ConfigError) -> BookrabError {
+    log::error!("config of a user doesn't work: {error}");
+    match error {
+        ConfigError::UnwritableBookPath { path, .. } => {
+            BookrabError::MessedUpBookFolder { error: (), path }
+        }
+        // the URL is the same for every user, so it was checked at startup
+        ConfigError::MalformedDatabaseUrl { reason, .. } => BookrabError::DatabaseUnavailable {
+            error: (),
+            operation: reason,
+        },
+    }
+}
+
+/// Config of the user that made the request, so that each user only
+/// sees their own books and history (see [BookrabConfig::for_user]).
+/// Without authentication, everybody shares the books of the config.
+pub struct UserConfig(pub BookrabConfig);
+
+impl UserConfig {
+    /// Config of the user of `req` (if any).
+    pub fn of(req: &HttpRequest) -> Result<UserConfig, BookrabError> {
+        let config = ensure_confy_works();
+        match req.extensions().get::<AuthenticatedUser>() {
+            Some(user) => ensure_config_works(config.for_user(user.id))
+                .map(UserConfig)
+                .map_err(user_config_error),
+            None => Ok(UserConfig(config)),
+        }
+    }
+}
+
+impl FromRequest for UserConfig {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<UserConfig, actix_web::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        match UserConfig::of(req) {
+            Ok(config) => ok(config),
+            Err(e) => {
+                let response: HttpResponse = ApiError(e).into();
+                err(
+                    InternalError::from_response("config of the user doesn't work", response)
+                        .into(),
+                )
+            }
+        }
+    }
 }
@@ -1,23 +1,29 @@
 use actix_web::error::ErrorServiceUnavailable;
 use actix_web::FromRequest;
-use bookrab_core::database::{PgPool, PgPooledConnection};
-use diesel::r2d2::ConnectionManager;
+use bookrab_core::books::RootBookDir;
+use bookrab_core::config::BookrabConfig;
+use bookrab_core::database::{DbConnectionManager, DbPool, DbPooledConnection};
 use futures::future::{err, ok, Ready};
 use lazy_static::lazy_static;
 
 use crate::config::ensure_confy_works;
 
 lazy_static! {
-    pub static ref DBCONNECTION: PgPool = {
+    /// `None` when there is no database in the config.
+    pub static ref DBCONNECTION: Option<DbPool> = {
         let config = ensure_confy_works();
-        PgPool::builder()
-            .max_size(8)
-            .build(ConnectionManager::new(config.database_url))
-            .expect("could not create db connection pool")
+        config.database_url.map(|database_url| {
+            DbPool::builder()
+                .max_size(8)
+                .build(DbConnectionManager::new(database_url))
+                .expect("could not create db connection pool")
+        })
     };
 }
+
+/// Connection for routes that can't work without a database.
 pub struct DB {
-    pub connection: PgPooledConnection,
+    pub connection: DbPooledConnection,
 }
 
 impl FromRequest for DB {
@@ -25,9 +31,57 @@ impl FromRequest for DB {
     type Future = Ready<Result<DB, actix_web::Error>>;
 
     fn from_request(_: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
-        match DBCONNECTION.get() {
+        let Some(pool) = DBCONNECTION.as_ref() else {
+            return err(ErrorServiceUnavailable(
+                "there is no database in the config",
+            ));
+        };
+        match pool.get() {
             Ok(connection) => ok(DB { connection }),
             Err(_) => err(ErrorServiceUnavailable("couldnt make connection to the db")),
         }
     }
 }
+
+/// Connection for routes that also work without a database
+/// (`None` when there is no database in the config).
+pub struct OptionalDB {
+    pub connection: Option<DbPooledConnection>,
+}
+
+impl OptionalDB {
+    /// Takes a connection from the pool (if there is one).
+    /// Routes should use the extractor, this is for connections
+    /// that are needed after the request was handled (e.g. websockets).
+    pub fn get() -> Result<OptionalDB, actix_web::Error> {
+        let Some(pool) = DBCONNECTION.as_ref() else {
+            return Ok(OptionalDB { connection: None });
+        };
+        match pool.get() {
+            Ok(connection) => Ok(OptionalDB {
+                connection: Some(connection),
+            }),
+            Err(_) => Err(ErrorServiceUnavailable("couldnt make connection to the db")),
+        }
+    }
+
+    /// Root book folder that uses the connection (if any).
+    pub fn root(&mut self, config: BookrabConfig) -> RootBookDir<'_> {
+        match self.connection.as_mut() {
+            Some(connection) => RootBookDir::new(config, connection),
+            None => RootBookDir::without_database(config),
+        }
+    }
+}
+
+impl FromRequest for OptionalDB {
+    type Error = actix_web::Error;
+    type Future = Ready<Result<OptionalDB, actix_web::Error>>;
+
+    fn from_request(_: &actix_web::HttpRequest, _: &mut actix_web::dev::Payload) -> Self::Future {
+        match OptionalDB::get() {
+            Ok(db) => ok(db),
+            Err(e) => err(e),
+        }
+    }
+}
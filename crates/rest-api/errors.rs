@@ -4,7 +4,7 @@ use actix_web::{
     http::{header::ContentType, StatusCode},
     HttpResponse, HttpResponseBuilder,
 };
-use bookrab_core::errors::BookrabError;
+use bookrab_core::{books::validation::FieldError, errors::BookrabError};
 use grep_searcher::SinkError;
 use serde::{de::Error, Serialize};
 use utoipa::{
@@ -15,16 +15,16 @@ use utoipa::{
 #[derive(Serialize)]
 pub struct ApiError(pub BookrabError);
 
-impl Into<HttpResponse> for ApiError {
-    fn into(self) -> HttpResponse {
-        HttpResponseBuilder::new(self.status())
+impl From<ApiError> for HttpResponse {
+    fn from(val: ApiError) -> Self {
+        HttpResponseBuilder::new(val.status())
             .content_type(ContentType::json())
-            .body(serde_json::to_string(&self.0).unwrap())
+            .body(serde_json::to_string(&val.0).unwrap())
     }
 }
 
 impl ApiError {
-    fn status(&self) -> StatusCode {
+    pub(crate) fn status(&self) -> StatusCode {
         match self.0 {
             BookrabError::CouldntSaveFile { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             BookrabError::CouldntCreateDir { .. } => StatusCode::INTERNAL_SERVER_ERROR,
@@ -32,6 +32,7 @@ impl ApiError {
             BookrabError::MessedUpBookFolder { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             BookrabError::CouldntReadChild { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             BookrabError::InvalidTags { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            BookrabError::InvalidLanguage { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             BookrabError::CouldntReadFile { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             BookrabError::CouldntReadDir { .. } => StatusCode::INTERNAL_SERVER_ERROR,
             BookrabError::GrepSearchError { .. } => StatusCode::INTERNAL_SERVER_ERROR,
@@ -40,6 +41,24 @@ impl ApiError {
             BookrabError::ShouldBeTextPlain { .. } => StatusCode::BAD_REQUEST,
             BookrabError::NotUnicode { .. } => StatusCode::BAD_REQUEST,
             BookrabError::RegexProblem { .. } => StatusCode::BAD_REQUEST,
+            BookrabError::UnconfirmedOperation { .. } => StatusCode::BAD_REQUEST,
+            BookrabError::InexistentSavedSearch { .. } => StatusCode::BAD_REQUEST,
+            BookrabError::CouldntConnectToDatabase { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            BookrabError::DatabaseUnavailable { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            BookrabError::UsernameTaken { .. } => StatusCode::BAD_REQUEST,
+            BookrabError::InvalidCredentials { .. } => StatusCode::UNAUTHORIZED,
+            BookrabError::InvalidPassword { .. } => StatusCode::BAD_REQUEST,
+            BookrabError::Unauthorized { .. } => StatusCode::UNAUTHORIZED,
+            BookrabError::CouldntHashPassword { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            BookrabError::UploadTooLarge { .. } => StatusCode::PAYLOAD_TOO_LARGE,
+            BookrabError::CouldntConvertBook { .. } => StatusCode::BAD_REQUEST,
+            BookrabError::BookAlreadyExists { .. } => StatusCode::CONFLICT,
+            BookrabError::InvalidArchive { .. } => StatusCode::BAD_REQUEST,
+            BookrabError::InvalidFields { .. } => StatusCode::UNPROCESSABLE_ENTITY,
+            BookrabError::IdempotencyKeyConflict { .. } => StatusCode::CONFLICT,
+            BookrabError::RemoteRequestFailed { .. } => StatusCode::INTERNAL_SERVER_ERROR,
+            BookrabError::InexistentHistoryEntry { .. } => StatusCode::BAD_REQUEST,
+            BookrabError::HistoryNotQueryable { .. } => StatusCode::NOT_IMPLEMENTED,
         }
     }
     fn examples() -> Vec<Self> {
@@ -79,6 +98,12 @@ impl ApiError {
                 path: PathBuf::from("path/to/file"),
                 err: serde_json::Error::custom("Cool serde error"),
             },
+            BookrabError::InvalidLanguage {
+                error: (),
+                language: "\"klingon\"".into(),
+                path: PathBuf::from("path/to/file"),
+                err: serde_json::Error::custom("Cool serde error"),
+            },
             BookrabError::CouldntReadFile {
                 error: (),
                 path: PathBuf::from("path/to/file"),
@@ -114,6 +139,86 @@ impl ApiError {
                 error: (),
                 err: grep_regex::RegexMatcher::new("(").unwrap_err(),
             },
+            BookrabError::UnconfirmedOperation {
+                error: (),
+                operation: "clear history".into(),
+            },
+            BookrabError::InexistentSavedSearch {
+                error: (),
+                name: "my search".into(),
+            },
+            BookrabError::CouldntConnectToDatabase {
+                error: (),
+                url: "path/to/history.sqlite3".into(),
+                err: diesel::ConnectionError::BadConnection("Cool connection error.".into()),
+            },
+            BookrabError::DatabaseUnavailable {
+                error: (),
+                operation: "run a saved search".into(),
+            },
+            BookrabError::UsernameTaken {
+                error: (),
+                username: "camoes".into(),
+            },
+            BookrabError::InvalidCredentials {
+                error: (),
+                username: "camoes".into(),
+            },
+            BookrabError::InvalidPassword {
+                error: (),
+                reason: "the password has less than 8 characters".into(),
+            },
+            BookrabError::Unauthorized {
+                error: (),
+                reason: "the token expired".into(),
+            },
+            BookrabError::CouldntHashPassword {
+                error: (),
+                err: argon2::password_hash::Error::Password,
+            },
+            BookrabError::UploadTooLarge {
+                error: (),
+                limit: 52_428_800,
+            },
+            BookrabError::CouldntConvertBook {
+                error: (),
+                filename: "lusiadas.epub".into(),
+                reason: "there is no rootfile in META-INF/container.xml".into(),
+            },
+            BookrabError::BookAlreadyExists {
+                error: (),
+                title: "lusiadas".into(),
+            },
+            BookrabError::InvalidArchive {
+                error: (),
+                reason: "there is no manifest.json".into(),
+            },
+            BookrabError::InvalidFields {
+                error: (),
+                errors: vec![
+                    FieldError::new("pattern", "unclosed group"),
+                    FieldError::new(
+                        "include_mode",
+                        "unknown variant `Al`, expected `All` or `Any`",
+                    ),
+                ],
+            },
+            BookrabError::IdempotencyKeyConflict {
+                error: (),
+                key: "6b1f0a52".into(),
+                reason: "a request with this key is still running".into(),
+            },
+            BookrabError::RemoteRequestFailed {
+                error: (),
+                url: "http://localhost:8000".into(),
+                reason: "the server answered 401 Unauthorized: E0024: authentication required."
+                    .into(),
+            },
+            BookrabError::InexistentHistoryEntry { error: (), id: 42 },
+            BookrabError::HistoryNotQueryable {
+                error: (),
+                backend: "JsonFile".into(),
+            },
         ]
         .into_iter()
         .map(ApiError)
@@ -160,6 +265,54 @@ impl PartialSchema for Bookrab400 {
     }
 }
 
+pub struct Bookrab401;
+impl ToSchema for Bookrab401 {
+    fn name() -> Cow<'static, str> {
+        std::borrow::Cow::Borrowed("Bookrab401")
+    }
+}
+impl PartialSchema for Bookrab401 {
+    fn schema() -> RefOr<Schema> {
+        api_errors_to_schema(StatusCode::UNAUTHORIZED)
+    }
+}
+
+pub struct Bookrab409;
+impl ToSchema for Bookrab409 {
+    fn name() -> Cow<'static, str> {
+        std::borrow::Cow::Borrowed("Bookrab409")
+    }
+}
+impl PartialSchema for Bookrab409 {
+    fn schema() -> RefOr<Schema> {
+        api_errors_to_schema(StatusCode::CONFLICT)
+    }
+}
+
+pub struct Bookrab413;
+impl ToSchema for Bookrab413 {
+    fn name() -> Cow<'static, str> {
+        std::borrow::Cow::Borrowed("Bookrab413")
+    }
+}
+impl PartialSchema for Bookrab413 {
+    fn schema() -> RefOr<Schema> {
+        api_errors_to_schema(StatusCode::PAYLOAD_TOO_LARGE)
+    }
+}
+
+pub struct Bookrab422;
+impl ToSchema for Bookrab422 {
+    fn name() -> Cow<'static, str> {
+        std::borrow::Cow::Borrowed("Bookrab422")
+    }
+}
+impl PartialSchema for Bookrab422 {
+    fn schema() -> RefOr<Schema> {
+        api_errors_to_schema(StatusCode::UNPROCESSABLE_ENTITY)
+    }
+}
+
 pub struct Bookrab500;
 impl ToSchema for Bookrab500 {
     fn name() -> Cow<'static, str> {
@@ -171,3 +324,15 @@ impl PartialSchema for Bookrab500 {
         api_errors_to_schema(StatusCode::INTERNAL_SERVER_ERROR)
     }
 }
+
+pub struct Bookrab501;
+impl ToSchema for Bookrab501 {
+    fn name() -> Cow<'static, str> {
+        std::borrow::Cow::Borrowed("Bookrab501")
+    }
+}
+impl PartialSchema for Bookrab501 {
+    fn schema() -> RefOr<Schema> {
+        api_errors_to_schema(StatusCode::NOT_IMPLEMENTED)
+    }
+}
@@ -0,0 +1,292 @@
+//! gRPC server (see `proto/bookrab.proto`). It shares the search,
+//! the upload and the listing of books with the REST routes.
+// `Status` is the error of every tonic service
+#![allow(clippy::result_large_err)]
+use std::{collections::HashSet, net::SocketAddr, pin::Pin, thread};
+
+use actix_web::http::StatusCode;
+use bookrab_core::{
+    books::{language::Language, FilterMode, Page, SearchEvent},
+    config::{ensure_config_works, BookrabConfig},
+    errors::BookrabError,
+};
+use tokio::sync::mpsc;
+use tokio_stream::{wrappers::ReceiverStream, Stream, StreamExt};
+use tonic::{transport::Server, Request, Response, Status, Streaming};
+
+use crate::{
+    auth::verify_token,
+    config::{ensure_confy_works, user_config_error},
+    database::OptionalDB,
+    errors::ApiError,
+    views::books::{
+        search::{SearchForm, SearchParams, TruncatedSearchResults},
+        upload::store_book,
+    },
+};
+
+pub mod proto {
+    tonic::include_proto!("bookrab.v1");
+}
+
+use proto::{
+    books_server::{Books, BooksServer},
+    upload_request::Part,
+    BookResults, ListReply, ListRequest, SearchRequest, UploadReply, UploadRequest,
+};
+
+/// Turns `error` into a status with the same JSON as the REST routes.
+fn status(error: BookrabError) -> Status {
+    let error = ApiError(error);
+    let message = serde_json::to_string(&error.0).unwrap();
+    match error.status() {
+        StatusCode::UNAUTHORIZED => Status::unauthenticated(message),
+        StatusCode::NOT_FOUND => Status::not_found(message),
+        StatusCode::CONFLICT => Status::already_exists(message),
+        StatusCode::PAYLOAD_TOO_LARGE => Status::resource_exhausted(message),
+        v if v.is_client_error() => Status::invalid_argument(message),
+        _ => Status::internal(message),
+    }
+}
+
+/// Config of the user that made the request. Like in the REST routes,
+/// a token (`authorization: Bearer <token>`) is only needed when
+/// there is a `jwt_secret` in the config.
+fn user_config<T>(request: &Request<T>) -> Result<BookrabConfig, Status> {
+    let config = ensure_confy_works();
+    if config.jwt_secret.is_none() {
+        return Ok(config);
+    }
+    let token = request
+        .metadata()
+        .get("authorization")
+        .and_then(|header| header.to_str().ok())
+        .and_then(|header| header.strip_prefix("Bearer "))
+        .ok_or_else(|| {
+            status(BookrabError::Unauthorized {
+                error: (),
+                reason: "there is no bearer token in the request".into(),
+            })
+        })?;
+    let claims = verify_token(&config, token.trim()).map_err(status)?;
+    ensure_config_works(config.for_user(claims.sub)).map_err(|e| status(user_config_error(e)))
+}
+
+fn database() -> Result<OptionalDB, Status> {
+    OptionalDB::get().map_err(|e| Status::unavailable(e.to_string()))
+}
+
+/// Language of its code (e.g. `pt`). Empty codes mean no language.
+fn language(code: Option<String>) -> Result<Option<Language>, Status> {
+    match code.filter(|code| !code.is_empty()) {
+        Some(code) => serde_json::from_value(serde_json::Value::String(code.clone()))
+            .map(Some)
+            .map_err(|_| Status::invalid_argument(format!("unknown language: {code}"))),
+        None => Ok(None),
+    }
+}
+
+fn filter_mode(mode: i32) -> FilterMode {
+    match proto::FilterMode::try_from(mode) {
+        Ok(proto::FilterMode::All) => FilterMode::All,
+        _ => FilterMode::Any,
+    }
+}
+
+impl SearchRequest {
+    fn into_form(self) -> Result<SearchForm, Status> {
+        Ok(SearchForm {
+            pattern: self.pattern,
+            after_context: Some(self.after_context as usize),
+            before_context: Some(self.before_context as usize),
+            case_insensitive: Some(self.case_insensitive),
+            case_smart: Some(self.case_smart),
+            ignore_accents: Some(self.ignore_accents),
+            include_tags: Some(self.include_tags),
+            include_mode: Some(filter_mode(self.include_mode)),
+            exclude_tags: Some(self.exclude_tags),
+            exclude_mode: Some(filter_mode(self.exclude_mode)),
+            language: language(self.language)?,
+            format: None,
+            kwic_width: None,
+            limit: None,
+            offset: None,
+            max_matches_per_book: self.max_matches_per_book.map(|max| max as usize),
+        })
+    }
+}
+
+impl From<TruncatedSearchResults> for BookResults {
+    fn from(value: TruncatedSearchResults) -> Self {
+        BookResults {
+            title: value.results.title,
+            results: value.results.results,
+            score: value.results.score,
+            duration_ms: value.results.duration_ms,
+            truncated: value.truncated,
+        }
+    }
+}
+
+/// Service that works on the books of the config
+/// (or on the books of the user, see [user_config]).
+pub struct BooksService;
+
+#[tonic::async_trait]
+impl Books for BooksService {
+    type SearchStream = Pin<Box<dyn Stream<Item = Result<BookResults, Status>> + Send>>;
+
+    /// Sends the results of each book as soon as the book is searched,
+    /// in no particular order. The search stops when the client goes away.
+    async fn search(
+        &self,
+        request: Request<SearchRequest>,
+    ) -> Result<Response<Self::SearchStream>, Status> {
+        let config = user_config(&request)?;
+        let form = request.into_inner().into_form()?;
+        let params = SearchParams::new(&form).map_err(status)?;
+        let max_matches = form.max_matches_per_book;
+        let mut db = database()?;
+        let (sender, receiver) = mpsc::channel(16);
+        // the search blocks, so it doesn't run in the async workers
+        tokio::task::spawn_blocking(move || {
+            let mut root = db.root(config);
+            let searched = params.search(&mut root, |event| match event {
+                SearchEvent::Finished(results) => {
+                    let results = TruncatedSearchResults::new(results.clone(), max_matches);
+                    sender.blocking_send(Ok(results.into())).is_ok()
+                }
+                _ => !sender.is_closed(),
+            });
+            if let Err(e) = searched {
+                let _ = sender.blocking_send(Err(status(e)));
+            }
+        });
+        Ok(Response::new(Box::pin(ReceiverStream::new(receiver))))
+    }
+
+    /// Uploads a book. The first message has its metadata
+    /// and the others have its text.
+    async fn upload(
+        &self,
+        request: Request<Streaming<UploadRequest>>,
+    ) -> Result<Response<UploadReply>, Status> {
+        let config = user_config(&request)?;
+        let mut parts = request.into_inner();
+        let metadata = match parts.next().await {
+            Some(Ok(UploadRequest {
+                part: Some(Part::Metadata(metadata)),
+            })) => metadata,
+            Some(Err(e)) => return Err(e),
+            _ => {
+                return Err(Status::invalid_argument(
+                    "the first message should have the metadata of the book",
+                ))
+            }
+        };
+        let mut bytes = vec![];
+        while let Some(part) = parts.next().await {
+            match part?.part {
+                Some(Part::Chunk(chunk)) => {
+                    bytes.extend(chunk);
+                    if bytes.len() > config.max_upload_bytes() {
+                        return Err(status(BookrabError::UploadTooLarge {
+                            error: (),
+                            limit: config.max_upload_bytes(),
+                        }));
+                    }
+                }
+                Some(Part::Metadata(_)) => {
+                    return Err(Status::invalid_argument(
+                        "only the first message should have metadata",
+                    ))
+                }
+                None => {}
+            }
+        }
+        let txt = String::from_utf8(bytes).map_err(|_| {
+            status(BookrabError::NotUnicode {
+                error: (),
+                what: metadata.title.clone(),
+            })
+        })?;
+        let language = language(metadata.language)?;
+        let tags: HashSet<String> = metadata.tags.into_iter().collect();
+        let mut db = database()?;
+        let title = metadata.title;
+        tokio::task::spawn_blocking(move || {
+            store_book(&mut db.root(config), &title, &txt, tags, language).map(|_| title)
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map(|title| Response::new(UploadReply { title }))
+        .map_err(status)
+    }
+
+    /// Lists books with their metadata, one page at a time.
+    async fn list(&self, request: Request<ListRequest>) -> Result<Response<ListReply>, Status> {
+        let config = user_config(&request)?;
+        let request = request.into_inner();
+        let language = language(request.language)?;
+        let mut db = database()?;
+        let listing = tokio::task::spawn_blocking(move || {
+            db.root(config)
+                .list_filtered(|book| language.is_none() || book.language == language)
+        })
+        .await
+        .map_err(|e| Status::internal(e.to_string()))?
+        .map_err(status)?;
+        let page = Page::new(
+            listing,
+            request.offset.unwrap_or(0) as usize,
+            request.limit.unwrap_or(100) as usize,
+        );
+        Ok(Response::new(ListReply {
+            books: page
+                .items
+                .into_iter()
+                .map(|book| proto::Book {
+                    title: book.title,
+                    tags: book.tags.into_iter().collect(),
+                    language: book.language.map(|language| {
+                        serde_json::to_value(language)
+                            .unwrap()
+                            .as_str()
+                            .unwrap()
+                            .to_string()
+                    }),
+                })
+                .collect(),
+            total: page.total as u64,
+            next: page.next.map(|next| next as u64),
+        }))
+    }
+}
+
+/// Serves [BooksService] at `addr` in a thread with its own runtime
+/// (actix runs a runtime per worker, which tonic can't use).
+pub fn spawn_server(addr: SocketAddr, config: &BookrabConfig) -> thread::JoinHandle<()> {
+    // same threads as the REST API (see [BookrabConfig::workers])
+    let mut builder = tokio::runtime::Builder::new_multi_thread();
+    if let Some(workers) = config.workers {
+        builder.worker_threads(workers.max(1));
+    }
+    if let Some(threads) = config.blocking_threads {
+        builder.max_blocking_threads(threads.max(1));
+    }
+    thread::spawn(move || {
+        let runtime = builder
+            .enable_all()
+            .build()
+            .expect("couldn't start the gRPC runtime");
+        runtime.block_on(async {
+            if let Err(e) = Server::builder()
+                .add_service(BooksServer::new(BooksService))
+                .serve(addr)
+                .await
+            {
+                log::error!("gRPC server stopped: {:?}", e);
+            }
+        });
+    })
+}
@@ -0,0 +1,140 @@
+//! `Idempotency-Key` header of uploads: retries of a request with the same
+//! key get the response of the first one instead of uploading the book again,
+//! and retries that arrive while it runs are rejected instead of racing it.
+//! Keys are kept in memory for [KEY_TTL], so they don't survive restarts.
+use std::{
+    collections::HashMap,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use actix_web::{http::header::ContentType, HttpRequest, HttpResponse};
+use bookrab_core::{books::validation::FieldError, config::BookrabConfig, errors::BookrabError};
+use lazy_static::lazy_static;
+use serde::Serialize;
+
+/// Header with the key chosen by the client.
+pub const IDEMPOTENCY_KEY: &str = "idempotency-key";
+/// Header set on responses that were replayed.
+pub const IDEMPOTENT_REPLAYED: &str = "idempotent-replayed";
+/// How long keys are remembered.
+const KEY_TTL: Duration = Duration::from_secs(24 * 60 * 60);
+const MAX_KEY_LENGTH: usize = 255;
+
+/// Keys are per user, so users can't see each other's responses.
+type Key = (Option<i32>, String);
+
+enum State {
+    Running,
+    /// JSON body of the response
+    Done(String),
+}
+
+struct Entry {
+    /// Identifies the request (e.g. title and checksum of the book)
+    fingerprint: String,
+    created: Instant,
+    state: State,
+}
+
+lazy_static! {
+    static ref KEYS: Mutex<HashMap<Key, Entry>> = Mutex::new(HashMap::new());
+}
+
+/// What to do with a request that has a key.
+pub(crate) enum Claim {
+    /// First request with the key: it should run and [KeyGuard::finish]
+    New(KeyGuard),
+    /// The request already succeeded, this is its response
+    Replay(HttpResponse),
+}
+
+/// Key of a running request. Dropping it without [KeyGuard::finish]
+/// (e.g. when the request fails) forgets the key, so it can be retried.
+pub(crate) struct KeyGuard {
+    key: Option<Key>,
+}
+
+impl KeyGuard {
+    /// Remembers `response` as the response of the key.
+    pub(crate) fn finish(mut self, response: &impl Serialize) {
+        if let Some(key) = self.key.take() {
+            if let Some(entry) = KEYS.lock().unwrap().get_mut(&key) {
+                entry.state = State::Done(serde_json::to_string(response).unwrap());
+            }
+        }
+    }
+}
+
+impl Drop for KeyGuard {
+    fn drop(&mut self) {
+        if let Some(key) = self.key.take() {
+            KEYS.lock().unwrap().remove(&key);
+        }
+    }
+}
+
+/// Key of the request (`None` when the client didn't send one).
+/// Keys must be visible ASCII with at most 255 characters.
+pub(crate) fn key_of(req: &HttpRequest) -> Result<Option<String>, BookrabError> {
+    let Some(value) = req.headers().get(IDEMPOTENCY_KEY) else {
+        return Ok(None);
+    };
+    match value.to_str() {
+        Ok(key) if !key.is_empty() && key.len() <= MAX_KEY_LENGTH => Ok(Some(key.to_string())),
+        _ => Err(BookrabError::InvalidFields {
+            error: (),
+            errors: vec![FieldError::new(
+                "Idempotency-Key",
+                format!("should have between 1 and {MAX_KEY_LENGTH} visible ASCII characters"),
+            )],
+        }),
+    }
+}
+
+/// Claims `key` for a request identified by `fingerprint`.
+/// Fails with [BookrabError::IdempotencyKeyConflict] when a request with
+/// the key is still running or had another fingerprint.
+pub(crate) fn claim(
+    config: &BookrabConfig,
+    key: String,
+    fingerprint: String,
+) -> Result<Claim, BookrabError> {
+    let conflict = |key: &Key, reason: &str| BookrabError::IdempotencyKeyConflict {
+        error: (),
+        key: key.1.clone(),
+        reason: reason.to_string(),
+    };
+    let key = (config.user_id, key);
+    let mut keys = KEYS.lock().unwrap();
+    keys.retain(|_, entry| entry.created.elapsed() < KEY_TTL);
+    match keys.get(&key) {
+        Some(entry) if entry.fingerprint != fingerprint => {
+            Err(conflict(&key, "the key was used for a different request"))
+        }
+        Some(Entry {
+            state: State::Running,
+            ..
+        }) => Err(conflict(&key, "a request with this key is still running")),
+        Some(Entry {
+            state: State::Done(body),
+            ..
+        }) => Ok(Claim::Replay(
+            HttpResponse::Ok()
+                .content_type(ContentType::json())
+                .insert_header((IDEMPOTENT_REPLAYED, "true"))
+                .body(body.clone()),
+        )),
+        None => {
+            keys.insert(
+                key.clone(),
+                Entry {
+                    fingerprint,
+                    created: Instant::now(),
+                    state: State::Running,
+                },
+            );
+            Ok(Claim::New(KeyGuard { key: Some(key) }))
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Logs of the server, configured by `log4rs.yml`. Their level can be
+//! replaced by [BookrabConfig::log_level] while the server runs.
+use std::{fs, sync::OnceLock};
+
+use bookrab_core::config::BookrabConfig;
+use log::LevelFilter;
+use log4rs::config::{Config, Deserializers, Logger, RawConfig, Root};
+
+/// Configuration of the appenders and of the levels of the logs.
+const LOG_CONFIG: &str = "log4rs.yml";
+
+static HANDLE: OnceLock<log4rs::Handle> = OnceLock::new();
+
+/// Config of [LOG_CONFIG] with every level replaced by `level` (if any).
+fn log_config(level: Option<LevelFilter>) -> anyhow::Result<Config> {
+    let raw: RawConfig = serde_yaml::from_str(&fs::read_to_string(LOG_CONFIG)?)?;
+    let (appenders, mut errors) = raw.appenders_lossy(&Deserializers::default());
+    errors.handle();
+    let root = raw.root();
+    let root = Root::builder()
+        .appenders(root.appenders().to_vec())
+        .build(level.unwrap_or(root.level()));
+    let loggers = raw.loggers().into_iter().map(|logger| {
+        Logger::builder()
+            .appenders(logger.appenders().to_vec())
+            .additive(logger.additive())
+            .build(logger.name(), level.unwrap_or(logger.level()))
+    });
+    Ok(Config::builder()
+        .appenders(appenders)
+        .loggers(loggers)
+        .build(root)?)
+}
+
+/// Level of `config` (`None` when it has none or it isn't a level).
+fn level(config: &BookrabConfig) -> Option<LevelFilter> {
+    let level = config.log_level.as_ref()?;
+    match level.parse() {
+        Ok(level) => Some(level),
+        Err(_) => {
+            log::warn!("log_level {level:?} isn't a level (e.g. info or debug), it was ignored");
+            None
+        }
+    }
+}
+
+/// Starts the logs with the levels of [LOG_CONFIG].
+pub fn init() -> anyhow::Result<()> {
+    let handle = log4rs::init_config(log_config(None)?)?;
+    let _ = HANDLE.set(handle);
+    Ok(())
+}
+
+/// Uses the level of `config` (or the ones of [LOG_CONFIG] without it).
+pub fn set_level(config: &BookrabConfig) {
+    let Some(handle) = HANDLE.get() else {
+        return;
+    };
+    match log_config(level(config)) {
+        Ok(log_config) => handle.set_config(log_config),
+        Err(e) => log::error!("couldn't reconfigure the logs: {e}"),
+    }
+}
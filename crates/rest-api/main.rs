@@ -1,18 +1,33 @@
-use crate::errors::{Bookrab400, Bookrab500};
-use actix_files::Files;
+use crate::errors::{
+    Bookrab400, Bookrab401, Bookrab409, Bookrab413, Bookrab422, Bookrab500, Bookrab501,
+};
 use actix_web::dev::Service;
 use futures_util::FutureExt;
-use std::fs;
 use utoipa_rapidoc::RapiDoc;
 use utoipa_redoc::{Redoc, Servable};
 use utoipa_swagger_ui::SwaggerUi;
+mod assets;
+pub mod auth;
 pub mod config;
 pub mod database;
 pub mod errors;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+mod idempotency;
+mod logging;
+mod reload;
+pub mod shutdown;
+mod validation;
 mod views;
+pub mod webhooks;
 use actix_multipart::form::tempfile::TempFileConfig;
-use actix_web::{middleware::Logger, App, HttpServer};
-use config::ensure_confy_works;
+use actix_web::{
+    middleware::{from_fn, Compress, Logger},
+    App, HttpServer,
+};
+use bookrab_core::books::history::SearchHistory;
+use config::{check_config, ensure_confy_works};
+use database::DBCONNECTION;
 use utoipa::{
     openapi::{self},
     Modify, OpenApi,
@@ -21,12 +36,38 @@ use utoipa_actix_web::AppExt;
 
 #[actix_web::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    log4rs::init_file("log4rs.yml", Default::default()).expect("logger didnt initialize");
+    logging::init().expect("logger didnt initialize");
+    match config::config_flag(std::env::args().skip(1)) {
+        Ok(Some(path)) => config::use_config_file(path),
+        Ok(None) => {}
+        Err(e) => {
+            eprintln!("bookrab-rest-api: {e}");
+            std::process::exit(2);
+        }
+    }
+    // the config is checked once here and the routes use it
+    // until the configuration file changes (see [reload])
+    let config = match check_config() {
+        Ok(config) => config,
+        Err(e) => {
+            log::error!("{e}");
+            eprintln!("bookrab-rest-api: {e}");
+            std::process::exit(1);
+        }
+    };
+    if config.log_level.is_some() {
+        logging::set_level(&config);
+    }
+    config::use_config(config.clone());
+    reload::watch();
     #[derive(OpenApi)]
     #[openapi(
         info(license(name = "MIT", identifier = "MIT")),
         modifiers(&ApiDocInfo),
-        components(schemas(Bookrab400, Bookrab500))
+        components(schemas(
+            Bookrab400, Bookrab401, Bookrab409, Bookrab413, Bookrab422, Bookrab500,
+            Bookrab501
+        ))
     )]
     struct ApiDoc;
 
@@ -37,17 +78,29 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         }
     }
 
-    let server = HttpServer::new(move || {
+    // the history is also pruned after each search
+    if let Some(pool) = DBCONNECTION.as_ref() {
+        let mut connection = pool.get()?;
+        if let Err(e) = SearchHistory::new(config.clone(), &mut connection).prune() {
+            log::error!("couldn't prune history: {:?}", e);
+        }
+    }
+
+    #[cfg(feature = "grpc")]
+    grpc::spawn_server("127.0.0.1:50051".parse()?, &config);
+
+    let mut server = HttpServer::new(move || {
         let doc = ApiDoc::openapi();
         let config = ensure_confy_works();
-        if !&config.book_path.is_dir() {
-            fs::create_dir_all(&config.book_path).expect("couldn't create book folder");
-        }
         let (app, _) = App::new()
             .into_utoipa_app()
             .openapi(doc)
             .map(|app| {
-                app.wrap(Logger::default())
+                app.wrap(from_fn(auth::authenticate))
+                    // gzip, brotli or zstd, as accepted by the client
+                    // (streams are flushed whenever the search pauses)
+                    .wrap(Compress::default())
+                    .wrap(Logger::default())
                     .wrap_fn(|req, srv| {
                         srv.call(req).map(|res| {
                             println!("{:#?}", res);
@@ -58,10 +111,23 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
                             res
                         })
                     })
-                    .service(Files::new("/static", "./static").show_files_listing())
+                    .service(assets::assets)
             })
+            .configure(views::health::configure())
+            .service(utoipa_actix_web::scope("/v1/auth").configure(views::auth::configure()))
+            .service(utoipa_actix_web::scope("/v1/admin").configure(views::admin::configure()))
+            .configure(views::ws::configure())
             .service(utoipa_actix_web::scope("/v1/books").configure(views::books::configure()))
-            .app_data(TempFileConfig::default().directory(&config.book_path))
+            .service(utoipa_actix_web::scope("/v2/books").configure(views::v2::configure()))
+            .service(utoipa_actix_web::scope("/v1/search").configure(views::search::configure()))
+            .service(utoipa_actix_web::scope("/v1/history").configure(views::history::configure()))
+            .service(
+                utoipa_actix_web::scope("/v1/searches").configure(views::searches::configure()),
+            )
+            .app_data(TempFileConfig::default().directory(config.upload_folder()))
+            .app_data(views::books::upload::multipart_config(&config))
+            .app_data(validation::query_config())
+            .app_data(validation::json_config())
             .openapi_service(|api| Redoc::with_url("/v1/redoc", api))
             .openapi_service(|api| {
                 RapiDoc::with_openapi("/api-docs/openapi.json", api).path("/rapidoc")
@@ -73,6 +139,21 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         app
     })
     .bind("127.0.0.1:8000")?;
-    server.run().await?;
+    // actix's defaults are used for what isn't in the config
+    if let Some(workers) = config.workers {
+        server = server.workers(workers.max(1));
+    }
+    if let Some(threads) = config.blocking_threads {
+        server = server.worker_max_blocking_threads(threads.max(1));
+    }
+    let server = server
+        // signals are handled by `shutdown`, which also cancels the searches
+        .disable_signals()
+        .shutdown_timeout(shutdown::SHUTDOWN_TIMEOUT.as_secs())
+        .run();
+    actix_web::rt::spawn(shutdown::stop_on_signal(server.handle()));
+    server.await?;
+    // streams are closed, but their searches may still be writing the history
+    shutdown::drain(shutdown::SHUTDOWN_TIMEOUT);
     Ok(())
 }
@@ -0,0 +1,148 @@
+//! Reloads the configuration file while the server runs. Fields that
+//! are only read when the server starts (see [NEEDS_RESTART]) keep their
+//! old values and their changes are reported in the logs.
+use std::{fs, path::Path, thread, time::Duration, time::SystemTime};
+
+use bookrab_core::config::{ensure_config_works, BookrabConfig};
+use serde_json::{Map, Value};
+
+use crate::{
+    config::{config_path, ensure_confy_works, load_config, use_config},
+    logging,
+};
+
+/// How often the configuration file is checked for changes.
+const RELOAD_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Fields that are only read when the server starts, e.g. the pool of
+/// the database and the limits of uploads are made with them.
+const NEEDS_RESTART: &[&str] = &[
+    "book_path",
+    "book_paths",
+    "upload_book_path",
+    "database_url",
+    "history_backend",
+    "history_file",
+    "book_storage",
+    "max_upload_bytes",
+    "max_upload_memory_bytes",
+    "workers",
+    "blocking_threads",
+];
+
+fn fields(config: &BookrabConfig) -> Map<String, Value> {
+    match serde_json::to_value(config) {
+        Ok(Value::Object(fields)) => fields,
+        _ => Map::new(),
+    }
+}
+
+/// `new` with the fields of [NEEDS_RESTART] of `old`. Changed fields are
+/// logged (only their names, since some are secrets). When the fields
+/// can't be put back together, `old` is kept.
+fn merge(old: &BookrabConfig, new: BookrabConfig) -> BookrabConfig {
+    let old_fields = fields(old);
+    let mut new_fields = fields(&new);
+    for (field, value) in new_fields.iter_mut() {
+        let Some(old_value) = old_fields.get(field).filter(|old| **old != *value) else {
+            continue;
+        };
+        if NEEDS_RESTART.contains(&field.as_str()) {
+            log::warn!("{field} changed in the configuration, restart the server to use it");
+            *value = old_value.clone();
+        } else {
+            log::info!("{field} was reloaded from the configuration");
+        }
+    }
+    match serde_json::from_value(Value::Object(new_fields)) {
+        Ok(config) => config,
+        Err(e) => {
+            log::warn!("configuration wasn't reloaded, the old one is kept: {e}");
+            old.clone()
+        }
+    }
+}
+
+/// Applies the changes of the configuration file. When it can't be read
+/// (e.g. it is being written) or doesn't work, the old config is kept.
+fn reload() {
+    let new = match load_config() {
+        Ok(new) => new,
+        Err(e) => {
+            log::error!("configuration wasn't reloaded: {e}");
+            return;
+        }
+    };
+    let old = ensure_confy_works();
+    match ensure_config_works(merge(&old, new)) {
+        Ok(config) => {
+            if config.log_level != old.log_level {
+                logging::set_level(&config);
+            }
+            use_config(config);
+        }
+        Err(e) => log::error!("configuration wasn't reloaded: {e}"),
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|file| file.modified()).ok()
+}
+
+/// Checks the configuration file every [RELOAD_INTERVAL] in the
+/// background and reloads it when it changes.
+pub fn watch() {
+    let path = match config_path() {
+        Ok(path) => path,
+        Err(e) => {
+            log::error!("the configuration won't be reloaded: {e}");
+            return;
+        }
+    };
+    thread::spawn(move || {
+        let mut last_modified = modified(&path);
+        loop {
+            thread::sleep(RELOAD_INTERVAL);
+            let now = modified(&path);
+            if now != last_modified {
+                last_modified = now;
+                reload();
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::PathBuf;
+
+    use super::*;
+
+    #[test]
+    fn restart_only_fields_keep_their_old_values() {
+        let old = BookrabConfig::default();
+        let new = BookrabConfig {
+            book_path: PathBuf::from("/somewhere/else"),
+            workers: Some(3),
+            ..BookrabConfig::default()
+        };
+        let merged = merge(&old, new);
+        assert_eq!(merged.book_path, old.book_path);
+        assert_eq!(merged.workers, None);
+    }
+
+    #[test]
+    fn live_fields_are_reloaded() {
+        let old = BookrabConfig::default();
+        let new = BookrabConfig {
+            webhook_urls: vec!["http://localhost:9000/hook".to_string()],
+            jwt_expiration_hours: Some(2),
+            book_path: PathBuf::from("/somewhere/else"),
+            ..BookrabConfig::default()
+        };
+        let merged = merge(&old, new);
+        assert_eq!(merged.webhook_urls, vec!["http://localhost:9000/hook"]);
+        assert_eq!(merged.jwt_expiration_hours, Some(2));
+        assert_eq!(merged.book_path, old.book_path);
+    }
+}
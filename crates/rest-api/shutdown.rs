@@ -0,0 +1,79 @@
+//! Graceful shutdown. On SIGINT or SIGTERM the server stops accepting
+//! connections, searches that are running stop before their next book
+//! and the process only exits after they wrote their history.
+use std::{
+    pin::pin,
+    sync::atomic::{AtomicBool, AtomicUsize, Ordering},
+    thread,
+    time::{Duration, Instant},
+};
+
+use actix_web::{dev::ServerHandle, rt::signal};
+
+/// How long open connections and searches have to finish.
+pub const SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Cancellation token shared by every search.
+static SHUTTING_DOWN: AtomicBool = AtomicBool::new(false);
+/// Searches that haven't written their history yet.
+static IN_FLIGHT: AtomicUsize = AtomicUsize::new(0);
+
+/// Whether searches should stop.
+pub fn is_shutting_down() -> bool {
+    SHUTTING_DOWN.load(Ordering::SeqCst)
+}
+
+/// Held during a search (history included), so that [drain] can wait for it.
+pub(crate) struct SearchGuard(());
+
+impl SearchGuard {
+    pub(crate) fn new() -> Self {
+        IN_FLIGHT.fetch_add(1, Ordering::SeqCst);
+        SearchGuard(())
+    }
+}
+
+impl Drop for SearchGuard {
+    fn drop(&mut self) {
+        IN_FLIGHT.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// Resolves on SIGTERM (only on unix) and never resolves if it can't be listened to.
+async fn terminate() {
+    #[cfg(unix)]
+    match signal::unix::signal(signal::unix::SignalKind::terminate()) {
+        Ok(mut sigterm) => {
+            sigterm.recv().await;
+            return;
+        }
+        Err(e) => log::error!("couldn't listen to SIGTERM: {:?}", e),
+    }
+    std::future::pending::<()>().await
+}
+
+/// Waits for SIGINT or SIGTERM, then cancels the searches and
+/// stops the server (see [SHUTDOWN_TIMEOUT]).
+pub async fn stop_on_signal(server: ServerHandle) {
+    futures::future::select(pin!(signal::ctrl_c()), pin!(terminate())).await;
+    log::info!("shutting down");
+    SHUTTING_DOWN.store(true, Ordering::SeqCst);
+    server.stop(true).await;
+}
+
+/// Waits until every search wrote its history or `timeout` passes.
+/// Returns whether every search finished.
+pub fn drain(timeout: Duration) -> bool {
+    let start = Instant::now();
+    loop {
+        let in_flight = IN_FLIGHT.load(Ordering::SeqCst);
+        if in_flight == 0 {
+            return true;
+        }
+        if start.elapsed() >= timeout {
+            log::error!("{} searches didn't finish before shutting down", in_flight);
+            return false;
+        }
+        thread::sleep(Duration::from_millis(50));
+    }
+}
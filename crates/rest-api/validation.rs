@@ -0,0 +1,73 @@
+//! Requests that can't be read are answered with
+//! [BookrabError::InvalidFields] (422) instead of actix's plain text errors,
+//! so clients know which field is wrong.
+use actix_multipart::MultipartError;
+use actix_web::{
+    error::{InternalError, JsonPayloadError, QueryPayloadError},
+    web, HttpRequest, HttpResponse,
+};
+use bookrab_core::{
+    books::validation::{field_of_serde_error, FieldError},
+    errors::BookrabError,
+};
+
+use crate::errors::ApiError;
+
+/// Response to a request with invalid fields.
+pub(crate) fn invalid_fields(errors: Vec<FieldError>) -> HttpResponse {
+    ApiError(BookrabError::InvalidFields { error: (), errors }).into()
+}
+
+fn into_error<E: std::fmt::Debug + std::fmt::Display + 'static>(
+    err: E,
+    errors: Vec<FieldError>,
+) -> actix_web::Error {
+    InternalError::from_response(err, invalid_fields(errors)).into()
+}
+
+/// Query strings that don't match their form. Errors that don't
+/// say which field is wrong are reported as the field `query`.
+pub(crate) fn query_config() -> web::QueryConfig {
+    web::QueryConfig::default().error_handler(|err, req: &HttpRequest| match err {
+        QueryPayloadError::Deserialize(e) => {
+            let message = e.to_string();
+            let pairs = web::Query::<Vec<(String, String)>>::from_query(req.query_string())
+                .map(|pairs| pairs.into_inner())
+                .unwrap_or_default();
+            let field = field_of_serde_error(
+                &message,
+                pairs.iter().map(|(k, v)| (k.as_str(), v.as_str())),
+            );
+            let field = field.unwrap_or_else(|| "query".into());
+            into_error(e, vec![FieldError::new(field, message)])
+        }
+        err => err.into(),
+    })
+}
+
+/// JSON bodies that don't match their form. The body is gone by the
+/// time it fails, so only missing and unknown fields are named;
+/// the others are reported as the field `body`.
+pub(crate) fn json_config() -> web::JsonConfig {
+    web::JsonConfig::default().error_handler(|err, _| match err {
+        JsonPayloadError::Deserialize(e) => {
+            let message = e.to_string();
+            let field = field_of_serde_error(&message, []).unwrap_or_else(|| "body".into());
+            into_error(e, vec![FieldError::new(field, message)])
+        }
+        err => err.into(),
+    })
+}
+
+/// Multipart forms with missing, unknown or broken fields
+/// (other errors are left to actix).
+pub(crate) fn multipart_error(err: MultipartError) -> actix_web::Error {
+    let field_error = match &err {
+        MultipartError::MissingField(name) => FieldError::new(name, "is missing"),
+        MultipartError::DuplicateField(name) => FieldError::new(name, "is duplicated"),
+        MultipartError::UnknownField(name) => FieldError::new(name, "is unknown"),
+        MultipartError::Field { name, source } => FieldError::new(name, source),
+        _ => return err.into(),
+    };
+    into_error(err, vec![field_error])
+}
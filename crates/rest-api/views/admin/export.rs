@@ -0,0 +1,72 @@
+use std::io::{self, BufWriter, Write};
+
+use crate::{config::UserConfig, database::OptionalDB, errors::Bookrab500};
+use actix_web::{
+    get,
+    http::header::ContentDisposition,
+    web::{self, Bytes},
+    HttpResponse,
+};
+use futures::{channel::mpsc, executor::block_on, SinkExt};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+/// Size of the chunks sent to the client.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct ExportForm {
+    /// Whether the search history goes along (default: false)
+    history: Option<bool>,
+}
+
+/// Sends what is written to the client, waiting while it is slow.
+struct ChannelWriter(mpsc::Sender<Result<Bytes, io::Error>>);
+
+impl Write for ChannelWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        block_on(self.0.send(Ok(Bytes::copy_from_slice(buf))))
+            .map_err(|_| io::Error::new(io::ErrorKind::BrokenPipe, "the client went away"))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+/// Downloads the whole library (texts, tags, languages and, optionally,
+/// the search history) as a `.tar.gz` that `POST /v1/admin/import`
+/// restores. The archive is sent while it is made, so errors in
+/// the middle of it cut the download short.
+#[utoipa::path(
+    params(ExportForm),
+    responses (
+        (status = 200, content_type = "application/gzip", body = Vec<u8>),
+        (status = 500, body = Bookrab500),
+    )
+)]
+#[get("/export")]
+pub async fn export(
+    UserConfig(config): UserConfig,
+    form: web::Query<ExportForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let history = form.history.unwrap_or(false);
+    let (sender, receiver) = mpsc::channel(4);
+    // the export blocks, so it doesn't run in the async workers
+    actix_web::rt::spawn(web::block(move || {
+        let mut sender = sender;
+        let mut writer = BufWriter::with_capacity(CHUNK_SIZE, ChannelWriter(sender.clone()));
+        if let Err(e) = db.root(config).export(&mut writer, history) {
+            log::error!("couldn't export the library: {:?}", e);
+            // the client sees an aborted download instead of a broken archive
+            let _ = block_on(sender.send(Err(io::Error::other("couldn't export the library"))));
+        }
+    }));
+    HttpResponse::Ok()
+        .content_type("application/gzip")
+        .insert_header(ContentDisposition::attachment("bookrab-library.tar.gz"))
+        .streaming(receiver)
+}
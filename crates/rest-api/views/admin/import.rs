@@ -0,0 +1,69 @@
+use std::io::BufReader;
+
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab413, Bookrab500},
+    webhooks::{notify, WebhookEvent},
+};
+use actix_multipart::form::{tempfile::TempFile, MultipartForm};
+use actix_web::{post, web, HttpResponse};
+use utoipa::ToSchema;
+
+/// Archive made by `GET /v1/admin/export`.
+#[derive(Debug, MultipartForm, ToSchema)]
+struct ImportForm {
+    #[schema(value_type = String, format = "binary")]
+    archive: TempFile,
+}
+
+/// Mirror of [bookrab_core::books::archive::ImportSummary] for the docs.
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct ImportSummaryUtoipa {
+    /// Books that were uploaded (existing books with
+    /// the same title are replaced)
+    books: usize,
+    history_entries: usize,
+}
+
+/// Restores a library exported by `GET /v1/admin/export`.
+/// Books with the same title as existing ones replace them and history
+/// entries are appended to the history. The archive is limited by
+/// `max_upload_bytes`, like book uploads. The webhooks are told about
+/// every imported book.
+#[utoipa::path(
+    request_body(content_type = "multipart/form-data", content = ImportForm),
+    responses (
+        (status = 200, body = ImportSummaryUtoipa),
+        (status = 400, body = Bookrab400),
+        (status = 413, body = Bookrab413),
+        (status = 500, body = Bookrab500),
+    )
+)]
+#[post("/import")]
+pub async fn import(
+    UserConfig(config): UserConfig,
+    MultipartForm(form): MultipartForm<ImportForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let archive = BufReader::new(form.archive.file);
+    let webhook_config = config.clone();
+    // the import blocks, so it doesn't run in the async workers
+    match web::block(move || db.root(config).import(archive)).await {
+        Ok(Ok(v)) => {
+            for book in &v.uploaded {
+                notify(
+                    &webhook_config,
+                    WebhookEvent::BookUploaded {
+                        title: book.title.clone(),
+                        tags: book.tags.clone(),
+                    },
+                );
+            }
+            HttpResponse::Ok().json(v)
+        }
+        Ok(Err(e)) => ApiError(e).into(),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
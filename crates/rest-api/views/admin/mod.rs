@@ -0,0 +1,13 @@
+pub mod export;
+pub mod import;
+pub mod stats;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config
+            .service(stats::stats)
+            .service(export::export)
+            .service(import::import);
+    }
+}
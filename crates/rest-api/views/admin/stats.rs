@@ -0,0 +1,126 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab500},
+    views::{
+        books::{tags::TagCountUtoipa, LanguageUtoipa},
+        history::stats::{HistoryStatsUtoipa, StatsForm},
+    },
+};
+use actix_web::{get, web, HttpResponse};
+use bookrab_core::{
+    books::{
+        history::HistoryStats,
+        history_backend::query_history,
+        stats::{IndexStatus, LibraryStats, StorageUsage},
+    },
+    config::BookrabConfig,
+    errors::BookrabError,
+};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct LanguageCountUtoipa {
+    /// null for books whose language is unknown
+    language: Option<LanguageUtoipa>,
+    count: usize,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct LibraryStatsUtoipa {
+    books: usize,
+    /// Most common languages first
+    languages: Vec<LanguageCountUtoipa>,
+    /// Most used tags first
+    tags: Vec<TagCountUtoipa>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct IndexStatusUtoipa {
+    /// Number of books in the catalog
+    entries: usize,
+    /// Books that are not in the catalog (e.g. copied by hand to the book folder)
+    missing: Vec<String>,
+    /// Books in the catalog that are not in the storage anymore
+    orphaned: Vec<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct StorageUsageUtoipa {
+    /// Size of the texts according to the catalog (null without a database)
+    text_bytes: Option<u64>,
+    /// Size of the files in the book folder
+    disk_bytes: u64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct AdminStatsUtoipa {
+    library: LibraryStatsUtoipa,
+    /// null when the history isn't stored in a database (see `/v1/history/stats`)
+    history: Option<HistoryStatsUtoipa>,
+    /// null without a database
+    index: Option<IndexStatusUtoipa>,
+    storage: StorageUsageUtoipa,
+}
+
+/// Everything in `/v1/admin/stats`.
+#[derive(Debug, Serialize)]
+struct AdminStats {
+    library: LibraryStats,
+    history: Option<HistoryStats>,
+    index: Option<IndexStatus>,
+    storage: StorageUsage,
+}
+
+/// Statistics of the library, of the search history, of the catalog
+/// (the table that tracks the books, see `index`) and of the disk,
+/// in a single document for dashboards. Like every route under `/v1`,
+/// it needs a token when authentication is enabled.
+#[utoipa::path(
+    params(StatsForm),
+    responses (
+        (status = 200, body = AdminStatsUtoipa),
+        (status = 500, body = Bookrab500),
+    )
+)]
+#[get("/stats")]
+pub async fn stats(
+    UserConfig(config): UserConfig,
+    form: web::Query<StatsForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    match admin_stats(config, &form, &mut db) {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => ApiError(e).into(),
+    }
+}
+
+fn admin_stats(
+    config: BookrabConfig,
+    form: &StatsForm,
+    db: &mut OptionalDB,
+) -> Result<AdminStats, BookrabError> {
+    let top = form.top.unwrap_or(10);
+    let history = match query_history(&config, db.connection.as_deref_mut(), |history| {
+        history.stats(top)
+    }) {
+        Ok(v) => Some(v),
+        Err(
+            BookrabError::HistoryNotQueryable { .. } | BookrabError::DatabaseUnavailable { .. },
+        ) => None,
+        Err(e) => return Err(e),
+    };
+    let mut book_dir = db.root(config);
+    Ok(AdminStats {
+        library: book_dir.library_stats()?,
+        history,
+        index: book_dir.index_status()?,
+        storage: book_dir.storage_usage()?,
+    })
+}
@@ -0,0 +1,42 @@
+use crate::{
+    auth::issue_token,
+    config::ensure_confy_works,
+    database::DB,
+    errors::{ApiError, Bookrab401, Bookrab500},
+};
+use actix_web::{post, web, HttpResponse};
+use bookrab_core::users::Users;
+use utoipa::ToSchema;
+
+use super::Credentials;
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct TokenUtoipa {
+    /// Send it in the `Authorization: Bearer <token>` header
+    token: String,
+    /// e.g. `2024-12-25T10:00:00Z`
+    expires_at: String,
+}
+
+/// Returns a token for the other routes.
+#[utoipa::path(
+    request_body = Credentials,
+    responses (
+        (status = 200, body=TokenUtoipa),
+        (status = 401, body=Bookrab401),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[post("/login")]
+pub async fn login(credentials: web::Json<Credentials>, mut db: DB) -> HttpResponse {
+    let config = ensure_confy_works();
+    let users = Users::new(config.clone(), &mut db.connection);
+    let token = users
+        .authenticate(&credentials.username, &credentials.password)
+        .and_then(|user| issue_token(&config, &user));
+    match token {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => ApiError(e).into(),
+    }
+}
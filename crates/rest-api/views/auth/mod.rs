@@ -0,0 +1,18 @@
+pub mod login;
+pub mod register;
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+/// Username and password of a user.
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct Credentials {
+    username: String,
+    password: String,
+}
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(register::register).service(login::login);
+    }
+}
@@ -0,0 +1,37 @@
+use crate::{
+    config::ensure_confy_works,
+    database::DB,
+    errors::{ApiError, Bookrab400, Bookrab500},
+};
+use actix_web::{post, web, HttpResponse};
+use bookrab_core::users::Users;
+use utoipa::ToSchema;
+
+use super::Credentials;
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct UserUtoipa {
+    id: i32,
+    username: String,
+    /// e.g. `2024-12-25T10:00:00.123456`
+    created_at: String,
+}
+
+/// Creates a user. Passwords must have at least 8 characters.
+#[utoipa::path(
+    request_body = Credentials,
+    responses (
+        (status = 201, body=UserUtoipa),
+        (status = 400, body=Bookrab400),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[post("/register")]
+pub async fn register(credentials: web::Json<Credentials>, mut db: DB) -> HttpResponse {
+    let users = Users::new(ensure_confy_works(), &mut db.connection);
+    match users.register(&credentials.username, &credentials.password) {
+        Ok(v) => HttpResponse::Created().json(v),
+        Err(e) => ApiError(e).into(),
+    }
+}
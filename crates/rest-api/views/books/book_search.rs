@@ -0,0 +1,62 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab422, Bookrab500},
+};
+use actix_web::{get, web, HttpResponse};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use super::search::{
+    ResultFormatForm, SearchForm, SearchParams, TruncatedSearchResults,
+    TruncatedSearchResultsUtoipa,
+};
+
+/// Query of `GET /v1/books/{title}/search`: the fields of
+/// `GET /v1/books/search` that make sense for a single book.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct BookSearchFormUtoipa {
+    after_context: Option<usize>,
+    before_context: Option<usize>,
+    case_insensitive: Option<bool>,
+    case_smart: Option<bool>,
+    /// `lines` (default) or `kwic`
+    format: Option<ResultFormatForm>,
+    ignore_accents: Option<bool>,
+    /// Characters on each side of a match in the `kwic` format (default: 40)
+    kwic_width: Option<usize>,
+    /// Maximum number of results (default: no limit)
+    max_matches_per_book: Option<usize>,
+    pattern: String,
+}
+
+/// Searches a single book. Unlike `/v1/books/search`, the other books
+/// are not listed nor filtered, so it is cheaper when the book is known.
+/// Tag, language and pagination parameters are ignored.
+#[utoipa::path(
+    params(("title" = String, Path, description = "Title of the book"), BookSearchFormUtoipa),
+    responses (
+        (status = 200, body=TruncatedSearchResultsUtoipa),
+        (status = 400, body=Bookrab400),
+        (status = 422, body=Bookrab422),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[get("/{title}/search")]
+pub async fn book_search(
+    UserConfig(config): UserConfig,
+    title: web::Path<String>,
+    form: web::Query<SearchForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let params = match SearchParams::new(&form) {
+        Ok(v) => v,
+        Err(e) => return ApiError(e).into(),
+    };
+    match params.search_book(&mut db.root(config), &title) {
+        Ok(v) => HttpResponse::Ok().json(TruncatedSearchResults::new(v, form.max_matches_per_book)),
+        Err(e) => ApiError(e).into(),
+    }
+}
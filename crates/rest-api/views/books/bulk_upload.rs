@@ -0,0 +1,199 @@
+use std::{
+    collections::HashSet,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+use actix_multipart::form::{json::Json, tempfile::TempFile, MultipartForm};
+use actix_web::{post, web, HttpResponse};
+use bookrab_core::{books::RootBookDir, errors::BookrabError};
+use serde::Serialize;
+use utoipa::ToSchema;
+use zip::ZipArchive;
+
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab413, Bookrab500},
+};
+
+use super::upload::{read_book, store_book};
+
+/// Represents a form for uploading many books at once.
+#[derive(Debug, MultipartForm, ToSchema)]
+struct BulkBookForm {
+    /// Books (in the formats of `/v1/books/upload`) or .zip archives with .txt files
+    /// (their other files are skipped)
+    #[schema(value_type = Vec<String>, format = "binary")]
+    books: Vec<TempFile>,
+    /// Tags of every book
+    #[schema(value_type = Vec<String>)]
+    tags: Json<Vec<String>>,
+}
+
+/// What happened to one of the uploaded files.
+#[derive(Serialize)]
+struct UploadReport {
+    /// Name of the file (`archive.zip/book.txt` for files in archives)
+    file: String,
+    /// Title of the book (if it was uploaded)
+    title: Option<String>,
+    error: Option<ApiError>,
+}
+
+#[allow(dead_code)]
+#[derive(ToSchema)]
+struct UploadReportUtoipa {
+    /// Name of the file (`archive.zip/book.txt` for files in archives)
+    file: String,
+    /// Title of the book (if it was uploaded)
+    title: Option<String>,
+    /// Why the file wasn't uploaded (see the errors of `/v1/books/upload`)
+    error: Option<Bookrab400>,
+}
+
+impl UploadReport {
+    fn new(file: String, result: Result<String, BookrabError>) -> Self {
+        match result {
+            Ok(title) => UploadReport {
+                file,
+                title: Some(title),
+                error: None,
+            },
+            Err(e) => UploadReport {
+                file,
+                title: None,
+                error: Some(ApiError(e)),
+            },
+        }
+    }
+}
+
+/// Whether `file` should be treated as a zip archive.
+fn is_zip(file: &TempFile) -> bool {
+    let by_type = file.content_type.as_ref().is_some_and(|mime| {
+        mime.essence_str() == "application/zip"
+            || mime.essence_str() == "application/x-zip-compressed"
+    });
+    let by_name = file
+        .file_name
+        .as_ref()
+        .is_some_and(|name| name.to_lowercase().ends_with(".zip"));
+    by_type || by_name
+}
+
+/// Reads an entry of a zip archive. Entries are read up to `limit`
+/// bytes, since a small archive can hold huge files.
+fn read_entry(entry: impl Read, file: &str, limit: usize) -> Result<String, BookrabError> {
+    let mut bytes = vec![];
+    if let Err(e) = entry.take(limit as u64 + 1).read_to_end(&mut bytes) {
+        return Err(BookrabError::CouldntReadFile {
+            error: (),
+            path: PathBuf::from(file),
+            err: e,
+        });
+    }
+    if bytes.len() > limit {
+        return Err(BookrabError::UploadTooLarge { error: (), limit });
+    }
+    String::from_utf8(bytes).map_err(|_| BookrabError::NotUnicode {
+        error: (),
+        what: file.to_string(),
+    })
+}
+
+/// Uploads every .txt file of a zip archive (other files are skipped).
+/// Folders are ignored, so the title is the name of the file and files
+/// with the same name in different folders fail after the first one.
+fn upload_zip(
+    book_dir: &mut RootBookDir,
+    archive_name: &str,
+    file: &mut TempFile,
+    tags: &HashSet<String>,
+) -> Vec<UploadReport> {
+    let limit = book_dir.config().max_upload_bytes();
+    let archive_error = |err: io::Error| {
+        vec![UploadReport::new(
+            archive_name.to_string(),
+            Err(BookrabError::CouldntReadFile {
+                error: (),
+                path: PathBuf::from(archive_name),
+                err,
+            }),
+        )]
+    };
+    let mut archive = match ZipArchive::new(file.file.as_file_mut()) {
+        Ok(v) => v,
+        Err(e) => return archive_error(e.into()),
+    };
+    let mut reports = vec![];
+    let mut titles = HashSet::new();
+    for i in 0..archive.len() {
+        let entry = match archive.by_index(i) {
+            Ok(v) => v,
+            Err(e) => return archive_error(e.into()),
+        };
+        if entry.is_dir() || !entry.name().to_lowercase().ends_with(".txt") {
+            continue;
+        }
+        let entry_name = entry.name().to_string();
+        let file = format!("{archive_name}/{entry_name}");
+        let title = Path::new(&entry_name)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+        let result = if titles.insert(title.clone()) {
+            read_entry(entry, &file, limit)
+                .and_then(|txt| store_book(book_dir, &title, &txt, tags.clone(), None))
+                .map(|_| title)
+        } else {
+            Err(BookrabError::BookAlreadyExists { error: (), title })
+        };
+        reports.push(UploadReport::new(file, result));
+    }
+    reports
+}
+
+/// Uploads many books at once. Every file becomes a book (in the formats of
+/// `/v1/books/upload`), as well as every .txt file inside of .zip archives.
+/// Files in archives are limited by `max_upload_bytes` one by one. A file
+/// that can't be uploaded doesn't stop the others: the response says what
+/// happened to each one.
+#[utoipa::path(
+    request_body(content_type = "multipart/form-data", content = BulkBookForm),
+    responses (
+        (status = 200, body = [UploadReportUtoipa]),
+        (status = 413, body = Bookrab413),
+        (status = 500, body = Bookrab500),
+    )
+)]
+#[post("/upload/bulk")]
+pub async fn bulk_upload(
+    UserConfig(config): UserConfig,
+    MultipartForm(form): MultipartForm<BulkBookForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    // the uploads block, so they don't run in the async workers
+    let uploaded = web::block(move || {
+        let mut book_dir = db.root(config);
+        let tags: HashSet<String> = form.tags.iter().cloned().collect();
+        let mut reports = vec![];
+        for mut file in form.books {
+            let file_name = file.file_name.clone().unwrap_or_default();
+            if is_zip(&file) {
+                reports.extend(upload_zip(&mut book_dir, &file_name, &mut file, &tags));
+                continue;
+            }
+            let result = read_book(&mut file).and_then(|(title, txt, _)| {
+                store_book(&mut book_dir, &title, &txt, tags.clone(), None).map(|_| title)
+            });
+            reports.push(UploadReport::new(file_name, result));
+        }
+        reports
+    })
+    .await;
+    match uploaded {
+        Ok(reports) => HttpResponse::Ok().json(reports),
+        Err(e) => HttpResponse::from_error(e),
+    }
+}
@@ -0,0 +1,44 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab422, Bookrab500},
+    webhooks::{notify, WebhookEvent},
+};
+use actix_web::{delete, web, HttpResponse};
+use bookrab_core::books::validation::check_title;
+
+/// Deletes a book: its text, its tags and its catalog entry.
+/// Its search history is kept. Titles that aren't valid folder
+/// names (`..` for example) are answered with 422.
+#[utoipa::path(
+    params(("title" = String, Path, description = "Title of the book")),
+    responses (
+        (status = 204),
+        (status = 400, body=Bookrab400),
+        (status = 422, body=Bookrab422),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[delete("/{title}")]
+pub async fn delete(
+    UserConfig(config): UserConfig,
+    title: web::Path<String>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    if let Err(e) = check_title("title", &title) {
+        return ApiError(e).into();
+    }
+    let mut book_dir = db.root(config);
+    match book_dir.delete(&title) {
+        Ok(_) => {
+            notify(
+                book_dir.config(),
+                WebhookEvent::BookDeleted {
+                    title: title.into_inner(),
+                },
+            );
+            HttpResponse::NoContent().finish()
+        }
+        Err(e) => ApiError(e).into(),
+    }
+}
@@ -0,0 +1,79 @@
+use std::io::{self, Read};
+
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab422, Bookrab500},
+};
+use actix_web::{
+    get,
+    http::header::{Charset, ContentDisposition, DispositionParam, ExtendedValue},
+    web::{self, Bytes},
+    HttpResponse,
+};
+use futures::stream;
+
+/// Size of the chunks sent to the client.
+const CHUNK_SIZE: usize = 64 * 1024;
+
+/// Name of the downloaded file.
+fn content_disposition(title: &str) -> ContentDisposition {
+    let filename = if title.ends_with(".txt") {
+        title.to_string()
+    } else {
+        format!("{title}.txt")
+    };
+    let mut disposition = ContentDisposition::attachment(filename.clone());
+    if !filename.is_ascii() {
+        disposition
+            .parameters
+            .push(DispositionParam::FilenameExt(ExtendedValue {
+                charset: Charset::Ext(String::from("UTF-8")),
+                language_tag: None,
+                value: filename.into_bytes(),
+            }));
+    }
+    disposition
+}
+
+/// Downloads the text of a book. The file is sent in chunks,
+/// so it is never loaded into memory at once.
+#[utoipa::path(
+    params(("title" = String, Path, description = "Title of the book")),
+    responses (
+        (status = 200, content_type = "text/plain", body = String),
+        (status = 400, body = Bookrab400),
+        (status = 422, body = Bookrab422),
+        (status = 500, body = Bookrab500),
+    )
+)]
+#[get("/{title}/download")]
+pub async fn download(
+    UserConfig(config): UserConfig,
+    title: web::Path<String>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let reader = match db.root(config).reader(&title) {
+        Ok(v) => v,
+        Err(e) => return ApiError(e).into(),
+    };
+    let chunks = stream::try_unfold(reader, |mut reader| async move {
+        // reads block, so they don't run in the async workers
+        let (reader, chunk) = web::block(move || {
+            let mut chunk = vec![0; CHUNK_SIZE];
+            let read = reader.read(&mut chunk)?;
+            chunk.truncate(read);
+            Ok::<_, io::Error>((reader, chunk))
+        })
+        .await
+        .map_err(io::Error::other)??;
+        if chunk.is_empty() {
+            return Ok(None);
+        }
+        Ok::<_, io::Error>(Some((Bytes::from(chunk), reader)))
+    });
+    HttpResponse::Ok()
+        .content_type("text/plain; charset=utf-8")
+        .insert_header(content_disposition(&title))
+        .streaming(chunks)
+}
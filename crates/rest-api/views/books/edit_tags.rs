@@ -0,0 +1,49 @@
+use std::collections::HashSet;
+
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab422, Bookrab500},
+};
+use actix_web::{patch, web, HttpResponse};
+use bookrab_core::books::TagEdit;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::BookListElementUtoipa;
+
+/// Mirror of [bookrab_core::books::TagEdit] for the docs.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, ToSchema)]
+struct TagEditUtoipa {
+    /// Tags to be added
+    add: Option<HashSet<String>>,
+    /// Tags to be removed
+    remove: Option<HashSet<String>>,
+    /// Replaces the tags before `add` and `remove` are applied
+    set: Option<HashSet<String>>,
+}
+
+/// Edits the tags of a book without uploading it again.
+#[utoipa::path(
+    params(("title" = String, Path, description = "Title of the book")),
+    request_body = TagEditUtoipa,
+    responses (
+        (status = 200, body=BookListElementUtoipa),
+        (status = 400, body=Bookrab400),
+        (status = 422, body=Bookrab422),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[patch("/{title}/tags")]
+pub async fn edit_tags(
+    UserConfig(config): UserConfig,
+    title: web::Path<String>,
+    edit: web::Json<TagEdit>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    match db.root(config).edit_tags(&title, &edit) {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => ApiError(e).into(),
+    }
+}
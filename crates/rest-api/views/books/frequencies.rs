@@ -0,0 +1,91 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab500},
+};
+use actix_web::{get, web, HttpResponse};
+use bookrab_core::books::{Exclude, FilterMode, Include};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use super::FilterModeUtoipa;
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct WordFrequencyUtoipa {
+    word: String,
+    count: usize,
+}
+
+/// Selects the books whose words are going to be counted.
+#[derive(Debug, Deserialize)]
+struct FrequenciesForm {
+    title: Option<String>,
+    include_tags: Option<Vec<String>>,
+    include_mode: Option<FilterMode>,
+    exclude_tags: Option<Vec<String>>,
+    exclude_mode: Option<FilterMode>,
+    top: Option<usize>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct FrequenciesFormUtoipa {
+    exclude_mode: Option<FilterModeUtoipa>,
+    exclude_tags: Option<Vec<String>>,
+    include_mode: Option<FilterModeUtoipa>,
+    include_tags: Option<Vec<String>>,
+    /// Only count words of this book
+    title: Option<String>,
+    /// Number of words in the response (default: 100)
+    top: Option<usize>,
+}
+
+/// Lists the most frequent words of books filtered by tags.
+#[utoipa::path(
+    params(FrequenciesFormUtoipa),
+    responses (
+        (status = 200, body=[WordFrequencyUtoipa]),
+        (status = 400, body=Bookrab400),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[get("/frequencies")]
+pub async fn frequencies(
+    UserConfig(config): UserConfig,
+    form: web::Query<FrequenciesForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let mut root = db.root(config);
+    let include = Include {
+        mode: form.include_mode.clone().unwrap_or_default(),
+        tags: form
+            .include_tags
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .collect(),
+    };
+    let exclude = Exclude {
+        mode: form.exclude_mode.clone().unwrap_or_default(),
+        tags: form
+            .exclude_tags
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .collect(),
+    };
+    let frequencies = match root.word_frequencies_filtered(
+        |book| {
+            include.matches(book)
+                && !exclude.matches(book)
+                && form.title.as_ref().is_none_or(|title| &book.title == title)
+        },
+        form.top.unwrap_or(100),
+    ) {
+        Ok(v) => v,
+        Err(e) => return ApiError(e).into(),
+    };
+    HttpResponse::Ok().json(frequencies)
+}
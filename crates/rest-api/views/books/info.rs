@@ -0,0 +1,62 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab500},
+};
+use actix_web::{
+    head,
+    http::header::{self, EntityTag},
+    web, HttpRequest, HttpResponse,
+};
+
+use super::not_modified;
+
+/// Size of the text of the book (in bytes).
+const SIZE_HEADER: &str = "x-book-size";
+/// Number of tags of the book.
+const TAG_COUNT_HEADER: &str = "x-book-tag-count";
+/// SHA-256 of the text of the book (in hexadecimal).
+const CHECKSUM_HEADER: &str = "x-book-checksum";
+
+/// Tells whether a book exists without sending its text.
+/// The size, the number of tags and the checksum of the book are
+/// in the headers, so sync tools can decide whether to upload it again.
+/// The `ETag` is the same as the one of `GET /v1/books/{title}`.
+#[utoipa::path(
+    params(("title" = String, Path, description = "Title of the book")),
+    responses (
+        (status = 204, description = "The book exists", headers(
+            ("x-book-size" = u64, description = "Size of the text (in bytes)"),
+            ("x-book-tag-count" = usize, description = "Number of tags"),
+            ("x-book-checksum" = String, description = "SHA-256 of the text (in hexadecimal)"),
+        )),
+        (status = 304, description = "The text matches the `If-None-Match` header"),
+        (status = 404, description = "There is no such book"),
+        (status = 500, body = Bookrab500),
+    )
+)]
+#[head("/{title}")]
+pub async fn info(
+    UserConfig(config): UserConfig,
+    req: HttpRequest,
+    title: web::Path<String>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let info = match db.root(config).book_info(&title) {
+        Ok(Some(v)) => v,
+        Ok(None) => return HttpResponse::NotFound().finish(),
+        Err(e) => return ApiError(e).into(),
+    };
+    let etag = EntityTag::new_strong(info.checksum.clone());
+    let mut response = if not_modified(&req, &etag) {
+        HttpResponse::NotModified()
+    } else {
+        HttpResponse::NoContent()
+    };
+    response
+        .insert_header(header::ETag(etag))
+        .insert_header((SIZE_HEADER, info.size.to_string()))
+        .insert_header((TAG_COUNT_HEADER, info.tags.len().to_string()))
+        .insert_header((CHECKSUM_HEADER, info.checksum))
+        .finish()
+}
@@ -1,25 +1,90 @@
 use crate::{
-    config::ensure_confy_works,
-    database::DB,
-    errors::{ApiError, Bookrab400},
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab500},
 };
-use actix_web::{get, HttpResponse, Responder};
-use bookrab_core::{books::RootBookDir, config::BookrabConfig, database::PgPooledConnection};
+use actix_web::{
+    get,
+    http::header::{self, EntityTag},
+    web, HttpRequest, HttpResponse, Responder,
+};
+use bookrab_core::books::{language::Language, Page, RootBookDir};
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use super::{not_modified, BookListElementUtoipa, LanguageUtoipa};
+
+/// Represents parameters that filter the listing.
+#[derive(Debug, Deserialize)]
+pub struct ListForm {
+    language: Option<Language>,
+    limit: Option<usize>,
+    offset: Option<usize>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct ListFormUtoipa {
+    language: Option<LanguageUtoipa>,
+    /// Number of books per page (default: 100)
+    limit: Option<usize>,
+    /// Number of books to skip (default: 0)
+    offset: Option<usize>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct BookListPageUtoipa {
+    items: Vec<BookListElementUtoipa>,
+    /// Number of books in all pages
+    total: usize,
+    /// Pass it as `offset` to get the next page (null if this is the last page)
+    next: Option<usize>,
+}
 
-/// Lists all books with their metadata.
-#[utoipa::path(responses((status = 404, body = Bookrab400)))]
+/// Lists books with their metadata, one page at a time.
+/// The `ETag` changes whenever a book is uploaded or its tags change,
+/// so clients can send `If-None-Match` when polling.
+#[utoipa::path(
+    params(ListFormUtoipa),
+    responses (
+        (status = 200, body = BookListPageUtoipa),
+        (status = 304, description = "The library matches the `If-None-Match` header"),
+        (status = 400, body = Bookrab400),
+        (status = 500, body = Bookrab500),
+    )
+)]
 #[get("/list")]
-pub async fn list(db: DB) -> impl Responder {
-    _list(ensure_confy_works(), db.connection)
+pub async fn list(
+    req: HttpRequest,
+    UserConfig(config): UserConfig,
+    form: web::Query<ListForm>,
+    mut db: OptionalDB,
+) -> impl Responder {
+    let mut book_dir = db.root(config);
+    let etag = match book_dir.version() {
+        Ok(v) => EntityTag::new_strong(v),
+        Err(e) => return ApiError(e).into(),
+    };
+    if not_modified(&req, &etag) {
+        return HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .finish();
+    }
+    _list(book_dir, &form, etag)
 }
 
-pub fn _list(config: BookrabConfig, mut connection: PgPooledConnection) -> HttpResponse {
-    let book_dir = RootBookDir::new(config, &mut connection);
-    let listing = match book_dir.list() {
+pub fn _list(mut book_dir: RootBookDir, form: &ListForm, etag: EntityTag) -> HttpResponse {
+    let listing = match book_dir
+        .list_filtered(|book| form.language.is_none() || book.language == form.language)
+    {
         Ok(v) => v,
         Err(e) => return ApiError(e).into(),
     };
+    let page = Page::new(listing, form.offset.unwrap_or(0), form.limit.unwrap_or(100));
     HttpResponse::Ok()
         .content_type("application/json")
-        .body(serde_json::to_string(&listing).unwrap())
+        .insert_header(header::ETag(etag))
+        .body(serde_json::to_string(&page).unwrap())
 }
@@ -1,13 +1,94 @@
+pub mod book_search;
+pub mod bulk_upload;
+pub mod delete;
+pub mod download;
+pub mod edit_tags;
+pub mod frequencies;
+pub mod info;
 pub mod list;
+pub mod ngrams;
+pub mod rename;
 pub mod search;
+pub mod search_formats;
+pub mod search_post;
+pub mod search_sse;
+pub mod tags;
+pub mod text;
 pub mod upload;
+use std::collections::HashSet;
+
+use actix_web::{
+    http::header::{EntityTag, IfNoneMatch},
+    HttpMessage, HttpRequest,
+};
+use serde::Deserialize;
+use utoipa::ToSchema;
 use utoipa_actix_web::service_config::ServiceConfig;
 
+/// Mirror of [bookrab_core::books::FilterMode] for the docs.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) enum FilterModeUtoipa {
+    All,
+    Any,
+}
+
+/// Mirror of [bookrab_core::books::language::Language] for the docs.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) enum LanguageUtoipa {
+    #[serde(rename = "pt")]
+    Portuguese,
+    #[serde(rename = "en")]
+    English,
+    #[serde(rename = "es")]
+    Spanish,
+    #[serde(rename = "fr")]
+    French,
+    #[serde(rename = "it")]
+    Italian,
+    #[serde(rename = "de")]
+    German,
+}
+
+/// Mirror of [bookrab_core::books::BookListElement] for the docs.
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct BookListElementUtoipa {
+    title: String,
+    tags: HashSet<String>,
+    language: Option<LanguageUtoipa>,
+}
+
+/// Whether the `If-None-Match` header of `req` matches `etag`,
+/// i.e. the client already has the response.
+pub(crate) fn not_modified(req: &HttpRequest, etag: &EntityTag) -> bool {
+    match req.get_header::<IfNoneMatch>() {
+        Some(IfNoneMatch::Any) => true,
+        Some(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(etag)),
+        None => false,
+    }
+}
+
 pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
     |config: &mut ServiceConfig| {
         config
             .service(upload::upload)
+            .service(bulk_upload::bulk_upload)
             .service(list::list)
-            .service(search::search);
+            .service(search::search)
+            .service(book_search::book_search)
+            .service(search_post::search_post)
+            .service(search_sse::search_sse)
+            .service(frequencies::frequencies)
+            .service(ngrams::ngrams)
+            .service(tags::tags)
+            .service(download::download)
+            .service(edit_tags::edit_tags)
+            .service(info::info)
+            .service(rename::rename)
+            .service(delete::delete)
+            // must come last, otherwise it matches the routes above
+            .service(text::text);
     }
 }
@@ -0,0 +1,98 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab500},
+};
+use actix_web::{get, web, HttpResponse};
+use bookrab_core::books::{Exclude, FilterMode, Include};
+use futures::stream;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use super::FilterModeUtoipa;
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct NGramFrequencyUtoipa {
+    ngram: String,
+    count: usize,
+}
+
+/// Selects the books whose n-grams are going to be counted.
+#[derive(Debug, Deserialize)]
+struct NGramsForm {
+    n: Option<usize>,
+    include_tags: Option<Vec<String>>,
+    include_mode: Option<FilterMode>,
+    exclude_tags: Option<Vec<String>>,
+    exclude_mode: Option<FilterMode>,
+    top: Option<usize>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct NGramsFormUtoipa {
+    exclude_mode: Option<FilterModeUtoipa>,
+    exclude_tags: Option<Vec<String>>,
+    include_mode: Option<FilterModeUtoipa>,
+    include_tags: Option<Vec<String>>,
+    /// Number of words in each n-gram (default: 2)
+    n: Option<usize>,
+    /// Number of n-grams in the response (default: 100)
+    top: Option<usize>,
+}
+
+/// Lists the most frequent n-grams of books filtered by tags.
+/// The response is streamed as newline-delimited JSON
+/// (one n-gram per line).
+#[utoipa::path(
+    params(NGramsFormUtoipa),
+    responses (
+        (status = 200, body=[NGramFrequencyUtoipa], content_type = "application/x-ndjson"),
+        (status = 400, body=Bookrab400),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[get("/ngrams")]
+pub async fn ngrams(
+    UserConfig(config): UserConfig,
+    form: web::Query<NGramsForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let mut root = db.root(config);
+    let include = Include {
+        mode: form.include_mode.clone().unwrap_or_default(),
+        tags: form
+            .include_tags
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .collect(),
+    };
+    let exclude = Exclude {
+        mode: form.exclude_mode.clone().unwrap_or_default(),
+        tags: form
+            .exclude_tags
+            .clone()
+            .unwrap_or_default()
+            .into_iter()
+            .collect(),
+    };
+    let frequencies = match root.ngram_frequencies(
+        &include,
+        &exclude,
+        form.n.unwrap_or(2),
+        form.top.unwrap_or(100),
+    ) {
+        Ok(v) => v,
+        Err(e) => return ApiError(e).into(),
+    };
+    let lines = frequencies.into_iter().map(|frequency| {
+        let line = serde_json::to_string(&frequency).unwrap() + "\n";
+        Ok::<_, actix_web::Error>(web::Bytes::from(line))
+    });
+    HttpResponse::Ok()
+        .content_type("application/x-ndjson")
+        .streaming(stream::iter(lines))
+}
@@ -0,0 +1,54 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab409, Bookrab422, Bookrab500},
+    webhooks::{notify, WebhookEvent},
+};
+use actix_web::{post, web, HttpResponse};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::BookListElementUtoipa;
+
+/// New title of a book.
+#[derive(Debug, Deserialize, ToSchema)]
+struct RenameForm {
+    new_title: String,
+}
+
+/// Changes the title of a book. Its catalog entry and its
+/// search history go along with it. Titles that aren't valid folder
+/// names (empty, `..`, with slashes...) are answered with 422.
+#[utoipa::path(
+    params(("title" = String, Path, description = "Title of the book")),
+    request_body = RenameForm,
+    responses (
+        (status = 200, body=BookListElementUtoipa),
+        (status = 400, body=Bookrab400),
+        (status = 409, body=Bookrab409),
+        (status = 422, body=Bookrab422),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[post("/{title}/rename")]
+pub async fn rename(
+    UserConfig(config): UserConfig,
+    title: web::Path<String>,
+    form: web::Json<RenameForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let mut book_dir = db.root(config);
+    match book_dir.rename(&title, &form.new_title) {
+        Ok(v) => {
+            notify(
+                book_dir.config(),
+                WebhookEvent::BookRenamed {
+                    title: title.into_inner(),
+                    new_title: v.title.clone(),
+                },
+            );
+            HttpResponse::Ok().json(v)
+        }
+        Err(e) => ApiError(e).into(),
+    }
+}
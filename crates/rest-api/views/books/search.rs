@@ -1,107 +1,388 @@
 use crate::{
-    config::ensure_confy_works,
-    database::DB,
-    errors::{ApiError, Bookrab400, Bookrab500},
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab422, Bookrab500},
+    shutdown::{is_shutting_down, SearchGuard},
+    webhooks::{notify, WebhookEvent},
 };
-use actix_web::{get, http::StatusCode, web, HttpResponse, HttpResponseBuilder};
-use bookrab_core::books::{Exclude, FilterMode, Include, RootBookDir};
+use actix_web::{
+    get,
+    http::StatusCode,
+    web::{self, Bytes},
+    HttpRequest, HttpResponse, HttpResponseBuilder,
+};
+use bookrab_core::{
+    books::{
+        history::HistoryContext, language::Language, validation::pattern_errors, Exclude,
+        FilterMode, Include, Page, ResultFormat, RootBookDir, SearchEvent, SearchOptions,
+        SearchResults,
+    },
+    config::BookrabConfig,
+    errors::BookrabError,
+};
+use futures::{channel::mpsc, Stream, StreamExt};
 use grep_regex::RegexMatcherBuilder;
-use grep_searcher::SearcherBuilder;
-use serde::Deserialize;
+use grep_searcher::{Searcher, SearcherBuilder};
+use serde::{Deserialize, Serialize};
 use utoipa::{IntoParams, ToSchema};
 
+use super::{
+    search_formats::{to_csv, to_text, SearchFormat},
+    FilterModeUtoipa, LanguageUtoipa,
+};
+
+#[allow(dead_code)]
 #[derive(Debug, Deserialize, ToSchema)]
-struct SearchResultsUtoipa {
+pub(crate) struct SearchResultsUtoipa {
     title: String,
     results: Vec<String>,
+    score: f64,
+    duration_ms: u64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct TruncatedSearchResultsUtoipa {
+    title: String,
+    results: Vec<String>,
+    score: f64,
+    duration_ms: u64,
+    /// Whether `results` was cut by `max_matches_per_book`
+    truncated: bool,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct SearchResultsPageUtoipa {
+    items: Vec<TruncatedSearchResultsUtoipa>,
+    /// Number of books in all pages
+    total: usize,
+    /// Pass it as `offset` to get the next page (null if this is the last page)
+    next: Option<usize>,
+}
+
+/// Results of a book with at most `max_matches_per_book` entries.
+#[derive(Debug, Serialize)]
+pub(crate) struct TruncatedSearchResults {
+    #[serde(flatten)]
+    pub(crate) results: SearchResults,
+    pub(crate) truncated: bool,
+}
+
+impl TruncatedSearchResults {
+    pub(crate) fn new(mut results: SearchResults, max_matches: Option<usize>) -> Self {
+        let max_matches = max_matches.unwrap_or(usize::MAX);
+        let truncated = results.results.len() > max_matches;
+        results.results.truncate(max_matches);
+        TruncatedSearchResults { results, truncated }
+    }
+}
+
+/// Format of the search results.
+/// `kwic` stands for "keyword in context" (a concordance).
+#[derive(Clone, Debug, Default, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum ResultFormatForm {
+    #[default]
+    Lines,
+    Kwic,
 }
 
 /// Represents parameters that determine the way
 /// a search is made.
 #[derive(Debug, Deserialize)]
-struct SearchForm {
-    pattern: String,
-    after_context: Option<usize>,
-    before_context: Option<usize>,
-    case_insensitive: Option<bool>,
-    case_smart: Option<bool>,
-    include_tags: Option<Vec<String>>,
-    include_mode: Option<FilterMode>,
-    exclude_tags: Option<Vec<String>>,
-    exclude_mode: Option<FilterMode>,
-}
-
-#[derive(Debug, Deserialize, ToSchema)]
-enum FilterModeUtoipa {
-    All,
-    Any,
+pub(crate) struct SearchForm {
+    pub(crate) pattern: String,
+    pub(crate) after_context: Option<usize>,
+    pub(crate) before_context: Option<usize>,
+    pub(crate) case_insensitive: Option<bool>,
+    pub(crate) case_smart: Option<bool>,
+    pub(crate) ignore_accents: Option<bool>,
+    pub(crate) include_tags: Option<Vec<String>>,
+    pub(crate) include_mode: Option<FilterMode>,
+    pub(crate) exclude_tags: Option<Vec<String>>,
+    pub(crate) exclude_mode: Option<FilterMode>,
+    pub(crate) language: Option<Language>,
+    pub(crate) format: Option<ResultFormatForm>,
+    pub(crate) kwic_width: Option<usize>,
+    pub(crate) limit: Option<usize>,
+    pub(crate) offset: Option<usize>,
+    pub(crate) max_matches_per_book: Option<usize>,
 }
 
+#[allow(dead_code)]
 #[derive(Debug, Deserialize, IntoParams)]
 #[into_params(parameter_in = Query)]
-struct SearchFormUtoipa {
+pub(crate) struct SearchFormUtoipa {
     after_context: Option<usize>,
     before_context: Option<usize>,
     case_insensitive: Option<bool>,
     case_smart: Option<bool>,
     exclude_mode: Option<FilterModeUtoipa>,
     exclude_tags: Option<Vec<String>>,
+    /// `lines` (default) or `kwic`
+    format: Option<ResultFormatForm>,
+    ignore_accents: Option<bool>,
     include_mode: Option<FilterModeUtoipa>,
     include_tags: Option<Vec<String>>,
+    /// Characters on each side of a match in the `kwic` format (default: 40)
+    kwic_width: Option<usize>,
+    language: Option<LanguageUtoipa>,
+    /// Number of books per page (default: 100)
+    limit: Option<usize>,
+    /// Maximum number of results of each book (default: no limit)
+    max_matches_per_book: Option<usize>,
+    /// Number of books to skip (default: 0)
+    offset: Option<usize>,
     pattern: String,
 }
 
-/// Searches books filtered by tags.
+/// Filters and options of a search, built from a [SearchForm].
+pub(crate) struct SearchParams {
+    include: Include,
+    exclude: Exclude,
+    language: Option<Language>,
+    pattern: String,
+    searcher: Searcher,
+    matcher_builder: RegexMatcherBuilder,
+    options: SearchOptions,
+}
+
+impl SearchParams {
+    /// Fails with [BookrabError::InvalidFields] when the pattern is empty or invalid.
+    pub(crate) fn new(form: &SearchForm) -> Result<Self, BookrabError> {
+        let searcher = SearcherBuilder::new()
+            .after_context(form.after_context.unwrap_or_default())
+            .before_context(form.before_context.unwrap_or_default())
+            .build();
+        let mut matcher_builder = RegexMatcherBuilder::new();
+        matcher_builder
+            .case_insensitive(form.case_insensitive.unwrap_or(false))
+            .case_smart(form.case_smart.unwrap_or(false));
+        let errors = pattern_errors(&form.pattern, &matcher_builder);
+        if !errors.is_empty() {
+            return Err(BookrabError::InvalidFields { error: (), errors });
+        }
+        //TODO: maybe there is a way to remove those .clone()'s?
+        let include = Include {
+            mode: form.include_mode.clone().unwrap_or_default(),
+            tags: form
+                .include_tags
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+        };
+        let exclude = Exclude {
+            mode: form.exclude_mode.clone().unwrap_or_default(),
+            tags: form
+                .exclude_tags
+                .clone()
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+        };
+        let options = SearchOptions {
+            ignore_accents: form.ignore_accents.unwrap_or(false),
+            format: match form.format.clone().unwrap_or_default() {
+                ResultFormatForm::Lines => ResultFormat::Lines,
+                ResultFormatForm::Kwic => ResultFormat::Concordance {
+                    width: form.kwic_width.unwrap_or(40),
+                },
+            },
+            history: HistoryContext {
+                include_mode: include.mode.clone(),
+                include_tags: include.tags.clone(),
+                exclude_mode: exclude.mode.clone(),
+                exclude_tags: exclude.tags.clone(),
+                case_insensitive: form.case_insensitive.unwrap_or(false),
+                case_smart: form.case_smart.unwrap_or(false),
+                ..Default::default()
+            },
+            skip_history: false,
+        };
+        Ok(SearchParams {
+            include,
+            exclude,
+            language: form.language,
+            pattern: form.pattern.clone(),
+            searcher,
+            matcher_builder,
+            options,
+        })
+    }
+
+    /// Searches the books that match the filters and calls the webhooks
+    /// (unless the history is skipped, see [SearchOptions::skip_history]).
+    /// The search stops before the next book when the server is shutting down.
+    /// See [RootBookDir::search_filtered_streaming].
+    pub(crate) fn search(
+        &self,
+        root: &mut RootBookDir,
+        mut on_event: impl FnMut(SearchEvent) -> bool,
+    ) -> Result<Vec<SearchResults>, BookrabError> {
+        let _guard = SearchGuard::new();
+        let results = root.search_filtered_streaming(
+            |book| {
+                self.include.matches(book)
+                    && !self.exclude.matches(book)
+                    && (self.language.is_none() || book.language == self.language)
+            },
+            self.pattern.clone(),
+            self.searcher.clone(),
+            self.matcher_builder.clone(),
+            &self.options,
+            |event| !is_shutting_down() && on_event(event),
+        )?;
+        if !self.options.skip_history {
+            notify(
+                root.config(),
+                WebhookEvent::searched(&self.pattern, &results),
+            );
+        }
+        Ok(results)
+    }
+
+    /// Searches a single book (ignoring the filters) and calls the webhooks.
+    /// See [RootBookDir::search].
+    pub(crate) fn search_book(
+        &self,
+        root: &mut RootBookDir,
+        title: &str,
+    ) -> Result<SearchResults, BookrabError> {
+        let _guard = SearchGuard::new();
+        let results = root.search(
+            title.to_string(),
+            self.pattern.clone(),
+            self.searcher.clone(),
+            self.matcher_builder.clone(),
+            &self.options,
+        )?;
+        notify(
+            root.config(),
+            WebhookEvent::searched(&self.pattern, std::slice::from_ref(&results)),
+        );
+        Ok(results)
+    }
+}
+
+/// Runs the search over the books of `config` in a blocking thread and streams what `to_message`
+/// makes of each event. When the search ends, `to_message` is called
+/// one last time with its result (and no event).
+/// The search stops when the client goes away.
+pub(crate) fn stream_search<T: Send + 'static>(
+    params: SearchParams,
+    config: BookrabConfig,
+    mut db: OptionalDB,
+    mut to_message: impl FnMut(Option<SearchEvent>, Option<Result<Vec<SearchResults>, BookrabError>>) -> Option<T>
+        + Send
+        + 'static,
+) -> impl Stream<Item = T> {
+    let (sender, receiver) = mpsc::unbounded::<T>();
+    // the search blocks, so it doesn't run in the async workers
+    actix_web::rt::spawn(web::block(move || {
+        let mut root = db.root(config);
+        let searched = params.search(&mut root, |event| match to_message(Some(event), None) {
+            Some(message) => sender.unbounded_send(message).is_ok(),
+            None => !sender.is_closed(),
+        });
+        if let Some(message) = to_message(None, Some(searched)) {
+            let _ = sender.unbounded_send(message);
+        }
+    }));
+    receiver
+}
+
+/// JSON of `value` followed by a line break.
+fn ndjson_line(value: &impl Serialize) -> Bytes {
+    let mut line = serde_json::to_vec(value).unwrap();
+    line.push(b'\n');
+    Bytes::from(line)
+}
+
+/// Searches books filtered by tags, one page of books at a time.
+/// Books are ordered by relevance, so every page searches every book. Only
+/// the first page (`offset` 0) is stored in the history and sent to the webhooks.
+///
+/// With `Accept: application/x-ndjson`, the results of each book are sent
+/// (one JSON object per line) as soon as the book is searched, in no
+/// particular order and without pagination. If the search fails midway,
+/// the last line is the error.
+///
+/// With `Accept: text/csv`, the page is sent as CSV with one row per match
+/// (`title,line,match`). With `Accept: text/plain`, it is sent like the
+/// output of `grep -H` (every line is prefixed with the title of the book).
 #[utoipa::path(
     params(SearchFormUtoipa),
     responses (
-        (status = 200, body=[SearchResultsUtoipa]),
+        (status = 200, content(
+            (SearchResultsPageUtoipa = "application/json"),
+            (TruncatedSearchResultsUtoipa = "application/x-ndjson"),
+            (String = "text/csv"),
+            (String = "text/plain"),
+        )),
         (status = 400, body=Bookrab400),
+        (status = 422, body=Bookrab422),
         (status = 500, body=Bookrab500),
     )
 )]
 #[get("/search")]
-pub async fn search(form: web::Query<SearchForm>, mut db: DB) -> HttpResponse {
-    let config = ensure_confy_works();
-    let searcher = SearcherBuilder::new()
-        .after_context(form.after_context.unwrap_or_default())
-        .before_context(form.before_context.unwrap_or_default())
-        .build();
-    let mut builder = RegexMatcherBuilder::new();
-    let matcher_builder = builder
-        .case_insensitive(form.case_insensitive.unwrap_or(false))
-        .case_smart(form.case_smart.unwrap_or(false));
-    let mut root = RootBookDir::new(config, &mut db.connection);
-    //TODO: maybe there is a way to remove those .clone()'s?
-    let include = Include {
-        mode: form.include_mode.clone().unwrap_or_default(),
-        tags: form
-            .include_tags
-            .clone()
-            .unwrap_or_default()
-            .into_iter()
-            .collect(),
-    };
-    let exclude = Exclude {
-        mode: form.exclude_mode.clone().unwrap_or_default(),
-        tags: form
-            .exclude_tags
-            .clone()
-            .unwrap_or_default()
-            .into_iter()
-            .collect(),
+pub async fn search(
+    req: HttpRequest,
+    UserConfig(config): UserConfig,
+    form: web::Query<SearchForm>,
+    db: OptionalDB,
+) -> HttpResponse {
+    search_response(&req, config, &form, db)
+}
+
+/// Searches according to `form` and responds in the format
+/// accepted by the client (see [search]).
+pub(crate) fn search_response(
+    req: &HttpRequest,
+    config: BookrabConfig,
+    form: &SearchForm,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let mut params = match SearchParams::new(form) {
+        Ok(v) => v,
+        Err(e) => return ApiError(e).into(),
     };
-    let search_results = match root.search_by_tags(
-        &include,
-        &exclude,
-        form.pattern.clone(),
-        searcher,
-        matcher_builder.clone(),
-    ) {
+    let max_matches = form.max_matches_per_book;
+    let format = SearchFormat::of(req);
+    if format == SearchFormat::Ndjson {
+        let lines = stream_search(params, config, db, move |event, finished| {
+            match (event, finished) {
+                (Some(SearchEvent::Finished(results)), _) => Some(ndjson_line(
+                    &TruncatedSearchResults::new(results.clone(), max_matches),
+                )),
+                (_, Some(Err(e))) => Some(ndjson_line(&ApiError(e))),
+                _ => None,
+            }
+        });
+        return HttpResponse::Ok()
+            .content_type(format.content_type())
+            .streaming(lines.map(Ok::<_, actix_web::Error>));
+    }
+    // every page runs the whole search (books are ordered by relevance,
+    // so all of them are searched), but only the first one is stored
+    let offset = form.offset.unwrap_or(0);
+    params.options.skip_history = offset > 0;
+    let search_results = match params.search(&mut db.root(config), |_| true) {
         Ok(v) => v,
         Err(e) => return ApiError(e).into(),
     };
-    HttpResponseBuilder::new(StatusCode::OK)
-        .content_type("application/json")
-        .json(search_results)
+    let search_results = search_results
+        .into_iter()
+        .map(|results| TruncatedSearchResults::new(results, max_matches))
+        .collect();
+    let page = Page::new(search_results, offset, form.limit.unwrap_or(100));
+    let mut response = HttpResponseBuilder::new(StatusCode::OK);
+    response.content_type(format.content_type());
+    let books = page.items.iter().map(|book| &book.results);
+    match format {
+        SearchFormat::Csv => response.body(to_csv(books)),
+        SearchFormat::Text => response.body(to_text(books)),
+        _ => response.json(page),
+    }
 }
@@ -0,0 +1,111 @@
+use actix_web::{http::header::Accept, HttpMessage, HttpRequest};
+use bookrab_core::books::SearchResults;
+
+const OPENING_TAG: &str = "[matched]";
+const CLOSING_TAG: &str = "[/matched]";
+
+/// Representations of the search results, chosen by the `Accept` header.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum SearchFormat {
+    Json,
+    /// One JSON object per book, streamed as the books are searched
+    Ndjson,
+    /// One row per match: `title,line,match`
+    Csv,
+    /// Lines prefixed with the title, like `grep -H`
+    Text,
+}
+
+impl SearchFormat {
+    /// Format preferred by the client (JSON if nothing else is accepted).
+    pub(crate) fn of(req: &HttpRequest) -> SearchFormat {
+        let Some(accept) = req.get_header::<Accept>() else {
+            return SearchFormat::Json;
+        };
+        accept
+            .ranked()
+            .iter()
+            .find_map(|mime| match mime.essence_str() {
+                "application/json" => Some(SearchFormat::Json),
+                "application/x-ndjson" => Some(SearchFormat::Ndjson),
+                "text/csv" => Some(SearchFormat::Csv),
+                "text/plain" => Some(SearchFormat::Text),
+                _ => None,
+            })
+            .unwrap_or(SearchFormat::Json)
+    }
+
+    pub(crate) fn content_type(self) -> &'static str {
+        match self {
+            SearchFormat::Json => "application/json",
+            SearchFormat::Ndjson => "application/x-ndjson",
+            SearchFormat::Csv => "text/csv; charset=utf-8",
+            SearchFormat::Text => "text/plain; charset=utf-8",
+        }
+    }
+}
+
+/// `result` without the tags that mark the matches.
+fn unmarked(result: &str) -> String {
+    result.replace(OPENING_TAG, "").replace(CLOSING_TAG, "")
+}
+
+/// Texts marked as matches in `result`.
+fn matches(result: &str) -> Vec<&str> {
+    result
+        .split(OPENING_TAG)
+        .skip(1)
+        .filter_map(|part| part.split_once(CLOSING_TAG).map(|(matched, _)| matched))
+        .collect()
+}
+
+/// Quotes `field` when it has commas, quotes or line breaks (RFC 4180).
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_string()
+    }
+}
+
+/// Renders `books` as CSV with a header. Each match is a row with the
+/// title, the result it was found in (context included) and the match.
+pub(crate) fn to_csv<'a>(books: impl IntoIterator<Item = &'a SearchResults>) -> String {
+    let mut csv = String::from("title,line,match\r\n");
+    for book in books {
+        for result in &book.results {
+            let line = unmarked(result);
+            let line = line.trim_end_matches(['\n', '\r']);
+            for matched in matches(result) {
+                csv.push_str(&format!(
+                    "{},{},{}\r\n",
+                    csv_field(&book.title),
+                    csv_field(line),
+                    csv_field(matched)
+                ));
+            }
+        }
+    }
+    csv
+}
+
+/// Renders `books` like `grep -H`: every line is prefixed with the title.
+/// Results with more than one line (i.e. with context) are separated by `--`.
+pub(crate) fn to_text<'a>(books: impl IntoIterator<Item = &'a SearchResults>) -> String {
+    let mut text = String::new();
+    let mut previous_had_context = false;
+    for book in books {
+        for result in &book.results {
+            let result = unmarked(result);
+            let has_context = result.trim_end().contains('\n');
+            if !text.is_empty() && (has_context || previous_had_context) {
+                text.push_str("--\n");
+            }
+            previous_had_context = has_context;
+            for line in result.lines() {
+                text.push_str(&format!("{}:{}\n", book.title, line));
+            }
+        }
+    }
+    text
+}
@@ -0,0 +1,70 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{Bookrab400, Bookrab422, Bookrab500},
+};
+use actix_web::{post, web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+use super::{
+    search::{
+        search_response, ResultFormatForm, SearchForm, SearchResultsPageUtoipa,
+        TruncatedSearchResultsUtoipa,
+    },
+    FilterModeUtoipa, LanguageUtoipa,
+};
+
+/// Body of `POST /v1/books/search`: the same fields as the query of
+/// `GET /v1/books/search`, without the limits of query strings.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, ToSchema)]
+struct SearchRequestUtoipa {
+    pattern: String,
+    after_context: Option<usize>,
+    before_context: Option<usize>,
+    case_insensitive: Option<bool>,
+    case_smart: Option<bool>,
+    ignore_accents: Option<bool>,
+    include_tags: Option<Vec<String>>,
+    include_mode: Option<FilterModeUtoipa>,
+    exclude_tags: Option<Vec<String>>,
+    exclude_mode: Option<FilterModeUtoipa>,
+    language: Option<LanguageUtoipa>,
+    /// `lines` (default) or `kwic`
+    format: Option<ResultFormatForm>,
+    /// Characters on each side of a match in the `kwic` format (default: 40)
+    kwic_width: Option<usize>,
+    /// Number of books per page (default: 100)
+    limit: Option<usize>,
+    /// Number of books to skip (default: 0)
+    offset: Option<usize>,
+    /// Maximum number of results of each book (default: no limit)
+    max_matches_per_book: Option<usize>,
+}
+
+/// Same as `GET /v1/books/search`, but the parameters are sent as JSON,
+/// which is easier with many tags. The response is negotiated the same way.
+#[utoipa::path(
+    request_body = SearchRequestUtoipa,
+    responses (
+        (status = 200, content(
+            (SearchResultsPageUtoipa = "application/json"),
+            (TruncatedSearchResultsUtoipa = "application/x-ndjson"),
+            (String = "text/csv"),
+            (String = "text/plain"),
+        )),
+        (status = 400, body=Bookrab400),
+        (status = 422, body=Bookrab422),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[post("/search")]
+pub async fn search_post(
+    req: HttpRequest,
+    UserConfig(config): UserConfig,
+    form: web::Json<SearchForm>,
+    db: OptionalDB,
+) -> HttpResponse {
+    search_response(&req, config, &form, db)
+}
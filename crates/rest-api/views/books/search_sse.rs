@@ -0,0 +1,114 @@
+use std::time::Instant;
+
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab422},
+};
+use actix_web::{
+    get,
+    http::header::{CacheControl, CacheDirective},
+    web::{self, Bytes},
+    HttpResponse,
+};
+use bookrab_core::books::SearchEvent;
+use futures::StreamExt;
+use serde::Serialize;
+
+use super::search::{
+    stream_search, SearchForm, SearchFormUtoipa, SearchParams, TruncatedSearchResults,
+};
+
+/// Data of the `started` event.
+#[derive(Debug, Serialize)]
+struct Started<'a> {
+    title: &'a str,
+    /// Position of the book (starting at 0)
+    index: usize,
+    /// Number of books that are going to be searched
+    total: usize,
+}
+
+/// Data of the `done` event.
+#[derive(Debug, Serialize)]
+struct Done {
+    /// Number of books searched
+    books: usize,
+    duration_ms: u64,
+}
+
+/// Server-sent event with JSON data.
+fn event(name: &str, data: &impl Serialize) -> Bytes {
+    // JSON without pretty printing never has line breaks
+    Bytes::from(format!(
+        "event: {name}\ndata: {}\n\n",
+        serde_json::to_string(data).unwrap()
+    ))
+}
+
+/// Searches books like `/v1/books/search`, but sends the progress as
+/// server-sent events, so that results can be shown as they come:
+///
+/// - `started`: a book is about to be searched (`{title, index, total}`,
+///   `index` starts at 0)
+/// - `results`: results of a book (same as the items of `/v1/books/search`)
+/// - `done`: every book was searched (`{books, duration_ms}`)
+/// - `error`: the search failed (same as the errors of `/v1/books/search`)
+///
+/// `limit` and `offset` are ignored.
+#[utoipa::path(
+    params(SearchFormUtoipa),
+    responses (
+        (status = 200, content_type = "text/event-stream", body = String),
+        (status = 422, body=Bookrab422),
+    )
+)]
+#[get("/search/sse")]
+pub async fn search_sse(
+    UserConfig(config): UserConfig,
+    form: web::Query<SearchForm>,
+    db: OptionalDB,
+) -> HttpResponse {
+    let params = match SearchParams::new(&form) {
+        Ok(v) => v,
+        Err(e) => return ApiError(e).into(),
+    };
+    let max_matches = form.max_matches_per_book;
+    let start = Instant::now();
+    let events = stream_search(params, config, db, move |search_event, finished| {
+        match (search_event, finished) {
+            (
+                Some(SearchEvent::Started {
+                    title,
+                    index,
+                    total,
+                }),
+                _,
+            ) => Some(event(
+                "started",
+                &Started {
+                    title,
+                    index,
+                    total,
+                },
+            )),
+            (Some(SearchEvent::Finished(results)), _) => Some(event(
+                "results",
+                &TruncatedSearchResults::new(results.clone(), max_matches),
+            )),
+            (None, Some(Ok(results))) => Some(event(
+                "done",
+                &Done {
+                    books: results.len(),
+                    duration_ms: start.elapsed().as_millis() as u64,
+                },
+            )),
+            (None, Some(Err(e))) => Some(event("error", &ApiError(e))),
+            (None, None) => None,
+        }
+    });
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        .streaming(events.map(Ok::<_, actix_web::Error>))
+}
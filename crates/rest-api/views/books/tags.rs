@@ -0,0 +1,54 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab500},
+};
+use actix_web::{
+    get,
+    http::header::{self, EntityTag},
+    HttpRequest, HttpResponse,
+};
+use utoipa::ToSchema;
+
+use super::not_modified;
+
+/// Mirror of [bookrab_core::books::TagCount] for the docs.
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct TagCountUtoipa {
+    tag: String,
+    /// Number of books with this tag
+    count: usize,
+}
+
+/// Lists all tags with the number of books that have them.
+/// The most used tags come first. Supports `If-None-Match`
+/// (see `/v1/books/list`).
+#[utoipa::path(
+    responses (
+        (status = 200, body=[TagCountUtoipa]),
+        (status = 304, description = "The library matches the `If-None-Match` header"),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[get("/tags")]
+pub async fn tags(
+    req: HttpRequest,
+    UserConfig(config): UserConfig,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let mut book_dir = db.root(config);
+    let etag = match book_dir.version() {
+        Ok(v) => EntityTag::new_strong(v),
+        Err(e) => return ApiError(e).into(),
+    };
+    if not_modified(&req, &etag) {
+        return HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .finish();
+    }
+    match book_dir.tag_counts() {
+        Ok(v) => HttpResponse::Ok().insert_header(header::ETag(etag)).json(v),
+        Err(e) => ApiError(e).into(),
+    }
+}
@@ -0,0 +1,81 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab422, Bookrab500},
+};
+use actix_web::{
+    get,
+    http::header::{self, ContentType, EntityTag},
+    web, HttpRequest, HttpResponse,
+};
+use bookrab_core::books::catalog::checksum;
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+use super::not_modified;
+
+/// Selects the lines of the book that are returned.
+/// Lines start at 1 and both ends are inclusive.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct TextForm {
+    /// First line (default: first line of the book)
+    start_line: Option<usize>,
+    /// Last line (default: last line of the book)
+    end_line: Option<usize>,
+}
+
+/// Returns the lines of `txt` selected by `form` (line endings included).
+fn line_range(txt: &str, form: &TextForm) -> String {
+    let start = form.start_line.unwrap_or(1).max(1);
+    let end = form.end_line.unwrap_or(usize::MAX);
+    if start > end {
+        return String::new();
+    }
+    txt.split_inclusive('\n')
+        .skip(start - 1)
+        .take(end - start + 1)
+        .collect()
+}
+
+/// Returns the text of a book as `text/plain`.
+/// The `ETag` changes whenever the returned text changes, so
+/// clients can send `If-None-Match` to avoid downloading it again.
+#[utoipa::path(
+    params(("title" = String, Path, description = "Title of the book"), TextForm),
+    responses (
+        (status = 200, content_type = "text/plain", body = String),
+        (status = 304, description = "The text matches the `If-None-Match` header"),
+        (status = 400, body = Bookrab400),
+        (status = 422, body = Bookrab422),
+        (status = 500, body = Bookrab500),
+    )
+)]
+#[get("/{title}")]
+pub async fn text(
+    UserConfig(config): UserConfig,
+    req: HttpRequest,
+    title: web::Path<String>,
+    form: web::Query<TextForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let txt = match db.root(config).text(&title) {
+        Ok(v) => v,
+        Err(e) => return ApiError(e).into(),
+    };
+    let txt = if form.start_line.is_none() && form.end_line.is_none() {
+        txt
+    } else {
+        line_range(&txt, &form)
+    };
+    let etag = EntityTag::new_strong(checksum(&txt));
+    if not_modified(&req, &etag) {
+        return HttpResponse::NotModified()
+            .insert_header(header::ETag(etag))
+            .finish();
+    }
+    HttpResponse::Ok()
+        .content_type(ContentType::plaintext())
+        .insert_header(header::ETag(etag))
+        .body(txt)
+}
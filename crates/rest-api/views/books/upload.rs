@@ -1,79 +1,243 @@
 use std::{collections::HashSet, io::Read, path::PathBuf};
 
-use actix_multipart::form::{json::Json, tempfile::TempFile, MultipartForm};
-use actix_web::{post, HttpResponse, Responder};
-use bookrab_core::{books::RootBookDir, errors::BookrabError};
+use actix_multipart::{
+    form::{json::Json, tempfile::TempFile, text::Text, MultipartForm, MultipartFormConfig},
+    MultipartError,
+};
+use actix_web::{
+    error::{InternalError, PayloadError},
+    http::header::CONTENT_LENGTH,
+    post, HttpRequest, HttpResponse, Responder,
+};
+use bookrab_core::{
+    books::{
+        catalog::checksum,
+        import::{self, SourceFormat},
+        language::Language,
+        validation::check_title,
+        RootBookDir,
+    },
+    config::BookrabConfig,
+    errors::BookrabError,
+};
+use serde::Serialize;
 use utoipa::ToSchema;
 
 use crate::{
-    config::ensure_confy_works,
-    database::DB,
-    errors::{ApiError, Bookrab400, Bookrab500},
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab409, Bookrab413, Bookrab422, Bookrab500},
+    idempotency::{self, Claim},
+    validation::multipart_error,
+    webhooks::{notify, WebhookEvent},
 };
 
+use super::LanguageUtoipa;
+
 /// Represents a form for book uploading.
-/// The books currently have to be .txt files.
 #[derive(Debug, MultipartForm, ToSchema)]
 struct BookForm {
-    /// Book in the .txt format
+    /// Book in the .txt, markdown, HTML or EPUB format (see [read_book])
     #[schema(value_type = String, format = "binary")]
     book: TempFile,
     /// Book tags
     #[schema(value_type = Vec<String>)]
     tags: Json<Vec<String>>,
+    /// Book language (detected from the text if absent)
+    #[schema(value_type = Option<LanguageUtoipa>)]
+    language: Option<Text<Language>>,
+}
+
+/// What was uploaded.
+#[derive(Debug, Serialize)]
+struct Uploaded {
+    title: String,
+    /// Format the book was converted from (`None` for plain text)
+    conversion: Option<SourceFormat>,
+}
+
+#[allow(dead_code)]
+#[derive(ToSchema)]
+struct UploadedUtoipa {
+    /// Title of the book (converted books get the `.txt` extension)
+    title: String,
+    /// Format the book was converted from: `markdown`, `html`
+    /// or `epub` (null for plain text)
+    conversion: Option<String>,
 }
 
 /// Uploads a book to be searched later.
+///
+/// Markdown (`text/markdown`), HTML (`text/html`) and EPUB
+/// (`application/epub+zip`) books are converted to plain text.
+/// Without a content type (or with `application/octet-stream`), the format
+/// is guessed from the extension of the file.
+///
+/// Retries of an upload with the same `Idempotency-Key` header get the
+/// response of the first one (with `Idempotent-Replayed: true`) without
+/// uploading the book again. Keys are remembered for a day and
+/// reusing one for a different upload is a conflict (409).
 #[utoipa::path(
     request_body(content_type = "multipart/form-data", content = BookForm),
     responses (
-        (status = 200, description = "Success (without response body)"),
+        (status = 200, body = UploadedUtoipa),
         (status = 400, body = Bookrab400),
+        (status = 409, body = Bookrab409),
+        (status = 413, body = Bookrab413),
+        (status = 422, body = Bookrab422),
         (status = 500, body = Bookrab500),
     )
 )]
 #[post("/upload")]
-pub async fn upload(MultipartForm(form): MultipartForm<BookForm>, mut db: DB) -> impl Responder {
-    let config = ensure_confy_works();
-    let book_dir = RootBookDir::new(config, &mut db.connection);
-
-    let mut file = form.book;
-    if let Some(v) = file.content_type {
-        if v != "text/plain" {
-            return ApiError(BookrabError::ShouldBeTextPlain {
-                error: (),
-                filename: file.file_name.unwrap_or("".to_string()),
-            })
-            .into();
-        }
+pub async fn upload(
+    req: HttpRequest,
+    UserConfig(config): UserConfig,
+    MultipartForm(form): MultipartForm<BookForm>,
+    mut db: OptionalDB,
+) -> impl Responder {
+    let key = match idempotency::key_of(&req) {
+        Ok(v) => v,
+        Err(e) => return ApiError(e).into(),
     };
-    let file_name = PathBuf::from(file.file_name.unwrap());
-    let mut txt = String::new();
-    if let Err(e) = file.file.read_to_string(&mut txt) {
-        return ApiError(BookrabError::CouldntReadFile {
-            error: (),
-            path: file_name,
-            err: e,
-        })
-        .into();
+    let mut file = form.book;
+    let (title, txt, format) = match read_book(&mut file) {
+        Ok(v) => v,
+        Err(e) => return ApiError(e).into(),
     };
     let mut tags = HashSet::new();
     for tag in form.tags.iter() {
         tags.insert(tag.to_string());
     }
+    let language = form.language.map(|language| language.into_inner());
+    let guard = match key {
+        Some(key) => {
+            let mut sorted_tags: Vec<&String> = tags.iter().collect();
+            sorted_tags.sort();
+            let fingerprint = format!("{title}\n{}\n{sorted_tags:?}\n{language:?}", checksum(&txt));
+            match idempotency::claim(&config, key, fingerprint) {
+                Ok(Claim::New(guard)) => Some(guard),
+                Ok(Claim::Replay(response)) => return response,
+                Err(e) => return ApiError(e).into(),
+            }
+        }
+        None => None,
+    };
+
+    let mut book_dir = db.root(config);
+    if let Err(e) = store_book(&mut book_dir, &title, &txt, tags, language) {
+        return ApiError(e).into();
+    };
+    let uploaded = Uploaded {
+        title,
+        conversion: Some(format).filter(|format| *format != SourceFormat::Text),
+    };
+    if let Some(guard) = guard {
+        guard.finish(&uploaded);
+    }
+    HttpResponse::Ok().json(uploaded)
+}
+
+/// Limits of the multipart forms of `config`. Forms that exceed
+/// them are rejected with [BookrabError::UploadTooLarge] and
+/// forms with broken fields with [BookrabError::InvalidFields].
+pub(crate) fn multipart_config(config: &BookrabConfig) -> MultipartFormConfig {
+    let total_limit = config.max_upload_bytes();
+    let memory_limit = config.max_upload_memory_bytes();
+    MultipartFormConfig::default()
+        .total_limit(total_limit)
+        .memory_limit(memory_limit)
+        .error_handler(move |err, req| match err {
+            MultipartError::Payload(PayloadError::Overflow) => {
+                // actix-multipart doesn't say which limit was exceeded, but
+                // small forms can only exceed the memory limit
+                let length = req
+                    .headers()
+                    .get(CONTENT_LENGTH)
+                    .and_then(|length| length.to_str().ok())
+                    .and_then(|length| length.parse::<usize>().ok());
+                let limit = match length {
+                    Some(length) if length <= total_limit => memory_limit,
+                    _ => total_limit,
+                };
+                let response: HttpResponse =
+                    ApiError(BookrabError::UploadTooLarge { error: (), limit }).into();
+                InternalError::from_response(err, response).into()
+            }
+            err => multipart_error(err),
+        })
+}
+
+/// Format of `file`, by its content type or, when the client didn't
+/// say (or sent `application/octet-stream`), by its extension.
+/// Files without a known extension are treated as plain text.
+fn book_format(file: &TempFile, file_name: &str) -> Result<SourceFormat, BookrabError> {
+    let by_name = || SourceFormat::from_file_name(file_name).unwrap_or(SourceFormat::Text);
+    match &file.content_type {
+        Some(v) if v.essence_str() == "application/octet-stream" => Ok(by_name()),
+        Some(v) => SourceFormat::from_content_type(v.as_ref()).ok_or_else(|| {
+            BookrabError::ShouldBeTextPlain {
+                error: (),
+                filename: file_name.to_string(),
+            }
+        }),
+        None => Ok(by_name()),
+    }
+}
+
+/// Reads `file` and converts it to plain text (see [import]).
+/// Returns the title of the book (the name of the file, with the `.txt`
+/// extension if it was converted), its text and its original format.
+pub(super) fn read_book(
+    file: &mut TempFile,
+) -> Result<(String, String, SourceFormat), BookrabError> {
+    let file_name = PathBuf::from(file.file_name.clone().unwrap_or_default());
     let title = match file_name.to_str() {
-        Some(v) => v,
+        Some(v) => v.to_string(),
         None => {
-            return ApiError(BookrabError::NotUnicode {
+            return Err(BookrabError::NotUnicode {
                 error: (),
                 what: file_name.to_string_lossy().to_string(),
             })
-            .into()
         }
     };
-
-    if let Err(e) = book_dir.upload(title, txt.as_str(), tags) {
-        return ApiError(e).into();
+    // the title names the folder of the book (see [check_title])
+    check_title("book", &title)?;
+    let format = book_format(file, &title)?;
+    let mut bytes = vec![];
+    if let Err(e) = file.file.read_to_end(&mut bytes) {
+        return Err(BookrabError::CouldntReadFile {
+            error: (),
+            path: file_name,
+            err: e,
+        });
+    };
+    let txt = import::convert(format, &title, bytes)?;
+    let title = match format {
+        SourceFormat::Text => title,
+        _ => import::converted_title(&title),
     };
-    HttpResponse::Ok().finish()
+    Ok((title, txt, format))
+}
+
+/// Uploads a book and calls the webhooks.
+/// Its language is detected unless `language` is given.
+pub(crate) fn store_book(
+    book_dir: &mut RootBookDir,
+    title: &str,
+    txt: &str,
+    tags: HashSet<String>,
+    language: Option<Language>,
+) -> Result<(), BookrabError> {
+    book_dir.upload(title, txt, tags.clone())?;
+    if let Some(language) = language {
+        book_dir.set_language(title, Some(language))?;
+    }
+    notify(
+        book_dir.config(),
+        WebhookEvent::BookUploaded {
+            title: title.to_string(),
+            tags,
+        },
+    );
+    Ok(())
 }
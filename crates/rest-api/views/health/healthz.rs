@@ -0,0 +1,24 @@
+use crate::config::ensure_confy_works;
+use actix_web::{get, HttpResponse};
+use bookrab_core::database::check_book_path;
+
+use super::CheckResultUtoipa;
+
+/// Liveness probe: checks whether the book folder can be read.
+/// The database is not checked here (see `/readyz`), so that
+/// the service isn't restarted when only the database is down.
+#[utoipa::path(
+    responses (
+        (status = 200, body=CheckResultUtoipa),
+        (status = 503, body=CheckResultUtoipa),
+    )
+)]
+#[get("/healthz")]
+pub async fn healthz() -> HttpResponse {
+    let check = check_book_path(&ensure_confy_works());
+    if check.ok {
+        HttpResponse::Ok().json(check)
+    } else {
+        HttpResponse::ServiceUnavailable().json(check)
+    }
+}
@@ -0,0 +1,25 @@
+pub mod healthz;
+pub mod readyz;
+use utoipa::ToSchema;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct CheckResultUtoipa {
+    ok: bool,
+    /// Description of the problem (when `ok` is `false`)
+    error: Option<String>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct HealthReportUtoipa {
+    book_path: CheckResultUtoipa,
+    database: CheckResultUtoipa,
+}
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(healthz::healthz).service(readyz::readyz);
+    }
+}
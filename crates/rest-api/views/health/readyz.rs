@@ -0,0 +1,24 @@
+use crate::{config::ensure_confy_works, database::DBCONNECTION};
+use actix_web::{get, HttpResponse};
+use bookrab_core::database::health_check;
+
+use super::HealthReportUtoipa;
+
+/// Readiness probe: checks the book folder and the database
+/// and reports each one separately.
+/// Without a database in the config, only the book folder matters.
+#[utoipa::path(
+    responses (
+        (status = 200, body=HealthReportUtoipa),
+        (status = 503, body=HealthReportUtoipa),
+    )
+)]
+#[get("/readyz")]
+pub async fn readyz() -> HttpResponse {
+    let report = health_check(&ensure_confy_works(), DBCONNECTION.as_ref());
+    if report.ok() {
+        HttpResponse::Ok().json(report)
+    } else {
+        HttpResponse::ServiceUnavailable().json(report)
+    }
+}
@@ -0,0 +1,45 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab500},
+};
+use actix_web::{delete, web, HttpResponse};
+use bookrab_core::{books::history_backend::history_backend, errors::BookrabError};
+use serde::Deserialize;
+use utoipa::IntoParams;
+
+/// Clearing the history can't be undone, so it must be confirmed.
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ClearForm {
+    /// Must be `true`
+    confirm: Option<bool>,
+}
+
+/// Deletes the entire search history.
+#[utoipa::path(
+    params(ClearForm),
+    responses (
+        (status = 204),
+        (status = 400, body=Bookrab400),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[delete("/clear")]
+pub async fn clear(
+    UserConfig(config): UserConfig,
+    form: web::Query<ClearForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    if form.confirm != Some(true) {
+        return ApiError(BookrabError::UnconfirmedOperation {
+            error: (),
+            operation: "clear history".to_string(),
+        })
+        .into();
+    }
+    match history_backend(&config, db.connection.as_deref_mut()).clear() {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => ApiError(e).into(),
+    }
+}
@@ -0,0 +1,137 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab500, Bookrab501},
+};
+use actix_web::{get, web, HttpResponse};
+use bookrab_core::books::{
+    history::{HistoryCursor, HistoryFilter, HistoryOrder},
+    history_backend::query_history,
+};
+use chrono::NaiveDateTime;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct SearchHistoryEntryUtoipa {
+    id: i32,
+    title: String,
+    pattern: String,
+    /// e.g. `2024-12-25T10:00:00.123456`
+    date: String,
+    /// `All` or `Any`
+    include_mode: String,
+    include_tags: Vec<String>,
+    /// `All` or `Any`
+    exclude_mode: String,
+    exclude_tags: Vec<String>,
+    case_insensitive: bool,
+    case_smart: bool,
+    ignore_accents: bool,
+    before_context: i32,
+    after_context: i32,
+    match_count: i32,
+    duration_ms: i64,
+    /// Number of times this search was made in a row
+    hits: i32,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct HistoryCursorUtoipa {
+    date: String,
+    id: i32,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct HistoryPageUtoipa {
+    entries: Vec<SearchHistoryEntryUtoipa>,
+    /// Pass `next.date` and `next.id` as `cursor_date` and `cursor_id`
+    /// to get the next page (null if this is the last page)
+    next: Option<HistoryCursorUtoipa>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) enum HistoryOrderUtoipa {
+    NewestFirst,
+    OldestFirst,
+}
+
+/// Filters and paginates the history.
+#[derive(Debug, Deserialize)]
+pub struct HistoryListForm {
+    order: Option<HistoryOrder>,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    pattern: Option<String>,
+    title: Option<String>,
+    cursor_date: Option<NaiveDateTime>,
+    cursor_id: Option<i32>,
+    size: Option<i64>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct HistoryListFormUtoipa {
+    /// Default: NewestFirst
+    order: Option<HistoryOrderUtoipa>,
+    /// Only entries made at or after this date (e.g. `2024-12-25T10:00:00`)
+    from: Option<String>,
+    /// Only entries made at or before this date
+    to: Option<String>,
+    /// Only entries whose pattern contains this string
+    pattern: Option<String>,
+    /// Only entries of this book
+    title: Option<String>,
+    /// Date of the cursor returned by the previous page
+    cursor_date: Option<String>,
+    /// Id of the cursor returned by the previous page
+    cursor_id: Option<i32>,
+    /// Number of entries per page (default: 50)
+    size: Option<i64>,
+}
+
+/// Lists search history entries, one page at a time.
+/// The history must be stored in the database or in a SQLite file,
+/// other backends answer 501.
+#[utoipa::path(
+    params(HistoryListFormUtoipa),
+    responses (
+        (status = 200, body=HistoryPageUtoipa),
+        (status = 400, body=Bookrab400),
+        (status = 500, body=Bookrab500),
+        (status = 501, body=Bookrab501),
+    )
+)]
+#[get("/list")]
+pub async fn list(
+    UserConfig(config): UserConfig,
+    form: web::Query<HistoryListForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let form = form.into_inner();
+    let filter = HistoryFilter {
+        order: form.order.unwrap_or_default(),
+        from: form.from,
+        to: form.to,
+        pattern: form.pattern,
+        title: form.title,
+        ..Default::default()
+    };
+    let cursor = match (form.cursor_date, form.cursor_id) {
+        (Some(date), Some(id)) => Some(HistoryCursor { date, id }),
+        _ => None,
+    };
+    let size = form.size.unwrap_or(50);
+    let page = query_history(&config, db.connection.as_deref_mut(), |history| {
+        history.get_history_page(&filter, cursor.as_ref(), size)
+    });
+    match page {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => ApiError(e).into(),
+    }
+}
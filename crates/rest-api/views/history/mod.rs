@@ -0,0 +1,15 @@
+pub mod clear;
+pub mod list;
+pub mod search;
+pub mod stats;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config
+            .service(list::list)
+            .service(clear::clear)
+            .service(stats::stats)
+            .service(search::search);
+    }
+}
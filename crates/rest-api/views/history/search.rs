@@ -0,0 +1,103 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab500, Bookrab501},
+};
+use actix_web::{get, web, HttpResponse};
+use bookrab_core::books::{
+    history::{HistoryFilter, HistoryOrder},
+    history_backend::query_history,
+};
+use chrono::NaiveDateTime;
+use grep_regex::RegexMatcherBuilder;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+use super::list::{HistoryOrderUtoipa, SearchHistoryEntryUtoipa};
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct HistoryResultMatchUtoipa {
+    entry: SearchHistoryEntryUtoipa,
+    results: Vec<String>,
+}
+
+/// Pattern searched in the stored results and the
+/// entries whose results are searched.
+#[derive(Debug, Deserialize)]
+pub struct HistorySearchForm {
+    pattern: String,
+    case_insensitive: Option<bool>,
+    case_smart: Option<bool>,
+    order: Option<HistoryOrder>,
+    from: Option<NaiveDateTime>,
+    to: Option<NaiveDateTime>,
+    searched_pattern: Option<String>,
+    title: Option<String>,
+    limit: Option<i64>,
+    offset: Option<i64>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct HistorySearchFormUtoipa {
+    /// Searched in the stored results
+    pattern: String,
+    case_insensitive: Option<bool>,
+    case_smart: Option<bool>,
+    /// Default: NewestFirst
+    order: Option<HistoryOrderUtoipa>,
+    /// Only entries made at or after this date (e.g. `2024-12-25T10:00:00`)
+    from: Option<String>,
+    /// Only entries made at or before this date
+    to: Option<String>,
+    /// Only entries whose pattern contains this string
+    searched_pattern: Option<String>,
+    /// Only entries of this book
+    title: Option<String>,
+    /// Maximum number of entries searched
+    limit: Option<i64>,
+    /// Number of entries skipped
+    offset: Option<i64>,
+}
+
+/// Searches the results stored in the history
+/// (e.g. every past result mentioning "Taprobana").
+#[utoipa::path(
+    params(HistorySearchFormUtoipa),
+    responses (
+        (status = 200, body=[HistoryResultMatchUtoipa]),
+        (status = 400, body=Bookrab400),
+        (status = 500, body=Bookrab500),
+        (status = 501, body=Bookrab501),
+    )
+)]
+#[get("/search")]
+pub async fn search(
+    UserConfig(config): UserConfig,
+    form: web::Query<HistorySearchForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let form = form.into_inner();
+    let mut builder = RegexMatcherBuilder::new();
+    builder
+        .case_insensitive(form.case_insensitive.unwrap_or(false))
+        .case_smart(form.case_smart.unwrap_or(false));
+    let filter = HistoryFilter {
+        order: form.order.unwrap_or_default(),
+        from: form.from,
+        to: form.to,
+        pattern: form.searched_pattern,
+        title: form.title,
+        limit: form.limit,
+        offset: form.offset,
+    };
+    let results = query_history(&config, db.connection.as_deref_mut(), |history| {
+        history.search_results(&form.pattern, &builder, &filter)
+    });
+    match results {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => ApiError(e).into(),
+    }
+}
@@ -0,0 +1,73 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab500, Bookrab501},
+};
+use actix_web::{get, web, HttpResponse};
+use bookrab_core::books::history_backend::query_history;
+use serde::Deserialize;
+use utoipa::{IntoParams, ToSchema};
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct PatternCountUtoipa {
+    pattern: String,
+    count: i64,
+    match_count: i64,
+    avg_duration_ms: f64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct DailyCountUtoipa {
+    /// e.g. `2024-12-25`
+    day: String,
+    count: i64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct BookCountUtoipa {
+    title: String,
+    count: i64,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct HistoryStatsUtoipa {
+    top_patterns: Vec<PatternCountUtoipa>,
+    searches_per_day: Vec<DailyCountUtoipa>,
+    top_books: Vec<BookCountUtoipa>,
+}
+
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct StatsForm {
+    /// Number of patterns and books in the response (default: 10)
+    pub(crate) top: Option<i64>,
+}
+
+/// Aggregates of the search history: most searched patterns,
+/// searches per day and books that matched most often.
+#[utoipa::path(
+    params(StatsForm),
+    responses (
+        (status = 200, body=HistoryStatsUtoipa),
+        (status = 500, body=Bookrab500),
+        (status = 501, body=Bookrab501),
+    )
+)]
+#[get("/stats")]
+pub async fn stats(
+    UserConfig(config): UserConfig,
+    form: web::Query<StatsForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let top = form.top.unwrap_or(10);
+    match query_history(&config, db.connection.as_deref_mut(), |history| {
+        history.stats(top)
+    }) {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => ApiError(e).into(),
+    }
+}
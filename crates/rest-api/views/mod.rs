@@ -1 +1,9 @@
+pub mod admin;
+pub mod auth;
 pub mod books;
+pub mod health;
+pub mod history;
+pub mod search;
+pub mod searches;
+pub mod v2;
+pub mod ws;
@@ -0,0 +1,8 @@
+pub mod validate;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(validate::validate);
+    }
+}
@@ -0,0 +1,47 @@
+use actix_web::{post, web, HttpResponse};
+use bookrab_core::books::validation::validate_pattern;
+use grep_regex::RegexMatcherBuilder;
+use serde::Deserialize;
+use utoipa::ToSchema;
+
+/// Pattern to be validated along with the options
+/// that affect the way it is built.
+#[derive(Debug, Deserialize, ToSchema)]
+struct ValidateForm {
+    pattern: String,
+    case_insensitive: Option<bool>,
+    case_smart: Option<bool>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct PatternSpanUtoipa {
+    start: usize,
+    end: usize,
+    line: usize,
+    column: usize,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+struct PatternDiagnosticsUtoipa {
+    valid: bool,
+    message: Option<String>,
+    span: Option<PatternSpanUtoipa>,
+}
+
+/// Checks whether a pattern is valid.
+/// Invalid patterns are not an error: the response says
+/// what is wrong with the pattern and where.
+#[utoipa::path(
+    request_body = ValidateForm,
+    responses((status = 200, body = PatternDiagnosticsUtoipa))
+)]
+#[post("/validate")]
+pub async fn validate(form: web::Json<ValidateForm>) -> HttpResponse {
+    let mut builder = RegexMatcherBuilder::new();
+    builder
+        .case_insensitive(form.case_insensitive.unwrap_or(false))
+        .case_smart(form.case_smart.unwrap_or(false));
+    HttpResponse::Ok().json(validate_pattern(&form.pattern, &builder))
+}
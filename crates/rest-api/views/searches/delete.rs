@@ -0,0 +1,28 @@
+use crate::{
+    config::UserConfig,
+    database::DB,
+    errors::{ApiError, Bookrab400, Bookrab500},
+};
+use actix_web::{delete, web, HttpResponse};
+use bookrab_core::books::saved_searches::SavedSearches;
+
+/// Deletes a saved search.
+#[utoipa::path(
+    params(("name" = String, Path, description = "Name of the saved search")),
+    responses (
+        (status = 204),
+        (status = 400, body=Bookrab400),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[delete("/{name}")]
+pub async fn delete(
+    UserConfig(config): UserConfig,
+    name: web::Path<String>,
+    mut db: DB,
+) -> HttpResponse {
+    match SavedSearches::new(config, &mut db.connection).delete(&name) {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => ApiError(e).into(),
+    }
+}
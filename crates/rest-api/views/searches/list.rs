@@ -0,0 +1,24 @@
+use crate::{
+    config::UserConfig,
+    database::DB,
+    errors::{ApiError, Bookrab500},
+};
+use actix_web::{get, HttpResponse};
+use bookrab_core::books::saved_searches::SavedSearches;
+
+use super::SavedSearchUtoipa;
+
+/// Lists saved searches ordered by name.
+#[utoipa::path(
+    responses (
+        (status = 200, body=[SavedSearchUtoipa]),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[get("/list")]
+pub async fn list(UserConfig(config): UserConfig, mut db: DB) -> HttpResponse {
+    match SavedSearches::new(config, &mut db.connection).list() {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => ApiError(e).into(),
+    }
+}
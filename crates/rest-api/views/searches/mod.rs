@@ -0,0 +1,38 @@
+pub mod delete;
+pub mod list;
+pub mod run;
+pub mod save;
+use std::collections::HashSet;
+
+use serde::Deserialize;
+use utoipa::ToSchema;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+use super::books::FilterModeUtoipa;
+
+/// Mirror of [bookrab_core::books::saved_searches::SavedSearch] for the docs.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, ToSchema)]
+pub(crate) struct SavedSearchUtoipa {
+    name: String,
+    pattern: String,
+    include_mode: Option<FilterModeUtoipa>,
+    include_tags: Option<HashSet<String>>,
+    exclude_mode: Option<FilterModeUtoipa>,
+    exclude_tags: Option<HashSet<String>>,
+    case_insensitive: Option<bool>,
+    case_smart: Option<bool>,
+    ignore_accents: Option<bool>,
+    before_context: Option<usize>,
+    after_context: Option<usize>,
+}
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config
+            .service(save::save)
+            .service(list::list)
+            .service(run::run)
+            .service(delete::delete);
+    }
+}
@@ -0,0 +1,32 @@
+use crate::{
+    config::UserConfig,
+    database::DB,
+    errors::{ApiError, Bookrab400, Bookrab500},
+};
+use actix_web::{post, web, HttpResponse};
+use bookrab_core::books::RootBookDir;
+
+use crate::views::books::search::SearchResultsUtoipa;
+
+/// Runs a saved search. The response is the same as the
+/// one of `/v1/books/search`.
+#[utoipa::path(
+    params(("name" = String, Path, description = "Name of the saved search")),
+    responses (
+        (status = 200, body=[SearchResultsUtoipa]),
+        (status = 400, body=Bookrab400),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[post("/{name}/run")]
+pub async fn run(
+    UserConfig(config): UserConfig,
+    name: web::Path<String>,
+    mut db: DB,
+) -> HttpResponse {
+    let mut root = RootBookDir::new(config, &mut db.connection);
+    match root.run_saved_search(&name) {
+        Ok(v) => HttpResponse::Ok().json(v),
+        Err(e) => ApiError(e).into(),
+    }
+}
@@ -0,0 +1,30 @@
+use crate::{
+    config::UserConfig,
+    database::DB,
+    errors::{ApiError, Bookrab500},
+};
+use actix_web::{post, web, HttpResponse};
+use bookrab_core::books::saved_searches::{SavedSearch, SavedSearches};
+
+use super::SavedSearchUtoipa;
+
+/// Saves a search under a name.
+/// A search with the same name is replaced.
+#[utoipa::path(
+    request_body = SavedSearchUtoipa,
+    responses (
+        (status = 204),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[post("/save")]
+pub async fn save(
+    UserConfig(config): UserConfig,
+    search: web::Json<SavedSearch>,
+    mut db: DB,
+) -> HttpResponse {
+    match SavedSearches::new(config, &mut db.connection).save(&search) {
+        Ok(_) => HttpResponse::NoContent().finish(),
+        Err(e) => ApiError(e).into(),
+    }
+}
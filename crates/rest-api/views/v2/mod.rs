@@ -0,0 +1,10 @@
+//! Version 2 of the API. Only the routes whose responses changed
+//! are here, the others are still served under `/v1`.
+pub mod search;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(search::search);
+    }
+}
@@ -0,0 +1,176 @@
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::{ApiError, Bookrab400, Bookrab422, Bookrab500},
+    views::books::{
+        search::{SearchForm, SearchParams},
+        FilterModeUtoipa, LanguageUtoipa,
+    },
+};
+use actix_web::{get, web, HttpResponse};
+use bookrab_core::books::{LineKind, Page, ResultLine, SearchResults};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct SpanUtoipa {
+    start: usize,
+    end: usize,
+}
+
+/// Mirror of [bookrab_core::books::LineKind] for the docs.
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum LineKindUtoipa {
+    Match,
+    /// Context before a match
+    Before,
+    /// Context after a match
+    After,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct ResultLineUtoipa {
+    /// Starting at 1
+    line_number: Option<u64>,
+    /// Position of the line in the book (in bytes)
+    byte_offset: u64,
+    /// The line, without its line break
+    text: String,
+    /// Byte ranges of the matches in `text` (empty for context lines)
+    spans: Vec<SpanUtoipa>,
+    kind: LineKindUtoipa,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct BookMatchesUtoipa {
+    title: String,
+    lines: Vec<ResultLineUtoipa>,
+    score: f64,
+    duration_ms: u64,
+    /// Whether `lines` was cut by `max_matches_per_book`
+    truncated: bool,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, ToSchema)]
+pub(crate) struct BookMatchesPageUtoipa {
+    items: Vec<BookMatchesUtoipa>,
+    /// Number of books in all pages
+    total: usize,
+    /// Pass it as `offset` to get the next page (null if this is the last page)
+    next: Option<usize>,
+}
+
+/// Lines of a book that matched the search, with at most
+/// `max_matches_per_book` matching lines (and their context).
+#[derive(Debug, Serialize)]
+pub(crate) struct BookMatches {
+    title: String,
+    lines: Vec<ResultLine>,
+    score: f64,
+    duration_ms: u64,
+    truncated: bool,
+}
+
+impl BookMatches {
+    fn new(results: SearchResults, max_matches: Option<usize>) -> Self {
+        let max_matches = max_matches.unwrap_or(usize::MAX);
+        let mut matches = 0;
+        let mut truncated = false;
+        let mut lines = vec![];
+        for line in results.lines {
+            match line.kind {
+                LineKind::Match if matches == max_matches => {
+                    truncated = true;
+                    break;
+                }
+                LineKind::Match => matches += 1,
+                // the context of the first match that is left out
+                LineKind::Before if matches == max_matches => {
+                    truncated = true;
+                    break;
+                }
+                _ => {}
+            }
+            lines.push(line);
+        }
+        BookMatches {
+            title: results.title,
+            lines,
+            score: results.score,
+            duration_ms: results.duration_ms,
+            truncated,
+        }
+    }
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct SearchFormUtoipa {
+    after_context: Option<usize>,
+    before_context: Option<usize>,
+    case_insensitive: Option<bool>,
+    case_smart: Option<bool>,
+    exclude_mode: Option<FilterModeUtoipa>,
+    exclude_tags: Option<Vec<String>>,
+    ignore_accents: Option<bool>,
+    include_mode: Option<FilterModeUtoipa>,
+    include_tags: Option<Vec<String>>,
+    language: Option<LanguageUtoipa>,
+    /// Number of books per page (default: 100)
+    limit: Option<usize>,
+    /// Maximum number of matching lines of each book (default: no limit)
+    max_matches_per_book: Option<usize>,
+    /// Number of books to skip (default: 0)
+    offset: Option<usize>,
+    pattern: String,
+}
+
+/// Searches books filtered by tags, one page of books at a time.
+/// Books are ordered by relevance.
+///
+/// Unlike `/v1/books/search`, each book has a list of lines with their
+/// position in the book and the byte ranges of the matches, instead of
+/// strings with `[matched]` markers. Context lines are in the same list.
+#[utoipa::path(
+    params(SearchFormUtoipa),
+    responses (
+        (status = 200, body=BookMatchesPageUtoipa),
+        (status = 400, body=Bookrab400),
+        (status = 422, body=Bookrab422),
+        (status = 500, body=Bookrab500),
+    )
+)]
+#[get("/search")]
+pub async fn search(
+    UserConfig(config): UserConfig,
+    form: web::Query<SearchForm>,
+    mut db: OptionalDB,
+) -> HttpResponse {
+    let mut form = form.into_inner();
+    // concordances have no lines
+    form.format = None;
+    let params = match SearchParams::new(&form) {
+        Ok(v) => v,
+        Err(e) => return ApiError(e).into(),
+    };
+    let search_results = match params.search(&mut db.root(config), |_| true) {
+        Ok(v) => v,
+        Err(e) => return ApiError(e).into(),
+    };
+    let search_results = search_results
+        .into_iter()
+        .map(|results| BookMatches::new(results, form.max_matches_per_book))
+        .collect();
+    HttpResponse::Ok().json(Page::new(
+        search_results,
+        form.offset.unwrap_or(0),
+        form.limit.unwrap_or(100),
+    ))
+}
@@ -0,0 +1,8 @@
+pub mod search;
+use utoipa_actix_web::service_config::ServiceConfig;
+
+pub fn configure() -> impl FnOnce(&mut ServiceConfig) {
+    |config: &mut ServiceConfig| {
+        config.service(search::ws);
+    }
+}
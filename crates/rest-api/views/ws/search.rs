@@ -0,0 +1,228 @@
+use std::time::Instant;
+
+use actix_web::{get, rt::task::JoinHandle, web, HttpRequest, HttpResponse};
+use actix_ws::{Message, MessageStream, Session};
+use bookrab_core::{books::SearchEvent, config::BookrabConfig};
+use futures::StreamExt;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    config::UserConfig,
+    database::OptionalDB,
+    errors::ApiError,
+    views::books::search::{stream_search, SearchForm, SearchParams, TruncatedSearchResults},
+};
+
+/// Messages sent by the client.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ClientMessage {
+    /// Starts a search, cancelling the one in progress (if any).
+    /// The other fields are the parameters of `/v1/books/search`.
+    Search {
+        id: u64,
+        #[serde(flatten)]
+        form: SearchForm,
+    },
+    /// Cancels the search with `id` (if it is still in progress).
+    Cancel { id: u64 },
+}
+
+/// Messages sent by the server. Every message has the `id` of its search.
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+enum ServerMessage<'a> {
+    /// A book is about to be searched
+    Started {
+        id: u64,
+        title: &'a str,
+        index: usize,
+        total: usize,
+    },
+    /// Results of a book (same as the items of `/v1/books/search`)
+    Results {
+        id: u64,
+        #[serde(flatten)]
+        results: TruncatedSearchResults,
+    },
+    /// Every book was searched
+    Done {
+        id: u64,
+        books: usize,
+        duration_ms: u64,
+    },
+    /// The search was cancelled before it finished
+    Cancelled { id: u64 },
+    /// The search failed (`id` is null if the message of the client was invalid)
+    Error {
+        id: Option<u64>,
+        error: serde_json::Value,
+    },
+}
+
+impl ServerMessage<'_> {
+    fn to_text(&self) -> String {
+        serde_json::to_string(self).unwrap()
+    }
+}
+
+/// Search in progress.
+struct Running {
+    id: u64,
+    handle: JoinHandle<()>,
+}
+
+/// Opens a websocket where searches can be made and cancelled,
+/// e.g. one search per keystroke. Messages are JSON objects with a `type`:
+///
+/// - client: `search` (`id` plus the parameters of `/v1/books/search`)
+///   and `cancel` (`id`). A new search cancels the one in progress.
+/// - server: `started`, `results` and `done` (like the events of
+///   `/v1/books/search/sse`), `cancelled` and `error`, all with the `id`
+///   of the search.
+#[utoipa::path(responses((status = 101, description = "Switching to the websocket protocol")))]
+#[get("/v1/ws")]
+pub async fn ws(
+    req: HttpRequest,
+    body: web::Payload,
+    UserConfig(config): UserConfig,
+) -> Result<HttpResponse, actix_web::Error> {
+    let (response, session, messages) = actix_ws::handle(&req, body)?;
+    actix_web::rt::spawn(handle_messages(config, session, messages));
+    Ok(response)
+}
+
+/// Handles the messages of a client until the websocket is closed.
+/// Searches use the books of `config`.
+async fn handle_messages(config: BookrabConfig, mut session: Session, mut messages: MessageStream) {
+    let mut running: Option<Running> = None;
+    while let Some(Ok(message)) = messages.next().await {
+        match message {
+            Message::Text(text) => match serde_json::from_str::<ClientMessage>(&text) {
+                Ok(ClientMessage::Search { id, form }) => {
+                    cancel(&mut session, running.take()).await;
+                    running = Some(Running {
+                        id,
+                        handle: spawn_search(id, &form, config.clone(), session.clone()),
+                    });
+                }
+                Ok(ClientMessage::Cancel { id }) => match running.take() {
+                    Some(search) if search.id == id => cancel(&mut session, Some(search)).await,
+                    other => running = other,
+                },
+                Err(e) => {
+                    let message = ServerMessage::Error {
+                        id: None,
+                        error: serde_json::Value::String(e.to_string()),
+                    };
+                    if session.text(message.to_text()).await.is_err() {
+                        break;
+                    }
+                }
+            },
+            Message::Ping(bytes) => {
+                // a closed session also ends `messages`
+                let _ = session.pong(&bytes).await;
+            }
+            Message::Close(reason) => {
+                if let Some(search) = running.take() {
+                    search.handle.abort();
+                }
+                let _ = session.close(reason).await;
+                return;
+            }
+            _ => {}
+        }
+    }
+    if let Some(search) = running {
+        search.handle.abort();
+    }
+}
+
+/// Stops `search` and tells the client (unless it had already finished).
+async fn cancel(session: &mut Session, search: Option<Running>) {
+    let Some(search) = search else {
+        return;
+    };
+    if search.handle.is_finished() {
+        return;
+    }
+    // dropping the stream of the search stops it
+    search.handle.abort();
+    let _ = session
+        .text(ServerMessage::Cancelled { id: search.id }.to_text())
+        .await;
+}
+
+/// Runs a search and sends its progress to the client.
+fn spawn_search(
+    id: u64,
+    form: &SearchForm,
+    config: BookrabConfig,
+    mut session: Session,
+) -> JoinHandle<()> {
+    let params = SearchParams::new(form);
+    let max_matches = form.max_matches_per_book;
+    actix_web::rt::spawn(async move {
+        let params = match params {
+            Ok(v) => v,
+            Err(e) => {
+                let message = ServerMessage::Error {
+                    id: Some(id),
+                    error: serde_json::to_value(ApiError(e)).unwrap(),
+                };
+                let _ = session.text(message.to_text()).await;
+                return;
+            }
+        };
+        let db = match OptionalDB::get() {
+            Ok(v) => v,
+            Err(e) => {
+                let message = ServerMessage::Error {
+                    id: Some(id),
+                    error: serde_json::Value::String(e.to_string()),
+                };
+                let _ = session.text(message.to_text()).await;
+                return;
+            }
+        };
+        let start = Instant::now();
+        let mut messages = stream_search(params, config, db, move |event, finished| {
+            let message = match (event, finished) {
+                (
+                    Some(SearchEvent::Started {
+                        title,
+                        index,
+                        total,
+                    }),
+                    _,
+                ) => ServerMessage::Started {
+                    id,
+                    title,
+                    index,
+                    total,
+                },
+                (Some(SearchEvent::Finished(results)), _) => ServerMessage::Results {
+                    id,
+                    results: TruncatedSearchResults::new(results.clone(), max_matches),
+                },
+                (None, Some(Ok(results))) => ServerMessage::Done {
+                    id,
+                    books: results.len(),
+                    duration_ms: start.elapsed().as_millis() as u64,
+                },
+                (None, Some(Err(e))) => ServerMessage::Error {
+                    id: Some(id),
+                    error: serde_json::to_value(ApiError(e)).unwrap(),
+                },
+                (None, None) => return None,
+            };
+            Some(message.to_text())
+        });
+        while let Some(message) = messages.next().await {
+            if session.text(message).await.is_err() {
+                return;
+            }
+        }
+    })
+}
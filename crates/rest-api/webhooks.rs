@@ -0,0 +1,115 @@
+//! Webhooks: `POST`s with JSON that tell other services what happened
+//! to the library (see [BookrabConfig::webhook_urls]).
+use std::{collections::HashSet, thread, time::Duration};
+
+use bookrab_core::{books::SearchResults, config::BookrabConfig};
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+/// Longest wait between two attempts.
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Something that happened to the library.
+#[derive(Clone, Debug, Serialize)]
+#[serde(tag = "event", rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// The book is new or replaced an old one
+    BookUploaded {
+        title: String,
+        tags: HashSet<String>,
+    },
+    BookDeleted {
+        title: String,
+    },
+    BookRenamed {
+        title: String,
+        new_title: String,
+    },
+    /// Only sent when [BookrabConfig::webhook_searches] is set
+    Searched {
+        pattern: String,
+        /// Number of books with matches
+        books: usize,
+        /// Number of matches in every book
+        matches: usize,
+    },
+}
+
+impl WebhookEvent {
+    pub fn searched(pattern: &str, results: &[SearchResults]) -> Self {
+        WebhookEvent::Searched {
+            pattern: pattern.to_string(),
+            books: results
+                .iter()
+                .filter(|book| !book.results.is_empty())
+                .count(),
+            matches: results.iter().map(SearchResults::match_count).sum(),
+        }
+    }
+}
+
+/// Body of the requests.
+#[derive(Debug, Serialize)]
+struct Payload {
+    #[serde(flatten)]
+    event: WebhookEvent,
+    date: DateTime<Utc>,
+    /// User whose library changed (`None` is the shared library)
+    user_id: Option<i32>,
+}
+
+/// Sends `event` to every webhook of `config` in the background.
+/// Failed deliveries (network errors, `429` and `5xx`) are retried
+/// with exponential backoff, other statuses are given up on.
+pub fn notify(config: &BookrabConfig, event: WebhookEvent) {
+    if config.webhook_urls.is_empty()
+        || (matches!(event, WebhookEvent::Searched { .. }) && !config.webhook_searches)
+    {
+        return;
+    }
+    let payload = Payload {
+        event,
+        date: Utc::now(),
+        user_id: config.user_id,
+    };
+    let body = serde_json::to_vec(&payload).unwrap();
+    let attempts = config.webhook_attempts.unwrap_or(5).max(1);
+    for url in config.webhook_urls.clone() {
+        let body = body.clone();
+        // deliveries block while they wait, so they don't run in the async workers
+        thread::spawn(move || deliver(&url, body, attempts));
+    }
+}
+
+fn deliver(url: &str, body: Vec<u8>, attempts: u32) {
+    let client = reqwest::blocking::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()
+        .expect("couldn't build webhook client");
+    let mut backoff = Duration::from_secs(1);
+    for attempt in 1..=attempts {
+        let response = client
+            .post(url)
+            .header("content-type", "application/json")
+            .body(body.clone())
+            .send();
+        let retry = match response {
+            Ok(response) if response.status().is_success() => return,
+            Ok(response) => {
+                let status = response.status();
+                log::warn!("webhook {url} answered {status} (attempt {attempt}/{attempts})");
+                status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+            }
+            Err(e) => {
+                log::warn!("webhook {url} failed: {e} (attempt {attempt}/{attempts})");
+                true
+            }
+        };
+        if !retry || attempt == attempts {
+            break;
+        }
+        thread::sleep(backoff);
+        backoff = (backoff * 2).min(MAX_BACKOFF);
+    }
+    log::error!("gave up on webhook {url}");
+}
@@ -0,0 +1,59 @@
+//! Copies text with the clipboard of the system or with the OSC 52 escape
+//! sequence (see [ClipboardKind]). OSC 52 asks the terminal to copy the text,
+//! so it also works over SSH. Inside tmux it needs `set -g set-clipboard on`.
+use std::error::Error;
+use std::io::{self, Write};
+
+use arboard::Clipboard;
+use base64::{engine::general_purpose::STANDARD, Engine};
+
+use crate::config::ClipboardKind;
+
+/// Copies `text` (and `html` for the programs that take it, the
+/// OSC 52 sequence only copies `text`).
+pub fn copy(
+    kind: &ClipboardKind,
+    text: String,
+    html: Option<String>,
+) -> Result<(), Box<dyn Error>> {
+    match kind {
+        ClipboardKind::System => Ok(copy_to_system(text, html)?),
+        ClipboardKind::Osc52 => Ok(osc52(&mut io::stdout(), &text)?),
+        ClipboardKind::Auto => match copy_to_system(text.clone(), html) {
+            Ok(()) => Ok(()),
+            Err(e) => {
+                tracing::info!("copying with OSC 52 ({e})");
+                Ok(osc52(&mut io::stdout(), &text)?)
+            }
+        },
+    }
+}
+
+fn copy_to_system(text: String, html: Option<String>) -> Result<(), arboard::Error> {
+    let mut clipboard = Clipboard::new()?;
+    match html {
+        Some(html) => clipboard.set().html(html, Some(text)),
+        None => clipboard.set_text(text),
+    }
+}
+
+/// Writes the OSC 52 sequence that copies `text` to `out`.
+fn osc52<W: Write>(out: &mut W, text: &str) -> io::Result<()> {
+    write!(out, "\x1b]52;c;{}\x07", STANDARD.encode(text))?;
+    out.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::osc52;
+
+    #[test]
+    fn osc52_sequence() {
+        let mut out = vec![];
+        osc52(&mut out, "As armas e os barões").unwrap();
+        assert_eq!(
+            String::from_utf8(out).unwrap(),
+            "\x1b]52;c;QXMgYXJtYXMgZSBvcyBiYXLDtWVz\x07"
+        );
+    }
+}
@@ -0,0 +1,114 @@
+//! Command line opened by `:` (see [App::run_command]).
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Clear, Paragraph};
+use tui_input::backend::crossterm::EventHandler;
+
+use crate::{error_details, App, TagStatus};
+
+impl App<'_> {
+    /// Runs a command of the command line (without the `:`):
+    /// - `q` or `quit`
+    /// - `search pattern` (or `s pattern`)
+    /// - `tag +a -b c` includes `a`, excludes `b` and neither includes
+    ///   nor excludes `c` (`tag` alone does that to every tag)
+    /// - `open title` reads the book
+    /// - `export path` saves the results (as html when `path` ends with `.html`)
+    ///
+    /// Returns whether the app should quit or the problem of the command.
+    pub fn run_command(&mut self, command: &str) -> Result<bool, String> {
+        let command = command.trim();
+        let (name, argument) = command.split_once(' ').unwrap_or((command, ""));
+        let argument = argument.trim();
+        match name {
+            "q" | "quit" => return Ok(true),
+            "s" | "search" if !argument.is_empty() => {
+                self.input = argument.into();
+                self.search();
+            }
+            "tag" | "tags" => {
+                let mut changes = vec![];
+                for word in argument.split_whitespace() {
+                    let (status, name) = if let Some(name) = word.strip_prefix('+') {
+                        (TagStatus::Include, name)
+                    } else if let Some(name) = word.strip_prefix('-') {
+                        (TagStatus::Exclude, name)
+                    } else {
+                        (TagStatus::None, word)
+                    };
+                    if !self.tags.list.iter().any(|tag| tag.name == name) {
+                        return Err(format!("there is no tag called {name}"));
+                    }
+                    changes.push((name, status));
+                }
+                for tag in self.tags.list.iter_mut() {
+                    if argument.is_empty() {
+                        tag.status = TagStatus::None;
+                    } else if let Some((_, status)) =
+                        changes.iter().rev().find(|(name, _)| *name == tag.name)
+                    {
+                        tag.status = status.clone();
+                    }
+                }
+            }
+            "open" if self.remote.is_some() => {
+                return Err("books can't be read in remote mode".to_string());
+            }
+            "open" if !argument.is_empty() => self
+                .open_book(argument.to_string(), 1)
+                .map_err(|e| error_details(&e))?,
+            "export" if !argument.is_empty() => {
+                let html = argument.ends_with(".html") || argument.ends_with(".htm");
+                let contents = if html {
+                    self.results_html()
+                } else {
+                    self.results_text()
+                };
+                std::fs::write(argument, contents)
+                    .map_err(|e| format!("couldn't write {argument}: {e}"))?;
+            }
+            "s" | "search" | "open" | "export" => {
+                return Err(format!(":{name} needs an argument"));
+            }
+            _ => return Err(format!("unknown command :{name}")),
+        }
+        Ok(false)
+    }
+
+    /// Renders the command line (if it is open) over the status line.
+    pub fn render_command(&self, rect: Rect, f: &mut Frame) {
+        let Some(command) = &self.command else {
+            return;
+        };
+        f.render_widget(Clear, rect);
+        f.render_widget(Paragraph::new(format!(":{}", command.value())), rect);
+        let width = rect.width.max(2) - 2; // keep 1 for the colon and 1 for cursor
+        let scroll = command.visual_scroll(width as usize);
+        f.set_cursor_position((
+            rect.x + ((command.visual_cursor()).max(scroll) - scroll) as u16 + 1,
+            rect.y,
+        ));
+    }
+}
+
+/// Keys of the command line. Returns whether the app should quit.
+pub fn bindings(key: KeyEvent, app: &mut App) -> bool {
+    let Some(command) = app.command.as_mut() else {
+        return false;
+    };
+    match key.code {
+        KeyCode::Esc => app.command = None,
+        KeyCode::Enter => {
+            let command = command.value().to_string();
+            app.command = None;
+            match app.run_command(&command) {
+                Ok(quit) => return quit,
+                Err(message) => app.show_error(&format!("run :{command}"), message),
+            }
+        }
+        _ => {
+            command.handle_event(&Event::Key(key));
+        }
+    }
+    false
+}
@@ -1,8 +1,141 @@
+use std::{env, path::PathBuf, sync::OnceLock};
+
 use bookrab_core::config::{ensure_config_works, BookrabConfig};
+use serde::{Deserialize, Serialize};
+
+/// How the TUI copies text.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub enum ClipboardKind {
+    /// The clipboard of the system, or the OSC 52 escape sequence when
+    /// there isn't one (e.g. over SSH)
+    #[default]
+    Auto,
+    /// Only the clipboard of the system
+    System,
+    /// Only the OSC 52 escape sequence, which asks the terminal to copy
+    /// the text (only plain text is copied)
+    Osc52,
+}
+
+/// Settings that only the TUI reads. They are in the `[tui]` section
+/// of the configuration file, which the REST API and the CLI skip.
+#[derive(Serialize, Deserialize, Debug, Clone, Default, PartialEq)]
+pub struct TuiConfig {
+    /// Width of the search panel in percent of the screen
+    /// (`None` means 35). The TUI changes it when the panels are resized
+    #[serde(default)]
+    pub search_panel_width: Option<u16>,
+    /// Lines shown before each match by the searches when the TUI starts
+    #[serde(default)]
+    pub before_context: usize,
+    /// Lines shown after each match by the searches when the TUI starts
+    #[serde(default)]
+    pub after_context: usize,
+    /// Whether the searches start ignoring case
+    /// ([TuiConfig::case_smart] takes precedence)
+    #[serde(default)]
+    pub case_insensitive: bool,
+    /// Whether the searches start with smart case
+    /// (case only matters when the pattern has uppercase letters)
+    #[serde(default)]
+    pub case_smart: bool,
+    /// Whether the searches start matching whole words only
+    #[serde(default)]
+    pub whole_word: bool,
+    /// Color of the matches: a name like `red` or `lightblue`, a hex
+    /// code like `#ff8800` or an index of the 256 colors of the
+    /// terminal (`None` means red)
+    #[serde(default)]
+    pub match_color: Option<String>,
+    /// Whether matches are shown in bold
+    #[serde(default)]
+    pub match_bold: bool,
+    /// Whether matches are shown in reverse video
+    #[serde(default)]
+    pub match_reversed: bool,
+    /// Whether the lines around the matches are dimmed (see
+    /// [TuiConfig::before_context] and [TuiConfig::after_context])
+    #[serde(default)]
+    pub dim_context: bool,
+    /// URL of a bookrab REST API (e.g. `http://localhost:8000`) whose library
+    /// is browsed instead of the local one (`None` uses the local one)
+    #[serde(default)]
+    pub remote_url: Option<String>,
+    /// Token sent to [TuiConfig::remote_url] when it needs authentication
+    #[serde(default)]
+    pub remote_token: Option<String>,
+    /// How text is copied
+    #[serde(default)]
+    pub clipboard: ClipboardKind,
+}
+
+/// The configuration file as the TUI reads it: the configuration
+/// shared with the other apps and its `[tui]` section.
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ConfigFile {
+    #[serde(flatten)]
+    pub config: BookrabConfig,
+    #[serde(default)]
+    pub tui: TuiConfig,
+}
+
+/// Configuration file given by `--config`.
+static CONFIG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Uses `path` instead of the default configuration file, so that
+/// several instances can use different libraries.
+pub fn use_config_file(path: PathBuf) {
+    let _ = CONFIG_FILE.set(path);
+}
+
+/// Configuration file given by `--config` or `BOOKRAB_CONFIG`
+/// (`None` means the default one of confy).
+fn config_file() -> Option<PathBuf> {
+    CONFIG_FILE
+        .get()
+        .cloned()
+        .or_else(|| env::var_os("BOOKRAB_CONFIG").map(PathBuf::from))
+}
+
+/// Loads the configuration file and makes sure it works
+/// (see [ensure_config_works]).
+pub fn check_config() -> Result<ConfigFile, String> {
+    let file = load_config().map_err(|e| format!("couldn't read the configuration: {e}"))?;
+    let config = ensure_config_works(file.config).map_err(|e| format!("bad configuration: {e}"))?;
+    Ok(ConfigFile {
+        config,
+        tui: file.tui,
+    })
+}
 
 /// Loads the configuration file and makes sure it works.
-pub fn ensure_confy_works<'a>() -> BookrabConfig {
-    let config: BookrabConfig = confy::load("bookrab", None).unwrap();
-    ensure_config_works(&config);
-    config
+/// It is checked by [check_config] when the app starts.
+pub fn ensure_confy_works() -> BookrabConfig {
+    check_config()
+        .expect("the configuration was checked when the app started")
+        .config
+}
+
+/// Loads the configuration file as it is (the book folder isn't created).
+pub fn load_config() -> Result<ConfigFile, confy::ConfyError> {
+    match config_file() {
+        Some(path) => confy::load_path(path),
+        None => confy::load("bookrab", None),
+    }
+}
+
+/// Saves `file` in the configuration file.
+pub fn store_config(file: ConfigFile) -> Result<(), confy::ConfyError> {
+    match config_file() {
+        Some(path) => confy::store_path(path, file),
+        None => confy::store("bookrab", None, file),
+    }
+}
+
+/// Saves the width of the search panel in the configuration file
+/// (see [TuiConfig::search_panel_width]).
+pub fn store_search_panel_width(width: u16) -> Result<(), confy::ConfyError> {
+    let mut file = load_config()?;
+    file.tui.search_panel_width = Some(width);
+    store_config(file)
 }
@@ -1,18 +1,17 @@
-use bookrab_core::database::{PgPool, PgPooledConnection};
-use diesel::r2d2::ConnectionManager;
+use bookrab_core::database::{DbConnectionManager, DbPool};
 use lazy_static::lazy_static;
 
 use crate::config::ensure_confy_works;
 
 lazy_static! {
-    pub static ref DBCONNECTION: PgPool = {
+    /// `None` when there is no database in the config.
+    pub static ref DBCONNECTION: Option<DbPool> = {
         let config = ensure_confy_works();
-        PgPool::builder()
-            .max_size(8)
-            .build(ConnectionManager::new(config.database_url))
-            .expect("could not create db connection pool")
+        config.database_url.map(|database_url| {
+            // connecting lazily: the app starts even when the database is down
+            DbPool::builder()
+                .max_size(8)
+                .build_unchecked(DbConnectionManager::new(database_url))
+        })
     };
 }
-pub struct DB {
-    pub connection: PgPooledConnection,
-}
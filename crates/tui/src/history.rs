@@ -0,0 +1,183 @@
+//! Past searches, opened by `Ctrl+r`, that can be run again.
+use bookrab_core::books::history::HistoryContext;
+use bookrab_core::books::history_backend::{history_backend, past_searches, PastSearch};
+use bookrab_core::errors::BookrabError;
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState};
+use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+
+use crate::{
+    render_filter, App, SearchSettings, TagStatus, WhereWeAre, EXCLUDED_FG_COLOR,
+    INCLUDED_FG_COLOR, SELECTED_STYLE, TEXT_FG_COLOR,
+};
+
+/// Past searches, newest first (see [App::open_history]).
+pub struct History {
+    pub searches: Vec<PastSearch>,
+    /// Part of the pattern or of a tag of the searches that are shown
+    pub filter: Input,
+    /// Selected search among the shown ones
+    pub state: ListState,
+}
+
+impl History {
+    /// Searches whose pattern or tags contain the filter (ignoring case).
+    pub fn filtered(&self) -> Vec<&PastSearch> {
+        let filter = self.filter.value().to_lowercase();
+        self.searches
+            .iter()
+            .filter(|search| {
+                search.pattern.to_lowercase().contains(&filter)
+                    || search
+                        .context
+                        .include_tags
+                        .iter()
+                        .chain(&search.context.exclude_tags)
+                        .any(|tag| tag.to_lowercase().contains(&filter))
+            })
+            .collect()
+    }
+
+    /// Selects the first search that is shown (if any).
+    pub fn select_first(&mut self) {
+        let selected = if self.filtered().is_empty() {
+            None
+        } else {
+            Some(0)
+        };
+        self.state = ListState::default().with_selected(selected);
+    }
+}
+
+impl App<'_> {
+    /// Lists the past searches with their match counts.
+    pub fn open_history(&mut self) -> Result<(), BookrabError> {
+        let config = self.root_book_dir.config().clone();
+        let entries = match &self.remote {
+            Some(remote) => remote.history()?,
+            None => {
+                history_backend(&config, self.root_book_dir.connection.as_deref_mut()).export()?
+            }
+        };
+        let mut history = History {
+            searches: past_searches(entries),
+            filter: Input::default(),
+            state: ListState::default(),
+        };
+        history.select_first();
+        self.history = Some(history);
+        Ok(())
+    }
+
+    /// Renders the history (a filter above the list of searches).
+    pub fn render_history(&mut self, rect: Rect, f: &mut Frame) {
+        let Some(history) = self.history.as_mut() else {
+            return;
+        };
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+            .split(rect);
+        render_filter(&history.filter, "Filter", areas[0], f);
+
+        let searches = history.filtered();
+        let title = format!(
+            "History ({} of {} searches, Enter to search again, Esc to go back)",
+            searches.len(),
+            history.searches.len()
+        );
+        let items: Vec<ListItem> = searches
+            .iter()
+            .map(|search| {
+                let mut include: Vec<&str> = search
+                    .context
+                    .include_tags
+                    .iter()
+                    .map(String::as_str)
+                    .collect();
+                let mut exclude: Vec<&str> = search
+                    .context
+                    .exclude_tags
+                    .iter()
+                    .map(String::as_str)
+                    .collect();
+                include.sort();
+                exclude.sort();
+                ListItem::new(Line::from(vec![
+                    Span::from(search.date.format("%Y-%m-%d %H:%M  ").to_string())
+                        .fg(TEXT_FG_COLOR),
+                    Span::from(search.pattern.clone()).blue(),
+                    Span::from(format!(
+                        "  {} matches, {} books searched",
+                        search.match_count, search.books
+                    ))
+                    .fg(TEXT_FG_COLOR),
+                    Span::from(format!("  {}", include.join(", "))).fg(INCLUDED_FG_COLOR),
+                    Span::from(format!("  {}", exclude.join(", "))).fg(EXCLUDED_FG_COLOR),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(SELECTED_STYLE)
+            .highlight_symbol(">");
+        f.render_stateful_widget(list, areas[1], &mut history.state);
+    }
+
+    /// Restores the pattern, tags and modes of the selected search of the
+    /// history and runs it again with its options. Tags that no longer
+    /// exist are left out.
+    pub fn rerun_selected_search(&mut self) {
+        let Some(history) = self.history.take() else {
+            return;
+        };
+        let Some(search) = history
+            .state
+            .selected()
+            .and_then(|i| history.filtered().get(i).map(|search| (*search).clone()))
+        else {
+            self.history = Some(history);
+            return;
+        };
+        self.restore_search(search.pattern, &search.context);
+    }
+
+    /// Puts `pattern` and the tags, modes and options of `context` in
+    /// the panels and searches them. Tags that no longer exist are left out.
+    pub fn restore_search(&mut self, pattern: String, context: &HistoryContext) {
+        self.input = pattern.as_str().into();
+        for tag in self.tags.list.iter_mut() {
+            tag.status = if context.include_tags.contains(&tag.name) {
+                TagStatus::Include
+            } else if context.exclude_tags.contains(&tag.name) {
+                TagStatus::Exclude
+            } else {
+                TagStatus::None
+            };
+        }
+        self.include = context.include_mode.clone();
+        self.exclude = context.exclude_mode.clone();
+        self.settings = SearchSettings::from_context(context);
+        self.where_we_are = WhereWeAre::Results;
+        self.search_with(pattern, context)
+    }
+}
+
+/// Keys of the history.
+pub fn bindings(key: KeyEvent, app: &mut App) {
+    let Some(history) = app.history.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => app.history = None,
+        KeyCode::Enter => app.rerun_selected_search(),
+        KeyCode::Down => history.state.select_next(),
+        KeyCode::Up => history.state.select_previous(),
+        _ => {
+            history.filter.handle_event(&Event::Key(key));
+            history.select_first();
+        }
+    }
+}
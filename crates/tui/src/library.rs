@@ -0,0 +1,237 @@
+//! Every book of the library, with a filter, opened by `Ctrl+b`.
+use std::collections::HashMap;
+
+use bookrab_core::books::catalog::{BookCatalog, CatalogOrder};
+use bookrab_core::errors::BookrabError;
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+
+use crate::{
+    format_size, render_filter, App, TagItem, TagStatus, EXCLUDED_FG_COLOR, INCLUDED_FG_COLOR,
+    SELECTED_STYLE, TEXT_FG_COLOR,
+};
+
+/// A book of the [Library].
+pub struct LibraryBook {
+    pub title: String,
+    /// Sorted tags
+    pub tags: Vec<String>,
+    /// Size of the text in bytes
+    pub size: u64,
+}
+
+/// Every book of the library (see [App::open_library]).
+pub struct Library {
+    pub books: Vec<LibraryBook>,
+    /// Part of the title or of a tag of the books that are shown
+    pub filter: Input,
+    /// Selected book among the shown ones
+    pub state: ListState,
+    /// Book that will be deleted if the user confirms
+    pub confirm_delete: Option<String>,
+}
+
+impl Library {
+    /// Books whose title or tags contain the filter (ignoring case).
+    pub fn filtered(&self) -> Vec<&LibraryBook> {
+        let filter = self.filter.value().to_lowercase();
+        self.books
+            .iter()
+            .filter(|book| {
+                book.title.to_lowercase().contains(&filter)
+                    || book
+                        .tags
+                        .iter()
+                        .any(|tag| tag.to_lowercase().contains(&filter))
+            })
+            .collect()
+    }
+
+    /// Selects the first book that is shown (if any).
+    pub fn select_first(&mut self) {
+        let selected = if self.filtered().is_empty() {
+            None
+        } else {
+            Some(0)
+        };
+        self.state = ListState::default().with_selected(selected);
+    }
+}
+
+impl App<'_> {
+    /// Lists every book with its tags and size. Sizes come from
+    /// the catalog, so texts are only read when there is no database.
+    pub fn open_library(&mut self) -> Result<(), BookrabError> {
+        let config = self.root_book_dir.config().clone();
+        let sizes: HashMap<String, i64> = match self.root_book_dir.connection.as_deref_mut() {
+            Some(connection) => BookCatalog::new(config, connection)
+                .list(CatalogOrder::Title)?
+                .into_iter()
+                .map(|entry| (entry.title, entry.size))
+                .collect(),
+            None => HashMap::new(),
+        };
+        let mut books = vec![];
+        for book in self.root_book_dir.list()? {
+            let size = match sizes.get(&book.title) {
+                Some(size) => u64::try_from(*size).unwrap_or(0),
+                None => self
+                    .root_book_dir
+                    .book_info(&book.title)?
+                    .map_or(0, |info| info.size),
+            };
+            let mut tags: Vec<String> = book.tags.into_iter().collect();
+            tags.sort();
+            books.push(LibraryBook {
+                title: book.title,
+                tags,
+                size,
+            });
+        }
+        books.sort_by(|a, b| a.title.cmp(&b.title));
+        let mut library = Library {
+            books,
+            filter: Input::default(),
+            state: ListState::default(),
+            confirm_delete: None,
+        };
+        library.select_first();
+        self.library = Some(library);
+        Ok(())
+    }
+
+    /// Title of the selected book of the library.
+    fn selected_book(&self) -> Option<String> {
+        let library = self.library.as_ref()?;
+        let i = library.state.selected()?;
+        library.filtered().get(i).map(|book| book.title.clone())
+    }
+
+    /// Opens the selected book of the library at its first line.
+    pub fn open_selected_book(&mut self) -> Result<(), BookrabError> {
+        match self.selected_book() {
+            Some(title) => self.open_book(title, 1),
+            None => Ok(()),
+        }
+    }
+
+    /// Asks whether the selected book of the library should be deleted
+    /// (see [App::delete_book]).
+    pub fn ask_to_delete_selected_book(&mut self) {
+        let title = self.selected_book();
+        if let Some(library) = self.library.as_mut() {
+            library.confirm_delete = title;
+        }
+    }
+
+    /// Deletes `title` and removes it from the library, the results and
+    /// the tags (tags that are still used keep their status).
+    pub fn delete_book(&mut self, title: &str) -> Result<(), BookrabError> {
+        self.root_book_dir.delete(title)?;
+        if let Some(library) = self.library.as_mut() {
+            library.books.retain(|book| book.title != title);
+            let selected = library.state.selected().unwrap_or(0);
+            let shown = library.filtered().len();
+            library.state.select(if shown == 0 {
+                None
+            } else {
+                Some(selected.min(shown - 1))
+            });
+        }
+        if self.results.iter().any(|result| result.title == title) {
+            self.results.retain(|result| result.title != title);
+            self.select_first_result();
+        }
+        let statuses: HashMap<String, TagStatus> = self
+            .tags
+            .list
+            .drain(..)
+            .map(|tag| (tag.name, tag.status))
+            .collect();
+        self.tags.list = self
+            .root_book_dir
+            .all_tags()?
+            .into_iter()
+            .map(|tag| TagItem {
+                status: statuses.get(&tag).cloned().unwrap_or(TagStatus::None),
+                name: tag,
+            })
+            .collect();
+        self.tags.state = ListState::default();
+        Ok(())
+    }
+
+    /// Renders the library (a filter above the list of books).
+    pub fn render_library(&mut self, rect: Rect, f: &mut Frame) {
+        let Some(library) = self.library.as_mut() else {
+            return;
+        };
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+            .split(rect);
+        if let Some(title) = &library.confirm_delete {
+            f.render_widget(
+                Paragraph::new(format!("Delete {title}? (y/n)"))
+                    .style(Style::default().fg(EXCLUDED_FG_COLOR))
+                    .block(Block::default().borders(Borders::ALL).title("Delete")),
+                areas[0],
+            );
+        } else {
+            render_filter(&library.filter, "Filter", areas[0], f);
+        }
+
+        let books = library.filtered();
+        let title = format!(
+            "Library ({} of {} books, Enter to read, Delete to delete, Esc to go back)",
+            books.len(),
+            library.books.len()
+        );
+        let items: Vec<ListItem> = books
+            .iter()
+            .map(|book| {
+                ListItem::new(Line::from(vec![
+                    Span::from(book.title.clone()).blue(),
+                    Span::from(format!("  {}", book.tags.join(", "))).fg(INCLUDED_FG_COLOR),
+                    Span::from(format!("  {}", format_size(book.size))).fg(TEXT_FG_COLOR),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(SELECTED_STYLE)
+            .highlight_symbol(">");
+        f.render_stateful_widget(list, areas[1], &mut library.state);
+    }
+}
+
+/// Keys of the library.
+pub fn bindings(key: KeyEvent, app: &mut App) {
+    let Some(library) = app.library.as_mut() else {
+        return;
+    };
+    if let Some(title) = library.confirm_delete.take() {
+        if key.code == KeyCode::Char('y') {
+            let deleted = app.delete_book(&title);
+            app.report(&format!("delete {title}"), deleted);
+        }
+        return;
+    }
+    match key.code {
+        KeyCode::Esc => app.library = None,
+        KeyCode::Delete => app.ask_to_delete_selected_book(),
+        KeyCode::Down => library.state.select_next(),
+        KeyCode::Up => library.state.select_previous(),
+        KeyCode::Enter => {
+            let opened = app.open_selected_book();
+            app.report("open the book", opened);
+        }
+        _ => {
+            library.filter.handle_event(&Event::Key(key));
+            library.select_first();
+        }
+    }
+}
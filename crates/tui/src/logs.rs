@@ -1,6 +1,6 @@
 // https://ratatui.rs/recipes/apps/log-with-tracing/
 
-use color_eyre::eyre::{Context, Result};
+use color_eyre::eyre::Result;
 use directories::ProjectDirs;
 use lazy_static::lazy_static;
 use std::path::PathBuf;
@@ -1,40 +1,294 @@
 use crate::database::DBCONNECTION;
-use arboard::Clipboard;
-use bookrab_core::books::{Exclude, FilterMode, Include, RootBookDir, SearchResults};
+use bookrab_core::books::history::{HistoryContext, SearchHistory};
+use bookrab_core::books::reading_progress::ReadingPosition;
+use bookrab_core::books::validation::validate_pattern;
+use bookrab_core::books::{
+    Exclude, FilterMode, Include, LineKind, RootBookDir, SearchEvent, SearchOptions, SearchResults,
+};
 use bookrab_core::errors::BookrabError;
-use config::ensure_confy_works;
-use crossterm::event::{KeyEvent, KeyModifiers};
+use config::{check_config, ensure_confy_works, ConfigFile, TuiConfig};
+use crossterm::event::{KeyEvent, KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
 use grep_regex::RegexMatcherBuilder;
 use grep_searcher::SearcherBuilder;
+use history::History;
+use library::Library;
 use logs::initialize_logging;
 use ratatui::prelude::*;
-use ratatui::widgets::{ListItem, ListState, Wrap};
+use ratatui::widgets::{ListItem, ListState};
 use ratatui::{
     crossterm::{
         event::{self, DisableMouseCapture, EnableMouseCapture, Event, KeyCode},
         execute,
         terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
     },
-    widgets::{Block, Borders, List, Paragraph},
+    widgets::{Block, Borders, Clear, List, Paragraph, Wrap},
 };
+use reader::Reader;
+use remote::Remote;
+use saved_searches::SavedSearchList;
+use settings::SettingsEditor;
 use std::collections::HashSet;
 use std::iter::Iterator;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver, TryRecvError};
+use std::thread;
+use std::time::{Duration, Instant};
 use std::{error::Error, io};
 use strum::EnumIter;
 use strum::IntoEnumIterator;
 use style::palette::tailwind::{GREEN, RED, SLATE};
+use tabs::SearchTab;
 use tui_input::backend::crossterm::EventHandler;
 use tui_input::Input;
+mod clipboard;
+mod command_line;
 mod config;
 mod database;
+mod history;
+mod library;
 mod logs;
+mod reader;
+mod remote;
+mod saved_searches;
+mod settings;
+mod tabs;
 
 const TEXT_FG_COLOR: Color = SLATE.c600;
 const INCLUDED_FG_COLOR: Color = GREEN.c500;
 const EXCLUDED_FG_COLOR: Color = RED.c500;
 const SELECTED_STYLE: Style = Style::new().bg(SLATE.c300).add_modifier(Modifier::BOLD);
+/// Frames of the spinner shown while a search runs.
+const SPINNER: [&str; 4] = ["|", "/", "-", "\\"];
+/// How often the spinner changes.
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+/// Width of the search panel in percent (see [App::resize_panels]).
+const DEFAULT_SEARCH_PANEL_WIDTH: u16 = 35;
+/// Narrowest and widest the search panel can get.
+const SEARCH_PANEL_WIDTHS: std::ops::RangeInclusive<u16> = 15..=85;
+/// Key bindings shown by the help (see [App::help]), grouped by focus area.
+const HELP: &[(&str, &[(&str, &str)])] = &[
+    (
+        "Everywhere",
+        &[
+            ("Tab / Shift+Tab", "focus the next / previous area"),
+            ("Esc", "leave the focused area"),
+            ("Enter", "search"),
+            ("Ctrl+y", "copy every result"),
+            ("Ctrl+Shift+y", "copy every result as plain text"),
+            ("Ctrl+b", "browse the library"),
+            ("Ctrl+r", "browse the history"),
+            ("Ctrl+s", "save the search (pattern, tags and options)"),
+            ("Ctrl+o", "browse the saved searches"),
+            ("Ctrl+p", "edit the settings"),
+            ("Ctrl+Left / Ctrl+Right", "narrow / widen the search panel"),
+            ("Ctrl+f", "show only the results (or both panels again)"),
+            ("1-9", "show that tab (outside of text fields)"),
+            ("Ctrl+t / Ctrl+w", "open a new tab / close the tab"),
+            ("Ctrl+c", "quit"),
+            ("?", "show this help (outside of text fields)"),
+            (":", "type a command (outside of text fields)"),
+        ],
+    ),
+    (
+        "Commands",
+        &[
+            (":q", "quit"),
+            (":search pattern, :s pattern", "search the pattern"),
+            (
+                ":tag +a -b c",
+                "include a, exclude b, neither c (none: every tag)",
+            ),
+            (":open title", "read the book"),
+            (
+                ":export path",
+                "save the results (as html if path ends with .html)",
+            ),
+        ],
+    ),
+    (
+        "Nothing focused",
+        &[("e", "edit the search"), ("q", "quit")],
+    ),
+    (
+        "Search",
+        &[("any key", "edit the pattern (:name runs a saved search)")],
+    ),
+    (
+        "Tags",
+        &[
+            ("j / k, Down / Up", "select the next / previous tag"),
+            ("l / Right", "include the tag"),
+            ("h / Left", "exclude the tag"),
+            ("Space", "cycle through include, exclude and neither"),
+        ],
+    ),
+    (
+        "Include / Exclude",
+        &[("Space", "switch between all and any of the tags")],
+    ),
+    (
+        "Options",
+        &[
+            ("h / l, Left / Right", "select the previous / next option"),
+            ("k / j, Up / Down, Space", "change the option"),
+        ],
+    ),
+    (
+        "Results",
+        &[
+            (
+                "j / k, Down / Up",
+                "select the next / previous book or snippet",
+            ),
+            (
+                "Enter",
+                "read the book at the snippet (or open / close the book)",
+            ),
+            (
+                "Enter (before searching)",
+                "continue reading the book where you stopped",
+            ),
+            ("Space", "open / close the book of the selection"),
+            (
+                "h / l, Left / Right",
+                "close / open the book of the selection",
+            ),
+            ("- / +", "close / open every book"),
+            ("y", "copy the snippet"),
+            ("Y", "copy the snippet as plain text"),
+            ("p", "pin / unpin the snippet (pins are marked with *)"),
+            ("w", "wrap / cut long lines"),
+            ("#", "show / hide the line numbers"),
+            ("n / N", "go to the next / previous match"),
+        ],
+    ),
+    (
+        "Pinned",
+        &[
+            ("j / k, Down / Up", "select the next / previous pin"),
+            ("Enter", "read the book at the pin"),
+            ("p / d, Delete", "unpin"),
+            ("y / Y", "copy the pin (as plain text)"),
+        ],
+    ),
+    (
+        "Mouse",
+        &[
+            (
+                "click",
+                "focus an area, select a snippet, open a book or cycle a tag",
+            ),
+            (
+                "wheel",
+                "select the next / previous tag or snippet, scroll the book",
+            ),
+        ],
+    ),
+    (
+        "Reader",
+        &[
+            ("j / k, Down / Up", "scroll one line"),
+            ("Space, PageDown / PageUp", "scroll one page"),
+            ("Esc, q", "close the book"),
+        ],
+    ),
+    (
+        "Library",
+        &[
+            ("any key", "filter by title or tag"),
+            ("Down / Up", "select the next / previous book"),
+            ("Enter", "read the book"),
+            ("Delete", "delete the book (y confirms)"),
+            ("Esc", "go back"),
+        ],
+    ),
+    (
+        "Saved searches",
+        &[
+            ("any key", "filter by name or pattern"),
+            ("Down / Up", "select the next / previous search"),
+            ("Enter", "load and run the search"),
+            ("Esc", "go back"),
+        ],
+    ),
+    (
+        "Settings",
+        &[
+            ("any key", "edit the selected field"),
+            (
+                "Down / Up, Tab / Shift+Tab",
+                "select the next / previous field",
+            ),
+            ("Enter", "save the settings"),
+            ("Esc", "cancel"),
+        ],
+    ),
+    (
+        "History",
+        &[
+            ("any key", "filter by pattern or tag"),
+            ("Down / Up", "select the next / previous search"),
+            ("Enter", "search again"),
+            ("Esc", "go back"),
+        ],
+    ),
+];
+
+/// Flags of the command line (`--flag value` or `--flag=value`).
+#[derive(Debug, Default, PartialEq)]
+struct Flags {
+    /// `--remote <url>`, which takes precedence over [TuiConfig::remote_url]
+    remote: Option<String>,
+    /// `--config <path>`, the configuration file (see [config::use_config_file])
+    config: Option<PathBuf>,
+}
+
+impl Flags {
+    fn parse(mut args: impl Iterator<Item = String>) -> Result<Flags, String> {
+        let mut flags = Flags::default();
+        while let Some(arg) = args.next() {
+            let (flag, value) = match arg.split_once('=') {
+                Some((flag, value)) => (flag.to_string(), Some(value.to_string())),
+                None => (arg, None),
+            };
+            if flag != "--remote" && flag != "--config" {
+                return Err(format!("unknown argument {flag}"));
+            }
+            let value = value
+                .or_else(|| args.next())
+                .ok_or_else(|| format!("{flag} needs a value"))?;
+            match flag.as_str() {
+                "--remote" => flags.remote = Some(value),
+                _ => flags.config = Some(PathBuf::from(value)),
+            }
+        }
+        Ok(flags)
+    }
+}
 
 fn main() -> Result<(), Box<dyn Error>> {
+    // before the terminal is taken over, so that the errors can be read
+    let flags = match Flags::parse(std::env::args().skip(1)) {
+        Ok(flags) => flags,
+        Err(e) => {
+            eprintln!("bookrab-tui: {e}");
+            std::process::exit(2);
+        }
+    };
+    if let Some(path) = flags.config {
+        config::use_config_file(path);
+    }
+    let ConfigFile { tui, .. } = match check_config() {
+        Ok(file) => file,
+        Err(e) => {
+            eprintln!("bookrab-tui: {e}");
+            std::process::exit(1);
+        }
+    };
+    let remote = flags
+        .remote
+        .or(tui.remote_url.clone())
+        .map(|url| Remote::new(&url, &tui));
     // setup terminal
     enable_raw_mode()?;
     initialize_logging()?;
@@ -42,11 +296,37 @@ fn main() -> Result<(), Box<dyn Error>> {
     execute!(stdout, EnterAlternateScreen, EnableMouseCapture)?;
     let backend = CrosstermBackend::new(stdout);
     let mut terminal = Terminal::new(backend)?;
-    let connection = &mut DBCONNECTION.get().unwrap();
-    let root = RootBookDir::new(ensure_confy_works(), connection);
+    // without a connection, the app works like there is no database
+    // (the remote mode doesn't need one)
+    let mut connection_error = None;
+    let pool = DBCONNECTION.as_ref().filter(|_| remote.is_none());
+    let mut connection = match pool.map(|pool| pool.get()) {
+        Some(Ok(connection)) => Some(connection),
+        Some(Err(e)) => {
+            tracing::error!("couldn't connect to the database: {:?}", e);
+            connection_error = Some(e.to_string());
+            None
+        }
+        None => None,
+    };
+    let root = match connection.as_mut() {
+        Some(connection) => {
+            if let Err(e) = SearchHistory::new(ensure_confy_works(), connection).prune() {
+                tracing::error!("couldn't prune history: {:?}", e);
+            }
+            RootBookDir::new(ensure_confy_works(), connection)
+        }
+        None => RootBookDir::without_database(ensure_confy_works()),
+    };
 
     // create app and run it
-    let app = App::new(root);
+    let mut app = App::new(root, tui);
+    if let Some(e) = connection_error {
+        app.show_error("connect to the database", e);
+    }
+    if let Some(remote) = remote {
+        app.use_remote(remote);
+    }
     let res = run_app(&mut terminal, app);
 
     // restore terminal
@@ -65,12 +345,15 @@ fn main() -> Result<(), Box<dyn Error>> {
     Ok(())
 }
 
-#[derive(PartialEq, EnumIter)]
+#[derive(Clone, Copy, PartialEq, EnumIter)]
 enum WhereWeAre {
     Input,
     Tags,
     Include,
     Exclude,
+    Options,
+    Results,
+    Pinned,
     Nowhere,
 }
 
@@ -91,43 +374,372 @@ enum TagStatus {
     None,
 }
 
+/// How letters are compared (see [SearchSettings]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, EnumIter)]
+enum CaseMode {
+    #[default]
+    Sensitive,
+    Insensitive,
+    /// Insensitive unless the pattern has uppercase letters
+    Smart,
+}
+
+/// Field of the options panel (see [SearchSettings::selected]).
+#[derive(Clone, Copy, Debug, Default, PartialEq, EnumIter)]
+enum SearchOption {
+    #[default]
+    BeforeContext,
+    AfterContext,
+    Case,
+    WholeWord,
+}
+
+/// How the matches are shown in the results
+/// (see [TuiConfig::match_color]).
+#[derive(Clone, Copy, PartialEq, Debug)]
+struct MatchStyle {
+    matched: Style,
+    /// Lines of the snippets without matches are dimmed
+    dim_context: bool,
+}
+
+impl Default for MatchStyle {
+    fn default() -> Self {
+        MatchStyle {
+            matched: Style::default().fg(Color::Red),
+            dim_context: false,
+        }
+    }
+}
+
+impl MatchStyle {
+    /// Style of the configuration or why its color isn't valid.
+    fn from_config(config: &TuiConfig) -> Result<Self, String> {
+        let color = match &config.match_color {
+            Some(color) => color
+                .trim()
+                .parse::<Color>()
+                .map_err(|_| format!("\"{color}\" isn't a color (tui.match_color)"))?,
+            None => Color::Red,
+        };
+        let mut matched = Style::default().fg(color);
+        if config.match_bold {
+            matched = matched.bold();
+        }
+        if config.match_reversed {
+            matched = matched.reversed();
+        }
+        Ok(MatchStyle {
+            matched,
+            dim_context: config.dim_context,
+        })
+    }
+}
+
+/// Options of the searches made with [App::search].
+#[derive(Default)]
+struct SearchSettings {
+    /// Lines shown before each match
+    before_context: usize,
+    /// Lines shown after each match
+    after_context: usize,
+    case: CaseMode,
+    /// Only whole words match. The pattern is wrapped in `\b(?:...)\b`,
+    /// so that the history still reproduces the search exactly.
+    whole_word: bool,
+    /// Field changed by the keys of the panel
+    selected: SearchOption,
+}
+
+impl SearchSettings {
+    /// Options as they are stored in the history (without the tags).
+    fn context(&self) -> HistoryContext {
+        HistoryContext {
+            case_insensitive: self.case == CaseMode::Insensitive,
+            case_smart: self.case == CaseMode::Smart,
+            before_context: self.before_context,
+            after_context: self.after_context,
+            ..Default::default()
+        }
+    }
+
+    /// Options the searches start with (see [TuiConfig::before_context]).
+    fn from_config(config: &TuiConfig) -> Self {
+        let case = if config.case_smart {
+            CaseMode::Smart
+        } else if config.case_insensitive {
+            CaseMode::Insensitive
+        } else {
+            CaseMode::Sensitive
+        };
+        SearchSettings {
+            before_context: config.before_context,
+            after_context: config.after_context,
+            case,
+            whole_word: config.whole_word,
+            ..Default::default()
+        }
+    }
+
+    /// Options of a search of the history.
+    fn from_context(context: &HistoryContext) -> Self {
+        let case = if context.case_smart {
+            CaseMode::Smart
+        } else if context.case_insensitive {
+            CaseMode::Insensitive
+        } else {
+            CaseMode::Sensitive
+        };
+        SearchSettings {
+            before_context: context.before_context,
+            after_context: context.after_context,
+            case,
+            ..Default::default()
+        }
+    }
+
+    /// Pattern that is actually searched.
+    fn pattern(&self, pattern: &str) -> String {
+        if self.whole_word && !pattern.is_empty() {
+            format!("\\b(?:{pattern})\\b")
+        } else {
+            pattern.to_string()
+        }
+    }
+
+    /// Selects the next field (or the previous one if `forward` isn't set).
+    fn select(&mut self, forward: bool) {
+        self.selected = if forward {
+            cycled(SearchOption::iter(), self.selected)
+        } else {
+            cycled(SearchOption::iter().rev(), self.selected)
+        };
+    }
+
+    /// Changes the selected field. Context lines go up or
+    /// down, the case cycles and whole words are toggled.
+    fn change(&mut self, up: bool) {
+        match self.selected {
+            SearchOption::BeforeContext if up => self.before_context += 1,
+            SearchOption::BeforeContext => {
+                self.before_context = self.before_context.saturating_sub(1)
+            }
+            SearchOption::AfterContext if up => self.after_context += 1,
+            SearchOption::AfterContext => self.after_context = self.after_context.saturating_sub(1),
+            SearchOption::Case if up => self.case = cycled(CaseMode::iter(), self.case),
+            SearchOption::Case => self.case = cycled(CaseMode::iter().rev(), self.case),
+            SearchOption::WholeWord => self.whole_word = !self.whole_word,
+        }
+    }
+}
+
+/// Item that comes after `current` in `items` (going back to the first one).
+fn cycled<T: PartialEq, I: Iterator<Item = T> + Clone>(items: I, current: T) -> T {
+    items
+        .cycle()
+        .skip_while(|item| *item != current)
+        .nth(1)
+        .unwrap()
+}
+
+/// Sent by the thread of a search (see [App::spawn_search]).
+enum SearchMessage {
+    /// The book at `index` (starting at 0) of `total` is being searched
+    /// (`total` is unknown for remote searches)
+    Progress {
+        index: usize,
+        total: Option<usize>,
+    },
+    Done(Result<Vec<SearchResults>, BookrabError>),
+}
+
+/// Error shown in a window above the rest until a key is pressed,
+/// so that the session continues (see [App::report]).
+struct ErrorPopup {
+    /// What couldn't be done (e.g. "open the library")
+    action: String,
+    /// Short message followed by the details
+    message: String,
+}
+
+/// Snippet kept across the searches of the session (see [App::toggle_pin]).
+#[derive(Clone, PartialEq, Debug)]
+struct Pin {
+    title: String,
+    snippet: String,
+    /// Line of its match (see [App::selected_line])
+    line: Option<u64>,
+}
+
+/// Row of the results (see [App::result_items]).
+#[derive(Clone, Copy, PartialEq, Debug)]
+enum ResultItem {
+    /// Title of a book (index of [App::results])
+    Book(usize),
+    /// Book and index of one of its snippets
+    Snippet(usize, usize),
+}
+
+impl ResultItem {
+    fn book(self) -> usize {
+        match self {
+            ResultItem::Book(book) | ResultItem::Snippet(book, _) => book,
+        }
+    }
+}
+
+/// Search that runs in another thread (see [App::spawn_search]).
+struct PendingSearch {
+    receiver: Receiver<SearchMessage>,
+    started: Instant,
+    /// Last [SearchMessage::Progress]
+    progress: Option<(usize, Option<usize>)>,
+}
+
 /// App holds the state of the application
 struct App<'a> {
     input: Input,
     where_we_are: WhereWeAre,
     root_book_dir: RootBookDir<'a>,
+    /// Settings of the `[tui]` section of the configuration
+    tui: TuiConfig,
     tags: TagList,
     results: Vec<SearchResults>,
+    /// Matches of each book of [App::results], counted once
+    /// instead of on every redraw (see [App::set_results])
+    match_counts: Vec<usize>,
+    /// Selected book or snippet (see [App::result_items])
+    results_state: ListState,
+    /// Books of [App::results] whose snippets are hidden
+    collapsed: HashSet<usize>,
+    /// Search whose results didn't arrive yet
+    pending: Option<PendingSearch>,
+    /// Shown instead of the panels when a book is open
+    reader: Option<Reader>,
+    /// Books whose reading stopped somewhere, shown instead of
+    /// the results before the first search (see [App::close_reader])
+    continue_reading: Vec<ReadingPosition>,
+    continue_state: ListState,
+    /// Shown instead of the panels while the library is browsed
+    library: Option<Library>,
+    /// Shown instead of the panels while the history is browsed
+    history: Option<History>,
+    /// Shown instead of the panels while saved searches are browsed
+    saved_searches: Option<SavedSearchList>,
+    /// Server whose library is browsed instead of the local one (see [remote])
+    remote: Option<Remote>,
+    /// Command line opened by `:` (see [App::run_command])
+    command: Option<Input>,
+    /// Shown instead of the panels while the configuration is edited
+    settings_editor: Option<SettingsEditor>,
+    /// Name of the current search, asked before it is saved
+    /// (see [App::save_current_search])
+    save_prompt: Option<Input>,
+    /// Shown above everything else (see [HELP])
+    help: bool,
+    /// Message of the error of the last search (see [App::status_line])
+    last_error: Option<String>,
+    /// Shown above everything else, even the help
+    error: Option<ErrorPopup>,
+    /// Width of the search panel in percent
+    /// (the results get the rest, see [App::resize_panels])
+    search_panel_width: u16,
+    /// Whether the results take the whole screen
+    full_screen_results: bool,
+    /// Book, snippet and index in the snippet of the match
+    /// highlighted by [App::jump_to_match]
+    current_match: Option<(usize, usize, usize)>,
+    /// Whether long lines of the results are wrapped (instead of cut)
+    wrap_results: bool,
+    /// Whether the results show the line numbers of the snippets
+    line_numbers: bool,
+    /// Columns of the results when they were last drawn
+    /// (see [App::wrap_results])
+    results_width: usize,
+    /// Shown below the results while there are any
+    pinned: Vec<Pin>,
+    pinned_state: ListState,
+    /// Where each area was last drawn, to find what is clicked
+    /// (see [App::handle_mouse])
+    areas: Vec<(WhereWeAre, Rect)>,
+    /// Options of the searches (see [WhereWeAre::Options])
+    settings: SearchSettings,
+    match_style: MatchStyle,
     include: FilterMode,
     exclude: FilterMode,
+    /// Searches of the tabs. The shown one is `None`, its search is in
+    /// the other fields of the app
+    tabs: Vec<Option<SearchTab>>,
+    /// Index of the shown tab
+    tab: usize,
 }
 
 impl App<'_> {
-    fn new<'a>(root: RootBookDir<'a>) -> App<'a> {
-        let tags = TagList {
-            list: root
-                .all_tags()
-                .unwrap()
-                .into_iter()
-                .map(|tag| TagItem {
-                    name: tag,
-                    status: TagStatus::None,
-                })
-                .collect(),
-            state: ListState::default(),
+    fn new<'a>(mut root: RootBookDir<'a>, tui: TuiConfig) -> App<'a> {
+        let search_panel_width = tui
+            .search_panel_width
+            .unwrap_or(DEFAULT_SEARCH_PANEL_WIDTH)
+            .clamp(*SEARCH_PANEL_WIDTHS.start(), *SEARCH_PANEL_WIDTHS.end());
+        let settings = SearchSettings::from_config(&tui);
+        let (match_style, style_error) = match MatchStyle::from_config(&tui) {
+            Ok(style) => (style, None),
+            Err(message) => (MatchStyle::default(), Some(message)),
+        };
+        let (all_tags, error) = match root.all_tags() {
+            Ok(tags) => (tags, None),
+            Err(e) => (HashSet::new(), Some(e)),
         };
+        let tags = TagList::from(all_tags);
         let include = FilterMode::All;
         let exclude = FilterMode::Any;
         let results = vec![];
-        App {
+        let mut app = App {
             input: Input::default(),
             where_we_are: WhereWeAre::Nowhere,
             root_book_dir: root,
+            tui,
             tags,
             include,
             exclude,
             results,
+            results_state: ListState::default(),
+            match_counts: vec![],
+            collapsed: HashSet::new(),
+            pending: None,
+            reader: None,
+            continue_reading: vec![],
+            continue_state: ListState::default(),
+            library: None,
+            history: None,
+            saved_searches: None,
+            settings_editor: None,
+            command: None,
+            remote: None,
+            save_prompt: None,
+            help: false,
+            last_error: None,
+            error: None,
+            areas: vec![],
+            search_panel_width,
+            full_screen_results: false,
+            current_match: None,
+            wrap_results: false,
+            line_numbers: false,
+            results_width: 0,
+            pinned: vec![],
+            pinned_state: ListState::default(),
+            settings,
+            match_style,
+            tabs: vec![None],
+            tab: 0,
+        };
+        if let Some(message) = style_error {
+            app.show_error("style the matches", message);
         }
+        app.report("read the tags", error.map_or(Ok(()), Err));
+        let listed = app.load_continue_reading();
+        app.report("list the books you were reading", listed);
+        app
     }
 
     /// Returns highlighted style if `area` matches with
@@ -150,18 +762,28 @@ impl App<'_> {
                     Constraint::Length(3),
                     Constraint::Min(1),
                     Constraint::Length(3),
+                    Constraint::Length(3),
                 ]
                 .as_ref(),
             )
             .split(rect);
+        self.areas.push((WhereWeAre::Input, search_panel[0]));
+        self.areas.push((WhereWeAre::Tags, search_panel[1]));
+        self.areas.push((WhereWeAre::Options, search_panel[3]));
         // let help = Paragraph::new(format!("{:?}", ensure_confy_works().book_path));
         // f.render_widget(help, search_panel[0]);
+        // the pattern is validated as the user types
+        let diagnostics = validate_pattern(self.input.value(), &RegexMatcherBuilder::new());
+        let query_title = match diagnostics.message {
+            Some(message) => Line::from(format!("Query ({message})")).red(),
+            None => Line::from("Query"),
+        };
         let input = Paragraph::new(self.input.value())
             .style(self.highlight_if_focused(WhereWeAre::Input))
-            .block(Block::default().borders(Borders::ALL).title("Query"));
+            .block(Block::default().borders(Borders::ALL).title(query_title));
         f.render_widget(input, search_panel[0]);
 
-        let tags_vec: Vec<ListItem> = self.tags.list.iter().map(|v| ListItem::from(v)).collect();
+        let tags_vec: Vec<ListItem> = self.tags.list.iter().map(ListItem::from).collect();
         let tags_ui = List::new(tags_vec)
             .block(Block::default().borders(Borders::ALL).title("Tags"))
             .style(self.highlight_if_focused(WhereWeAre::Tags))
@@ -174,6 +796,8 @@ impl App<'_> {
             .direction(Direction::Horizontal)
             .constraints([Constraint::Percentage(50), Constraint::Fill(1)].as_ref())
             .split(search_panel[2]);
+        self.areas.push((WhereWeAre::Include, filter_modes[0]));
+        self.areas.push((WhereWeAre::Exclude, filter_modes[1]));
 
         f.render_widget(
             Paragraph::new(format!("{:?}", self.include))
@@ -188,71 +812,1004 @@ impl App<'_> {
             filter_modes[1],
         );
 
+        let settings = &self.settings;
+        let fields = [
+            (
+                SearchOption::BeforeContext,
+                format!("before: {}", settings.before_context),
+            ),
+            (
+                SearchOption::AfterContext,
+                format!("after: {}", settings.after_context),
+            ),
+            (
+                SearchOption::Case,
+                format!("case: {:?}", settings.case).to_lowercase(),
+            ),
+            (
+                SearchOption::WholeWord,
+                format!("word: {}", if settings.whole_word { "on" } else { "off" }),
+            ),
+        ];
+        let focused = self.where_we_are == WhereWeAre::Options;
+        let mut spans = vec![];
+        for (field, text) in fields {
+            if !spans.is_empty() {
+                spans.push(Span::from("  "));
+            }
+            if focused && field == settings.selected {
+                spans.push(Span::styled(text, SELECTED_STYLE));
+            } else {
+                spans.push(Span::from(text));
+            }
+        }
+        f.render_widget(
+            Paragraph::new(Line::from(spans))
+                .block(Block::default().title("Options").borders(Borders::ALL))
+                .style(self.highlight_if_focused(WhereWeAre::Options)),
+            search_panel[3],
+        );
+
         let width = search_panel[0].width.max(3) - 3; // keep 2 for borders and 1 for cursor
         let scroll = self.input.visual_scroll(width as usize);
-        match self.where_we_are {
-            WhereWeAre::Input => f.set_cursor_position((
+        if self.where_we_are == WhereWeAre::Input {
+            f.set_cursor_position((
                 search_panel[0].x + ((self.input.visual_cursor()).max(scroll) - scroll) as u16 + 1,
                 search_panel[0].y + 1,
-            )),
-            _ => {}
+            ))
+        }
+    }
+
+    /// Shows new results (every book is open and the first snippet is selected).
+    fn set_results(&mut self, results: Vec<SearchResults>) {
+        self.match_counts = results.iter().map(|r| r.match_count()).collect();
+        self.results = results;
+        self.collapsed.clear();
+        self.current_match = None;
+        self.select_first_result();
+    }
+
+    /// Selects the snippet of the next match (or of the previous one if
+    /// `forward` isn't set) and highlights the match, going around at the
+    /// ends of the results. The first jump goes to the selected snippet.
+    fn jump_to_match(&mut self, forward: bool) {
+        let matches: Vec<(usize, usize, usize)> = self
+            .results
+            .iter()
+            .enumerate()
+            .flat_map(|(book, result)| {
+                result
+                    .results
+                    .iter()
+                    .enumerate()
+                    .flat_map(move |(snippet, text)| {
+                        (0..text.matches("[matched]").count()).map(move |i| (book, snippet, i))
+                    })
+            })
+            .collect();
+        if matches.is_empty() {
+            return;
+        }
+        let selected = match self.selected_item() {
+            Some(ResultItem::Book(book)) => Some((book, 0)),
+            Some(ResultItem::Snippet(book, snippet)) => Some((book, snippet)),
+            None => None,
+        };
+        let current = self
+            .current_match
+            .filter(|(book, snippet, _)| selected == Some((*book, *snippet)))
+            .and_then(|current| matches.iter().position(|m| *m == current));
+        let (book, snippet) = selected.unwrap_or_default();
+        let index = match current {
+            Some(current) if forward => (current + 1) % matches.len(),
+            Some(current) => (current + matches.len() - 1) % matches.len(),
+            // the matches are in the order of the results
+            None if forward => matches
+                .iter()
+                .position(|m| *m >= (book, snippet, 0))
+                .unwrap_or(0),
+            None => matches
+                .iter()
+                .rposition(|m| *m < (book, snippet, 0))
+                .unwrap_or(matches.len() - 1),
+        };
+        let (book, snippet, i) = matches[index];
+        self.collapsed.remove(&book);
+        let item = self
+            .result_items()
+            .iter()
+            .position(|item| *item == ResultItem::Snippet(book, snippet));
+        self.results_state.select(item);
+        self.current_match = Some((book, snippet, i));
+    }
+
+    /// Rows that `item` takes in the results.
+    fn item_height(&self, item: ResultItem) -> usize {
+        match item {
+            _ if self.wrap_results => self.result_lines(item).len(),
+            ResultItem::Book(_) => 1,
+            ResultItem::Snippet(book, result) => self.results[book].results[result].lines().count(),
         }
     }
 
+    fn result_list_item(&self, item: ResultItem) -> ListItem<'_> {
+        ListItem::new(Text::from(self.result_lines(item)))
+    }
+
+    /// Line numbers of the lines of a snippet (`None` when they aren't
+    /// known, e.g. in the results of a [Remote]).
+    fn snippet_line_numbers(&self, book: usize, result: usize) -> Vec<Option<u64>> {
+        let book = &self.results[book];
+        // each line of the snippets is a line of the book
+        let start: usize = book.results[..result]
+            .iter()
+            .map(|snippet| snippet.lines().count())
+            .sum();
+        (start..start + book.results[result].lines().count())
+            .map(|i| book.lines.get(i).and_then(|line| line.line_number))
+            .collect()
+    }
+
+    /// Lines of `item` in the results, wrapped to the width of the
+    /// results if [App::wrap_results] is set.
+    fn result_lines(&self, item: ResultItem) -> Vec<Line<'_>> {
+        let SearchResults {
+            title,
+            results,
+            lines: book_lines,
+            ..
+        } = &self.results[item.book()];
+        let mut indent = 2;
+        let lines: Vec<Line> = match item {
+            ResultItem::Book(book) => {
+                let arrow = if self.collapsed.contains(&book) {
+                    "▸"
+                } else {
+                    "▾"
+                };
+                let match_count = self.match_counts[book];
+                vec![
+                    Span::from(format!("{arrow} {title} ({match_count} matches)"))
+                        .blue()
+                        .into(),
+                ]
+            }
+            ResultItem::Snippet(book, result) => {
+                let line_numbers = self.snippet_line_numbers(book, result);
+                // as wide as the last line number of the book
+                let number_width = book_lines
+                    .last()
+                    .and_then(|line| line.line_number)
+                    .map_or(0, |number| number.to_string().len());
+                if self.line_numbers {
+                    indent += number_width + 3;
+                }
+                // index of the current match among the ones of each line
+                let mut current = match self.current_match {
+                    Some((b, r, i)) if (b, r) == (book, result) => Some(i),
+                    _ => None,
+                };
+                results[result]
+                    .lines()
+                    .zip(line_numbers)
+                    .enumerate()
+                    .map(|(i, (line, number))| {
+                        let match_count = line.matches("[matched]").count();
+                        let context = match_count == 0;
+                        let mut line = color_match(line, self.match_style.matched);
+                        match current {
+                            // matches are every other span
+                            Some(nth) if nth < match_count => {
+                                let span = &mut line.spans[2 * nth + 1];
+                                span.style = span.style.underlined().reversed();
+                                current = None;
+                            }
+                            Some(nth) => current = Some(nth - match_count),
+                            None => {}
+                        }
+                        if self.line_numbers {
+                            let number = number.map(|n| n.to_string()).unwrap_or_default();
+                            line.spans.insert(
+                                0,
+                                Span::from(format!("{number:>number_width$} │ ")).fg(TEXT_FG_COLOR),
+                            );
+                        }
+                        // pinned snippets are marked on their first line
+                        let marker = if i == 0 && self.is_pinned(title, &results[result]) {
+                            "* "
+                        } else {
+                            "  "
+                        };
+                        line.spans.insert(0, Span::from(marker));
+                        if context && self.match_style.dim_context {
+                            line = line.dim();
+                        }
+                        line
+                    })
+                    .collect()
+            }
+        };
+        if !self.wrap_results {
+            return lines;
+        }
+        lines
+            .into_iter()
+            .flat_map(|line| wrap_line(line, self.results_width, indent))
+            .collect()
+    }
+
+    /// First item shown by the results: the one shown before,
+    /// unless the selected item wouldn't be visible with it.
+    fn visible_offset(
+        &self,
+        items: &[ResultItem],
+        selected: Option<usize>,
+        height: usize,
+    ) -> usize {
+        let mut offset = self
+            .results_state
+            .offset()
+            .min(items.len().saturating_sub(1));
+        if let Some(selected) = selected {
+            // the selected item and the ones above it that fit
+            let mut first = selected;
+            let mut rows = self.item_height(items[selected]);
+            while first > 0 && rows + self.item_height(items[first - 1]) <= height {
+                first -= 1;
+                rows += self.item_height(items[first]);
+            }
+            offset = offset.clamp(first, selected);
+        }
+        offset
+    }
+
     /// Renders the search results part of the application (right side)
+    /// as a tree: the title of each book (with its number of matches)
+    /// followed by its snippets (unless they are collapsed). Only the
+    /// items that fit are built, so huge results don't slow redraws down.
     fn render_result_panel(&mut self, rect: Rect, f: &mut Frame) {
-        //TODO: remover unwraps
-        let result_panel = Layout::default()
-            .constraints([Constraint::Fill(1)].as_ref())
-            .split(rect);
-        let mut result_text: Vec<Line> = vec![];
-        for result in self.results.iter() {
-            let SearchResults { title, results } = result;
-            if results.len() > 0 {
-                result_text.push(Span::from(title).blue().into());
-                for result_contents in results {
-                    let colored_result = color_match(&result_contents);
-                    result_text.push(colored_result.into());
-                }
+        let all_items = self.result_items();
+        let height = rect.height.saturating_sub(2) as usize;
+        // without the borders and the highlight symbol
+        self.results_width = rect.width.saturating_sub(3) as usize;
+        // like a List, going past the last item stays on it
+        let selected = match self.results_state.selected() {
+            _ if all_items.is_empty() => None,
+            selected => selected.map(|selected| selected.min(all_items.len() - 1)),
+        };
+        self.results_state.select(selected);
+        let offset = self.visible_offset(&all_items, selected, height);
+        let mut items: Vec<ListItem> = vec![];
+        let mut rows = 0;
+        for item in all_items.iter().skip(offset) {
+            if rows >= height {
+                break;
+            }
+            rows += self.item_height(*item);
+            items.push(self.result_list_item(*item));
+        }
+        let match_count: usize = self.match_counts.iter().sum();
+        let duration_ms: u64 = self.results.iter().map(|r| r.duration_ms).sum();
+        let results_title = if let Some(pending) = &self.pending {
+            let elapsed = pending.started.elapsed();
+            let frame = SPINNER
+                [(elapsed.as_millis() / SPINNER_INTERVAL.as_millis()) as usize % SPINNER.len()];
+            let book = match pending.progress {
+                Some((index, Some(total))) => format!(" book {}/{total}", index + 1),
+                Some((index, None)) => format!(" book {}", index + 1),
+                None => String::new(),
+            };
+            format!(
+                "Results ({frame} searching{book}, {:.1} s)",
+                elapsed.as_secs_f64()
+            )
+        } else if self.results.is_empty() {
+            "Results".to_string()
+        } else {
+            format!("Results ({match_count} matches in {duration_ms} ms)")
+        };
+        let result_ui = List::new(items)
+            .block(Block::new().borders(Borders::ALL).title(results_title))
+            .style(self.highlight_if_focused(WhereWeAre::Results))
+            .highlight_style(SELECTED_STYLE)
+            .highlight_symbol(">");
+        let mut state = ListState::default().with_selected(selected.map(|i| i - offset));
+        f.render_stateful_widget(result_ui, rect, &mut state);
+        *self.results_state.offset_mut() = offset;
+        self.areas.push((WhereWeAre::Results, rect));
+    }
+
+    /// Renders the pinned snippets (see [App::toggle_pin]).
+    fn render_pinned_panel(&mut self, rect: Rect, f: &mut Frame) {
+        let items: Vec<ListItem> = self
+            .pinned
+            .iter()
+            .map(|pin| {
+                let mut lines = vec![Line::from(Span::from(pin.title.as_str()).blue())];
+                lines.extend(pin.snippet.lines().map(|line| {
+                    let mut line = color_match(line, self.match_style.matched);
+                    line.spans.insert(0, Span::from("  "));
+                    line
+                }));
+                ListItem::new(Text::from(lines))
+            })
+            .collect();
+        let pinned_ui = List::new(items)
+            .block(
+                Block::new()
+                    .borders(Borders::ALL)
+                    .title(format!("Pinned ({})", self.pinned.len())),
+            )
+            .style(self.highlight_if_focused(WhereWeAre::Pinned))
+            .highlight_style(SELECTED_STYLE)
+            .highlight_symbol(">");
+        f.render_stateful_widget(pinned_ui, rect, &mut self.pinned_state);
+        self.areas.push((WhereWeAre::Pinned, rect));
+    }
+
+    fn is_pinned(&self, title: &str, snippet: &str) -> bool {
+        self.pinned
+            .iter()
+            .any(|pin| pin.title == title && pin.snippet == snippet)
+    }
+
+    /// Pins the selected snippet or unpins it if it is pinned. Pins stay
+    /// until they are unpinned or the application quits.
+    fn toggle_pin(&mut self) {
+        let Some((title, snippet)) = self.selected_result() else {
+            return;
+        };
+        let (title, snippet) = (title.to_string(), snippet.to_string());
+        if self.is_pinned(&title, &snippet) {
+            let index = self
+                .pinned
+                .iter()
+                .position(|pin| pin.title == title && pin.snippet == snippet);
+            self.unpin(index);
+        } else {
+            let line = self.selected_line();
+            self.pinned.push(Pin {
+                title,
+                snippet,
+                line,
+            });
+            if self.pinned_state.selected().is_none() {
+                self.pinned_state.select(Some(0));
+            }
+        }
+    }
+
+    /// Removes the pin at `index`. The selection goes to the next pin
+    /// and the results are focused when there are no pins left.
+    fn unpin(&mut self, index: Option<usize>) {
+        let Some(index) = index.filter(|index| *index < self.pinned.len()) else {
+            return;
+        };
+        self.pinned.remove(index);
+        let selected = match self.pinned_state.selected() {
+            _ if self.pinned.is_empty() => None,
+            Some(selected) if selected > index || selected == self.pinned.len() => {
+                Some(selected - 1)
             }
+            selected => selected,
+        };
+        self.pinned_state.select(selected);
+        if self.pinned.is_empty() && self.where_we_are == WhereWeAre::Pinned {
+            self.where_we_are = WhereWeAre::Results;
         }
-        let result_ui = Paragraph::new(Text::from(result_text));
+    }
+
+    fn selected_pin(&self) -> Option<&Pin> {
+        self.pinned.get(self.pinned_state.selected()?)
+    }
+
+    /// Opens the book of the selected pin at the line of its match.
+    fn open_selected_pin(&mut self) -> Result<(), BookrabError> {
+        let Some(Pin {
+            title,
+            line: Some(line),
+            ..
+        }) = self.selected_pin()
+        else {
+            return Ok(());
+        };
+        self.open_book(title.clone(), *line)
+    }
+
+    /// Index of the pin shown at `row` of the pinned panel.
+    fn pin_at(&self, row: usize) -> Option<usize> {
+        let mut top = 0;
+        for (i, pin) in self
+            .pinned
+            .iter()
+            .enumerate()
+            .skip(self.pinned_state.offset())
+        {
+            let height = 1 + pin.snippet.lines().count();
+            if row < top + height {
+                return Some(i);
+            }
+            top += height;
+        }
+        None
+    }
+
+    /// Rows of the results in the order in which they are shown: books
+    /// with snippets and their snippets (unless they are collapsed).
+    fn result_items(&self) -> Vec<ResultItem> {
+        let mut items = vec![];
+        for (book, result) in self.results.iter().enumerate() {
+            if result.results.is_empty() {
+                continue;
+            }
+            items.push(ResultItem::Book(book));
+            if !self.collapsed.contains(&book) {
+                items.extend((0..result.results.len()).map(|i| ResultItem::Snippet(book, i)));
+            }
+        }
+        items
+    }
+
+    fn selected_item(&self) -> Option<ResultItem> {
+        self.result_items()
+            .get(self.results_state.selected()?)
+            .copied()
+    }
+
+    /// Title of the book and text of the selected snippet
+    /// (`None` when a book is selected).
+    fn selected_result(&self) -> Option<(&str, &str)> {
+        let ResultItem::Snippet(book, result) = self.selected_item()? else {
+            return None;
+        };
+        let book = &self.results[book];
+        Some((&book.title, &book.results[result]))
+    }
+
+    /// Hides (or shows) the snippets of `book` or of every book when it is
+    /// `None`. The selection stays in the same book (on its title when its
+    /// snippets are hidden).
+    fn collapse(&mut self, book: Option<usize>, collapsed: bool) {
+        let selected = self.selected_item();
+        let books = match book {
+            Some(book) => vec![book],
+            None => (0..self.results.len()).collect(),
+        };
+        for book in books {
+            if collapsed {
+                self.collapsed.insert(book);
+            } else {
+                self.collapsed.remove(&book);
+            }
+        }
+        let items = self.result_items();
+        let selected = selected.and_then(|selected| {
+            items.iter().position(|item| *item == selected).or_else(|| {
+                items
+                    .iter()
+                    .position(|item| *item == ResultItem::Book(selected.book()))
+            })
+        });
+        self.results_state.select(selected);
+    }
+
+    /// Hides the snippets of the selected book if they are shown and shows them otherwise.
+    fn toggle_selected_book(&mut self) {
+        if let Some(item) = self.selected_item() {
+            let book = item.book();
+            self.collapse(Some(book), !self.collapsed.contains(&book));
+        }
+    }
+
+    /// Line number of the (first) match of the selected snippet.
+    fn selected_line(&self) -> Option<u64> {
+        let ResultItem::Snippet(book, result) = self.selected_item()? else {
+            return None;
+        };
+        let results = &self.results[book];
+        let snippet = results.results[result]
+            .replace("[matched]", "")
+            .replace("[/matched]", "");
+        let mut matches = results
+            .lines
+            .iter()
+            .filter(|line| line.kind == LineKind::Match);
+        // without context, the n-th snippet is the n-th match
+        let line = match matches.clone().nth(result) {
+            Some(line) if snippet.contains(&line.text) => line,
+            _ => matches.find(|line| snippet.contains(&line.text))?,
+        };
+        line.line_number
+    }
+
+    /// Counts of the last search and of the tags that filter the next one,
+    /// followed by the error of the last search (if it failed).
+    fn status_line(&self) -> Line<'_> {
+        let matched = self.match_counts.iter().filter(|count| **count > 0).count();
+        let match_count: usize = self.match_counts.iter().sum();
+        let count = |status: TagStatus| {
+            self.tags
+                .list
+                .iter()
+                .filter(|tag| tag.status == status)
+                .count()
+        };
+        let mut spans = vec![];
+        if self.tabs.len() > 1 {
+            for i in 0..self.tabs.len() {
+                let tab = Span::from(format!(" {} ", i + 1)).fg(TEXT_FG_COLOR);
+                spans.push(if i == self.tab { tab.reversed() } else { tab });
+            }
+            spans.push(Span::from("  "));
+        }
+        spans.extend([
+            Span::from(format!(
+                "{matched} of {} books matched, {match_count} matches",
+                self.results.len()
+            ))
+            .fg(TEXT_FG_COLOR),
+            Span::from(format!(
+                "  include {} tags ({:?})",
+                count(TagStatus::Include),
+                self.include
+            ))
+            .fg(INCLUDED_FG_COLOR),
+            Span::from(format!(
+                "  exclude {} tags ({:?})",
+                count(TagStatus::Exclude),
+                self.exclude
+            ))
+            .fg(EXCLUDED_FG_COLOR),
+        ]);
+        if let Some(remote) = &self.remote {
+            spans.push(Span::from(format!("  remote: {}", remote.url)).fg(TEXT_FG_COLOR));
+        }
+        if let Some(error) = &self.last_error {
+            spans.push(Span::from(format!("  {error}")).red().bold());
+        }
+        Line::from(spans)
+    }
+
+    /// Browses the library of `remote` instead of the local one.
+    fn use_remote(&mut self, remote: Remote) {
+        let tags = remote.all_tags();
+        self.remote = Some(remote);
+        self.tags = TagList::from(HashSet::new());
+        match tags {
+            Ok(tags) => self.tags = TagList::from(tags),
+            Err(e) => self.report("read the tags of the server", Err(e)),
+        }
+    }
+
+    /// Shows an error and returns `true` in remote mode
+    /// (`action` only works with the local library).
+    fn local_only(&mut self, action: &str) -> bool {
+        if self.remote.is_none() {
+            return false;
+        }
+        self.show_error(
+            action,
+            "only searches, tags and the history are available in remote mode".to_string(),
+        );
+        true
+    }
+
+    /// Shows the error of `result` (if any) in an [ErrorPopup].
+    /// `action` is what couldn't be done (e.g. "open the library").
+    fn report(&mut self, action: &str, result: Result<(), BookrabError>) {
+        if let Err(e) = result {
+            tracing::error!("couldn't {}: {:?}", action, e);
+            self.show_error(action, error_details(&e));
+        }
+    }
+
+    /// Shows `message` in an [ErrorPopup] (see [App::report]).
+    fn show_error(&mut self, action: &str, message: String) {
+        self.error = Some(ErrorPopup {
+            action: action.to_string(),
+            message,
+        });
+    }
+
+    /// Renders the [ErrorPopup] (if any) in a window above the rest.
+    fn render_error(&self, rect: Rect, f: &mut Frame) {
+        let Some(error) = &self.error else {
+            return;
+        };
+        let height = error.message.lines().count() as u16 + 4;
+        let area = centered(rect, 80, height);
+        f.render_widget(Clear, area);
         f.render_widget(
-            result_ui
-                .wrap(Wrap { trim: true })
-                .block(Block::new().borders(Borders::ALL).title("Results")),
-            result_panel[0],
+            Paragraph::new(error.message.as_str())
+                .wrap(Wrap { trim: false })
+                .style(Style::default().fg(EXCLUDED_FG_COLOR))
+                .block(
+                    Block::default()
+                        .borders(Borders::ALL)
+                        .title(format!("Couldn't {} (any key to close)", error.action)),
+                ),
+            area,
         );
     }
 
-    /// Searches the books. [`self.results`] is updated.
-    fn search(&mut self) -> Result<(), BookrabError> {
-        let query = self.input.value();
-        let searcher = SearcherBuilder::new().build();
-        let regex_builder = RegexMatcherBuilder::new();
-        let include = Include::from(&self.tags);
-        let exclude = Exclude::from(&self.tags);
-        let results = self.root_book_dir.search_by_tags(
-            &include,
-            &exclude,
-            query.to_string(),
-            searcher,
-            regex_builder,
-        )?;
-        self.results = results;
+    /// Whether keys go to a text field (so `?` can't open the help).
+    fn is_typing(&self) -> bool {
+        self.command.is_some()
+            || self.library.is_some()
+            || self.settings_editor.is_some()
+            || self.history.is_some()
+            || self.saved_searches.is_some()
+            || self.save_prompt.is_some()
+            || (self.reader.is_none() && self.where_we_are == WhereWeAre::Input)
+    }
+
+    /// Renders [HELP] in a window above the rest.
+    fn render_help(&self, rect: Rect, f: &mut Frame) {
+        let mut lines = vec![];
+        for (area, bindings) in HELP {
+            if !lines.is_empty() {
+                lines.push(Line::default());
+            }
+            lines.push(Line::from(*area).bold().fg(Color::Yellow));
+            for (keys, action) in *bindings {
+                lines.push(Line::from(vec![
+                    Span::from(format!("  {keys:<26}")).blue(),
+                    Span::from(*action),
+                ]));
+            }
+        }
+        let area = centered(rect, 80, lines.len() as u16 + 2);
+        f.render_widget(Clear, area);
+        f.render_widget(
+            Paragraph::new(lines).block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title("Help (any key to close)"),
+            ),
+            area,
+        );
+    }
+
+    /// Clicks focus the area under the mouse (and select the snippet or
+    /// cycle the status of the tag under it), the wheel selects the
+    /// next/previous tag or snippet and scrolls the open book.
+    fn handle_mouse(&mut self, mouse: MouseEvent) {
+        let (column, row) = (mouse.column, mouse.row);
+        if self.reader.is_some() {
+            let delta = match mouse.kind {
+                MouseEventKind::ScrollDown => 3,
+                MouseEventKind::ScrollUp => -3,
+                _ => return,
+            };
+            let scrolled = self.scroll_reader(delta);
+            self.report("read the book", scrolled);
+            return;
+        }
+        // the other windows are only used with the keyboard
+        if self.library.is_some()
+            || self.settings_editor.is_some()
+            || self.history.is_some()
+            || self.saved_searches.is_some()
+            || self.save_prompt.is_some()
+        {
+            return;
+        }
+        let Some((area, rect)) = self
+            .areas
+            .iter()
+            .find(|(_, rect)| rect.contains(Position::new(column, row)))
+            .copied()
+        else {
+            return;
+        };
+        match (mouse.kind, area) {
+            (MouseEventKind::ScrollDown, WhereWeAre::Results) => self.select_next_result(),
+            (MouseEventKind::ScrollUp, WhereWeAre::Results) => self.select_previous_result(),
+            (MouseEventKind::ScrollDown, WhereWeAre::Pinned) => self.pinned_state.select_next(),
+            (MouseEventKind::ScrollUp, WhereWeAre::Pinned) => self.pinned_state.select_previous(),
+            (MouseEventKind::ScrollDown, WhereWeAre::Tags) => self.select_next_tag(),
+            (MouseEventKind::ScrollUp, WhereWeAre::Tags) => self.select_previous_tag(),
+            (MouseEventKind::Down(MouseButton::Left), _) => {
+                self.where_we_are = area;
+                // without the borders
+                let row = row as usize;
+                let inner = rect.y as usize + 1..rect.bottom().saturating_sub(1) as usize;
+                if !inner.contains(&row) {
+                    return;
+                }
+                let row = row - inner.start;
+                match area {
+                    WhereWeAre::Tags => {
+                        let tag = self.tags.state.offset() + row;
+                        if tag < self.tags.list.len() {
+                            self.tags.state.select(Some(tag));
+                            self.cycle_status();
+                        }
+                    }
+                    WhereWeAre::Results if self.showing_continue_reading() => {
+                        let book = self.continue_state.offset() + row;
+                        if book < self.continue_reading.len() {
+                            self.continue_state.select(Some(book));
+                        }
+                    }
+                    WhereWeAre::Results => {
+                        if let Some(result) = self.result_at(row) {
+                            self.results_state.select(Some(result));
+                            // clicking a book opens or closes it
+                            if let Some(ResultItem::Book(_)) = self.selected_item() {
+                                self.toggle_selected_book();
+                            }
+                        }
+                    }
+                    WhereWeAre::Pinned => {
+                        if let Some(pin) = self.pin_at(row) {
+                            self.pinned_state.select(Some(pin));
+                        }
+                    }
+                    _ => {}
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Index (see [App::result_items]) of the book or snippet shown at `row`
+    /// of the results (the rows of the snippets depend on their lines).
+    fn result_at(&self, row: usize) -> Option<usize> {
+        let mut top = 0;
+        for (i, item) in self
+            .result_items()
+            .into_iter()
+            .enumerate()
+            .skip(self.results_state.offset())
+        {
+            let height = self.item_height(item);
+            if row < top + height {
+                return Some(i);
+            }
+            top += height;
+        }
+        None
+    }
+
+    /// Widens the search panel by `delta` percent of the screen (negative
+    /// narrows it) within [SEARCH_PANEL_WIDTHS]. The results leave the
+    /// full screen. Returns the new width.
+    fn resize_panels(&mut self, delta: i16) -> u16 {
+        self.full_screen_results = false;
+        self.search_panel_width = self
+            .search_panel_width
+            .saturating_add_signed(delta)
+            .clamp(*SEARCH_PANEL_WIDTHS.start(), *SEARCH_PANEL_WIDTHS.end());
+        self.search_panel_width
+    }
+
+    fn select_next_result(&mut self) {
+        if self.showing_continue_reading() {
+            self.continue_state.select_next();
+        } else {
+            self.results_state.select_next();
+        }
+    }
+
+    fn select_previous_result(&mut self) {
+        if self.showing_continue_reading() {
+            self.continue_state.select_previous();
+        } else {
+            self.results_state.select_previous();
+        }
+    }
+
+    /// Searches the books in another thread (see [App::spawn_search]).
+    /// A query like `:name` runs the saved search called `name`.
+    fn search(&mut self) {
+        if let Some(name) = self.input.value().strip_prefix(':') {
+            let name = name.to_string();
+            if self.local_only("run a saved search") {
+                return;
+            }
+            self.spawn_search(move |root, _| root.run_saved_search(&name));
+            return;
+        }
+        let pattern = self.settings.pattern(self.input.value());
+        self.search_with(pattern, &self.settings.context())
+    }
+
+    /// Searches `pattern` with the tags and modes of the app and
+    /// the options of `options` (case, accents and context lines).
+    fn search_with(&mut self, pattern: String, options: &HistoryContext) {
+        let searcher = SearcherBuilder::new()
+            .before_context(options.before_context)
+            .after_context(options.after_context)
+            .build();
+        let mut regex_builder = RegexMatcherBuilder::new();
+        regex_builder
+            .case_insensitive(options.case_insensitive)
+            .case_smart(options.case_smart);
+        let mut include = Include::from(&self.tags);
+        include.mode = self.include.clone();
+        let mut exclude = Exclude::from(&self.tags);
+        exclude.mode = self.exclude.clone();
+        let options = SearchOptions {
+            ignore_accents: options.ignore_accents,
+            history: HistoryContext {
+                include_mode: include.mode.clone(),
+                include_tags: include.tags.clone(),
+                exclude_mode: exclude.mode.clone(),
+                exclude_tags: exclude.tags.clone(),
+                case_insensitive: options.case_insensitive,
+                case_smart: options.case_smart,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        if let Some(remote) = self.remote.clone() {
+            let options = HistoryContext {
+                before_context: searcher.before_context(),
+                after_context: searcher.after_context(),
+                ignore_accents: options.ignore_accents,
+                ..options.history
+            };
+            self.spawn_remote_search(move |on_book| remote.search(pattern, &options, on_book));
+            return;
+        }
+        self.spawn_search(move |root, on_event| {
+            root.search_filtered_streaming(
+                |book| include.matches(book) && !exclude.matches(book),
+                pattern,
+                searcher,
+                regex_builder,
+                &options,
+                on_event,
+            )
+        });
+    }
+
+    /// Runs `search` in another thread, so that the terminal stays
+    /// responsive. The thread takes its own connection from the pool.
+    /// Searches that stream their events (see [SearchEvent]) show their
+    /// progress. Results are shown by [App::receive_results] and a search
+    /// that is still running stops before its next book.
+    fn spawn_search(
+        &mut self,
+        search: impl FnOnce(
+                &mut RootBookDir,
+                &dyn Fn(SearchEvent) -> bool,
+            ) -> Result<Vec<SearchResults>, BookrabError>
+            + Send
+            + 'static,
+    ) {
+        let config = self.root_book_dir.config().clone();
+        let with_database = self.root_book_dir.connection.is_some();
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            // the receiver is gone when the search was replaced
+            let on_event = |event: SearchEvent| match event {
+                SearchEvent::Started { index, total, .. } => sender
+                    .send(SearchMessage::Progress {
+                        index,
+                        total: Some(total),
+                    })
+                    .is_ok(),
+                SearchEvent::Finished(_) => true,
+            };
+            let results = match DBCONNECTION.as_ref().filter(|_| with_database) {
+                Some(pool) => match pool.get() {
+                    Ok(mut connection) => {
+                        search(&mut RootBookDir::new(config, &mut connection), &on_event)
+                    }
+                    Err(e) => {
+                        tracing::error!("couldn't connect to the database: {:?}", e);
+                        Err(BookrabError::DatabaseUnavailable {
+                            error: (),
+                            operation: "search".to_string(),
+                        })
+                    }
+                },
+                None => search(&mut RootBookDir::without_database(config), &on_event),
+            };
+            let _ = sender.send(SearchMessage::Done(results));
+        });
+        self.pending = Some(PendingSearch {
+            receiver,
+            started: Instant::now(),
+            progress: None,
+        });
+    }
+
+    /// Like [App::spawn_search] for the server of [App::remote]
+    /// (the progress counts the books received so far).
+    fn spawn_remote_search(
+        &mut self,
+        search: impl FnOnce(&dyn Fn(usize) -> bool) -> Result<Vec<SearchResults>, BookrabError>
+            + Send
+            + 'static,
+    ) {
+        let (sender, receiver) = mpsc::channel();
+        thread::spawn(move || {
+            let on_book = |received| {
+                sender
+                    .send(SearchMessage::Progress {
+                        index: received,
+                        total: None,
+                    })
+                    .is_ok()
+            };
+            let results = search(&on_book);
+            let _ = sender.send(SearchMessage::Done(results));
+        });
+        self.pending = Some(PendingSearch {
+            receiver,
+            started: Instant::now(),
+            progress: None,
+        });
+    }
+
+    /// Shows the results of the running search if it finished
+    /// (`wait` blocks until it does). [`self.results`] is updated
+    /// and errors are kept in [`self.last_error`].
+    fn receive_results(&mut self, wait: bool) -> Result<(), BookrabError> {
+        while let Some(pending) = self.pending.as_mut() {
+            let received = if wait {
+                pending
+                    .receiver
+                    .recv()
+                    .map_err(|_| TryRecvError::Disconnected)
+            } else {
+                pending.receiver.try_recv()
+            };
+            match received {
+                Ok(SearchMessage::Progress { index, total }) => {
+                    pending.progress = Some((index, total));
+                }
+                Ok(SearchMessage::Done(results)) => {
+                    self.pending = None;
+                    if let Err(e) = &results {
+                        self.last_error = Some(error_message(e));
+                    }
+                    self.set_results(results?);
+                    self.last_error = None;
+                }
+                Err(TryRecvError::Empty) => break,
+                Err(TryRecvError::Disconnected) => {
+                    tracing::error!("the search thread panicked");
+                    self.pending = None;
+                }
+            }
+        }
         Ok(())
     }
 
+    /// Selects the first snippet (if there is one) of new results.
+    fn select_first_result(&mut self) {
+        let selected = self
+            .result_items()
+            .iter()
+            .position(|item| matches!(item, ResultItem::Snippet(..)));
+        self.results_state = ListState::default().with_selected(selected);
+    }
+
     /// Cycles through selectable items on the screen.
     fn next_position(&mut self) {
-        let positions = WhereWeAre::iter()
-            .filter(|pos| pos != &WhereWeAre::Nowhere)
-            .cycle();
+        let positions = self.focusable_positions().into_iter().cycle();
         self.cycle_position(positions);
     }
 
+    /// Areas that can be focused (the pins only while there are any).
+    fn focusable_positions(&self) -> Vec<WhereWeAre> {
+        WhereWeAre::iter()
+            .filter(|pos| pos != &WhereWeAre::Nowhere)
+            .filter(|pos| pos != &WhereWeAre::Pinned || !self.pinned.is_empty())
+            .collect()
+    }
+
     /// See `next_position` and `previous_position`.
     fn cycle_position<T: Iterator<Item = WhereWeAre>>(&mut self, mut positions: T) {
+        // only the results are shown
+        if self.full_screen_results {
+            self.where_we_are = WhereWeAre::Results;
+            return;
+        }
         if self.where_we_are == WhereWeAre::Nowhere {
             self.where_we_are = positions.next().unwrap();
             return;
@@ -267,10 +1824,7 @@ impl App<'_> {
 
     /// Cycles through selectable items on the screen in the reversed order.
     fn previous_position(&mut self) {
-        let positions = WhereWeAre::iter()
-            .filter(|pos| pos != &WhereWeAre::Nowhere)
-            .rev()
-            .cycle();
+        let positions = self.focusable_positions().into_iter().rev().cycle();
         self.cycle_position(positions);
     }
 
@@ -305,20 +1859,70 @@ impl App<'_> {
         }
     }
 
-    /// Copies the results in the html format.
-    fn copy_results(&self) -> Result<(), arboard::Error> {
-        let mut ctx = Clipboard::new()?;
+    /// Results in the html format.
+    fn results_html(&self) -> String {
         let mut html = String::new();
         for result in self.results.iter() {
-            let SearchResults { title, results } = result;
-            if result.results.len() > 0 {
+            let SearchResults { title, results, .. } = result;
+            if !result.results.is_empty() {
                 html = format!("{html}<div><span style=\"color: blue\">{title}</span></div>");
                 for single_result in results.clone() {
                     html = format!("{html}<p>{}</p>", color_match_html(single_result))
                 }
             }
         }
-        Ok(ctx.set().html(html, None)?)
+        html
+    }
+
+    /// Results as plain text: the title of each book followed
+    /// by its snippets, with a blank line between books.
+    fn results_text(&self) -> String {
+        self.results
+            .iter()
+            .filter(|result| !result.results.is_empty())
+            .map(|result| {
+                let snippets: Vec<String> =
+                    result.results.iter().map(|r| strip_matches(r)).collect();
+                plain_snippets(&result.title, &snippets)
+            })
+            .collect::<Vec<String>>()
+            .join("\n")
+    }
+
+    /// Copies the results in the html format (with a plain text alternative
+    /// for programs that don't take html) or only as plain text
+    /// (see [TuiConfig::clipboard]).
+    fn copy_results(&self, plain: bool) -> Result<(), Box<dyn Error>> {
+        let html = (!plain).then(|| self.results_html());
+        clipboard::copy(&self.tui.clipboard, self.results_text(), html)
+    }
+
+    /// Copies the selected snippet (and the title of its book) like [App::copy_results].
+    fn copy_selected_result(&self, plain: bool) -> Result<(), Box<dyn Error>> {
+        let Some((title, result)) = self.selected_result() else {
+            return Ok(());
+        };
+        self.copy_snippet(title, result, plain)
+    }
+
+    /// Copies the selected pin like [App::copy_selected_result].
+    fn copy_selected_pin(&self, plain: bool) -> Result<(), Box<dyn Error>> {
+        let Some(pin) = self.selected_pin() else {
+            return Ok(());
+        };
+        self.copy_snippet(&pin.title, &pin.snippet, plain)
+    }
+
+    /// Copies `result` and the `title` of its book like [App::copy_results].
+    fn copy_snippet(&self, title: &str, result: &str, plain: bool) -> Result<(), Box<dyn Error>> {
+        let text = plain_snippets(title, &[strip_matches(result)]);
+        let html = (!plain).then(|| {
+            format!(
+                "<div><span style=\"color: blue\">{title}</span></div><p>{}</p>",
+                color_match_html(result.to_string())
+            )
+        });
+        clipboard::copy(&self.tui.clipboard, text, html)
     }
 }
 
@@ -329,8 +1933,30 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
                 KeyCode::Esc => {
                     app.where_we_are = WhereWeAre::Nowhere;
                 }
+                KeyCode::Enter
+                    if app.where_we_are == WhereWeAre::Results
+                        && app.showing_continue_reading() =>
+                {
+                    let opened = app.continue_selected_book();
+                    app.report("open the book", opened);
+                }
+                KeyCode::Enter if app.where_we_are == WhereWeAre::Results => {
+                    if let Some(ResultItem::Book(_)) = app.selected_item() {
+                        app.toggle_selected_book();
+                    } else if !app.local_only("open the book") {
+                        let opened = app.open_selected_result();
+                        app.report("open the book", opened);
+                    }
+                }
+                KeyCode::Enter if app.where_we_are == WhereWeAre::Pinned => {
+                    if app.local_only("open the book") {
+                        return;
+                    }
+                    let opened = app.open_selected_pin();
+                    app.report("open the book", opened);
+                }
                 KeyCode::Enter => {
-                    app.search().unwrap();
+                    app.search();
                 }
                 KeyCode::Tab => {
                     app.next_position();
@@ -338,38 +1964,151 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
                 _ => {}
             }
         } else if key.modifiers == KeyModifiers::SHIFT {
-            match key.code {
-                KeyCode::BackTab => {
-                    app.previous_position();
+            if key.code == KeyCode::BackTab {
+                app.previous_position();
+            }
+        } else if key.modifiers == KeyModifiers::CONTROL | KeyModifiers::SHIFT {
+            if let KeyCode::Char('y' | 'Y') = key.code {
+                if let Err(e) = app.copy_results(true) {
+                    app.show_error("copy the results", e.to_string());
                 }
-                _ => {}
             }
         } else if key.modifiers == KeyModifiers::CONTROL {
             match key.code {
                 KeyCode::Char('y') => {
-                    app.copy_results().expect("Error when copying results");
+                    if let Err(e) = app.copy_results(false) {
+                        app.show_error("copy the results", e.to_string());
+                    }
+                }
+                KeyCode::Char('b') if !app.local_only("open the library") => {
+                    let opened = app.open_library();
+                    app.report("open the library", opened);
+                }
+                KeyCode::Char('r') => {
+                    let opened = app.open_history();
+                    app.report("open the history", opened);
+                }
+                KeyCode::Char('s') if !app.local_only("save the search") => {
+                    app.save_prompt = Some(Input::default())
+                }
+                KeyCode::Left | KeyCode::Right => {
+                    let delta = if key.code == KeyCode::Left { -5 } else { 5 };
+                    let width = app.resize_panels(delta);
+                    if let Err(e) = config::store_search_panel_width(width) {
+                        app.show_error("remember the width of the panels", e.to_string());
+                    }
+                }
+                KeyCode::Char('p') => {
+                    if let Err(e) = app.open_settings() {
+                        app.show_error("read the settings", e.to_string());
+                    }
+                }
+                KeyCode::Char('t') => app.switch_tab(app.tabs.len()),
+                KeyCode::Char('w') => app.close_tab(),
+                KeyCode::Char('f') => {
+                    app.full_screen_results = !app.full_screen_results;
+                    // the search panel can't be used while it is hidden
+                    if app.full_screen_results {
+                        app.where_we_are = WhereWeAre::Results;
+                    }
+                }
+                KeyCode::Char('o') if !app.local_only("open the saved searches") => {
+                    let opened = app.open_saved_searches();
+                    app.report("open the saved searches", opened);
                 }
                 _ => {}
             }
         }
     }
     loop {
+        // errors are also shown in the status line
+        let received = app.receive_results(false);
+        app.report("search", received);
         terminal.draw(|f| ui(f, &mut app))?;
 
-        if let Event::Key(key) = event::read()? {
+        // the spinner is redrawn while a search runs
+        if app.pending.is_some() && !event::poll(SPINNER_INTERVAL)? {
+            continue;
+        }
+        let event = event::read()?;
+        if let Event::Mouse(mouse) = event {
+            // popups close with a click, like with a key
+            if mouse.kind == MouseEventKind::Down(MouseButton::Left)
+                && (app.error.is_some() || app.help)
+            {
+                app.error = None;
+                app.help = false;
+            } else if app.error.is_none() && !app.help {
+                app.handle_mouse(mouse);
+            }
+            continue;
+        }
+        if let Event::Key(key) = event {
             if key.modifiers == KeyModifiers::CONTROL {
-                match key.code {
-                    KeyCode::Char('c') => return Ok(()),
-                    _ => {}
+                if let KeyCode::Char('c') = key.code {
+                    if let Err(e) = app.close_reader() {
+                        tracing::error!("couldn't store the reading position: {:?}", e);
+                    }
+                    return Ok(());
+                }
+            }
+            if app.error.is_some() {
+                app.error = None;
+                continue;
+            }
+            if app.help {
+                app.help = false;
+                continue;
+            }
+            if app.command.is_some() {
+                if command_line::bindings(key, &mut app) {
+                    return Ok(());
+                }
+                continue;
+            }
+            if key.code == KeyCode::Char('?') && !app.is_typing() {
+                app.help = true;
+                continue;
+            }
+            if key.code == KeyCode::Char(':') && !app.is_typing() && app.reader.is_none() {
+                app.command = Some(Input::default());
+                continue;
+            }
+            if app.reader.is_some() {
+                reader::bindings(key, &mut app);
+                continue;
+            }
+            if app.library.is_some() {
+                library::bindings(key, &mut app);
+                continue;
+            }
+            if app.save_prompt.is_some() {
+                saved_searches::save_prompt_bindings(key, &mut app);
+                continue;
+            }
+            if app.history.is_some() {
+                history::bindings(key, &mut app);
+                continue;
+            }
+            if app.saved_searches.is_some() {
+                saved_searches::bindings(key, &mut app);
+                continue;
+            }
+            if app.settings_editor.is_some() {
+                settings::bindings(key, &mut app);
+                continue;
+            }
+            if let KeyCode::Char(digit @ '1'..='9') = key.code {
+                if key.modifiers == KeyModifiers::NONE && !app.is_typing() {
+                    app.switch_tab(digit as usize - '1' as usize);
+                    continue;
                 }
             }
             common_bindings(key, &mut app);
             match app.where_we_are {
-                WhereWeAre::Input => match key.code {
-                    _ => {
-                        app.input.handle_event(&Event::Key(key));
-                    }
-                },
+                WhereWeAre::Input => {
+                    app.input.handle_event(&Event::Key(key));
+                }
                 WhereWeAre::Include => match key.code {
                     KeyCode::Char(' ') => match app.include {
                         FilterMode::All => app.include = FilterMode::Any,
@@ -401,8 +2140,78 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
                     }
                     _ => {}
                 },
+                WhereWeAre::Options => match key.code {
+                    KeyCode::Char('h') | KeyCode::Left => app.settings.select(false),
+                    KeyCode::Char('l') | KeyCode::Right => app.settings.select(true),
+                    KeyCode::Char('k') | KeyCode::Up | KeyCode::Char(' ') => {
+                        app.settings.change(true)
+                    }
+                    KeyCode::Char('j') | KeyCode::Down => app.settings.change(false),
+                    KeyCode::Char('q') => {
+                        return Ok(());
+                    }
+                    _ => {}
+                },
+                WhereWeAre::Results => match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => app.select_next_result(),
+                    KeyCode::Char('k') | KeyCode::Up => app.select_previous_result(),
+                    KeyCode::Char(' ') => app.toggle_selected_book(),
+                    KeyCode::Char('h') | KeyCode::Left => {
+                        if let Some(item) = app.selected_item() {
+                            app.collapse(Some(item.book()), true);
+                        }
+                    }
+                    KeyCode::Char('l') | KeyCode::Right => {
+                        if let Some(item) = app.selected_item() {
+                            app.collapse(Some(item.book()), false);
+                        }
+                    }
+                    KeyCode::Char('-') => app.collapse(None, true),
+                    KeyCode::Char('+' | '=') => app.collapse(None, false),
+                    KeyCode::Char('y') if key.modifiers == KeyModifiers::NONE => {
+                        if let Err(e) = app.copy_selected_result(false) {
+                            app.show_error("copy the snippet", e.to_string());
+                        }
+                    }
+                    KeyCode::Char('Y') => {
+                        if let Err(e) = app.copy_selected_result(true) {
+                            app.show_error("copy the snippet", e.to_string());
+                        }
+                    }
+                    KeyCode::Char('p') => app.toggle_pin(),
+                    KeyCode::Char('w') => app.wrap_results = !app.wrap_results,
+                    KeyCode::Char('#') => app.line_numbers = !app.line_numbers,
+                    KeyCode::Char('n') => app.jump_to_match(true),
+                    KeyCode::Char('N') => app.jump_to_match(false),
+                    KeyCode::Char('q') => {
+                        return Ok(());
+                    }
+                    _ => {}
+                },
+                WhereWeAre::Pinned => match key.code {
+                    KeyCode::Char('j') | KeyCode::Down => app.pinned_state.select_next(),
+                    KeyCode::Char('k') | KeyCode::Up => app.pinned_state.select_previous(),
+                    KeyCode::Char('p' | 'd') | KeyCode::Delete => {
+                        app.unpin(app.pinned_state.selected())
+                    }
+                    KeyCode::Char('y') if key.modifiers == KeyModifiers::NONE => {
+                        if let Err(e) = app.copy_selected_pin(false) {
+                            app.show_error("copy the snippet", e.to_string());
+                        }
+                    }
+                    KeyCode::Char('Y') => {
+                        if let Err(e) = app.copy_selected_pin(true) {
+                            app.show_error("copy the snippet", e.to_string());
+                        }
+                    }
+                    KeyCode::Char('q') => {
+                        return Ok(());
+                    }
+                    _ => {}
+                },
                 _ => match key.code {
                     KeyCode::Char('e') => {
+                        app.full_screen_results = false;
                         app.where_we_are = WhereWeAre::Input;
                     }
                     KeyCode::Char('q') => {
@@ -416,18 +2225,198 @@ fn run_app<B: Backend>(terminal: &mut Terminal<B>, mut app: App) -> io::Result<(
 }
 
 fn ui(f: &mut Frame, app: &mut App) {
-    let two_panels = Layout::default()
-        .direction(Direction::Horizontal)
-        .margin(2)
-        .constraints([Constraint::Percentage(35), Constraint::Percentage(65)])
-        .split(f.area());
-    app.render_search_panel(two_panels[0], f);
-    app.render_result_panel(two_panels[1], f);
+    let area = f.area().inner(Margin::new(2, 2));
+    if app.reader.is_some() {
+        app.render_reader(area, f);
+    } else if app.library.is_some() {
+        app.render_library(area, f);
+    } else if app.history.is_some() {
+        app.render_history(area, f);
+    } else if app.saved_searches.is_some() {
+        app.render_saved_searches(area, f);
+    } else if app.settings_editor.is_some() {
+        app.render_settings(area, f);
+    } else {
+        app.areas.clear();
+        let search_panel_width = if app.full_screen_results {
+            0
+        } else {
+            app.search_panel_width
+        };
+        let two_panels = Layout::default()
+            .direction(Direction::Horizontal)
+            .margin(2)
+            .constraints([
+                Constraint::Percentage(search_panel_width),
+                Constraint::Percentage(100 - search_panel_width),
+            ])
+            .split(f.area());
+        if !app.full_screen_results {
+            app.render_search_panel(two_panels[0], f);
+        }
+        if app.showing_continue_reading() {
+            app.render_continue_reading(two_panels[1], f);
+        } else if app.pinned.is_empty() {
+            app.render_result_panel(two_panels[1], f);
+        } else {
+            let results_and_pins = Layout::default()
+                .direction(Direction::Vertical)
+                .constraints([Constraint::Percentage(65), Constraint::Percentage(35)])
+                .split(two_panels[1]);
+            app.render_result_panel(results_and_pins[0], f);
+            app.render_pinned_panel(results_and_pins[1], f);
+        }
+    }
+    // the status line goes in the bottom margin
+    let frame = f.area();
+    if frame.height > 0 {
+        let status = Rect::new(
+            frame.x + 2.min(frame.width),
+            frame.bottom() - 1,
+            frame.width.saturating_sub(4),
+            1,
+        );
+        f.render_widget(Paragraph::new(app.status_line()), status);
+        app.render_command(status, f);
+    }
+    app.render_save_prompt(f.area(), f);
+    if app.help {
+        app.render_help(f.area(), f);
+    }
+    app.render_error(f.area(), f);
+}
+
+/// Short message of `error` (e.g. "E0012: problematic regex pattern.").
+fn error_message(error: &BookrabError) -> String {
+    let json = serde_json::to_value(error).unwrap_or_default();
+    let message = json
+        .as_object()
+        .and_then(|variant| variant.values().next())
+        .and_then(|fields| fields.get("error"))
+        .and_then(|message| message.as_str());
+    match message {
+        Some(message) => message.to_string(),
+        None => format!("{error:?}"),
+    }
+}
+
+/// Short message of `error` followed by its other fields
+/// (e.g. the path of a file or the problem of a regex), one per line.
+fn error_details(error: &BookrabError) -> String {
+    let mut text = error_message(error);
+    let json = serde_json::to_value(error).unwrap_or_default();
+    let fields = json
+        .as_object()
+        .and_then(|variant| variant.values().next())
+        .and_then(|fields| fields.as_object());
+    for (name, value) in fields.into_iter().flatten() {
+        if name == "error" {
+            continue;
+        }
+        let value = match value {
+            serde_json::Value::String(value) => value.clone(),
+            value => value.to_string(),
+        };
+        text.push_str(&format!("\n{name}: {value}"));
+    }
+    text
+}
+
+/// Area of at most `width` by `height` in the middle of `rect`.
+fn centered(rect: Rect, width: u16, height: u16) -> Rect {
+    let width = width.min(rect.width);
+    let height = height.min(rect.height);
+    Rect::new(
+        rect.x + (rect.width - width) / 2,
+        rect.y + (rect.height - height) / 2,
+        width,
+        height,
+    )
+}
+
+/// Renders a text field (e.g. the filter of a list) with its cursor.
+fn render_filter(filter: &Input, title: &str, rect: Rect, f: &mut Frame) {
+    f.render_widget(
+        Paragraph::new(filter.value())
+            .style(Style::default().fg(Color::Yellow))
+            .block(Block::default().borders(Borders::ALL).title(title)),
+        rect,
+    );
+    let width = rect.width.max(3) - 3; // keep 2 for borders and 1 for cursor
+    let scroll = filter.visual_scroll(width as usize);
+    f.set_cursor_position((
+        rect.x + ((filter.visual_cursor()).max(scroll) - scroll) as u16 + 1,
+        rect.y + 1,
+    ));
+}
+
+/// Size in bytes, KiB or MiB.
+fn format_size(size: u64) -> String {
+    match size {
+        0..1024 => format!("{size} B"),
+        1024..1_048_576 => format!("{:.1} KiB", size as f64 / 1024.0),
+        _ => format!("{:.1} MiB", size as f64 / 1_048_576.0),
+    }
+}
+
+/// Removes the `[matched][/matched]` markers of `str_match`.
+fn strip_matches(str_match: &str) -> String {
+    str_match.replace("[matched]", "").replace("[/matched]", "")
+}
+
+/// Title of a book followed by its `snippets` (one per line at least).
+fn plain_snippets(title: &str, snippets: &[String]) -> String {
+    let mut text = format!("{title}\n");
+    for snippet in snippets {
+        text.push_str(snippet);
+        if !snippet.ends_with('\n') {
+            text.push('\n');
+        }
+    }
+    text
+}
+
+/// Splits `line` in lines of at most `width` characters. The lines after
+/// the first start with `indent` spaces.
+fn wrap_line(line: Line<'_>, width: usize, indent: usize) -> Vec<Line<'static>> {
+    let new_line = |spans: Vec<Span<'static>>| Line::from(spans).style(line.style);
+    // too narrow to wrap
+    if width <= indent {
+        let spans = line
+            .spans
+            .iter()
+            .map(|span| Span::styled(span.content.to_string(), span.style))
+            .collect();
+        return vec![new_line(spans)];
+    }
+    let mut lines = vec![];
+    let mut spans: Vec<Span<'static>> = vec![];
+    let mut columns = 0;
+    for span in &line.spans {
+        let mut piece = String::new();
+        for c in span.content.chars() {
+            if columns == width {
+                if !piece.is_empty() {
+                    spans.push(Span::styled(std::mem::take(&mut piece), span.style));
+                }
+                lines.push(new_line(std::mem::take(&mut spans)));
+                spans.push(Span::from(" ".repeat(indent)));
+                columns = indent;
+            }
+            piece.push(c);
+            columns += 1;
+        }
+        if !piece.is_empty() {
+            spans.push(Span::styled(piece, span.style));
+        }
+    }
+    lines.push(new_line(spans));
+    lines
 }
 
 /// Returns `str_match` in a [`Line`] format.
-/// Characters inside `[matched][/matched]` will be colored.
-fn color_match<'a>(str_match: &'a str) -> Line<'a> {
+/// Characters inside `[matched][/matched]` will have `match_style`.
+fn color_match(str_match: &str, match_style: Style) -> Line<'_> {
     let open = "[matched]";
     let close = "[/matched]";
     let step1 = str_match.split(close);
@@ -437,7 +2426,7 @@ fn color_match<'a>(str_match: &'a str) -> Line<'a> {
         let normal_side = Span::from(possible_pair[0]); // left side is not a match
         step2.push(normal_side);
         if possible_pair.len() == 2 {
-            let match_side = Span::styled(possible_pair[1], Color::Red);
+            let match_side = Span::styled(possible_pair[1], match_style);
             step2.push(match_side);
         }
     }
@@ -446,7 +2435,7 @@ fn color_match<'a>(str_match: &'a str) -> Line<'a> {
 
 /// Returns `str_match` in a [`Line`] format.
 /// Characters inside `[matched][/matched]` will be colored (in html).
-fn color_match_html<'a>(str_match: String) -> String {
+fn color_match_html(str_match: String) -> String {
     let open = "[matched]";
     let close = "[/matched]";
     let step1 = str_match.split(close);
@@ -467,9 +2456,9 @@ fn color_match_html<'a>(str_match: String) -> String {
 impl From<&TagItem> for ListItem<'_> {
     fn from(value: &TagItem) -> Self {
         let line = match value.status {
-            TagStatus::None => Line::styled(format!("{}", value.name), TEXT_FG_COLOR),
-            TagStatus::Include => Line::styled(format!("{}", value.name), INCLUDED_FG_COLOR),
-            TagStatus::Exclude => Line::styled(format!("{}", value.name), EXCLUDED_FG_COLOR),
+            TagStatus::None => Line::styled(value.name.to_string(), TEXT_FG_COLOR),
+            TagStatus::Include => Line::styled(value.name.to_string(), INCLUDED_FG_COLOR),
+            TagStatus::Exclude => Line::styled(value.name.to_string(), EXCLUDED_FG_COLOR),
         };
         ListItem::new(line)
     }
@@ -488,6 +2477,20 @@ impl From<&TagList> for Include {
         }
     }
 }
+impl From<HashSet<String>> for TagList {
+    fn from(tags: HashSet<String>) -> Self {
+        TagList {
+            list: tags
+                .into_iter()
+                .map(|tag| TagItem {
+                    name: tag,
+                    status: TagStatus::None,
+                })
+                .collect(),
+            state: ListState::default(),
+        }
+    }
+}
 impl From<&TagList> for Exclude {
     fn from(value: &TagList) -> Self {
         let excluded: HashSet<String> = value
@@ -504,80 +2507,4 @@ impl From<&TagList> for Exclude {
 }
 
 #[cfg(test)]
-mod tests {
-    use crate::database::DBCONNECTION;
-    use crate::{color_match, color_match_html, App};
-    use arboard::Clipboard;
-    use bookrab_core::books::test_utils::root_for_tag_tests;
-    use bookrab_core::books::SearchResults;
-    use ratatui::prelude::*;
-    use ratatui::text::{Line, Span};
-
-    #[test]
-    fn test_color_match() {
-        let color = "not a match\nstill not a [matched]match[/matched]\nwhat??";
-        let result = color_match(color);
-        assert_eq!(
-            result,
-            Line::from_iter([
-                Span::from("not a match\nstill not a "),
-                Span::from("match").red(),
-                Span::from("\nwhat??")
-            ])
-        );
-    }
-
-    #[test]
-    fn test_color_match_html() {
-        let color = "not a match\nstill not a [matched]match[/matched]\nwhat??";
-        let result = color_match_html(String::from(color));
-        assert_eq!(
-            result,
-            String::from(
-                "not a match\nstill not a <span style=\"color: red\">match</span>\nwhat??"
-            )
-        );
-    }
-
-    #[test]
-    fn test_search_and_copy() {
-        let connection = &mut DBCONNECTION.get().unwrap();
-        let root = root_for_tag_tests(connection);
-
-        // create app and run it
-        let mut app = App::new(root);
-        app.input = "armas".into();
-        app.search().unwrap();
-        assert_eq!(
-            app.results,
-            vec![
-                SearchResults {
-                    title: "1".into(),
-                    results: vec![
-                        "Se as [matched]armas[/matched] queres ver, como tens dito,\n".into()
-                    ]
-                },
-                SearchResults {
-                    title: "2".into(),
-                    results: vec!["As [matched]armas[/matched] e os barões assinalados,\n".into()]
-                },
-                SearchResults {
-                    title: "3".into(),
-                    results: vec![]
-                },
-                SearchResults {
-                    title: "4".into(),
-                    results: vec![]
-                }
-            ]
-        );
-
-        app.copy_results().expect("App could not copy results");
-        let copied = Clipboard::new()
-            .expect("Clipboard not supported")
-            .get()
-            .text()
-            .expect("empty or non-UTF-8 clipboard");
-        assert_eq!(copied, "<div><span style=\"color: blue\">1</span></div><p>Se as <span style=\"color: red\">armas</span> queres ver, como tens dito,\n</p><div><span style=\"color: blue\">2</span></div><p>As <span style=\"color: red\">armas</span> e os barões assinalados,\n</p>");
-    }
-}
+mod tests;
@@ -0,0 +1,209 @@
+//! Book opened from a result or from the library, read a window of lines at a time.
+use bookrab_core::books::reading_progress::ReadingProgress;
+use bookrab_core::errors::BookrabError;
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, List, ListItem, ListState, Paragraph};
+
+use crate::{App, WhereWeAre, SELECTED_STYLE, TEXT_FG_COLOR};
+
+/// Lines of the book kept in memory by the [Reader].
+const READER_WINDOW: usize = 1000;
+
+/// Book opened from a result (see [App::open_selected_result]).
+/// Only [READER_WINDOW] lines are loaded at a time.
+pub struct Reader {
+    pub title: String,
+    /// Line of the match (line numbers start at 1)
+    pub line: u64,
+    /// First line on the screen
+    pub top: u64,
+    /// Number of the first line of `window`
+    window_start: u64,
+    pub window: Vec<String>,
+    /// Lines that fit on the screen (updated when rendering)
+    pub height: u16,
+}
+
+impl Reader {
+    /// Whether the last line of the book is in `window`.
+    fn window_has_end(&self) -> bool {
+        self.window.len() < READER_WINDOW
+    }
+}
+
+impl App<'_> {
+    /// Opens the book of the selected snippet at the line of its match.
+    pub fn open_selected_result(&mut self) -> Result<(), BookrabError> {
+        let (Some((title, _)), Some(line)) = (self.selected_result(), self.selected_line()) else {
+            return Ok(());
+        };
+        self.open_book(title.to_string(), line)
+    }
+
+    /// Opens `title` in the [Reader] at `line`.
+    pub fn open_book(&mut self, title: String, line: u64) -> Result<(), BookrabError> {
+        let mut reader = Reader {
+            title,
+            line,
+            top: line,
+            window_start: 1,
+            window: vec![],
+            height: 0,
+        };
+        self.load_reader_window(&mut reader, line)?;
+        self.reader = Some(reader);
+        Ok(())
+    }
+
+    /// Closes the open book and stores where its reading stopped
+    /// (when there is a database).
+    pub fn close_reader(&mut self) -> Result<(), BookrabError> {
+        let Some(reader) = self.reader.take() else {
+            return Ok(());
+        };
+        let config = self.root_book_dir.config().clone();
+        let Some(connection) = self.root_book_dir.connection.as_deref_mut() else {
+            return Ok(());
+        };
+        ReadingProgress::new(config, connection).save(&reader.title, reader.top)?;
+        self.load_continue_reading()
+    }
+
+    /// Lists the books whose reading stopped somewhere, the most recent first.
+    pub fn load_continue_reading(&mut self) -> Result<(), BookrabError> {
+        let config = self.root_book_dir.config().clone();
+        let Some(connection) = self.root_book_dir.connection.as_deref_mut() else {
+            return Ok(());
+        };
+        self.continue_reading = ReadingProgress::new(config, connection).list()?;
+        let selected = (!self.continue_reading.is_empty()).then_some(0);
+        self.continue_state = ListState::default().with_selected(selected);
+        Ok(())
+    }
+
+    /// Whether the results show [App::continue_reading] (until the first search).
+    pub fn showing_continue_reading(&self) -> bool {
+        self.results.is_empty() && self.pending.is_none() && !self.continue_reading.is_empty()
+    }
+
+    /// Opens the selected book of [App::continue_reading] where its reading stopped.
+    pub fn continue_selected_book(&mut self) -> Result<(), BookrabError> {
+        let Some(position) = self
+            .continue_state
+            .selected()
+            .and_then(|selected| self.continue_reading.get(selected))
+        else {
+            return Ok(());
+        };
+        self.open_book(position.title.clone(), position.line)
+    }
+
+    /// Renders [App::continue_reading] in the place of the results.
+    pub fn render_continue_reading(&mut self, rect: Rect, f: &mut Frame) {
+        let items: Vec<ListItem> = self
+            .continue_reading
+            .iter()
+            .map(|position| {
+                ListItem::new(Line::from_iter([
+                    Span::from(position.title.as_str()).blue(),
+                    Span::from(format!(
+                        "  line {} ({})",
+                        position.line,
+                        position.updated_at.format("%Y-%m-%d %H:%M")
+                    ))
+                    .fg(TEXT_FG_COLOR),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::new().borders(Borders::ALL).title("Continue reading"))
+            .style(self.highlight_if_focused(WhereWeAre::Results))
+            .highlight_style(SELECTED_STYLE)
+            .highlight_symbol(">");
+        f.render_stateful_widget(list, rect, &mut self.continue_state);
+        self.areas.push((WhereWeAre::Results, rect));
+    }
+
+    /// Loads the lines of `reader` around `line`.
+    fn load_reader_window(&mut self, reader: &mut Reader, line: u64) -> Result<(), BookrabError> {
+        let start = line.saturating_sub(READER_WINDOW as u64 / 4).max(1);
+        reader.window = self
+            .root_book_dir
+            .get_lines(&reader.title, start, READER_WINDOW)?;
+        reader.window_start = start;
+        Ok(())
+    }
+
+    /// Scrolls the open book by `delta` lines (negative goes up).
+    pub fn scroll_reader(&mut self, delta: i64) -> Result<(), BookrabError> {
+        let Some(mut reader) = self.reader.take() else {
+            return Ok(());
+        };
+        let mut top = reader.top.saturating_add_signed(delta).max(1);
+        let window_end = reader.window_start + reader.window.len() as u64;
+        let loaded = if top < reader.window_start
+            || (top + reader.height as u64 > window_end && !reader.window_has_end())
+        {
+            self.load_reader_window(&mut reader, top)
+        } else {
+            Ok(())
+        };
+        if reader.window_has_end() {
+            // the last line of the book doesn't leave the screen
+            let last = reader.window_start + reader.window.len().max(1) as u64 - 1;
+            top = top.min(last);
+        }
+        reader.top = top;
+        self.reader = Some(reader);
+        loaded
+    }
+
+    /// Renders the open book (the line of the match is highlighted).
+    pub fn render_reader(&mut self, rect: Rect, f: &mut Frame) {
+        let Some(reader) = self.reader.as_mut() else {
+            return;
+        };
+        reader.height = rect.height.saturating_sub(2);
+        let skip = reader.top.saturating_sub(reader.window_start) as usize;
+        let lines: Vec<Line> = reader
+            .window
+            .iter()
+            .enumerate()
+            .skip(skip)
+            .take(reader.height as usize)
+            .map(|(i, text)| {
+                let number = reader.window_start + i as u64;
+                let line = Line::from(vec![
+                    Span::from(format!("{number:>6} ")).fg(TEXT_FG_COLOR),
+                    Span::from(text.as_str()),
+                ]);
+                if number == reader.line {
+                    line.style(SELECTED_STYLE)
+                } else {
+                    line
+                }
+            })
+            .collect();
+        let title = format!("{} (line {}, Esc to go back)", reader.title, reader.line);
+        f.render_widget(
+            Paragraph::new(Text::from(lines))
+                .block(Block::new().borders(Borders::ALL).title(title)),
+            rect,
+        );
+    }
+}
+
+/// Keys of the open book.
+pub fn bindings(key: KeyEvent, app: &mut App) {
+    let page = app.reader.as_ref().map_or(1, |reader| reader.height.max(1)) as i64;
+    let scrolled = match key.code {
+        KeyCode::Esc | KeyCode::Char('q') => app.close_reader(),
+        KeyCode::Char('j') | KeyCode::Down => app.scroll_reader(1),
+        KeyCode::Char('k') | KeyCode::Up => app.scroll_reader(-1),
+        KeyCode::PageDown | KeyCode::Char(' ') => app.scroll_reader(page),
+        KeyCode::PageUp => app.scroll_reader(-page),
+        _ => Ok(()),
+    };
+    app.report("read the book", scrolled);
+}
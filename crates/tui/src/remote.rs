@@ -0,0 +1,262 @@
+//! Remote mode: the TUI browses the library of a bookrab REST API
+//! (see [TuiConfig::remote_url]) instead of the local one.
+//! Only what the API offers works remotely: tags, searches and history.
+use std::collections::HashSet;
+
+use bookrab_client::{Client, ClientError, HistoryEntry, HistoryQuery, ListQuery, SearchRequest};
+use bookrab_core::{
+    books::{
+        history::HistoryContext, history_backend::JsonHistoryEntry, FilterMode, SearchResults,
+    },
+    errors::BookrabError,
+};
+
+use crate::config::TuiConfig;
+
+/// Server whose library is browsed.
+#[derive(Clone, Debug)]
+pub struct Remote {
+    /// e.g. `http://localhost:8000`
+    pub url: String,
+    client: Client,
+}
+
+impl Remote {
+    /// Server of `url` (see [TuiConfig::remote_token]).
+    pub fn new(url: &str, config: &TuiConfig) -> Remote {
+        let mut client = Client::new(url);
+        if let Some(token) = &config.remote_token {
+            client = client.with_token(token);
+        }
+        Remote {
+            url: url.to_string(),
+            client,
+        }
+    }
+
+    fn error(&self, err: ClientError) -> BookrabError {
+        BookrabError::RemoteRequestFailed {
+            error: (),
+            url: self.url.clone(),
+            reason: err.reason(),
+        }
+    }
+
+    /// Tags of every book of the server.
+    pub fn all_tags(&self) -> Result<HashSet<String>, BookrabError> {
+        let books = self
+            .client
+            .list_all(&ListQuery::default())
+            .map_err(|e| self.error(e))?;
+        Ok(books.into_iter().flat_map(|book| book.tags).collect())
+    }
+
+    /// Searches every book in a single request. `on_book` receives the
+    /// number of books received after each one; the search stops (with
+    /// the results it has) when it returns `false`.
+    pub fn search(
+        &self,
+        pattern: String,
+        options: &HistoryContext,
+        on_book: &dyn Fn(usize) -> bool,
+    ) -> Result<Vec<SearchResults>, BookrabError> {
+        let mode = |mode: &FilterMode| match mode {
+            FilterMode::All => bookrab_client::FilterMode::All,
+            FilterMode::Any => bookrab_client::FilterMode::Any,
+        };
+        let tags = |tags: &HashSet<String>| Some(tags.iter().cloned().collect());
+        let request = SearchRequest {
+            pattern,
+            before_context: Some(options.before_context),
+            after_context: Some(options.after_context),
+            case_insensitive: Some(options.case_insensitive),
+            case_smart: Some(options.case_smart),
+            ignore_accents: Some(options.ignore_accents),
+            include_tags: tags(&options.include_tags),
+            include_mode: Some(mode(&options.include_mode)),
+            exclude_tags: tags(&options.exclude_tags),
+            exclude_mode: Some(mode(&options.exclude_mode)),
+            ..Default::default()
+        };
+        let mut received = 0;
+        let books = self
+            .client
+            .search_streaming(&request, |_| {
+                received += 1;
+                on_book(received)
+            })
+            .map_err(|e| self.error(e))?;
+        Ok(books
+            .into_iter()
+            .map(|book| SearchResults {
+                title: book.title,
+                results: book.results,
+                score: book.score,
+                duration_ms: book.duration_ms,
+                lines: vec![],
+            })
+            .collect())
+    }
+
+    /// Every entry of the history of the server (without their results).
+    pub fn history(&self) -> Result<Vec<JsonHistoryEntry>, BookrabError> {
+        let entries = self
+            .client
+            .history_all(&HistoryQuery::default(), None)
+            .map_err(|e| self.error(e))?;
+        Ok(entries.into_iter().map(history_entry).collect())
+    }
+}
+
+fn history_entry(entry: HistoryEntry) -> JsonHistoryEntry {
+    let mode = |mode: bookrab_client::FilterMode| match mode {
+        bookrab_client::FilterMode::All => FilterMode::All,
+        bookrab_client::FilterMode::Any => FilterMode::Any,
+    };
+    JsonHistoryEntry {
+        date: entry.date,
+        title: entry.title,
+        pattern: entry.pattern,
+        context: HistoryContext {
+            include_mode: mode(entry.include_mode),
+            include_tags: entry.include_tags.into_iter().collect(),
+            exclude_mode: mode(entry.exclude_mode),
+            exclude_tags: entry.exclude_tags.into_iter().collect(),
+            case_insensitive: entry.case_insensitive,
+            case_smart: entry.case_smart,
+            ignore_accents: entry.ignore_accents,
+            before_context: entry.before_context.max(0) as usize,
+            after_context: entry.after_context.max(0) as usize,
+        },
+        results: vec![],
+        match_count: entry.match_count.max(0) as usize,
+        duration_ms: entry.duration_ms.max(0) as u64,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    use bookrab_core::errors::BookrabError;
+
+    use super::Remote;
+    use crate::config::TuiConfig;
+
+    /// Server that answers `requests` requests with `respond(path and query)`
+    /// (a status and a JSON body).
+    fn serve(requests: usize, respond: fn(&str) -> (u16, String)) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        thread::spawn(move || {
+            for stream in listener.incoming().take(requests) {
+                let mut stream = stream.unwrap();
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                let mut request_line = String::new();
+                reader.read_line(&mut request_line).unwrap();
+                let mut content_length = 0;
+                loop {
+                    let mut header = String::new();
+                    reader.read_line(&mut header).unwrap();
+                    if header.trim().is_empty() {
+                        break;
+                    }
+                    if let Some((name, value)) = header.split_once(':') {
+                        if name.eq_ignore_ascii_case("content-length") {
+                            content_length = value.trim().parse().unwrap();
+                        }
+                    }
+                }
+                let mut body = vec![0; content_length];
+                reader.read_exact(&mut body).unwrap();
+                let path = request_line.split(' ').nth(1).unwrap();
+                let (status, body) = respond(path);
+                write!(
+                    stream,
+                    "HTTP/1.1 {status} X\r\nContent-Type: application/json\r\n\
+                     Content-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    fn remote(url: &str) -> Remote {
+        Remote::new(url, &TuiConfig::default())
+    }
+
+    #[test]
+    fn tags_of_every_page() {
+        let url = serve(2, |path| {
+            let body = if path.contains("offset=0") {
+                r#"{"items": [{"title": "1", "tags": ["a", "b"], "language": null}],
+                    "total": 2, "next": 1}"#
+            } else {
+                r#"{"items": [{"title": "2", "tags": ["c"], "language": "pt"}],
+                    "total": 2, "next": null}"#
+            };
+            (200, body.to_string())
+        });
+        let mut tags: Vec<String> = remote(&url).all_tags().unwrap().into_iter().collect();
+        tags.sort();
+        assert_eq!(tags, ["a", "b", "c"]);
+    }
+
+    #[test]
+    fn search_streams_until_cancelled() {
+        // every book comes in a single response
+        let url = serve(1, |path| {
+            assert_eq!(path, "/v1/books/search");
+            let book = |title: &str, score: f64| {
+                format!(
+                    r#"{{"title": "{title}", "results": ["[matched]armas[/matched]"], "score": {score}, "duration_ms": 3, "truncated": false}}"#
+                )
+            };
+            (
+                200,
+                [book("1", 1.0), book("2", 2.0), book("3", 3.0)].join("\n"),
+            )
+        });
+        let remote = remote(&url);
+        let options = Default::default();
+        let progress = std::cell::RefCell::new(vec![]);
+        let results = remote
+            .search("armas".to_string(), &options, &|received| {
+                progress.borrow_mut().push(received);
+                received < 2
+            })
+            .unwrap();
+        assert_eq!(progress.into_inner(), [1, 2]);
+        let titles: Vec<&str> = results.iter().map(|r| r.title.as_str()).collect();
+        assert_eq!(titles, ["2", "1"]);
+        assert_eq!(results[0].match_count(), 1);
+    }
+
+    #[test]
+    fn errors_of_the_server() {
+        let url = serve(1, |_| {
+            let body = r#"{"Unauthorized": {"error": "E0024: authentication required.",
+                "reason": "the token expired"}}"#;
+            (401, body.to_string())
+        });
+        let Err(BookrabError::RemoteRequestFailed {
+            url: failed,
+            reason,
+            ..
+        }) = remote(&url).history()
+        else {
+            panic!("the request should fail");
+        };
+        assert_eq!(failed, url);
+        assert_eq!(
+            reason,
+            "the server answered 401 Unauthorized: E0024: authentication required."
+        );
+    }
+}
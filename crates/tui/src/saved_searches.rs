@@ -0,0 +1,222 @@
+//! Searches saved by name (`Ctrl+s`) and loaded again (`Ctrl+o`).
+use bookrab_core::books::history::HistoryContext;
+use bookrab_core::books::saved_searches::{SavedSearch, SavedSearches};
+use bookrab_core::books::{Exclude, Include};
+use bookrab_core::errors::BookrabError;
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Clear, List, ListItem, ListState};
+use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+
+use crate::{centered, render_filter, App, EXCLUDED_FG_COLOR, INCLUDED_FG_COLOR, SELECTED_STYLE};
+
+/// Saved searches (see [App::open_saved_searches]).
+pub struct SavedSearchList {
+    pub searches: Vec<SavedSearch>,
+    /// Part of the name or of the pattern of the searches that are shown
+    pub filter: Input,
+    /// Selected search among the shown ones
+    pub state: ListState,
+}
+
+impl SavedSearchList {
+    /// Searches whose name or pattern contain the filter (ignoring case).
+    pub fn filtered(&self) -> Vec<&SavedSearch> {
+        let filter = self.filter.value().to_lowercase();
+        self.searches
+            .iter()
+            .filter(|search| {
+                search.name.to_lowercase().contains(&filter)
+                    || search.pattern.to_lowercase().contains(&filter)
+            })
+            .collect()
+    }
+
+    /// Selects the first search that is shown (if any).
+    pub fn select_first(&mut self) {
+        let selected = if self.filtered().is_empty() {
+            None
+        } else {
+            Some(0)
+        };
+        self.state = ListState::default().with_selected(selected);
+    }
+}
+
+impl App<'_> {
+    /// The current search (pattern, tags, modes and options) named `name`.
+    fn current_search(&self, name: &str) -> SavedSearch {
+        let context = self.settings.context();
+        let include = Include::from(&self.tags);
+        let exclude = Exclude::from(&self.tags);
+        SavedSearch {
+            name: name.to_string(),
+            pattern: self.settings.pattern(self.input.value()),
+            include_mode: self.include.clone(),
+            include_tags: include.tags,
+            exclude_mode: self.exclude.clone(),
+            exclude_tags: exclude.tags,
+            case_insensitive: context.case_insensitive,
+            case_smart: context.case_smart,
+            ignore_accents: context.ignore_accents,
+            before_context: context.before_context,
+            after_context: context.after_context,
+        }
+    }
+
+    /// Stores the current search under `name` (see [SavedSearches::save]),
+    /// so that it can be loaded with [App::open_saved_searches] or run with `:name`.
+    pub fn save_current_search(&mut self, name: &str) -> Result<(), BookrabError> {
+        let search = self.current_search(name);
+        let config = self.root_book_dir.config().clone();
+        let Some(connection) = self.root_book_dir.connection.as_deref_mut() else {
+            return Err(BookrabError::DatabaseUnavailable {
+                error: (),
+                operation: "save a search".to_string(),
+            });
+        };
+        SavedSearches::new(config, connection).save(&search)
+    }
+
+    /// Lists the saved searches.
+    pub fn open_saved_searches(&mut self) -> Result<(), BookrabError> {
+        let config = self.root_book_dir.config().clone();
+        let Some(connection) = self.root_book_dir.connection.as_deref_mut() else {
+            return Err(BookrabError::DatabaseUnavailable {
+                error: (),
+                operation: "list saved searches".to_string(),
+            });
+        };
+        let mut saved_searches = SavedSearchList {
+            searches: SavedSearches::new(config, connection).list()?,
+            filter: Input::default(),
+            state: ListState::default(),
+        };
+        saved_searches.select_first();
+        self.saved_searches = Some(saved_searches);
+        Ok(())
+    }
+
+    /// Puts the selected saved search in the panels and runs it.
+    pub fn load_selected_search(&mut self) {
+        let Some(saved_searches) = self.saved_searches.take() else {
+            return;
+        };
+        let Some(search) = saved_searches.state.selected().and_then(|i| {
+            saved_searches
+                .filtered()
+                .get(i)
+                .map(|search| (*search).clone())
+        }) else {
+            self.saved_searches = Some(saved_searches);
+            return;
+        };
+        let context = HistoryContext {
+            include_mode: search.include_mode.clone(),
+            include_tags: search.include_tags.clone(),
+            exclude_mode: search.exclude_mode.clone(),
+            exclude_tags: search.exclude_tags.clone(),
+            case_insensitive: search.case_insensitive,
+            case_smart: search.case_smart,
+            ignore_accents: search.ignore_accents,
+            before_context: search.before_context,
+            after_context: search.after_context,
+        };
+        self.restore_search(search.pattern, &context);
+    }
+
+    /// Renders the saved searches (a filter above the list of searches).
+    pub fn render_saved_searches(&mut self, rect: Rect, f: &mut Frame) {
+        let Some(saved_searches) = self.saved_searches.as_mut() else {
+            return;
+        };
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Length(3), Constraint::Min(1)].as_ref())
+            .split(rect);
+        render_filter(&saved_searches.filter, "Filter", areas[0], f);
+
+        let searches = saved_searches.filtered();
+        let title = format!(
+            "Saved searches ({} of {}, Enter to load, Esc to go back)",
+            searches.len(),
+            saved_searches.searches.len()
+        );
+        let items: Vec<ListItem> = searches
+            .iter()
+            .map(|search| {
+                let mut include: Vec<&str> =
+                    search.include_tags.iter().map(String::as_str).collect();
+                let mut exclude: Vec<&str> =
+                    search.exclude_tags.iter().map(String::as_str).collect();
+                include.sort();
+                exclude.sort();
+                ListItem::new(Line::from(vec![
+                    Span::from(format!("{}  ", search.name)).fg(Color::Yellow),
+                    Span::from(search.pattern.clone()).blue(),
+                    Span::from(format!("  {}", include.join(", "))).fg(INCLUDED_FG_COLOR),
+                    Span::from(format!("  {}", exclude.join(", "))).fg(EXCLUDED_FG_COLOR),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(Block::default().borders(Borders::ALL).title(title))
+            .highlight_style(SELECTED_STYLE)
+            .highlight_symbol(">");
+        f.render_stateful_widget(list, areas[1], &mut saved_searches.state);
+    }
+
+    /// Renders the name asked by [App::save_prompt] in a window above the rest.
+    pub fn render_save_prompt(&self, rect: Rect, f: &mut Frame) {
+        let Some(name) = &self.save_prompt else {
+            return;
+        };
+        let area = centered(rect, 60, 3);
+        f.render_widget(Clear, area);
+        render_filter(
+            name,
+            "Save search as (Enter to save, Esc to cancel)",
+            area,
+            f,
+        );
+    }
+}
+
+/// Keys of the saved searches.
+pub fn bindings(key: KeyEvent, app: &mut App) {
+    let Some(saved_searches) = app.saved_searches.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => app.saved_searches = None,
+        KeyCode::Down => saved_searches.state.select_next(),
+        KeyCode::Up => saved_searches.state.select_previous(),
+        KeyCode::Enter => app.load_selected_search(),
+        _ => {
+            saved_searches.filter.handle_event(&Event::Key(key));
+            saved_searches.select_first();
+        }
+    }
+}
+
+/// Keys of the name of the search that is being saved.
+pub fn save_prompt_bindings(key: KeyEvent, app: &mut App) {
+    let Some(name) = app.save_prompt.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => app.save_prompt = None,
+        KeyCode::Enter => {
+            let name = name.value().trim().to_string();
+            if !name.is_empty() {
+                let saved = app.save_current_search(&name);
+                app.save_prompt = None;
+                app.report("save the search", saved);
+            }
+        }
+        _ => {
+            name.handle_event(&Event::Key(key));
+        }
+    }
+}
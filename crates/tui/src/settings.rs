@@ -0,0 +1,251 @@
+//! Editor of the configuration file, opened by `Ctrl+p`.
+use std::path::PathBuf;
+
+use bookrab_core::books::validation::FieldError;
+use crossterm::event::{Event, KeyCode, KeyEvent};
+use ratatui::prelude::*;
+use ratatui::widgets::{Block, Borders, Paragraph};
+use strum::{EnumIter, IntoEnumIterator};
+use tui_input::backend::crossterm::EventHandler;
+use tui_input::Input;
+
+use crate::{
+    config::{self, ConfigFile},
+    App, SearchSettings,
+};
+
+/// Field of the [SettingsEditor].
+#[derive(Clone, Copy, Debug, PartialEq, EnumIter)]
+pub enum SettingField {
+    BookPath,
+    DatabaseUrl,
+    BeforeContext,
+    AfterContext,
+    Case,
+    WholeWord,
+}
+
+impl SettingField {
+    /// Name of the field in the configuration file (or in the options panel).
+    pub fn name(self) -> &'static str {
+        match self {
+            SettingField::BookPath => "book_path",
+            SettingField::DatabaseUrl => "database_url",
+            SettingField::BeforeContext => "before_context",
+            SettingField::AfterContext => "after_context",
+            SettingField::Case => "case",
+            SettingField::WholeWord => "whole_word",
+        }
+    }
+}
+
+/// Configuration file being edited (see [App::open_settings]).
+pub struct SettingsEditor {
+    /// Configuration that was loaded (fields that aren't edited are kept)
+    pub file: ConfigFile,
+    /// Text of each [SettingField], in order
+    pub inputs: Vec<(SettingField, Input)>,
+    /// Index of the field that is being edited
+    pub selected: usize,
+    /// Problems found by the last attempt to save
+    pub errors: Vec<FieldError>,
+}
+
+impl SettingsEditor {
+    pub fn new(file: ConfigFile) -> Self {
+        let settings = SearchSettings::from_config(&file.tui);
+        let inputs = SettingField::iter()
+            .map(|field| {
+                let value = match field {
+                    SettingField::BookPath => file.config.book_path.display().to_string(),
+                    SettingField::DatabaseUrl => {
+                        file.config.database_url.clone().unwrap_or_default()
+                    }
+                    SettingField::BeforeContext => settings.before_context.to_string(),
+                    SettingField::AfterContext => settings.after_context.to_string(),
+                    SettingField::Case => format!("{:?}", settings.case).to_lowercase(),
+                    SettingField::WholeWord => {
+                        if settings.whole_word { "on" } else { "off" }.to_string()
+                    }
+                };
+                (field, Input::new(value))
+            })
+            .collect();
+        SettingsEditor {
+            file,
+            inputs,
+            selected: 0,
+            errors: vec![],
+        }
+    }
+
+    /// Configuration with the values of the fields or the problems of
+    /// the fields. An empty `database_url` runs without a database.
+    pub fn validated(&self) -> Result<ConfigFile, Vec<FieldError>> {
+        let mut file = self.file.clone();
+        let mut errors = vec![];
+        for (field, input) in &self.inputs {
+            let value = input.value().trim();
+            let mut error = |message: &str| errors.push(FieldError::new(field.name(), message));
+            match field {
+                SettingField::BookPath => {
+                    let path = PathBuf::from(value);
+                    if value.is_empty() {
+                        error("can't be empty");
+                    } else if path.exists() && !path.is_dir() {
+                        error("isn't a folder");
+                    } else {
+                        file.config.book_path = path;
+                    }
+                }
+                SettingField::DatabaseUrl => {
+                    if value.is_empty() {
+                        file.config.database_url = None;
+                    } else if value.starts_with("postgres://") || value.starts_with("postgresql://")
+                    {
+                        file.config.database_url = Some(value.to_string());
+                    } else {
+                        error("should start with postgres:// or postgresql://");
+                    }
+                }
+                SettingField::BeforeContext | SettingField::AfterContext => {
+                    match value.parse::<usize>() {
+                        Ok(lines) if *field == SettingField::BeforeContext => {
+                            file.tui.before_context = lines
+                        }
+                        Ok(lines) => file.tui.after_context = lines,
+                        Err(_) => error("should be a number of lines"),
+                    }
+                }
+                SettingField::Case => match value {
+                    "sensitive" | "insensitive" | "smart" => {
+                        file.tui.case_insensitive = value == "insensitive";
+                        file.tui.case_smart = value == "smart";
+                    }
+                    _ => error("should be sensitive, insensitive or smart"),
+                },
+                SettingField::WholeWord => match value {
+                    "on" | "off" => file.tui.whole_word = value == "on",
+                    _ => error("should be on or off"),
+                },
+            }
+        }
+        if errors.is_empty() {
+            Ok(file)
+        } else {
+            Err(errors)
+        }
+    }
+
+    /// Selects the next field (or the previous one if `forward` isn't set).
+    pub fn select(&mut self, forward: bool) {
+        let len = self.inputs.len();
+        self.selected = if forward {
+            (self.selected + 1) % len
+        } else {
+            (self.selected + len - 1) % len
+        };
+    }
+}
+
+impl App<'_> {
+    /// Edits the configuration file (see [SettingsEditor]).
+    pub fn open_settings(&mut self) -> Result<(), confy::ConfyError> {
+        self.settings_editor = Some(SettingsEditor::new(config::load_config()?));
+        Ok(())
+    }
+
+    /// Saves the configuration if every field is valid (the problems are
+    /// shown otherwise). The options of the searches change right away,
+    /// the book folder and the database are used after a restart.
+    fn save_settings(&mut self) {
+        let Some(editor) = self.settings_editor.as_mut() else {
+            return;
+        };
+        let file = match editor.validated() {
+            Ok(file) => file,
+            Err(errors) => {
+                editor.errors = errors;
+                return;
+            }
+        };
+        self.settings_editor = None;
+        let selected = self.settings.selected;
+        self.settings = SearchSettings {
+            selected,
+            ..SearchSettings::from_config(&file.tui)
+        };
+        self.tui = file.tui.clone();
+        if let Err(e) = config::store_config(file) {
+            self.show_error("save the settings", e.to_string());
+        }
+    }
+
+    /// Renders the [SettingsEditor]: one box per field,
+    /// with its problem (if any) in the title.
+    pub fn render_settings(&self, rect: Rect, f: &mut Frame) {
+        let Some(editor) = &self.settings_editor else {
+            return;
+        };
+        let mut constraints = vec![Constraint::Length(1)];
+        constraints.extend(editor.inputs.iter().map(|_| Constraint::Length(3)));
+        let areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(constraints)
+            .split(rect);
+        f.render_widget(
+            Paragraph::new(
+                "Settings (Enter to save, Esc to cancel; the book folder \
+                 and the database are used after restarting)",
+            ),
+            areas[0],
+        );
+        for (i, (field, input)) in editor.inputs.iter().enumerate() {
+            let area = areas[i + 1];
+            let error = editor
+                .errors
+                .iter()
+                .find(|error| error.field == field.name());
+            let title = match error {
+                Some(error) => Line::from(format!("{} ({})", field.name(), error.message)).red(),
+                None => Line::from(field.name()),
+            };
+            let style = if i == editor.selected {
+                Style::default().fg(Color::Yellow)
+            } else {
+                Style::default()
+            };
+            f.render_widget(
+                Paragraph::new(input.value())
+                    .style(style)
+                    .block(Block::default().borders(Borders::ALL).title(title)),
+                area,
+            );
+            if i == editor.selected {
+                let width = area.width.max(3) - 3; // keep 2 for borders and 1 for cursor
+                let scroll = input.visual_scroll(width as usize);
+                f.set_cursor_position((
+                    area.x + ((input.visual_cursor()).max(scroll) - scroll) as u16 + 1,
+                    area.y + 1,
+                ));
+            }
+        }
+    }
+}
+
+/// Keys of the settings.
+pub fn bindings(key: KeyEvent, app: &mut App) {
+    let Some(editor) = app.settings_editor.as_mut() else {
+        return;
+    };
+    match key.code {
+        KeyCode::Esc => app.settings_editor = None,
+        KeyCode::Down | KeyCode::Tab => editor.select(true),
+        KeyCode::Up | KeyCode::BackTab => editor.select(false),
+        KeyCode::Enter => app.save_settings(),
+        _ => {
+            let selected = editor.selected;
+            editor.inputs[selected].1.handle_event(&Event::Key(key));
+        }
+    }
+}
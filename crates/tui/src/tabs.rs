@@ -0,0 +1,110 @@
+//! Tabs, each with its own search (see [App::switch_tab]).
+use std::collections::HashSet;
+
+use bookrab_core::books::{FilterMode, SearchResults};
+use ratatui::widgets::ListState;
+use tui_input::Input;
+
+use crate::{App, PendingSearch, TagItem, TagList, TagStatus};
+
+/// Tabs that can be open, one per number key (see [App::switch_tab]).
+const MAX_TABS: usize = 9;
+
+/// Search of a tab that isn't shown: the fields of [App] with the same names
+/// (see [App::switch_tab]).
+pub struct SearchTab {
+    input: Input,
+    tags: TagList,
+    include: FilterMode,
+    exclude: FilterMode,
+    results: Vec<SearchResults>,
+    match_counts: Vec<usize>,
+    results_state: ListState,
+    collapsed: HashSet<usize>,
+    current_match: Option<(usize, usize, usize)>,
+    pending: Option<PendingSearch>,
+    last_error: Option<String>,
+}
+
+impl App<'_> {
+    /// Empty search with every tag (see [App::switch_tab]).
+    fn new_tab(&self) -> SearchTab {
+        let tags = TagList {
+            list: self
+                .tags
+                .list
+                .iter()
+                .map(|tag| TagItem {
+                    name: tag.name.clone(),
+                    status: TagStatus::None,
+                })
+                .collect(),
+            state: ListState::default(),
+        };
+        SearchTab {
+            input: Input::default(),
+            tags,
+            include: FilterMode::All,
+            exclude: FilterMode::Any,
+            results: vec![],
+            match_counts: vec![],
+            results_state: ListState::default(),
+            collapsed: HashSet::new(),
+            current_match: None,
+            pending: None,
+            last_error: None,
+        }
+    }
+
+    /// Exchanges the search of the shown tab with `tab`.
+    fn swap_tab(&mut self, tab: &mut SearchTab) {
+        std::mem::swap(&mut self.input, &mut tab.input);
+        std::mem::swap(&mut self.tags, &mut tab.tags);
+        std::mem::swap(&mut self.include, &mut tab.include);
+        std::mem::swap(&mut self.exclude, &mut tab.exclude);
+        std::mem::swap(&mut self.results, &mut tab.results);
+        std::mem::swap(&mut self.match_counts, &mut tab.match_counts);
+        std::mem::swap(&mut self.results_state, &mut tab.results_state);
+        std::mem::swap(&mut self.collapsed, &mut tab.collapsed);
+        std::mem::swap(&mut self.current_match, &mut tab.current_match);
+        std::mem::swap(&mut self.pending, &mut tab.pending);
+        std::mem::swap(&mut self.last_error, &mut tab.last_error);
+    }
+
+    /// Shows the tab at `index` or opens a new one when `index` is
+    /// past the last tab. The searches of the other tabs keep running.
+    pub fn switch_tab(&mut self, index: usize) {
+        let index = index.min(self.tabs.len());
+        if index == self.tab || index >= MAX_TABS {
+            return;
+        }
+        if index == self.tabs.len() {
+            let tab = self.new_tab();
+            self.tabs.push(Some(tab));
+        }
+        let Some(mut tab) = self.tabs[index].take() else {
+            return;
+        };
+        self.swap_tab(&mut tab);
+        self.tabs[self.tab] = Some(tab);
+        self.tab = index;
+    }
+
+    /// Closes the shown tab (unless it is the only one)
+    /// and shows the next one.
+    pub fn close_tab(&mut self) {
+        let closed = self.tab;
+        if self.tabs.len() == 1 {
+            return;
+        }
+        self.switch_tab(if closed + 1 < self.tabs.len() {
+            closed + 1
+        } else {
+            closed - 1
+        });
+        self.tabs.remove(closed);
+        if self.tab > closed {
+            self.tab -= 1;
+        }
+    }
+}
@@ -0,0 +1,1386 @@
+use crate::config::{ensure_confy_works, ClipboardKind, ConfigFile, TuiConfig};
+use crate::settings::SettingField;
+use crate::{
+    color_match, color_match_html, format_size, ui, wrap_line, App, CaseMode, Flags, MatchStyle,
+    PendingSearch, ResultItem, SearchMessage, SearchOption, SettingsEditor, TagStatus, WhereWeAre,
+    HELP,
+};
+use arboard::Clipboard;
+use bookrab_core::books::history::HistoryContext;
+use bookrab_core::books::history_backend::{history_backend, JsonHistoryEntry};
+use bookrab_core::books::reading_progress::ReadingProgress;
+use bookrab_core::books::saved_searches::{SavedSearch, SavedSearches};
+use crossterm::event::{KeyModifiers, MouseButton, MouseEvent, MouseEventKind};
+use tui_input::Input;
+// searches take their connections from the pool of the app, so
+// the connections of the tests come from another one
+use bookrab_core::books::test_utils::{
+    create_book_dir, random_string, root_for_tag_tests, s, DBCONNECTION,
+};
+use bookrab_core::books::{FilterMode, RootBookDir, SearchResults};
+use bookrab_core::config::{BookrabConfig, HistoryBackendKind};
+use ratatui::backend::TestBackend;
+use ratatui::prelude::*;
+use ratatui::text::{Line, Span};
+use std::sync::mpsc;
+use std::time::Instant;
+
+#[test]
+fn test_flags() {
+    let args = |args: &[&str]| args.iter().map(|arg| arg.to_string()).collect::<Vec<_>>();
+    let flags = Flags::parse(args(&["--config", "a.toml", "--remote=http://b"]).into_iter());
+    assert_eq!(
+        flags.unwrap(),
+        Flags {
+            remote: Some("http://b".into()),
+            config: Some("a.toml".into()),
+        }
+    );
+    assert!(Flags::parse(args(&["--config"]).into_iter()).is_err());
+    assert!(Flags::parse(args(&["--color"]).into_iter()).is_err());
+}
+
+#[test]
+fn test_config_file() {
+    let path = std::env::temp_dir().join(format!("bookrab-tui-{}.toml", random_string()));
+    let file = ConfigFile {
+        config: BookrabConfig {
+            book_path: "/tmp/bookrab-tui-books".into(),
+            ..Default::default()
+        },
+        tui: TuiConfig {
+            match_bold: true,
+            clipboard: ClipboardKind::Osc52,
+            ..Default::default()
+        },
+    };
+    confy::store_path(&path, file.clone()).unwrap();
+    assert!(std::fs::read_to_string(&path).unwrap().contains("[tui]"));
+    let loaded: ConfigFile = confy::load_path(&path).unwrap();
+    assert_eq!(loaded.tui, file.tui);
+    assert_eq!(loaded.config.book_path, file.config.book_path);
+    // the other apps skip the section of the TUI
+    let config: BookrabConfig = confy::load_path(&path).unwrap();
+    assert_eq!(config.book_path, file.config.book_path);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[test]
+fn test_color_match() {
+    let color = "not a match\nstill not a [matched]match[/matched]\nwhat??";
+    let result = color_match(color, Style::default().fg(Color::Red));
+    assert_eq!(
+        result,
+        Line::from_iter([
+            Span::from("not a match\nstill not a "),
+            Span::from("match").red(),
+            Span::from("\nwhat??")
+        ])
+    );
+}
+
+#[test]
+fn test_color_match_html() {
+    let color = "not a match\nstill not a [matched]match[/matched]\nwhat??";
+    let result = color_match_html(String::from(color));
+    assert_eq!(
+        result,
+        String::from("not a match\nstill not a <span style=\"color: red\">match</span>\nwhat??")
+    );
+}
+
+#[test]
+fn test_match_style() {
+    let mut config = TuiConfig::default();
+    assert_eq!(MatchStyle::from_config(&config), Ok(MatchStyle::default()));
+
+    config.match_color = Some("#ff8800".to_string());
+    config.match_bold = true;
+    config.match_reversed = true;
+    config.dim_context = true;
+    let style = MatchStyle::from_config(&config).unwrap();
+    assert_eq!(
+        style.matched,
+        Style::default()
+            .fg(Color::Rgb(255, 136, 0))
+            .bold()
+            .reversed()
+    );
+    assert!(style.dim_context);
+
+    config.match_color = Some("reddish".to_string());
+    assert_eq!(
+        MatchStyle::from_config(&config),
+        Err("\"reddish\" isn't a color (tui.match_color)".to_string())
+    );
+}
+
+#[test]
+fn test_dim_context() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.match_style = MatchStyle {
+        matched: Style::default().fg(Color::Blue),
+        dim_context: true,
+    };
+    app.set_results(vec![SearchResults {
+        title: "1".to_string(),
+        results: vec!["before\nas [matched]armas[/matched]".to_string()],
+        score: 0.0,
+        duration_ms: 0,
+        lines: vec![],
+    }]);
+    let item = app.result_list_item(ResultItem::Snippet(0, 0));
+    let mut terminal = Terminal::new(TestBackend::new(20, 2)).unwrap();
+    terminal
+        .draw(|f| f.render_widget(ratatui::widgets::List::new([item]), f.area()))
+        .unwrap();
+    let buffer = terminal.backend().buffer();
+    assert_eq!(buffer[(2, 0)].symbol(), "b");
+    assert!(buffer[(2, 0)].modifier.contains(Modifier::DIM));
+    assert_eq!(buffer[(5, 1)].symbol(), "a");
+    assert_eq!(buffer[(5, 1)].fg, Color::Blue);
+    assert!(!buffer[(2, 1)].modifier.contains(Modifier::DIM));
+}
+
+#[test]
+fn test_run_saved_search() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let mut root = root_for_tag_tests(connection);
+    SavedSearches::new(
+        ensure_confy_works(),
+        root.connection.as_deref_mut().unwrap(),
+    )
+    .save(&SavedSearch {
+        name: "tui-barões".into(),
+        pattern: "barões".into(),
+        include_mode: FilterMode::Any,
+        include_tags: ["c".to_string()].into(),
+        exclude_mode: FilterMode::Any,
+        exclude_tags: ["d".to_string()].into(),
+        case_insensitive: false,
+        case_smart: false,
+        ignore_accents: false,
+        before_context: 0,
+        after_context: 0,
+    })
+    .unwrap();
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.input = ":tui-barões".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    assert_eq!(app.results.len(), 1);
+    assert_eq!(app.results[0].title, "2");
+    assert_eq!(
+        app.results[0].results,
+        vec!["As armas e os [matched]barões[/matched] assinalados,\n"]
+    );
+}
+
+#[test]
+fn test_search_in_background() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.receive_results(false).unwrap();
+    app.input = "armas".into();
+    app.search();
+    assert!(app.pending.is_some());
+    // a new search replaces the one that is running
+    app.input = "barões".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    assert!(app.pending.is_none());
+    assert_eq!(app.selected_result().unwrap().0, "2");
+
+    app.input = "(".into();
+    app.search();
+    assert!(app.receive_results(true).is_err());
+    assert!(app.pending.is_none());
+}
+
+#[test]
+fn test_search_progress() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    let (sender, receiver) = mpsc::channel();
+    app.pending = Some(PendingSearch {
+        receiver,
+        started: Instant::now(),
+        progress: None,
+    });
+    for index in 0..37 {
+        sender
+            .send(SearchMessage::Progress {
+                index,
+                total: Some(312),
+            })
+            .unwrap();
+    }
+    app.receive_results(false).unwrap();
+    assert_eq!(
+        app.pending.as_ref().unwrap().progress,
+        Some((36, Some(312)))
+    );
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 20)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    assert!(screen.contains("searching book 37/312, 0.0 s"));
+
+    sender.send(SearchMessage::Done(Ok(vec![]))).unwrap();
+    app.receive_results(false).unwrap();
+    assert!(app.pending.is_none());
+}
+
+#[test]
+fn test_save_and_load_search() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+    let name = format!("tui-{}", random_string());
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.input = "BARÕES".into();
+    for tag in app.tags.list.iter_mut().filter(|tag| tag.name == "c") {
+        tag.status = TagStatus::Include;
+    }
+    app.settings.case = CaseMode::Insensitive;
+    app.settings.after_context = 1;
+    app.save_current_search(&name).unwrap();
+
+    app.input = "".into();
+    for tag in app.tags.list.iter_mut() {
+        tag.status = TagStatus::None;
+    }
+    app.settings = Default::default();
+    app.open_saved_searches().unwrap();
+    let saved_searches = app.saved_searches.as_mut().unwrap();
+    saved_searches.filter = name.as_str().into();
+    saved_searches.select_first();
+    assert_eq!(saved_searches.filtered().len(), 1);
+    app.load_selected_search();
+    app.receive_results(true).unwrap();
+
+    assert!(app.saved_searches.is_none());
+    assert_eq!(app.input.value(), "BARÕES");
+    let included: Vec<&str> = app
+        .tags
+        .list
+        .iter()
+        .filter(|tag| tag.status == TagStatus::Include)
+        .map(|tag| tag.name.as_str())
+        .collect();
+    assert_eq!(included, vec!["c"]);
+    assert_eq!(app.settings.case, CaseMode::Insensitive);
+    assert_eq!(app.settings.after_context, 1);
+    assert_eq!(app.selected_result().unwrap().0, "2");
+
+    SavedSearches::new(
+        app.root_book_dir.config().clone(),
+        app.root_book_dir.connection.as_deref_mut().unwrap(),
+    )
+    .delete(&name)
+    .unwrap();
+}
+
+#[test]
+fn test_status_line() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    let text = |app: &App| app.status_line().to_string();
+    for tag in app.tags.list.iter_mut() {
+        tag.status = match tag.name.as_str() {
+            "a" => TagStatus::Include,
+            "c" | "d" => TagStatus::Exclude,
+            _ => TagStatus::None,
+        }
+    }
+    app.input = "armas".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    // only "3" and "4" have no "c"
+    assert_eq!(
+        text(&app),
+        "0 of 2 books matched, 0 matches  include 1 tags (All)  exclude 2 tags (Any)"
+    );
+
+    app.input = "(".into();
+    app.search();
+    assert!(app.receive_results(true).is_err());
+    assert!(text(&app).ends_with("  E0012: problematic regex pattern."));
+
+    for tag in app.tags.list.iter_mut() {
+        tag.status = TagStatus::None;
+    }
+    app.input = "armas".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    assert_eq!(
+        text(&app),
+        "2 of 4 books matched, 2 matches  include 0 tags (All)  exclude 0 tags (Any)"
+    );
+}
+
+#[test]
+fn test_error_popup() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    assert!(app.error.is_none());
+    app.input = "(".into();
+    app.search();
+    let received = app.receive_results(true);
+    app.report("search", received);
+
+    let mut terminal = Terminal::new(TestBackend::new(100, 40)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    assert!(screen.contains("Couldn't search (any key to close)"));
+    assert!(screen.contains("E0012: problematic regex pattern."));
+    assert!(screen.contains("err: "));
+
+    // the session goes on
+    app.error = None;
+    app.input = "armas".into();
+    app.search();
+    let received = app.receive_results(true);
+    app.report("search", received);
+    assert!(app.error.is_none());
+    let match_count: usize = app.results.iter().map(|r| r.match_count()).sum();
+    assert_eq!(match_count, 2);
+}
+
+#[test]
+fn test_select_results() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    assert_eq!(app.selected_result(), None);
+    app.input = "armas".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    // books without matches have no items, the others have
+    // their title and their snippet
+    assert_eq!(app.result_items().len(), 4);
+    assert_eq!(app.results_state.selected(), Some(1));
+    let first = app.selected_result().unwrap();
+    assert!(first.1.contains("[matched]armas[/matched]"));
+    let first = (first.0.to_string(), first.1.to_string());
+
+    app.select_next_result();
+    assert!(matches!(app.selected_item(), Some(ResultItem::Book(_))));
+    assert_eq!(app.selected_result(), None);
+    app.select_next_result();
+    let (title, result) = app.selected_result().unwrap();
+    assert_ne!(title, first.0);
+    assert!(result.contains("[matched]armas[/matched]"));
+    app.select_previous_result();
+    app.select_previous_result();
+    let (title, result) = app.selected_result().unwrap();
+    assert_eq!((title.to_string(), result.to_string()), first);
+
+    app.input = "nothing matches this".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    assert_eq!(app.selected_result(), None);
+}
+
+#[test]
+fn test_huge_results() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    let results: Vec<SearchResults> = (0..20_000)
+        .map(|i| SearchResults {
+            title: format!("book {i}"),
+            results: vec![format!("line {i}\n[matched]armas[/matched] {i}")],
+            score: 0.0,
+            duration_ms: 0,
+            lines: vec![],
+        })
+        .collect();
+    app.set_results(results);
+    assert_eq!(app.results_state.selected(), Some(1));
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    let screen = |terminal: &Terminal<TestBackend>| -> String {
+        terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect()
+    };
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    assert!(screen(&terminal).contains("20000 matches"));
+    assert!(screen(&terminal).contains("book 0 (1 matches)"));
+    assert_eq!(app.results_state.offset(), 0);
+
+    // the last snippet is shown at the bottom
+    app.results_state.select(Some(39_999));
+    app.select_next_result();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    assert_eq!(app.results_state.selected(), Some(39_999));
+    let text = screen(&terminal);
+    assert!(text.contains("book 19999 (1 matches)"));
+    assert!(text.contains("armas 19999"));
+    assert!(!text.contains("book 0 "));
+    let offset = app.results_state.offset();
+    assert!(offset > 39_980 && offset < 39_999);
+
+    // going up keeps the offset until the selection leaves the screen
+    app.select_previous_result();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    assert_eq!(app.results_state.offset(), offset);
+    app.results_state.select(Some(10));
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    assert_eq!(app.results_state.offset(), 10);
+    assert!(screen(&terminal).contains("line 5"));
+}
+
+#[test]
+fn test_collapse_results() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.input = "armas".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    let items = app.result_items();
+    let (first, second) = (items[0].book(), items[2].book());
+
+    // the selection goes to the title of the book it was in
+    app.toggle_selected_book();
+    assert_eq!(app.selected_item(), Some(ResultItem::Book(first)));
+    assert_eq!(app.result_items().len(), 3);
+    app.toggle_selected_book();
+    assert_eq!(app.result_items(), items);
+    assert_eq!(app.selected_item(), Some(ResultItem::Book(first)));
+
+    app.select_next_result();
+    app.select_next_result();
+    app.select_next_result();
+    app.collapse(None, true);
+    assert_eq!(
+        app.result_items(),
+        vec![ResultItem::Book(first), ResultItem::Book(second)]
+    );
+    assert_eq!(app.selected_item(), Some(ResultItem::Book(second)));
+    app.collapse(Some(second), false);
+    assert_eq!(app.result_items().len(), 3);
+    app.collapse(None, false);
+    assert_eq!(app.result_items(), items);
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    for book in [first, second] {
+        let results = &app.results[book];
+        let title = format!("▾ {} ({} matches)", results.title, results.match_count());
+        assert!(screen.contains(&title));
+    }
+
+    // new results are open
+    app.collapse(None, true);
+    app.search();
+    app.receive_results(true).unwrap();
+    assert_eq!(app.result_items(), items);
+}
+
+#[test]
+fn test_open_result() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.input = "barões".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    app.open_selected_result().unwrap();
+    let reader = app.reader.as_ref().unwrap();
+    assert_eq!(reader.title, "2");
+    assert_eq!(reader.line, 1);
+    assert_eq!(reader.window[0], "As armas e os barões assinalados,");
+
+    app.reader.as_mut().unwrap().height = 2;
+    app.scroll_reader(1).unwrap();
+    assert_eq!(app.reader.as_ref().unwrap().top, 2);
+    // it can't go above the first line or below the last one
+    app.scroll_reader(-10).unwrap();
+    assert_eq!(app.reader.as_ref().unwrap().top, 1);
+    app.scroll_reader(10_000).unwrap();
+    let reader = app.reader.as_ref().unwrap();
+    assert_eq!(reader.top, reader.window.len() as u64);
+}
+
+#[test]
+fn test_pinned() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.input = "barões".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    // titles can't be pinned
+    app.results_state.select(Some(0));
+    app.toggle_pin();
+    assert!(app.pinned.is_empty());
+    app.select_next_result();
+    app.toggle_pin();
+    assert_eq!(app.pinned.len(), 1);
+    assert_eq!(app.pinned[0].title, "2");
+    assert_eq!(app.pinned[0].line, Some(1));
+
+    // pins survive other searches
+    app.input = "armas".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    app.toggle_pin();
+    assert_eq!(app.pinned.len(), 2);
+    assert_eq!(app.pinned_state.selected(), Some(0));
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    assert!(screen.contains("Pinned (2)"));
+    assert!(screen.contains("* "));
+
+    // the pins can be focused while there are any
+    app.where_we_are = WhereWeAre::Results;
+    app.next_position();
+    assert!(app.where_we_are == WhereWeAre::Pinned);
+    app.open_selected_pin().unwrap();
+    let reader = app.reader.take().unwrap();
+    assert_eq!((reader.title.as_str(), reader.line), ("2", 1));
+
+    // pinning again unpins
+    app.toggle_pin();
+    assert_eq!(app.pinned.len(), 1);
+    assert_eq!(app.pinned[0].title, "2");
+    app.unpin(Some(0));
+    assert!(app.pinned.is_empty());
+    assert_eq!(app.pinned_state.selected(), None);
+    assert!(app.where_we_are == WhereWeAre::Results);
+    app.next_position();
+    assert!(app.where_we_are == WhereWeAre::Input);
+}
+
+#[test]
+fn test_tabs() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.input = "armas".into();
+    app.tags.state.select(Some(0));
+    app.change_status(TagStatus::Include);
+    app.search();
+    app.receive_results(true).unwrap();
+    let armas = app.result_items();
+
+    // a number past the last tab opens a new one
+    app.switch_tab(5);
+    assert_eq!((app.tab, app.tabs.len()), (1, 2));
+    assert_eq!(app.input.value(), "");
+    assert!(app.results.is_empty());
+    assert_eq!(app.tags.list.len(), 4);
+    assert!(app
+        .tags
+        .list
+        .iter()
+        .all(|tag| tag.status == TagStatus::None));
+    app.input = "barões".into();
+    app.search();
+    app.receive_results(true).unwrap();
+
+    app.switch_tab(0);
+    assert_eq!(app.input.value(), "armas");
+    assert_eq!(app.result_items(), armas);
+    assert_eq!(app.tags.list[0].status, TagStatus::Include);
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    assert!(screen.contains(" 1  2   "));
+
+    // the next tab is shown when one is closed
+    app.close_tab();
+    assert_eq!((app.tab, app.tabs.len()), (0, 1));
+    assert_eq!(app.input.value(), "barões");
+    app.close_tab();
+    assert_eq!(app.tabs.len(), 1);
+}
+
+#[test]
+fn test_wrap_line() {
+    let line = Line::from_iter([Span::from("  "), Span::from("abcde").red(), "fgh".into()]);
+    let wrapped = wrap_line(line, 5, 2);
+    assert_eq!(
+        wrapped,
+        vec![
+            Line::from_iter([Span::from("  "), Span::from("abc").red()]),
+            Line::from_iter([Span::from("  "), Span::from("de").red(), "f".into()]),
+            Line::from_iter([Span::from("  "), "gh".into()]),
+        ]
+    );
+    // nothing fits after the indentation
+    assert_eq!(wrap_line(Line::from("abcdef"), 2, 2).len(), 1);
+}
+
+#[test]
+fn test_wrap_and_line_numbers() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.input = "barões".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    let snippet = ResultItem::Snippet(app.selected_item().unwrap().book(), 0);
+    let text =
+        |lines: Vec<Line>| -> Vec<String> { lines.iter().map(|line| line.to_string()).collect() };
+    assert_eq!(
+        text(app.result_lines(snippet)),
+        ["  As armas e os barões assinalados,"]
+    );
+
+    app.line_numbers = true;
+    assert_eq!(
+        text(app.result_lines(snippet)),
+        ["  1 │ As armas e os barões assinalados,"]
+    );
+
+    app.wrap_results = true;
+    app.results_width = 20;
+    assert_eq!(
+        text(app.result_lines(snippet)),
+        [
+            "  1 │ As armas e os ",
+            "      barões assinal",
+            "      ados,"
+        ]
+    );
+    assert_eq!(app.item_height(snippet), 3);
+}
+
+#[test]
+fn test_jump_to_match() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    let snippet = |text: &str| SearchResults {
+        title: text.to_string(),
+        results: vec![text.to_string()],
+        score: 0.0,
+        duration_ms: 0,
+        lines: vec![],
+    };
+    app.set_results(vec![
+        snippet("[matched]a[/matched] [matched]a[/matched]\n[matched]a[/matched]"),
+        snippet("b"),
+        snippet("[matched]c[/matched]"),
+    ]);
+    app.collapse(Some(2), true);
+
+    // the first jump goes to the selected snippet
+    app.jump_to_match(true);
+    assert_eq!(app.current_match, Some((0, 0, 0)));
+    app.jump_to_match(true);
+    app.jump_to_match(true);
+    assert_eq!(app.current_match, Some((0, 0, 2)));
+    let lines = app.result_lines(ResultItem::Snippet(0, 0));
+    assert!(lines[1].spans[2]
+        .style
+        .add_modifier
+        .contains(Modifier::REVERSED));
+    assert!(!lines[0].spans[2]
+        .style
+        .add_modifier
+        .contains(Modifier::REVERSED));
+
+    // collapsed books are opened
+    app.jump_to_match(true);
+    assert_eq!(app.current_match, Some((2, 0, 0)));
+    assert_eq!(app.selected_item(), Some(ResultItem::Snippet(2, 0)));
+    assert!(app.collapsed.is_empty());
+
+    // it goes around the ends
+    app.jump_to_match(true);
+    assert_eq!(app.current_match, Some((0, 0, 0)));
+    app.jump_to_match(false);
+    assert_eq!(app.current_match, Some((2, 0, 0)));
+
+    // after another selection, it starts from there
+    app.results_state.select(Some(2));
+    assert_eq!(app.selected_item(), Some(ResultItem::Book(1)));
+    app.jump_to_match(false);
+    assert_eq!(app.current_match, Some((0, 0, 2)));
+}
+
+#[test]
+fn test_continue_reading() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    // positions of another user, so that other tests don't show them
+    let mut config = root_for_tag_tests(connection).config().clone();
+    config.user_id = Some(-(std::process::id() as i32));
+    let root = RootBookDir::new(config.clone(), connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    assert!(app.continue_reading.is_empty());
+    app.open_book("2".to_string(), 1).unwrap();
+    app.reader.as_mut().unwrap().height = 2;
+    app.scroll_reader(1).unwrap();
+    app.close_reader().unwrap();
+    assert!(app.reader.is_none());
+    assert_eq!(app.continue_reading.len(), 1);
+    assert_eq!(app.continue_reading[0].title, "2");
+    assert_eq!(app.continue_reading[0].line, 2);
+    assert!(app.showing_continue_reading());
+
+    let mut terminal = Terminal::new(TestBackend::new(120, 30)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    assert!(screen.contains("Continue reading"));
+    assert!(screen.contains("2  line 2"));
+
+    app.continue_selected_book().unwrap();
+    let reader = app.reader.as_ref().unwrap();
+    assert_eq!((reader.title.as_str(), reader.top), ("2", 2));
+
+    // the results take its place
+    app.input = "armas".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    assert!(!app.showing_continue_reading());
+
+    ReadingProgress::new(config, app.root_book_dir.connection.as_deref_mut().unwrap())
+        .forget("2")
+        .unwrap();
+}
+
+#[test]
+fn test_library() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.open_library().unwrap();
+    let library = app.library.as_mut().unwrap();
+    let titles: Vec<&str> = library
+        .filtered()
+        .iter()
+        .map(|b| b.title.as_str())
+        .collect();
+    assert_eq!(titles, vec!["1", "2", "3", "4"]);
+    assert_eq!(library.books[0].tags, vec!["a", "b", "c", "d"]);
+    assert!(library.books.iter().all(|book| book.size > 0));
+
+    // tags are also filtered
+    library.filter = "D".into();
+    library.select_first();
+    let titles: Vec<&str> = library
+        .filtered()
+        .iter()
+        .map(|b| b.title.as_str())
+        .collect();
+    assert_eq!(titles, vec!["1"]);
+
+    library.filter = "nothing".into();
+    library.select_first();
+    assert_eq!(library.state.selected(), None);
+
+    library.filter = "2".into();
+    library.select_first();
+    app.open_selected_book().unwrap();
+    let reader = app.reader.as_ref().unwrap();
+    assert_eq!(reader.title, "2");
+    assert_eq!(reader.top, 1);
+}
+
+#[test]
+fn test_delete_book() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let mut root = create_book_dir(connection);
+    root.upload("1", "As armas", s(vec!["a", "b"]))
+        .unwrap()
+        .upload("2", "e os barões", s(vec!["a"]))
+        .unwrap();
+
+    let mut app = App::new(root, TuiConfig::default());
+    for tag in app.tags.list.iter_mut().filter(|tag| tag.name == "a") {
+        tag.status = TagStatus::Include;
+    }
+    app.input = "armas|barões".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    app.open_library().unwrap();
+    app.ask_to_delete_selected_book();
+    let title = app.library.as_mut().unwrap().confirm_delete.take().unwrap();
+    assert_eq!(title, "1");
+    app.delete_book(&title).unwrap();
+
+    let library = app.library.as_ref().unwrap();
+    let titles: Vec<&str> = library.books.iter().map(|b| b.title.as_str()).collect();
+    assert_eq!(titles, vec!["2"]);
+    assert_eq!(library.state.selected(), Some(0));
+    assert!(app.results.iter().all(|result| result.title == "2"));
+    // "b" was only used by the deleted book
+    let tags: Vec<(&str, TagStatus)> = app
+        .tags
+        .list
+        .iter()
+        .map(|tag| (tag.name.as_str(), tag.status.clone()))
+        .collect();
+    assert_eq!(tags, vec![("a", TagStatus::Include)]);
+}
+
+#[test]
+fn test_history() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    // its own history, so that other tests don't get in the way
+    let mut config = create_book_dir(connection).config().clone();
+    config.history_backend = HistoryBackendKind::JsonFile;
+    config.history_file = Some(config.book_path.with_extension("jsonl"));
+    let mut root = RootBookDir::new(config, connection);
+    root.upload("1", "As armas", s(vec!["a"]))
+        .unwrap()
+        .upload("2", "As armas e os barões", s(vec![]))
+        .unwrap();
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.open_history().unwrap();
+    assert!(app.history.as_ref().unwrap().searches.is_empty());
+    assert_eq!(app.history.as_ref().unwrap().state.selected(), None);
+
+    app.input = "armas".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    app.input = "barões".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    app.open_history().unwrap();
+    let history = app.history.as_mut().unwrap();
+    let searches: Vec<(&str, usize, usize)> = history
+        .searches
+        .iter()
+        .map(|search| (search.pattern.as_str(), search.books, search.match_count))
+        .collect();
+    assert_eq!(searches, vec![("barões", 2, 1), ("armas", 2, 2)]);
+    assert_eq!(history.state.selected(), Some(0));
+
+    history.filter = "ARM".into();
+    history.select_first();
+    let patterns: Vec<&str> = history
+        .filtered()
+        .iter()
+        .map(|search| search.pattern.as_str())
+        .collect();
+    assert_eq!(patterns, vec!["armas"]);
+}
+
+#[test]
+fn test_rerun_search() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let mut config = create_book_dir(connection).config().clone();
+    config.history_backend = HistoryBackendKind::JsonFile;
+    config.history_file = Some(config.book_path.with_extension("jsonl"));
+    let context = HistoryContext {
+        include_mode: FilterMode::Any,
+        include_tags: s(vec!["a", "gone"]),
+        exclude_tags: s(vec!["c"]),
+        case_insensitive: true,
+        ..Default::default()
+    };
+    history_backend(&config, None)
+        .restore(&[JsonHistoryEntry {
+            date: "2024-01-01T00:00:00".parse().unwrap(),
+            title: "1".into(),
+            pattern: "ARMAS".into(),
+            context: context.clone(),
+            results: vec![],
+            match_count: 0,
+            duration_ms: 0,
+        }])
+        .unwrap();
+    let mut root = RootBookDir::new(config, connection);
+    root.upload("1", "As armas", s(vec!["a", "b"]))
+        .unwrap()
+        .upload("2", "As armas", s(vec!["a", "c"]))
+        .unwrap()
+        .upload("3", "As armas", s(vec!["b"]))
+        .unwrap();
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.open_history().unwrap();
+    app.rerun_selected_search();
+    app.receive_results(true).unwrap();
+    assert!(app.history.is_none());
+    assert_eq!(app.input.value(), "ARMAS");
+    assert_eq!(app.include, FilterMode::Any);
+    assert_eq!(app.settings.case, CaseMode::Insensitive);
+    let mut tags: Vec<(&str, TagStatus)> = app
+        .tags
+        .list
+        .iter()
+        .map(|tag| (tag.name.as_str(), tag.status.clone()))
+        .collect();
+    tags.sort_by_key(|tag| tag.0);
+    assert_eq!(
+        tags,
+        vec![
+            ("a", TagStatus::Include),
+            ("b", TagStatus::None),
+            ("c", TagStatus::Exclude)
+        ]
+    );
+    // the search was case insensitive
+    assert_eq!(app.results.len(), 1);
+    assert_eq!(app.results[0].title, "1");
+    assert_eq!(app.results[0].match_count(), 1);
+
+    app.open_history().unwrap();
+    let searches = &app.history.as_ref().unwrap().searches;
+    assert_eq!(searches.len(), 2);
+    assert!(searches[0].context.case_insensitive);
+}
+
+#[test]
+fn test_help() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    assert!(!app.is_typing());
+    app.where_we_are = WhereWeAre::Input;
+    assert!(app.is_typing());
+    app.where_we_are = WhereWeAre::Tags;
+
+    app.help = true;
+    let mut terminal = Terminal::new(TestBackend::new(100, 100)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    for (area, bindings) in HELP {
+        assert!(screen.contains(area));
+        for (keys, _) in *bindings {
+            assert!(screen.contains(keys));
+        }
+    }
+}
+
+#[test]
+fn test_pane_layout() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+    let tui = TuiConfig {
+        search_panel_width: Some(95),
+        ..Default::default()
+    };
+
+    let mut app = App::new(root, tui);
+    assert_eq!(app.search_panel_width, 85);
+    assert_eq!(app.resize_panels(5), 85);
+    assert_eq!(app.resize_panels(-50), 35);
+    assert_eq!(app.resize_panels(-50), 15);
+    assert_eq!(app.resize_panels(5), 20);
+
+    let mut terminal = Terminal::new(TestBackend::new(104, 20)).unwrap();
+    let width = |app: &App, area: WhereWeAre| {
+        app.areas
+            .iter()
+            .find(|(other, _)| *other == area)
+            .map(|(_, rect)| rect.width)
+    };
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    assert_eq!(width(&app, WhereWeAre::Tags), Some(20));
+    assert_eq!(width(&app, WhereWeAre::Results), Some(80));
+
+    app.full_screen_results = true;
+    app.next_position();
+    assert!(app.where_we_are == WhereWeAre::Results);
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    assert_eq!(width(&app, WhereWeAre::Tags), None);
+    assert_eq!(width(&app, WhereWeAre::Results), Some(100));
+
+    app.resize_panels(0);
+    assert!(!app.full_screen_results);
+}
+
+#[test]
+fn test_settings() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+    let file = ConfigFile {
+        config: root.config().clone(),
+        tui: TuiConfig {
+            after_context: 2,
+            case_smart: true,
+            ..Default::default()
+        },
+    };
+
+    // the options start with the defaults of the config
+    let mut app = App::new(root, file.tui.clone());
+    assert_eq!(app.settings.after_context, 2);
+    assert_eq!(app.settings.case, CaseMode::Smart);
+
+    let mut editor = SettingsEditor::new(file.clone());
+    let values: Vec<&str> = editor.inputs.iter().map(|(_, i)| i.value()).collect();
+    assert_eq!(values[2..], ["0", "2", "smart", "off"]);
+    assert_eq!(
+        editor.validated().unwrap().config.database_url,
+        file.config.database_url
+    );
+
+    let set = |editor: &mut SettingsEditor, field: SettingField, value: &str| {
+        let (_, input) = editor.inputs.iter_mut().find(|(f, _)| *f == field).unwrap();
+        *input = Input::new(value.to_string());
+    };
+    set(&mut editor, SettingField::DatabaseUrl, "mysql://localhost");
+    set(&mut editor, SettingField::BeforeContext, "-1");
+    set(&mut editor, SettingField::Case, "smart");
+    set(&mut editor, SettingField::WholeWord, "yes");
+    let errors = editor.validated().unwrap_err();
+    let fields: Vec<&str> = errors.iter().map(|e| e.field.as_str()).collect();
+    assert_eq!(fields, ["database_url", "before_context", "whole_word"]);
+
+    editor.errors = errors;
+    app.settings_editor = Some(editor);
+    assert!(app.is_typing());
+    let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let screen: String = terminal
+        .backend()
+        .buffer()
+        .content()
+        .iter()
+        .map(|cell| cell.symbol())
+        .collect();
+    assert!(screen.contains("whole_word (should be on or off)"));
+    assert!(screen.contains("mysql://localhost"));
+
+    let editor = app.settings_editor.as_mut().unwrap();
+    set(editor, SettingField::DatabaseUrl, "");
+    set(editor, SettingField::BeforeContext, "3");
+    set(editor, SettingField::Case, "insensitive");
+    set(editor, SettingField::WholeWord, "on");
+    let saved = editor.validated().unwrap();
+    assert_eq!(saved.config.database_url, None);
+    assert_eq!(saved.tui.before_context, 3);
+    assert!(saved.tui.case_insensitive && !saved.tui.case_smart);
+    assert!(saved.tui.whole_word);
+    assert_eq!(saved.config.book_path, file.config.book_path);
+}
+
+#[test]
+fn test_commands() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    let status = |app: &App, name: &str| {
+        app.tags
+            .list
+            .iter()
+            .find(|tag| tag.name == name)
+            .unwrap()
+            .status
+            .clone()
+    };
+    assert_eq!(app.run_command("q"), Ok(true));
+    assert_eq!(app.run_command("tag +a -b"), Ok(false));
+    assert!(status(&app, "a") == TagStatus::Include);
+    assert!(status(&app, "b") == TagStatus::Exclude);
+    assert!(status(&app, "c") == TagStatus::None);
+    app.run_command("tag b -c").unwrap();
+    assert!(status(&app, "a") == TagStatus::Include);
+    assert!(status(&app, "b") == TagStatus::None);
+    assert!(status(&app, "c") == TagStatus::Exclude);
+    assert!(app.run_command("tag +nope").is_err());
+    app.run_command("tag").unwrap();
+    assert!(app
+        .tags
+        .list
+        .iter()
+        .all(|tag| tag.status == TagStatus::None));
+
+    app.run_command("search armas").unwrap();
+    app.receive_results(true).unwrap();
+    assert_eq!(app.input.value(), "armas");
+    let match_count: usize = app.results.iter().map(|r| r.match_count()).sum();
+    assert_eq!(match_count, 2);
+
+    let path = std::env::temp_dir().join(format!("bookrab-{}.txt", random_string()));
+    app.run_command(&format!("export {}", path.display()))
+        .unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), app.results_text());
+    std::fs::remove_file(&path).unwrap();
+    let path = path.with_extension("html");
+    app.run_command(&format!("export {}", path.display()))
+        .unwrap();
+    assert_eq!(std::fs::read_to_string(&path).unwrap(), app.results_html());
+    std::fs::remove_file(&path).unwrap();
+
+    app.run_command("open 2").unwrap();
+    assert_eq!(app.reader.as_ref().unwrap().title, "2");
+    app.reader = None;
+    assert!(app.run_command("open nonexistent").is_err());
+    assert!(app.run_command("open").is_err());
+    assert!(app.run_command("frobnicate").is_err());
+}
+
+#[test]
+fn test_mouse() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.input = "armas".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    let mut terminal = Terminal::new(TestBackend::new(120, 40)).unwrap();
+    terminal.draw(|f| ui(f, &mut app)).unwrap();
+    let area = |app: &App, area: WhereWeAre| {
+        app.areas
+            .iter()
+            .find(|(other, _)| *other == area)
+            .map(|(_, rect)| *rect)
+            .unwrap()
+    };
+    let mouse = |kind: MouseEventKind, column: u16, row: u16| MouseEvent {
+        kind,
+        column,
+        row,
+        modifiers: KeyModifiers::NONE,
+    };
+    let click = |rect: Rect, row: u16| {
+        mouse(
+            MouseEventKind::Down(MouseButton::Left),
+            rect.x + 2,
+            rect.y + row,
+        )
+    };
+
+    // clicking a tag focuses the tags and cycles its status
+    let tags = area(&app, WhereWeAre::Tags);
+    app.handle_mouse(click(tags, 2));
+    assert!(app.where_we_are == WhereWeAre::Tags);
+    assert_eq!(app.tags.state.selected(), Some(1));
+    assert!(app.tags.list[1].status == TagStatus::Include);
+    app.handle_mouse(click(tags, 2));
+    assert!(app.tags.list[1].status == TagStatus::Exclude);
+    // the border and the rows below the tags do nothing
+    app.handle_mouse(click(tags, 0));
+    app.handle_mouse(click(tags, tags.height - 2));
+    assert_eq!(app.tags.state.selected(), Some(1));
+
+    app.handle_mouse(click(area(&app, WhereWeAre::Exclude), 1));
+    assert!(app.where_we_are == WhereWeAre::Exclude);
+
+    // the title of the second book is below the first book and its snippet
+    let results = area(&app, WhereWeAre::Results);
+    let items = app.result_items();
+    let ResultItem::Snippet(book, 0) = items[1] else {
+        panic!("the first snippet should follow its book");
+    };
+    let first_height = app.results[book].results[0].lines().count() as u16;
+    let second = 2;
+    app.handle_mouse(click(results, 2 + first_height));
+    assert!(app.where_we_are == WhereWeAre::Results);
+    assert_eq!(app.results_state.selected(), Some(second));
+    // clicking the title of a book closes it
+    assert!(app.collapsed.contains(&items[second].book()));
+
+    app.handle_mouse(mouse(
+        MouseEventKind::ScrollUp,
+        results.x + 2,
+        results.y + 2,
+    ));
+    assert_eq!(app.results_state.selected(), Some(second - 1));
+    app.handle_mouse(mouse(
+        MouseEventKind::ScrollDown,
+        results.x + 2,
+        results.y + 2,
+    ));
+    assert_eq!(app.results_state.selected(), Some(second));
+    app.handle_mouse(mouse(MouseEventKind::ScrollDown, tags.x + 2, tags.y + 2));
+    assert_eq!(app.tags.state.selected(), Some(2));
+
+    app.handle_mouse(click(results, 1));
+    assert_eq!(app.results_state.selected(), Some(0));
+    assert!(app.collapsed.contains(&book));
+    assert_eq!(app.result_items().len(), 2);
+}
+
+#[test]
+fn test_search_options() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let mut root = create_book_dir(connection);
+    root.upload(
+        "1",
+        "Que outro valor mais alto se alevanta.\nCesse tudo o que a Musa antiga canta,\n",
+        s(vec![]),
+    )
+    .unwrap();
+
+    let mut app = App::new(root, TuiConfig::default());
+    app.settings.select(false);
+    assert_eq!(app.settings.selected, SearchOption::WholeWord);
+    app.settings.change(true);
+    app.settings.select(true);
+    app.settings.select(true);
+    assert_eq!(app.settings.selected, SearchOption::AfterContext);
+    app.settings.change(false);
+    app.settings.change(true);
+    app.settings.select(true);
+    app.settings.change(false);
+    app.settings.change(true);
+    app.settings.change(true);
+    assert_eq!(app.settings.case, CaseMode::Insensitive);
+    assert!(app.settings.whole_word);
+    assert_eq!(app.settings.after_context, 1);
+
+    // "mais" is a whole word, "canta" is part of "alevanta"
+    app.input = "MAIS|anta".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    assert_eq!(
+        app.results[0].results,
+        vec!["Que outro valor [matched]mais[/matched] alto se alevanta.\nCesse tudo o que a Musa antiga canta,\n"]
+    );
+
+    app.settings.whole_word = false;
+    app.search();
+    app.receive_results(true).unwrap();
+    assert_eq!(app.results[0].match_count(), 3);
+}
+
+#[test]
+fn test_format_size() {
+    assert_eq!(format_size(10), "10 B");
+    assert_eq!(format_size(1536), "1.5 KiB");
+    assert_eq!(format_size(3 * 1_048_576), "3.0 MiB");
+}
+
+#[test]
+fn test_search_and_copy() {
+    let connection = &mut DBCONNECTION.get().unwrap();
+    let root = root_for_tag_tests(connection);
+
+    // create app and run it
+    let mut app = App::new(root, TuiConfig::default());
+    app.input = "armas".into();
+    app.search();
+    app.receive_results(true).unwrap();
+    // books are ranked by score, but we only care about the text here
+    app.results.sort_by(|a, b| a.title.cmp(&b.title));
+    for result in app.results.iter_mut() {
+        result.score = 0.0;
+        result.duration_ms = 0;
+        result.lines.clear();
+    }
+    assert_eq!(
+        app.results,
+        vec![
+            SearchResults {
+                title: "1".into(),
+                results: vec![
+                    "Se as [matched]armas[/matched] queres ver, como tens dito,\n".into()
+                ],
+                score: 0.0,
+                duration_ms: 0,
+                lines: vec![],
+            },
+            SearchResults {
+                title: "2".into(),
+                results: vec!["As [matched]armas[/matched] e os barões assinalados,\n".into()],
+                score: 0.0,
+                duration_ms: 0,
+                lines: vec![],
+            },
+            SearchResults {
+                title: "3".into(),
+                results: vec![],
+                score: 0.0,
+                duration_ms: 0,
+                lines: vec![],
+            },
+            SearchResults {
+                title: "4".into(),
+                results: vec![],
+                score: 0.0,
+                duration_ms: 0,
+                lines: vec![],
+            }
+        ]
+    );
+
+    assert_eq!(app.results_html(), "<div><span style=\"color: blue\">1</span></div><p>Se as <span style=\"color: red\">armas</span> queres ver, como tens dito,\n</p><div><span style=\"color: blue\">2</span></div><p>As <span style=\"color: red\">armas</span> e os barões assinalados,\n</p>");
+    let text =
+        "1\nSe as armas queres ver, como tens dito,\n\n2\nAs armas e os barões assinalados,\n";
+    assert_eq!(app.results_text(), text);
+
+    app.copy_results(false).expect("App could not copy results");
+    let copied = Clipboard::new()
+        .expect("Clipboard not supported")
+        .get()
+        .text()
+        .expect("empty or non-UTF-8 clipboard");
+    // programs that read text get the plain text alternative
+    assert_eq!(copied, text);
+}